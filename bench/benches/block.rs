@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use alloy_consensus::TxEnvelope;
+use alloy_rlp::{Decodable, Encodable};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use raiko_bench::{sample_receipts, sample_transactions};
+use raiko_core::{
+    fixture::{Fixture, FIXTURES_DIR},
+    preflight::preflight,
+    provider::fixture::FixtureBlockDataProvider,
+};
+use raiko_lib::{
+    builder::execute::{build_tx_and_receipt_tries, recover_senders},
+    input::TaikoProverData,
+    primitives::{keccak::keccak, mpt::MptNode, trie_bloom::TrieBloom},
+    utils::decode_transactions,
+};
+
+const SIZES: [usize; 3] = [10, 100, 1000];
+
+fn bench_recover_senders(c: &mut Criterion) {
+    let mut group = c.benchmark_group("recover_senders");
+    for n in SIZES {
+        let transactions = sample_transactions(n);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &transactions, |b, txs| {
+            b.iter(|| recover_senders(txs).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_trie_root(c: &mut Criterion) {
+    let mut group = c.benchmark_group("trie_root");
+    for n in SIZES {
+        let mut trie = MptNode::default();
+        for i in 0..n {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        group.throughput(Throughput::Elements(n as u64));
+        // the references are cached in the nodes, so every iteration hashes a fresh copy
+        group.bench_with_input(BenchmarkId::from_parameter(n), &trie, |b, trie| {
+            b.iter_batched(|| trie.clone(), |trie| trie.hash(), BatchSize::SmallInput)
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("tx_and_receipt_tries");
+    for n in SIZES {
+        let transactions = sample_transactions(n);
+        let receipts = sample_receipts(n);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_function(BenchmarkId::from_parameter(n), |b| {
+            b.iter(|| build_tx_and_receipt_tries(&transactions, &receipts).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_rlp(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rlp");
+    for n in SIZES {
+        let transactions = sample_transactions(n);
+        let encoded = alloy_rlp::encode(&transactions);
+        group.throughput(Throughput::Bytes(encoded.len() as u64));
+        group.bench_with_input(BenchmarkId::new("encode", n), &transactions, |b, txs| {
+            b.iter(|| {
+                let mut out = Vec::with_capacity(encoded.len());
+                txs.encode(&mut out);
+                out
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("decode", n), &encoded, |b, encoded| {
+            b.iter(|| Vec::<TxEnvelope>::decode(&mut encoded.as_slice()).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("decode_tx_list", n),
+            &encoded,
+            |b, encoded| b.iter(|| decode_transactions(encoded)),
+        );
+    }
+    group.finish();
+}
+
+/// Compares trie lookups with and without the bloom filter of `trie-bloom`, for keys that
+/// are in the trie (hits, which pay for the filter on top of the lookup) and keys that are
+/// not (misses, which the filter mostly answers alone), as well as building the filter.
+fn bench_trie_bloom(c: &mut Criterion) {
+    if !cfg!(feature = "trie-bloom") {
+        println!("trie_bloom needs the trie-bloom feature, skipping");
+        return;
+    }
+
+    let mut group = c.benchmark_group("trie_bloom");
+    for n in SIZES {
+        let mut trie = MptNode::default();
+        for i in 0..n {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        let hits: Vec<_> = (0..n).map(|i| keccak(i.to_be_bytes())).collect();
+        let misses: Vec<_> = (n..2 * n).map(|i| keccak(i.to_be_bytes())).collect();
+        let bloom = TrieBloom::new(&trie);
+
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("build", n), &trie, |b, trie| {
+            b.iter(|| TrieBloom::new(trie))
+        });
+        for (name, keys) in [("hit", &hits), ("miss", &misses)] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{name}/trie"), n),
+                keys,
+                |b, keys| {
+                    b.iter(|| {
+                        keys.iter()
+                            .filter(|key| trie.get_rlp::<usize>(key.as_slice()).unwrap().is_some())
+                            .count()
+                    })
+                },
+            );
+            group.bench_with_input(
+                BenchmarkId::new(format!("{name}/bloom"), n),
+                keys,
+                |b, keys| {
+                    b.iter(|| {
+                        keys.iter()
+                            .filter(|key| {
+                                bloom
+                                    .get_rlp::<usize>(&trie, key.as_slice())
+                                    .unwrap()
+                                    .is_some()
+                            })
+                            .count()
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+/// Re-executes the blocks of all fixtures in `core/fixtures` from their witness and checks
+/// the result, i.e. the work of the guest. The preflight isn't covered, as it needs a node.
+fn load_fixtures() -> Vec<Fixture> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../core")
+        .join(FIXTURES_DIR);
+    let fixtures = Fixture::load_dir(&dir).unwrap();
+    if fixtures.is_empty() {
+        println!("No fixtures in {}, skipping", dir.display());
+    }
+    fixtures.into_iter().map(|(_, fixture)| fixture).collect()
+}
+
+fn bench_fixture_execution(c: &mut Criterion) {
+    let fixtures = load_fixtures();
+    let mut group = c.benchmark_group("fixture_execution");
+    group.sample_size(10);
+    for fixture in fixtures {
+        group.bench_function(&fixture.name, |b| b.iter(|| fixture.check().unwrap()));
+    }
+    group.finish();
+}
+
+/// The preflight of the fixtures, with the state served from their witness instead of a
+/// node. Taiko fixtures are skipped, as their preflight also needs the L1 chain.
+fn bench_fixture_preflight(c: &mut Criterion) {
+    // the preflight blocks on the runtime from within the execution of the block
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("fixture_preflight");
+    group.sample_size(10);
+    for fixture in load_fixtures() {
+        let input = &fixture.input;
+        if input.chain_spec.is_taiko() {
+            continue;
+        }
+        let provider = FixtureBlockDataProvider::new(input).unwrap();
+        group.bench_function(&fixture.name, |b| {
+            b.iter_batched(
+                || provider.clone(),
+                |provider| {
+                    rt.block_on(preflight(
+                        provider,
+                        input.block_number,
+                        input.chain_spec.clone(),
+                        input.chain_spec.clone(),
+                        TaikoProverData::default(),
+                        None,
+                    ))
+                    .unwrap()
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_recover_senders,
+    bench_trie_root,
+    bench_rlp,
+    bench_trie_bloom,
+    bench_fixture_execution,
+    bench_fixture_preflight
+);
+criterion_main!(benches);
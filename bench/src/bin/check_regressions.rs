@@ -0,0 +1,64 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use raiko_bench::regression::{read_changes, regressions, CRITERION_DIR, DEFAULT_THRESHOLD};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "check-regressions",
+    about = "Fails if a benchmark got slower than all runs of its criterion baseline"
+)]
+struct Cli {
+    #[arg(long, require_equals = true, default_value = CRITERION_DIR)]
+    /// Directory of the criterion reports
+    criterion_dir: PathBuf,
+
+    #[arg(long, require_equals = true, default_value = "main")]
+    /// Name of the baseline, whose runs are saved as `<baseline>-<n>`
+    baseline: String,
+
+    #[arg(long, require_equals = true, default_value = "current")]
+    /// Name the run to check was saved as
+    current: String,
+
+    #[arg(long, require_equals = true, default_value_t = DEFAULT_THRESHOLD)]
+    /// Maximum relative slowdown of the mean time compared to the slowest baseline run,
+    /// e.g. 0.1 for 10%
+    threshold: f64,
+}
+
+fn main() -> anyhow::Result<ExitCode> {
+    let cli = Cli::parse();
+
+    let changes = read_changes(&cli.criterion_dir, &cli.baseline, &cli.current)?;
+    if changes.is_empty() {
+        anyhow::bail!(
+            "No benchmark of {} has a run of baseline {} in {}",
+            cli.current,
+            cli.baseline,
+            cli.criterion_dir.display()
+        );
+    }
+    for change in &changes {
+        println!(
+            "{:<40} {:+.2}% ({} baseline runs)",
+            change.name,
+            change.mean * 100.0,
+            change.baseline_runs
+        );
+    }
+
+    let regressed = regressions(&changes, cli.threshold);
+    if regressed.is_empty() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    println!(
+        "\n{} benchmark(s) regressed by more than {:.1}%:",
+        regressed.len(),
+        cli.threshold * 100.0
+    );
+    for change in regressed {
+        println!("  {}", change.name);
+    }
+    Ok(ExitCode::FAILURE)
+}
@@ -0,0 +1,49 @@
+//! Benchmarks of the performance critical parts of building a block, see `benches/block.rs`.
+//!
+//! A bench run compared against a saved criterion baseline can be checked with the
+//! `check-regressions` binary, which fails if any benchmark got slower than a threshold.
+
+use alloy_consensus::{SignableTransaction, TxEip1559, TxEnvelope};
+use alloy_primitives::{uint, Address, Bytes, Signature, TxKind, U256};
+use raiko_lib::primitives::receipt::{Log, Receipt};
+
+pub mod regression;
+
+/// The x coordinate of the secp256k1 generator, which makes any signature recoverable.
+const GX: U256 = uint!(0x79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798_U256);
+
+/// Returns `n` signed EIP-1559 transfers with distinct senders.
+pub fn sample_transactions(n: usize) -> Vec<TxEnvelope> {
+    (0..n)
+        .map(|i| {
+            let tx = TxEip1559 {
+                chain_id: 1,
+                nonce: i as u64,
+                gas_limit: 21_000,
+                max_fee_per_gas: 10_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                to: TxKind::Call(Address::with_last_byte(i as u8)),
+                value: U256::from(i),
+                access_list: Default::default(),
+                input: Bytes::new(),
+            };
+            let signature = Signature::from_rs_and_parity(GX, U256::from(i + 1), i % 2 == 0)
+                .expect("valid signature");
+            tx.into_signed(signature).into()
+        })
+        .collect()
+}
+
+/// Returns `n` receipts, each with a single transfer log.
+pub fn sample_receipts(n: usize) -> Vec<Receipt> {
+    (0..n)
+        .map(|i| {
+            let log = Log {
+                address: Address::with_last_byte(i as u8),
+                topics: vec![Default::default(); 3],
+                data: Bytes::from(U256::from(i).to_be_bytes_vec()),
+            };
+            Receipt::new(2, true, U256::from(21_000 * (i + 1)), vec![log])
+        })
+        .collect()
+}
@@ -0,0 +1,158 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+/// The directory criterion writes its reports to, relative to the workspace root.
+pub const CRITERION_DIR: &str = "target/criterion";
+/// Default slowdown of the mean time, relative to the slowest baseline run, that counts as
+/// regression.
+pub const DEFAULT_THRESHOLD: f64 = 0.1;
+
+/// The relative change of a benchmark compared to the runs of the baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// The benchmark id, e.g. `trie_root/1000`.
+    pub name: String,
+    /// Relative change of the mean time compared to the slowest baseline run, e.g. `0.05`
+    /// for 5% slower.
+    pub mean: f64,
+    /// The number of baseline runs the benchmark was compared against.
+    pub baseline_runs: usize,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    point_estimate: f64,
+}
+
+#[derive(Deserialize)]
+struct Estimates {
+    mean: Estimate,
+}
+
+/// Reads the changes of all benchmarks of the run saved as `current`, compared to the runs
+/// saved as `<baseline>-<n>`.
+///
+/// The mean times of a shared runner vary from run to run, so a benchmark only counts as
+/// slower than the slowest of several runs of the baseline. Criterion saves the estimates
+/// of each run to `<benchmark>/<name>/estimates.json`.
+pub fn read_changes(
+    criterion_dir: &Path,
+    baseline: &str,
+    current: &str,
+) -> anyhow::Result<Vec<Change>> {
+    let mut benchmarks = Vec::new();
+    find_benchmarks(criterion_dir, current, &mut benchmarks)?;
+
+    let mut changes = Vec::with_capacity(benchmarks.len());
+    for dir in benchmarks {
+        let current_mean = read_mean(&dir.join(current))?;
+        let mut baseline_means = Vec::new();
+        for entry in
+            fs::read_dir(&dir).with_context(|| format!("Couldn't read {}", dir.display()))?
+        {
+            let path = entry?.path();
+            let is_baseline_run = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(baseline))
+                .and_then(|run| run.strip_prefix('-'))
+                .is_some_and(|run| run.parse::<usize>().is_ok());
+            if is_baseline_run && path.join("estimates.json").exists() {
+                baseline_means.push(read_mean(&path)?);
+            }
+        }
+        let Some(slowest) = baseline_means.iter().copied().reduce(f64::max) else {
+            // new benchmarks have nothing to compare against
+            continue;
+        };
+        let name = dir
+            .strip_prefix(criterion_dir)
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default();
+        changes.push(Change {
+            name,
+            mean: current_mean / slowest - 1.0,
+            baseline_runs: baseline_means.len(),
+        });
+    }
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(changes)
+}
+
+/// Returns the changes that are slower than the baseline by more than `threshold`.
+pub fn regressions(changes: &[Change], threshold: f64) -> Vec<&Change> {
+    changes
+        .iter()
+        .filter(|change| change.mean > threshold)
+        .collect()
+}
+
+fn read_mean(run_dir: &Path) -> anyhow::Result<f64> {
+    let file = run_dir.join("estimates.json");
+    let content =
+        fs::read_to_string(&file).with_context(|| format!("Couldn't read {}", file.display()))?;
+    let estimates: Estimates = serde_json::from_str(&content)
+        .with_context(|| format!("Couldn't parse {}", file.display()))?;
+    Ok(estimates.mean.point_estimate)
+}
+
+/// Finds the directories of the benchmarks that have a run saved as `current`.
+fn find_benchmarks(dir: &Path, current: &str, benchmarks: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if dir.join(current).join("estimates.json").exists() {
+        benchmarks.push(dir.to_owned());
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir).with_context(|| format!("Couldn't read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_benchmarks(&path, current, benchmarks)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_run(dir: &Path, name: &str, run: &str, mean: f64) {
+        let run_dir = dir.join(name).join(run);
+        fs::create_dir_all(&run_dir).unwrap();
+        let estimates = format!(r#"{{"mean":{{"point_estimate":{mean}}}}}"#);
+        fs::write(run_dir.join("estimates.json"), estimates).unwrap();
+    }
+
+    #[test]
+    fn test_read_changes() {
+        let dir = std::env::temp_dir().join(format!("raiko-bench-{}", std::process::id()));
+        // within the spread of the baseline runs
+        for (run, mean) in [("main-1", 100.0), ("main-2", 120.0), ("main-3", 105.0)] {
+            write_run(&dir, "trie_root/1000", run, mean);
+        }
+        write_run(&dir, "trie_root/1000", "current", 125.0);
+        // slower than every baseline run
+        for (run, mean) in [("main-1", 100.0), ("main-2", 102.0)] {
+            write_run(&dir, "recover_senders/100", run, mean);
+        }
+        write_run(&dir, "recover_senders/100", "current", 127.5);
+        // baselines with another name and new benchmarks are ignored
+        write_run(&dir, "recover_senders/100", "other-1", 10.0);
+        write_run(&dir, "rlp/encode/10", "current", 1.0);
+
+        let changes = read_changes(&dir, "main", "current").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].name, "recover_senders/100");
+        assert_eq!(changes[0].baseline_runs, 2);
+        assert!((changes[0].mean - 0.25).abs() < 1e-9);
+        let regressed = regressions(&changes, DEFAULT_THRESHOLD);
+        assert_eq!(regressed.len(), 1);
+        assert_eq!(regressed[0].name, "recover_senders/100");
+    }
+}
@@ -0,0 +1,119 @@
+//! Counts the heap allocations of decoding trie nodes, compared to the decoder built on the
+//! legacy `rlp` crate that was used before the nodes were decoded from borrowed buffers.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use alloy_primitives::B256;
+use raiko_lib::primitives::{
+    keccak::keccak,
+    mpt::{MptNode, MptNodeData},
+};
+use rlp::{DecoderError, Prototype, Rlp};
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts the allocations of the current thread, so the other tests don't interfere.
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the number of allocations made by `f`.
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (usize, T) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (ALLOCATIONS.with(Cell::get) - before, result)
+}
+
+/// The node decoder as it was implemented with the `rlp` crate.
+fn decode_legacy(rlp: &Rlp) -> Result<MptNode, DecoderError> {
+    match rlp.prototype()? {
+        Prototype::Null | Prototype::Data(0) => Ok(MptNodeData::Null.into()),
+        Prototype::List(2) => {
+            let path: Vec<u8> = rlp.val_at(0)?;
+            let prefix = path[0];
+            if (prefix & (2 << 4)) == 0 {
+                let node = decode_legacy(&rlp.at(1)?)?;
+                Ok(MptNodeData::Extension(path, Box::new(node)).into())
+            } else {
+                Ok(MptNodeData::Leaf(path, rlp.val_at(1)?).into())
+            }
+        }
+        Prototype::List(17) => {
+            let mut node_list = Vec::with_capacity(16);
+            for node_rlp in rlp.iter().take(16) {
+                match node_rlp.prototype()? {
+                    Prototype::Null | Prototype::Data(0) => node_list.push(None),
+                    _ => node_list.push(Some(Box::new(decode_legacy(&node_rlp)?))),
+                }
+            }
+            let value: Vec<u8> = rlp.val_at(16)?;
+            if value.is_empty() {
+                Ok(MptNodeData::Branch(node_list.try_into().unwrap()).into())
+            } else {
+                Err(DecoderError::Custom("branch node with value"))
+            }
+        }
+        Prototype::Data(32) => {
+            let bytes: Vec<u8> = rlp.as_val()?;
+            Ok(MptNodeData::Digest(B256::from_slice(&bytes)).into())
+        }
+        _ => Err(DecoderError::RlpIncorrectListLen),
+    }
+}
+
+#[test]
+fn test_trie_node_decode_allocations() {
+    let mut trie = MptNode::default();
+    for i in 0..1000u64 {
+        trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+    }
+    // The nodes of a proof reference their siblings by hash, like the nodes of the witness
+    let nodes: Vec<Vec<u8>> = (0..100u64)
+        .flat_map(|i| trie.proof(&keccak(i.to_be_bytes())).unwrap())
+        .collect();
+
+    let (legacy, legacy_nodes) = count_allocations(|| {
+        nodes
+            .iter()
+            .map(|node| decode_legacy(&Rlp::new(node)).unwrap())
+            .collect::<Vec<_>>()
+    });
+    let (borrowed, borrowed_nodes) = count_allocations(|| {
+        nodes
+            .iter()
+            .map(|node| MptNode::decode(node).unwrap())
+            .collect::<Vec<_>>()
+    });
+    for (a, b) in legacy_nodes.iter().zip(&borrowed_nodes) {
+        assert_eq!(a.hash(), b.hash());
+    }
+
+    println!(
+        "Decoding {} nodes: {legacy} allocations with rlp, {borrowed} from borrowed buffers",
+        nodes.len()
+    );
+    // Every hash reference used to be copied into a temporary vector
+    assert!(borrowed < legacy);
+}
@@ -0,0 +1,359 @@
+//! Finds the first transaction whose local execution diverges from the execution of the
+//! node, using the `debug_traceBlockByNumber` traces of the node.
+
+use std::{collections::HashMap, fmt};
+
+use alloy_primitives::{Address, B256, U256, U64};
+use alloy_rpc_types::BlockNumberOrTag;
+use raiko_lib::{
+    builder::{trace::TxTrace, BlockBuilderStrategy, TaikoStrategy},
+    input::GuestInput,
+    prover::ProverError,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{
+    interfaces::{RaikoError, RaikoResult},
+    provider::rpc::RpcBlockDataProvider,
+};
+
+/// The first difference between the local execution and the execution of the node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the transaction among the included transactions of the block.
+    pub tx_index: usize,
+    pub tx_hash: B256,
+    pub kind: DivergenceKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DivergenceKind {
+    /// The block includes a different number of transactions.
+    TxCount {
+        expected: usize,
+        actual: usize,
+    },
+    Gas {
+        expected: u64,
+        actual: u64,
+    },
+    Balance {
+        address: Address,
+        expected: U256,
+        actual: U256,
+    },
+    Nonce {
+        address: Address,
+        expected: u64,
+        actual: u64,
+    },
+    /// A storage slot has a different value, `None` if the node didn't change it.
+    Storage {
+        address: Address,
+        slot: U256,
+        expected: Option<U256>,
+        actual: U256,
+    },
+    /// The account was only deleted by one of the executions.
+    Deleted {
+        address: Address,
+        expected: bool,
+        actual: bool,
+    },
+    /// The node changed an account that the local execution didn't touch.
+    MissingChange {
+        address: Address,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tx {} ({}): ", self.tx_index, self.tx_hash)?;
+        match &self.kind {
+            DivergenceKind::TxCount { expected, actual } => {
+                write!(f, "{actual} transactions, expected {expected}")
+            }
+            DivergenceKind::Gas { expected, actual } => {
+                write!(f, "gas used {actual}, expected {expected}")
+            }
+            DivergenceKind::Balance {
+                address,
+                expected,
+                actual,
+            } => write!(f, "balance of {address} is {actual}, expected {expected}"),
+            DivergenceKind::Nonce {
+                address,
+                expected,
+                actual,
+            } => write!(f, "nonce of {address} is {actual}, expected {expected}"),
+            DivergenceKind::Storage {
+                address,
+                slot,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "storage {slot} of {address} is {actual}, expected {expected:?}"
+            ),
+            DivergenceKind::Deleted {
+                address,
+                expected,
+                actual,
+            } => write!(f, "{address} deleted: {actual}, expected {expected}"),
+            DivergenceKind::MissingChange { address } => {
+                write!(f, "{address} was changed by the node only")
+            }
+        }
+    }
+}
+
+/// Executes the block of `input` and compares every included transaction against the
+/// traces of the node, returning the first difference.
+///
+/// The node must support the `callTracer` and the `prestateTracer` in diff mode.
+pub async fn find_divergence(
+    provider: &RpcBlockDataProvider,
+    input: &GuestInput,
+) -> RaikoResult<Option<Divergence>> {
+    let local = TaikoStrategy::trace_from(input)
+        .map_err(|e| RaikoError::Guest(ProverError::GuestError(e.to_string())))?;
+
+    let calls: Vec<TraceResult<CallFrame>> = trace_block(
+        provider,
+        input.block_number,
+        json!({ "tracer": "callTracer", "tracerConfig": { "onlyTopCall": true } }),
+    )
+    .await?;
+    let diffs: Vec<TraceResult<PrestateDiff>> = trace_block(
+        provider,
+        input.block_number,
+        json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } }),
+    )
+    .await?;
+
+    let gas_used: Vec<u64> = calls.iter().map(|call| call.result.gas_used.to()).collect();
+    let diffs: Vec<PrestateDiff> = diffs.into_iter().map(|diff| diff.result).collect();
+    Ok(first_divergence(&local, &gas_used, &diffs))
+}
+
+async fn trace_block<T: DeserializeOwned + fmt::Debug + Send + Sync + Unpin + 'static>(
+    provider: &RpcBlockDataProvider,
+    block_number: u64,
+    options: Value,
+) -> RaikoResult<Vec<T>> {
+    provider
+        .client
+        .request(
+            "debug_traceBlockByNumber",
+            (BlockNumberOrTag::from(block_number), options),
+        )
+        .await
+        .map_err(|e| RaikoError::RPC(format!("debug_traceBlockByNumber failed: {e}")))
+}
+
+#[derive(Debug, Deserialize)]
+struct TraceResult<T> {
+    result: T,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CallFrame {
+    gas_used: U64,
+}
+
+/// The accounts changed by a transaction, with the values before and after it. Only the
+/// changed fields are part of `post`.
+#[derive(Debug, Default, Deserialize)]
+struct PrestateDiff {
+    #[serde(default)]
+    pre: HashMap<Address, NodeAccount>,
+    #[serde(default)]
+    post: HashMap<Address, NodeAccount>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct NodeAccount {
+    balance: Option<U256>,
+    nonce: Option<u64>,
+    #[serde(default)]
+    storage: HashMap<B256, U256>,
+}
+
+fn first_divergence(
+    local: &[TxTrace],
+    gas_used: &[u64],
+    diffs: &[PrestateDiff],
+) -> Option<Divergence> {
+    for (tx_index, (trace, (gas_used, diff))) in
+        local.iter().zip(gas_used.iter().zip(diffs)).enumerate()
+    {
+        if let Some(kind) = compare(trace, *gas_used, diff) {
+            return Some(Divergence {
+                tx_index,
+                tx_hash: trace.tx_hash,
+                kind,
+            });
+        }
+    }
+
+    if local.len() != gas_used.len() {
+        let tx_index = local.len().min(gas_used.len());
+        return Some(Divergence {
+            tx_index,
+            tx_hash: local
+                .get(tx_index)
+                .map(|trace| trace.tx_hash)
+                .unwrap_or_default(),
+            kind: DivergenceKind::TxCount {
+                expected: gas_used.len(),
+                actual: local.len(),
+            },
+        });
+    }
+    None
+}
+
+fn compare(trace: &TxTrace, gas_used: u64, diff: &PrestateDiff) -> Option<DivergenceKind> {
+    if trace.gas_used != gas_used {
+        return Some(DivergenceKind::Gas {
+            expected: gas_used,
+            actual: trace.gas_used,
+        });
+    }
+
+    for (address, account) in &trace.accounts {
+        let pre = diff.pre.get(address);
+        let post = diff.post.get(address);
+        // deleted accounts are only part of the pre state
+        let deleted = pre.is_some() && post.is_none();
+        if account.deleted || deleted {
+            if account.deleted != deleted {
+                return Some(DivergenceKind::Deleted {
+                    address: *address,
+                    expected: deleted,
+                    actual: account.deleted,
+                });
+            }
+            continue;
+        }
+
+        // unchanged fields are only part of the pre state
+        let balance = post.and_then(|a| a.balance).or(pre.and_then(|a| a.balance));
+        if let Some(expected) = balance.filter(|balance| *balance != account.balance) {
+            return Some(DivergenceKind::Balance {
+                address: *address,
+                expected,
+                actual: account.balance,
+            });
+        }
+        let nonce = post.and_then(|a| a.nonce).or(pre.and_then(|a| a.nonce));
+        // a zero nonce is omitted by the tracer
+        let nonce = nonce
+            .or(post.map(|_| 0))
+            .filter(|nonce| *nonce != account.nonce);
+        if let Some(expected) = nonce {
+            return Some(DivergenceKind::Nonce {
+                address: *address,
+                expected,
+                actual: account.nonce,
+            });
+        }
+
+        for (slot, value) in &account.storage {
+            let key = B256::from(slot.to_be_bytes());
+            let expected = match post.and_then(|a| a.storage.get(&key)) {
+                Some(value) => Some(*value),
+                // cleared slots are only part of the pre state
+                None if pre.is_some_and(|a| a.storage.contains_key(&key)) => Some(U256::ZERO),
+                None => None,
+            };
+            if expected != Some(*value) {
+                return Some(DivergenceKind::Storage {
+                    address: *address,
+                    slot: *slot,
+                    expected,
+                    actual: *value,
+                });
+            }
+        }
+    }
+
+    diff.post
+        .keys()
+        .find(|address| !trace.accounts.contains_key(*address))
+        .map(|address| DivergenceKind::MissingChange { address: *address })
+}
+
+#[cfg(test)]
+mod tests {
+    use raiko_lib::builder::trace::AccountTrace;
+
+    use super::*;
+
+    #[test]
+    fn test_first_divergence() {
+        let sender = Address::repeat_byte(1);
+        let contract = Address::repeat_byte(2);
+        let trace = |value: u64| TxTrace {
+            tx_hash: B256::repeat_byte(3),
+            gas_used: 43_000,
+            accounts: [
+                (
+                    sender,
+                    AccountTrace {
+                        balance: U256::from(1_000),
+                        nonce: 1,
+                        ..Default::default()
+                    },
+                ),
+                (
+                    contract,
+                    AccountTrace {
+                        storage: [(U256::from(1), U256::from(value))].into(),
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into(),
+        };
+        let diff: PrestateDiff = serde_json::from_str(&format!(
+            r#"{{
+                "pre": {{
+                    "{sender}": {{ "balance": "0x7d0" }},
+                    "{contract}": {{ "balance": "0x0", "storage": {{}} }}
+                }},
+                "post": {{
+                    "{sender}": {{ "balance": "0x3e8", "nonce": 1 }},
+                    "{contract}": {{ "storage": {{ "{}": "0x2a" }} }}
+                }}
+            }}"#,
+            B256::from(U256::from(1))
+        ))
+        .unwrap();
+        let diffs = [diff];
+
+        assert_eq!(first_divergence(&[trace(42)], &[43_000], &diffs), None);
+        assert_eq!(
+            first_divergence(&[trace(42)], &[21_000], &diffs).map(|d| d.kind),
+            Some(DivergenceKind::Gas {
+                expected: 21_000,
+                actual: 43_000
+            })
+        );
+        assert_eq!(
+            first_divergence(&[trace(7)], &[43_000], &diffs).map(|d| d.kind),
+            Some(DivergenceKind::Storage {
+                address: contract,
+                slot: U256::from(1),
+                expected: Some(U256::from(42)),
+                actual: U256::from(7)
+            })
+        );
+        assert!(matches!(
+            first_divergence(&[], &[43_000], &diffs).map(|d| d.kind),
+            Some(DivergenceKind::TxCount { .. })
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use alloy_primitives::B256;
+use raiko_lib::{
+    builder::{BlockBuilderStrategy, TaikoStrategy},
+    consts::{ChainSpec, VerifierType},
+    input::{GuestInput, TaikoProverData},
+    protocol_instance::ProtocolInstance,
+    prover::ProverError,
+    utils::HeaderHasher,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interfaces::{RaikoError, RaikoResult},
+    preflight::preflight,
+    provider::BlockDataProvider,
+};
+
+/// The directory, relative to this crate, that the integration tests load fixtures from.
+pub const FIXTURES_DIR: &str = "fixtures";
+/// Environment variable with another directory for the integration tests to load the
+/// fixtures from, e.g. the fixtures captured to reproduce a failing block.
+pub const FIXTURES_DIR_ENV: &str = "RAIKO_FIXTURES_DIR";
+/// File extension of fixtures in the compact binary format.
+pub const COMPACT_EXTENSION: &str = "bin";
+
+/// A self-contained block fixture: the block together with the complete witness needed to
+/// re-execute it, so it can be tested without access to the node it was captured from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fixture {
+    /// The name of the fixture, also used as its file name.
+    pub name: String,
+    /// The input of the block, including the chain spec it was captured with.
+    pub input: GuestInput,
+    /// The public input hash the block is expected to be proven with, only set for Taiko
+    /// blocks.
+    #[serde(default)]
+    pub public_input: Option<B256>,
+    /// The error the block failed with when it was captured, if any.
+    #[serde(default)]
+    pub failure: Option<String>,
+}
+
+impl Fixture {
+    /// Captures the input of the block at `block_number` from the given provider, with
+    /// the prover and graffiti the block is proven for.
+    ///
+    /// For non-Taiko chains the same chain spec is passed as `l1_chain_spec`, Taiko blocks
+    /// also fetch the proposal from the L1 chain. The block isn't executed, see
+    /// [Fixture::record].
+    pub async fn capture<BDP: BlockDataProvider>(
+        name: &str,
+        provider: BDP,
+        block_number: u64,
+        l1_chain_spec: ChainSpec,
+        chain_spec: ChainSpec,
+        prover_data: TaikoProverData,
+    ) -> RaikoResult<Self> {
+        let input = preflight(
+            provider,
+            block_number,
+            l1_chain_spec,
+            chain_spec,
+            prover_data,
+            None,
+        )
+        .await?;
+        Ok(Self::new(name, input))
+    }
+
+    /// Creates the fixture of an input, without executing the block.
+    pub fn new(name: &str, input: GuestInput) -> Self {
+        Self {
+            name: name.to_owned(),
+            input,
+            public_input: None,
+            failure: None,
+        }
+    }
+
+    /// Re-executes the block to record the public input it is expected to be proven with,
+    /// or the error it fails with.
+    pub fn record(&mut self) {
+        match self.execute() {
+            Ok((_, public_input)) => {
+                self.public_input = public_input;
+                self.failure = None;
+            }
+            Err(e) => {
+                self.public_input = None;
+                self.failure = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Loads a fixture from a JSON file, or from a compact file if it has the
+    /// [COMPACT_EXTENSION].
+    pub fn load(path: &Path) -> RaikoResult<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        if path.extension().is_some_and(|ext| ext == COMPACT_EXTENSION) {
+            return bincode::deserialize_from(reader).map_err(|e| RaikoError::Anyhow(e.into()));
+        }
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Loads all fixtures in `dir`, sorted by name. A missing directory has no fixtures.
+    pub fn load_dir(dir: &Path) -> RaikoResult<Vec<(PathBuf, Self)>> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Ok(Vec::new());
+        };
+        let mut fixtures = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let is_fixture = path
+                .extension()
+                .is_some_and(|ext| ext == "json" || ext == COMPACT_EXTENSION);
+            if is_fixture {
+                let fixture = Self::load(&path)?;
+                fixtures.push((path, fixture));
+            }
+        }
+        fixtures.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+        Ok(fixtures)
+    }
+
+    /// Writes the fixture as `<name>.json` into `dir` and returns the path of the file.
+    pub fn save(&self, dir: &Path) -> RaikoResult<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.json", self.name));
+        let file = File::create(&path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(path)
+    }
+
+    /// Writes the fixture as `<name>.bin` into `dir` and returns the path of the file.
+    ///
+    /// The compact format is a fraction of the size of the JSON format, which matters for
+    /// Taiko blocks with their blob data.
+    pub fn save_compact(&self, dir: &Path) -> RaikoResult<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.{COMPACT_EXTENSION}", self.name));
+        let file = File::create(&path)?;
+        bincode::serialize_into(BufWriter::new(file), self)
+            .map_err(|e| RaikoError::Anyhow(e.into()))?;
+        Ok(path)
+    }
+
+    /// Re-executes the block from the witness only and checks that it results in the
+    /// captured block hash and public input.
+    pub fn check(&self) -> RaikoResult<B256> {
+        let (hash, public_input) = self.execute()?;
+        if self.public_input.is_some() && public_input != self.public_input {
+            return Err(RaikoError::Guest(ProverError::GuestError(format!(
+                "Fixture {} resulted in public input {public_input:?}, expected {:?}",
+                self.name, self.public_input
+            ))));
+        }
+        Ok(hash)
+    }
+
+    /// Re-executes the block and returns its hash, and the public input for Taiko blocks.
+    fn execute(&self) -> RaikoResult<(B256, Option<B256>)> {
+        let guest_error =
+            |e: anyhow::Error| RaikoError::Guest(ProverError::GuestError(e.to_string()));
+        let (header, _) = TaikoStrategy::build_from(&self.input).map_err(guest_error)?;
+        let hash = header.hash();
+        if hash != self.input.block_hash_reference {
+            return Err(RaikoError::Guest(ProverError::GuestError(format!(
+                "Fixture {} built block hash {hash}, expected {}",
+                self.name, self.input.block_hash_reference
+            ))));
+        }
+        if !self.input.chain_spec.is_taiko() {
+            return Ok((hash, None));
+        }
+        let public_input = ProtocolInstance::new(&self.input, &header, VerifierType::None)
+            .map_err(guest_error)?
+            .instance_hash();
+        Ok((hash, Some(public_input)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{Fixture, FIXTURES_DIR, FIXTURES_DIR_ENV};
+
+    /// Replays all fixtures, by default the ones in `core/fixtures`.
+    #[test]
+    fn test_fixtures() {
+        let dir = std::env::var_os(FIXTURES_DIR_ENV).map_or_else(
+            || Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES_DIR),
+            PathBuf::from,
+        );
+        for (path, fixture) in Fixture::load_dir(&dir).unwrap() {
+            fixture
+                .check()
+                .unwrap_or_else(|e| panic!("Fixture {path:?} failed: {e}"));
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! Statistics of a [GuestInput], to see what makes the witness of a block large.
+
+use std::fmt;
+
+use alloy_primitives::{Address, B256};
+use raiko_lib::{
+    input::GuestInput,
+    primitives::{
+        keccak::keccak,
+        mpt::{MptNode, MptNodeData, StateAccount},
+    },
+};
+use serde::Serialize;
+
+use crate::interfaces::{RaikoError, RaikoResult};
+
+/// Number of contracts and accounts listed individually.
+const TOP_ENTRIES: usize = 10;
+
+/// The number of nodes of a trie by kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrieStats {
+    /// Resolved nodes, including the leaves.
+    pub nodes: usize,
+    pub leaves: usize,
+    /// Unresolved nodes, which are only part of the witness as their hash.
+    pub digests: usize,
+}
+
+impl TrieStats {
+    pub fn new(trie: &MptNode) -> Self {
+        let mut stats = Self::default();
+        stats.add(trie);
+        stats
+    }
+
+    fn add(&mut self, node: &MptNode) {
+        match node.as_data() {
+            MptNodeData::Null => {}
+            MptNodeData::Branch(children) => {
+                self.nodes += 1;
+                for child in children.iter().flatten() {
+                    self.add(child);
+                }
+            }
+            MptNodeData::Leaf(_, _) => {
+                self.nodes += 1;
+                self.leaves += 1;
+            }
+            MptNodeData::Extension(_, child) => {
+                self.nodes += 1;
+                self.add(child);
+            }
+            MptNodeData::Digest(_) => self.digests += 1,
+        }
+    }
+}
+
+impl std::ops::AddAssign for TrieStats {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.leaves += other.leaves;
+        self.digests += other.digests;
+    }
+}
+
+/// The bytecode of a contract and the witnessed accounts using it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractStats {
+    pub code_hash: B256,
+    pub size: usize,
+    pub addresses: Vec<Address>,
+}
+
+/// The storage witness of an account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageStats {
+    pub address: Address,
+    /// Storage slots accessed by the block.
+    pub slots: usize,
+    pub trie: TrieStats,
+}
+
+/// Statistics of the witness of a block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WitnessStats {
+    pub network: String,
+    pub block_number: u64,
+    /// Serialized size of the complete input.
+    pub size: u64,
+    /// Serialized size of the largest parts of the input.
+    pub sections: Vec<(&'static str, u64)>,
+    pub state_trie: TrieStats,
+    /// Over all storage tries.
+    pub storage_trie: TrieStats,
+    pub storage_slots: usize,
+    /// Accounts with a storage witness, by the number of accessed slots.
+    pub storage: Vec<StorageStats>,
+    pub bytecode_bytes: usize,
+    /// Contracts by their bytecode size.
+    pub contracts: Vec<ContractStats>,
+    pub ancestor_headers: usize,
+    /// The oldest and the newest ancestor header.
+    pub ancestor_range: Option<(u64, u64)>,
+}
+
+impl WitnessStats {
+    pub fn new(input: &GuestInput) -> RaikoResult<Self> {
+        let size = serialized_size(input)?;
+        let sections = vec![
+            ("state trie", serialized_size(&input.parent_state_trie)?),
+            ("storage tries", serialized_size(&input.parent_storage)?),
+            ("bytecode", serialized_size(&input.contracts)?),
+            (
+                "ancestor headers",
+                serialized_size(&input.ancestor_headers)?,
+            ),
+            ("tx list", serialized_size(&input.taiko.tx_data)?),
+        ];
+
+        let mut storage_trie = TrieStats::default();
+        let mut storage: Vec<StorageStats> = input
+            .parent_storage
+            .iter()
+            .map(|(address, (trie, slots))| {
+                let trie = TrieStats::new(trie);
+                storage_trie += trie;
+                StorageStats {
+                    address: *address,
+                    slots: slots.len(),
+                    trie,
+                }
+            })
+            .collect();
+        storage.sort_by(|a, b| b.slots.cmp(&a.slots).then(a.address.cmp(&b.address)));
+        let storage_slots = storage.iter().map(|account| account.slots).sum();
+
+        let mut contracts: Vec<ContractStats> = input
+            .contracts
+            .iter()
+            .map(|code| ContractStats {
+                code_hash: keccak(code).into(),
+                size: code.len(),
+                addresses: Vec::new(),
+            })
+            .collect();
+        // every account executed by the block has a storage witness
+        for address in input.parent_storage.keys() {
+            let account = input
+                .parent_state_trie
+                .get_rlp::<StateAccount>(&keccak(address))
+                .ok()
+                .flatten();
+            let Some(account) = account else {
+                continue;
+            };
+            if let Some(contract) = contracts
+                .iter_mut()
+                .find(|contract| contract.code_hash == account.code_hash)
+            {
+                contract.addresses.push(*address);
+            }
+        }
+        for contract in &mut contracts {
+            contract.addresses.sort();
+        }
+        contracts.sort_by(|a, b| b.size.cmp(&a.size).then(a.code_hash.cmp(&b.code_hash)));
+
+        let ancestor_numbers = input.ancestor_headers.iter().map(|header| header.number);
+        let ancestor_range = ancestor_numbers.clone().min().zip(ancestor_numbers.max());
+
+        Ok(Self {
+            network: input.chain_spec.name.clone(),
+            block_number: input.block_number,
+            size,
+            sections,
+            state_trie: TrieStats::new(&input.parent_state_trie),
+            storage_trie,
+            storage_slots,
+            storage,
+            bytecode_bytes: contracts.iter().map(|contract| contract.size).sum(),
+            contracts,
+            ancestor_headers: input.ancestor_headers.len(),
+            ancestor_range,
+        })
+    }
+}
+
+impl fmt::Display for WitnessStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Block {} on {}", self.block_number, self.network)?;
+        writeln!(f, "  input size:       {} bytes", self.size)?;
+        for (name, size) in &self.sections {
+            writeln!(f, "    {:<18}{size} bytes", format!("{name}:"))?;
+        }
+
+        writeln!(f, "  accounts:         {}", self.state_trie.leaves)?;
+        writeln!(
+            f,
+            "  state trie:       {} nodes, {} digests",
+            self.state_trie.nodes, self.state_trie.digests
+        )?;
+        writeln!(
+            f,
+            "  storage tries:    {} nodes, {} digests, {} leaves",
+            self.storage_trie.nodes, self.storage_trie.digests, self.storage_trie.leaves
+        )?;
+        writeln!(
+            f,
+            "  storage slots:    {} over {} accounts",
+            self.storage_slots,
+            self.storage.len()
+        )?;
+        for account in self.storage.iter().take(TOP_ENTRIES) {
+            writeln!(
+                f,
+                "    {}  {} slots, {} nodes",
+                account.address, account.slots, account.trie.nodes
+            )?;
+        }
+
+        writeln!(
+            f,
+            "  bytecode:         {} bytes in {} contracts",
+            self.bytecode_bytes,
+            self.contracts.len()
+        )?;
+        for contract in self.contracts.iter().take(TOP_ENTRIES) {
+            let addresses: Vec<String> =
+                contract.addresses.iter().map(Address::to_string).collect();
+            writeln!(
+                f,
+                "    {}  {} bytes  {}",
+                contract.code_hash,
+                contract.size,
+                addresses.join(", ")
+            )?;
+        }
+
+        write!(f, "  ancestor headers: {}", self.ancestor_headers)?;
+        if let Some((oldest, newest)) = self.ancestor_range {
+            write!(f, " ({oldest} to {newest})")?;
+        }
+        writeln!(f)
+    }
+}
+
+fn serialized_size<T: Serialize>(value: &T) -> RaikoResult<u64> {
+    bincode::serialized_size(value)
+        .map_err(|e| RaikoError::Conversion(format!("Could not serialize input: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{Bytes, U256};
+
+    use super::*;
+
+    #[test]
+    fn test_witness_stats() {
+        let code = Bytes::from(vec![0x60, 0x00, 0x60, 0x00, 0xf3]);
+        let code_hash = B256::from(keccak(&code));
+        let contract = Address::repeat_byte(1);
+        let eoa = Address::repeat_byte(2);
+
+        let mut input = GuestInput::default();
+        let account = StateAccount {
+            code_hash,
+            ..Default::default()
+        };
+        input
+            .parent_state_trie
+            .insert_rlp(&keccak(contract), account)
+            .unwrap();
+        input
+            .parent_state_trie
+            .insert_rlp(&keccak(eoa), StateAccount::default())
+            .unwrap();
+        let mut storage_trie = MptNode::default();
+        storage_trie
+            .insert_rlp(&keccak(U256::from(1).to_be_bytes::<32>()), U256::from(7))
+            .unwrap();
+        input
+            .parent_storage
+            .insert(contract, (storage_trie, vec![U256::from(1), U256::from(2)]));
+        input
+            .parent_storage
+            .insert(eoa, (MptNode::default(), Vec::new()));
+        input.contracts.push(code);
+
+        let stats = WitnessStats::new(&input).unwrap();
+        assert_eq!(stats.state_trie.leaves, 2);
+        assert_eq!(stats.storage_trie.leaves, 1);
+        assert_eq!(stats.storage_slots, 2);
+        assert_eq!(stats.storage[0].address, contract);
+        assert_eq!(stats.bytecode_bytes, 5);
+        assert_eq!(stats.contracts[0].addresses, vec![contract]);
+        assert!(stats.size > stats.sections.iter().map(|(_, size)| size).sum::<u64>());
+    }
+}
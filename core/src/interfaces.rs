@@ -1,6 +1,6 @@
 use std::{collections::HashMap, path::Path, str::FromStr};
 
-use alloy_primitives::{Address, B256};
+use alloy_primitives::{Address, B256, U256};
 use clap::{Args, ValueEnum};
 use raiko_lib::{
     input::{GuestInput, GuestOutput},
@@ -11,6 +11,8 @@ use serde_json::Value;
 use serde_with::{serde_as, DisplayFromStr};
 use utoipa::ToSchema;
 
+#[cfg(feature = "sandbox")]
+use crate::prover::SandboxProver;
 use crate::{merge, prover::NativeProver};
 
 #[derive(Debug, thiserror::Error, ToSchema)]
@@ -95,6 +97,10 @@ pub enum ProofType {
     ///
     /// Uses the RISC0 prover to build the block.
     Risc0,
+    /// # Sandbox
+    ///
+    /// Returns a fake proof after a configurable delay, for integration tests.
+    Sandbox,
 }
 
 impl std::fmt::Display for ProofType {
@@ -104,6 +110,7 @@ impl std::fmt::Display for ProofType {
             ProofType::Sp1 => "sp1",
             ProofType::Sgx => "sgx",
             ProofType::Risc0 => "risc0",
+            ProofType::Sandbox => "sandbox",
         })
     }
 }
@@ -117,6 +124,7 @@ impl FromStr for ProofType {
             "sp1" => Ok(ProofType::Sp1),
             "sgx" => Ok(ProofType::Sgx),
             "risc0" => Ok(ProofType::Risc0),
+            "sandbox" => Ok(ProofType::Sandbox),
             _ => Err(RaikoError::InvalidProofType(s.to_string())),
         }
     }
@@ -158,6 +166,14 @@ impl ProofType {
 
                 Err(RaikoError::FeatureNotSupportedError(self.clone()))
             }
+            ProofType::Sandbox => {
+                #[cfg(feature = "sandbox")]
+                return SandboxProver::run(input, output, config)
+                    .await
+                    .map_err(|e| e.into());
+
+                Err(RaikoError::FeatureNotSupportedError(self.clone()))
+            }
         }
     }
 }
@@ -179,11 +195,34 @@ pub struct ProofRequest {
     pub prover: Address,
     /// The proof type.
     pub proof_type: ProofType,
+    /// The fee offered for the proof.
+    #[serde(default)]
+    pub fee: Option<FeeTerms>,
     #[serde(flatten)]
     /// Additional prover params.
     pub prover_args: HashMap<String, Value>,
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+/// The fee offered for a proof.
+pub struct FeeTerms {
+    #[schema(value_type = String)]
+    /// The token the fee is paid in, the zero address for ether.
+    pub token: Address,
+    #[schema(value_type = String)]
+    /// The fee in the smallest unit of the token.
+    pub amount: U256,
+}
+
+impl FromStr for FeeTerms {
+    type Err = RaikoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+            .map_err(|e| RaikoError::InvalidRequestConfig(format!("Invalid fee: {e}")))
+    }
+}
+
 #[derive(Default, Clone, Serialize, Deserialize, Debug, ToSchema, Args)]
 #[serde(default)]
 /// A partial proof request config.
@@ -206,6 +245,10 @@ pub struct ProofRequestOpt {
     #[arg(long, require_equals = true)]
     /// The proof type.
     pub proof_type: Option<String>,
+    #[arg(skip)]
+    /// The fee offered for the proof, e.g. `{"token":"0x0...0","amount":"0x1"}`. Only taken
+    /// from the request, never from the defaults of the host.
+    pub fee: Option<FeeTerms>,
     #[command(flatten)]
     #[serde(flatten)]
     /// Any additional prover params in JSON format.
@@ -222,6 +265,8 @@ pub struct ProverSpecificOpts {
     pub sp1: Option<Value>,
     /// RISC0 prover specific options.
     pub risc0: Option<Value>,
+    /// Sandbox prover specific options.
+    pub sandbox: Option<Value>,
 }
 
 impl<S: ::std::hash::BuildHasher + ::std::default::Default> From<ProverSpecificOpts>
@@ -233,6 +278,7 @@ impl<S: ::std::hash::BuildHasher + ::std::default::Default> From<ProverSpecificO
             ("sgx", value.sgx.clone()),
             ("sp1", value.sp1.clone()),
             ("risc0", value.risc0.clone()),
+            ("sandbox", value.sandbox.clone()),
         ]
         .into_iter()
         .filter_map(|(name, value)| value.map(|v| (name.to_string(), v)))
@@ -296,6 +342,7 @@ impl TryFrom<ProofRequestOpt> for ProofRequest {
                 ))?
                 .parse()
                 .map_err(|_| RaikoError::InvalidRequestConfig("Invalid proof_type".to_string()))?,
+            fee: value.fee,
             prover_args: value.prover_args.into(),
         })
     }
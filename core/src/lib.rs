@@ -3,9 +3,9 @@ use std::collections::HashMap;
 use alloy_primitives::{Address, FixedBytes};
 use alloy_rpc_types::EIP1186AccountProofResponse;
 use raiko_lib::{
-    builder::{BlockBuilderStrategy, TaikoStrategy},
+    builder::{BlockBuilderStrategy, BuiltState, TaikoStrategy},
     consts::{ChainSpec, VerifierType},
-    input::{GuestInput, GuestOutput, TaikoProverData},
+    input::{GuestInput, GuestOutput, InputLimits, TaikoProverData},
     protocol_instance::ProtocolInstance,
     prover::Proof,
     utils::HeaderHasher,
@@ -13,6 +13,9 @@ use raiko_lib::{
 use serde_json::Value;
 use tracing::{error, info, warn};
 
+pub mod divergence;
+pub mod fixture;
+pub mod inspect;
 pub mod interfaces;
 pub mod preflight;
 pub mod prover;
@@ -21,7 +24,7 @@ pub mod provider;
 use crate::{
     interfaces::{ProofRequest, RaikoError, RaikoResult},
     preflight::preflight,
-    provider::BlockDataProvider,
+    provider::{db::StateCache, BlockDataProvider},
 };
 
 pub type MerkleProof = HashMap<Address, EIP1186AccountProofResponse>;
@@ -30,6 +33,8 @@ pub struct Raiko {
     l1_chain_spec: ChainSpec,
     taiko_chain_spec: ChainSpec,
     request: ProofRequest,
+    state_cache: Option<StateCache>,
+    input_limits: InputLimits,
 }
 
 impl Raiko {
@@ -42,9 +47,25 @@ impl Raiko {
             l1_chain_spec,
             taiko_chain_spec,
             request,
+            state_cache: None,
+            input_limits: InputLimits::default(),
         }
     }
 
+    /// Checks the decoded parts of the input against `input_limits` when building the
+    /// block, instead of the limits of the guest.
+    pub fn with_input_limits(mut self, input_limits: InputLimits) -> Self {
+        self.input_limits = input_limits;
+        self
+    }
+
+    /// Reuses the post-state of the previously preflighted block when generating the input
+    /// for its direct successor.
+    pub fn with_state_cache(mut self, state_cache: StateCache) -> Self {
+        self.state_cache = Some(state_cache);
+        self
+    }
+
     pub async fn generate_input<BDP: BlockDataProvider>(
         &self,
         provider: BDP,
@@ -58,18 +79,28 @@ impl Raiko {
                 graffiti: self.request.graffiti,
                 prover: self.request.prover,
             },
+            self.state_cache.as_ref(),
         )
         .await
         .map_err(Into::<RaikoError>::into)
     }
 
     pub fn get_output(&self, input: &GuestInput) -> RaikoResult<GuestOutput> {
-        match TaikoStrategy::build_from(input) {
-            Ok((header, _mpt_node)) => {
+        self.get_output_with_state(input).map(|(output, _)| output)
+    }
+
+    /// Builds the block like [Raiko::get_output] and also returns the resulting state.
+    pub fn get_output_with_state(
+        &self,
+        input: &GuestInput,
+    ) -> RaikoResult<(GuestOutput, BuiltState)> {
+        match TaikoStrategy::build_state_with_limits(input, self.input_limits) {
+            Ok(state) => {
+                let header = &state.header;
                 info!("Verifying final state using provider data ...");
                 info!("Final block hash derived successfully. {}", header.hash());
                 info!("Final block header derived successfully. {header:?}");
-                let pi = ProtocolInstance::new(input, &header, VerifierType::None)?.instance_hash();
+                let pi = ProtocolInstance::new(input, header, VerifierType::None)?.instance_hash();
 
                 // Check against the expected value of all fields for easy debugability
                 let exp = &input.block_header_reference;
@@ -119,15 +150,25 @@ impl Raiko {
                 );
 
                 // Make sure the blockhash from the node matches the one from the builder
-                assert_eq!(
-                    Into::<FixedBytes<32>>::into(header.hash().0),
-                    input.block_hash_reference,
-                    "block hash unexpected for block {}",
-                    input.block_number,
-                );
-                let output = GuestOutput::Success { header, hash: pi };
+                let block_hash = Into::<FixedBytes<32>>::into(header.hash().0);
+                if block_hash != input.block_hash_reference {
+                    error!(
+                        "block hash unexpected for block {}: expected {}, got {block_hash}",
+                        input.block_number, input.block_hash_reference
+                    );
+                    return Err(RaikoError::Guest(
+                        raiko_lib::prover::ProverError::GuestError(format!(
+                            "Block hash mismatch for block {}",
+                            input.block_number
+                        )),
+                    ));
+                }
+                let output = GuestOutput::Success {
+                    header: header.clone(),
+                    hash: pi,
+                };
 
-                Ok(output)
+                Ok((output, state))
             }
             Err(e) => {
                 warn!("Proving bad block construction!");
@@ -268,6 +309,7 @@ mod tests {
             prover: Address::ZERO,
             l1_network,
             proof_type,
+            fee: None,
             prover_args: test_proof_params(),
         };
         prove_block(l1_chain_spec, taiko_chain_spec, proof_request).await;
@@ -294,6 +336,7 @@ mod tests {
                 prover: Address::ZERO,
                 l1_network,
                 proof_type,
+                fee: None,
                 prover_args: test_proof_params(),
             };
             prove_block(l1_chain_spec, taiko_chain_spec, proof_request).await;
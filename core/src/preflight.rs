@@ -6,30 +6,31 @@ use alloy_provider::{Provider, ReqwestProvider};
 use alloy_rpc_types::{Block, BlockTransactions, Filter, Transaction as AlloyRpcTransaction};
 use alloy_sol_types::{SolCall, SolEvent};
 use anyhow::{anyhow, bail, Result};
-use c_kzg::{Blob, KzgCommitment};
 use raiko_lib::{
     builder::{
         prepare::TaikoHeaderPrepStrategy, BlockBuilder, OptimisticDatabase, TkoTxExecStrategy,
     },
     consts::ChainSpec,
     input::{
-        decode_anchor, proposeBlockCall, BlockProposed, GuestInput, TaikoGuestInput,
+        decode_anchor, proposeBlockCall, BlockProposed, GuestInput, InputLimits, TaikoGuestInput,
         TaikoProverData,
     },
-    primitives::{
-        eip4844::{kzg_to_versioned_hash, MAINNET_KZG_TRUSTED_SETUP},
-        mpt::proofs_to_tries,
-    },
+    primitives::{compact::CompactTxEip1559, mpt::proofs_to_tries},
     utils::{generate_transactions, to_header, zlib_compress_data},
     Measurement,
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, sync::Arc};
+use std::collections::HashSet;
 use tracing::{info, warn};
 
 use crate::{
     interfaces::{RaikoError, RaikoResult},
-    provider::{db::ProviderDb, rpc::RpcBlockDataProvider, BlockDataProvider},
+    provider::{
+        beacon::BeaconClient,
+        db::{ProviderDb, StateCache, StateTries},
+        rpc::RpcBlockDataProvider,
+        BlockDataProvider,
+    },
 };
 
 pub async fn preflight<BDP: BlockDataProvider>(
@@ -38,6 +39,7 @@ pub async fn preflight<BDP: BlockDataProvider>(
     l1_chain_spec: ChainSpec,
     taiko_chain_spec: ChainSpec,
     prover_data: TaikoProverData,
+    state_cache: Option<&StateCache>,
 ) -> RaikoResult<GuestInput> {
     let measurement = Measurement::start("Fetching block data...", false);
 
@@ -138,17 +140,27 @@ pub async fn preflight<BDP: BlockDataProvider>(
         taiko: taiko_guest_input,
     };
 
+    // Start from the post-state of the parent block if it was preflighted right before
+    let parent_block_number = parent_block.header.number.ok_or_else(|| {
+        RaikoError::Preflight("No parent block number for the requested block".to_owned())
+    })?;
+    let network = taiko_chain_spec.name.clone();
+    let parent_state = state_cache.and_then(|state_cache| {
+        state_cache.get_parent(&network, parent_block_number, parent_block.header.hash?)
+    });
+    if let Some(parent_state) = &parent_state {
+        info!(
+            "Reusing the post-state of block {parent_block_number} ({} accounts)",
+            parent_state.db.accounts_len()
+        );
+    }
+
     // Create the block builder, run the transactions and extract the DB
     let provider_db = ProviderDb::new(
         provider,
         taiko_chain_spec,
-        if let Some(parent_block_number) = parent_block.header.number {
-            parent_block_number
-        } else {
-            return Err(RaikoError::Preflight(
-                "No parent block number for the requested block".to_owned(),
-            ));
-        },
+        parent_block_number,
+        parent_state,
     )
     .await?;
 
@@ -203,6 +215,24 @@ pub async fn preflight<BDP: BlockDataProvider>(
     }
     measurement.stop();
 
+    // Keep the state after this block around for the preflight of the next one. The values
+    // only steer which data ends up in the witness. The proofs of the next block are taken
+    // from the tries of this witness with the changes of the block applied, which are only
+    // kept if they result in the state root of the block.
+    if let Some(state_cache) = state_cache {
+        let mut post_state = provider_db.post_state(hash, input.block_header_reference.clone());
+        post_state.tries = StateTries::apply(
+            &provider_db.current_db,
+            state_trie.clone(),
+            storage.clone(),
+            input.block_header_reference.state_root,
+        );
+        if post_state.tries.is_none() {
+            warn!("The witness of block {block_number} doesn't result in its state root");
+        }
+        state_cache.insert(&network, post_state);
+    }
+
     // Add the collected data to the input
     Ok(GuestInput {
         parent_state_trie: state_trie,
@@ -291,13 +321,18 @@ async fn prepare_taiko_chain_input(
         (proposal_call.txList.as_ref().to_owned(), None)
     };
 
+    let anchor_tx = CompactTxEip1559::try_from(&anchor_tx)
+        .map_err(|e| RaikoError::Preflight(format!("Invalid anchor tx: {e}")))?;
+
     // Create the transactions from the proposed tx list
     let transactions = generate_transactions(
         taiko_chain_spec,
         proposal_event.meta.blobUsed,
         &tx_data,
-        Some(anchor_tx.clone()),
-    );
+        Some(&anchor_tx),
+        &InputLimits::default(),
+    )
+    .map_err(|e| RaikoError::Preflight(format!("Invalid tx list: {e}")))?;
     // Do a sanity check using the transactions returned by the node
     assert!(
         transactions.len() >= block.transactions.len(),
@@ -308,7 +343,7 @@ async fn prepare_taiko_chain_input(
     Ok(TaikoGuestInput {
         l1_header: to_header(&l1_state_block.header),
         tx_data,
-        anchor_tx: serde_json::to_string(&anchor_tx).map_err(RaikoError::Serde)?,
+        anchor_tx,
         tx_blob_hash,
         block_proposed: proposal_event,
         prover_data,
@@ -342,17 +377,6 @@ fn blob_to_bytes(blob_str: &str) -> Vec<u8> {
     }
 }
 
-fn calc_blob_versioned_hash(blob_str: &str) -> [u8; 32] {
-    let blob_bytes: Vec<u8> = hex::decode(blob_str.to_lowercase().trim_start_matches("0x"))
-        .expect("Could not decode blob");
-    let kzg_settings = Arc::clone(&*MAINNET_KZG_TRUSTED_SETUP);
-    let blob = Blob::from_bytes(&blob_bytes).expect("Could not create blob");
-    let kzg_commit = KzgCommitment::blob_to_kzg_commitment(&blob, &kzg_settings)
-        .expect("Could not create kzg commitment from blob");
-    let version_hash: [u8; 32] = kzg_to_versioned_hash(&kzg_commit).0;
-    version_hash
-}
-
 async fn get_blob_data(
     beacon_rpc_url: &str,
     block_id: u64,
@@ -370,60 +394,11 @@ async fn get_blob_data_beacon(
     block_id: u64,
     blob_hash: FixedBytes<32>,
 ) -> Result<Vec<u8>> {
-    // Blob data from the beacon chain
-    // type Sidecar struct {
-    // Index                    string                   `json:"index"`
-    // Blob                     string                   `json:"blob"`
-    // SignedBeaconBlockHeader  *SignedBeaconBlockHeader `json:"signed_block_header"`
-    // KzgCommitment            string                   `json:"kzg_commitment"`
-    // KzgProof                 string                   `json:"kzg_proof"`
-    // CommitmentInclusionProof []string
-    // `json:"kzg_commitment_inclusion_proof"` }
-    #[derive(Clone, Debug, Deserialize, Serialize)]
-    struct GetBlobData {
-        pub index: String,
-        pub blob: String,
-        // pub signed_block_header: SignedBeaconBlockHeader, // ignore for now
-        pub kzg_commitment: String,
-        pub kzg_proof: String,
-        //pub kzg_commitment_inclusion_proof: Vec<String>,
-    }
-
-    #[derive(Clone, Debug, Deserialize, Serialize)]
-    struct GetBlobsResponse {
-        pub data: Vec<GetBlobData>,
-    }
-
-    let url = format!(
-        "{}/eth/v1/beacon/blob_sidecars/{block_id}",
-        beacon_rpc_url.trim_end_matches('/'),
-    );
-    info!("Retrieve blob from {url}.");
-    let response = reqwest::get(url.clone()).await?;
-    if response.status().is_success() {
-        let blobs: GetBlobsResponse = response.json().await?;
-        assert!(!blobs.data.is_empty(), "blob data not available anymore");
-        // Get the blob data for the blob storing the tx list
-        let tx_blob = blobs
-            .data
-            .iter()
-            .find(|blob| {
-                // calculate from plain blob
-                blob_hash == calc_blob_versioned_hash(&blob.blob)
-            })
-            .cloned();
-        assert!(tx_blob.is_some());
-        Ok(blob_to_bytes(&tx_blob.unwrap().blob))
-    } else {
-        warn!(
-            "Request {url} failed with status code: {}",
-            response.status()
-        );
-        Err(anyhow::anyhow!(
-            "Request failed with status code: {}",
-            response.status()
-        ))
-    }
+    // The sidecars are verified against their KZG commitments, so only the blob matching the
+    // versioned hash committed to on L1 can be returned.
+    Ok(BeaconClient::new(beacon_rpc_url)
+        .get_blob(block_id, blob_hash)
+        .await?)
 }
 
 async fn get_blob_data_blobscan(
@@ -612,10 +587,16 @@ fn from_block_tx(tx: &AlloyRpcTransaction) -> RaikoResult<TxEnvelope> {
 
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+
+    use c_kzg::KzgCommitment;
     use ethers_core::types::Transaction;
     use raiko_lib::{
         consts::{Network, SupportedChainSpecs},
-        primitives::{eip4844::parse_kzg_trusted_setup, kzg::KzgSettings},
+        primitives::{
+            eip4844::{kzg_to_versioned_hash, parse_kzg_trusted_setup, MAINNET_KZG_TRUSTED_SETUP},
+            kzg::KzgSettings,
+        },
         utils::decode_transactions,
     };
 
@@ -1,11 +1,16 @@
+use std::time::Duration;
+
+use alloy_primitives::B256;
 use raiko_lib::{
     consts::VerifierType,
     input::{GuestInput, GuestOutput},
+    primitives::keccak::keccak,
     protocol_instance::ProtocolInstance,
     prover::{to_proof, Proof, Prover, ProverError, ProverResult},
+    utils::HeaderHasher,
 };
 use serde::{Deserialize, Serialize};
-use tracing::trace;
+use tracing::{info, trace};
 
 pub struct NativeProver;
 
@@ -34,3 +39,125 @@ impl Prover for NativeProver {
         }))
     }
 }
+
+/// The options of the sandbox prover, read from the `sandbox` prover args.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxParam {
+    /// Time every proof takes, in milliseconds.
+    pub delay_ms: u64,
+    /// Maximum time added to the delay, in milliseconds. The added time depends on the
+    /// block, so a block always takes equally long.
+    pub jitter_ms: u64,
+    /// Share of the blocks whose proof fails, between 0 and 1.
+    pub failure_rate: f64,
+    /// Changes which blocks fail and how long they take.
+    pub seed: u64,
+}
+
+impl SandboxParam {
+    /// Returns a number in `[0, 1)` derived from the block and the seed.
+    fn sample(&self, block_hash: B256, domain: &str) -> f64 {
+        let digest = keccak([domain.as_bytes(), &self.seed.to_be_bytes(), &block_hash.0].concat());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns how long the proof of the block takes.
+    pub fn delay(&self, block_hash: B256) -> Duration {
+        let jitter = (self.jitter_ms as f64 * self.sample(block_hash, "jitter")) as u64;
+        Duration::from_millis(self.delay_ms + jitter)
+    }
+
+    /// Returns whether the proof of the block fails.
+    pub fn fails(&self, block_hash: B256) -> bool {
+        self.sample(block_hash, "failure") < self.failure_rate
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SandboxResponse {
+    pub output: GuestOutput,
+    /// A fake proof, derived from the block and its public input.
+    pub proof: String,
+}
+
+/// A prover that doesn't prove anything, but answers like a real backend would: after a
+/// delay, with a proof that is the same for every run, or with an error.
+///
+/// Meant for testing the integration of other services with the host, without any
+/// proving hardware. The proofs don't verify anywhere.
+pub struct SandboxProver;
+
+impl Prover for SandboxProver {
+    async fn run(
+        _input: GuestInput,
+        output: &GuestOutput,
+        config: &serde_json::Value,
+    ) -> ProverResult<Proof> {
+        let param = match config.get("sandbox") {
+            Some(param) => SandboxParam::deserialize(param)
+                .map_err(|e| ProverError::GuestError(format!("Invalid sandbox params: {e}")))?,
+            None => SandboxParam::default(),
+        };
+        let GuestOutput::Success { header, hash } = output else {
+            return Err(ProverError::GuestError("Unexpected output".to_owned()));
+        };
+        let block_hash = header.hash();
+
+        let delay = param.delay(block_hash);
+        info!("Sandbox proof of block {block_hash} takes {delay:?}");
+        tokio::time::sleep(delay).await;
+        if param.fails(block_hash) {
+            return Err(ProverError::GuestError(format!(
+                "Simulated failure of the sandbox proof of block {block_hash}"
+            )));
+        }
+
+        let proof = keccak([b"sandbox".as_slice(), &block_hash.0, &hash.0].concat());
+        to_proof(Ok(SandboxResponse {
+            output: output.clone(),
+            proof: format!("0x{}", alloy_primitives::hex::encode(proof)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_param() {
+        let param = SandboxParam {
+            delay_ms: 1000,
+            jitter_ms: 500,
+            failure_rate: 0.25,
+            seed: 7,
+        };
+        let blocks: Vec<B256> = (0..1000u64)
+            .map(|i| B256::from(keccak(i.to_be_bytes())))
+            .collect();
+
+        for block in &blocks {
+            let delay = param.delay(*block);
+            assert!(delay >= Duration::from_millis(1000) && delay < Duration::from_millis(1500));
+            assert_eq!(delay, param.delay(*block));
+            assert_eq!(param.fails(*block), param.fails(*block));
+        }
+        let failed = blocks.iter().filter(|block| param.fails(**block)).count();
+        assert!((200..300).contains(&failed), "{failed} failures");
+
+        let reseeded = SandboxParam {
+            seed: 8,
+            ..param.clone()
+        };
+        assert!(blocks
+            .iter()
+            .any(|block| param.fails(*block) != reseeded.fails(*block)));
+
+        let never = SandboxParam::default();
+        assert!(blocks.iter().all(|block| !never.fails(*block)));
+        assert_eq!(never.delay(blocks[0]), Duration::ZERO);
+    }
+}
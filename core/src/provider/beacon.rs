@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use alloy_primitives::{hex, B256};
+use c_kzg::{Blob, Bytes48, KzgCommitment, KzgProof};
+use raiko_lib::primitives::eip4844::{kzg_to_versioned_hash, MAINNET_KZG_TRUSTED_SETUP};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::interfaces::{RaikoError, RaikoResult};
+
+/// Depth of the Merkle proof of a KZG commitment in the beacon block body.
+const KZG_COMMITMENT_INCLUSION_PROOF_DEPTH: usize = 17;
+
+/// Index of the first KZG commitment within the beacon block body subtree of depth
+/// [KZG_COMMITMENT_INCLUSION_PROOF_DEPTH].
+const KZG_COMMITMENT_SUBTREE_INDEX: u64 = 90112;
+
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BeaconBlockHeader {
+    #[serde_as(as = "DisplayFromStr")]
+    pub slot: u64,
+    #[serde_as(as = "DisplayFromStr")]
+    pub proposer_index: u64,
+    pub parent_root: B256,
+    pub state_root: B256,
+    pub body_root: B256,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SignedBeaconBlockHeader {
+    pub message: BeaconBlockHeader,
+    pub signature: String,
+}
+
+/// A blob sidecar as returned by `/eth/v1/beacon/blob_sidecars/{block_id}`.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BlobSidecar {
+    #[serde_as(as = "DisplayFromStr")]
+    pub index: u64,
+    pub blob: String,
+    pub kzg_commitment: String,
+    pub kzg_proof: String,
+    pub signed_block_header: SignedBeaconBlockHeader,
+    pub kzg_commitment_inclusion_proof: Vec<B256>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct BlobSidecarsResponse {
+    data: Vec<BlobSidecar>,
+}
+
+impl BlobSidecar {
+    /// Returns the versioned hash of the blob, derived from its KZG commitment.
+    pub fn versioned_hash(&self) -> RaikoResult<B256> {
+        let commitment = KzgCommitment::from_bytes(&decode_hex(&self.kzg_commitment)?)
+            .map_err(|e| RaikoError::Conversion(format!("Invalid KZG commitment: {e:?}")))?;
+        Ok(kzg_to_versioned_hash(&commitment))
+    }
+
+    /// Returns the raw blob data.
+    pub fn blob_bytes(&self) -> RaikoResult<Vec<u8>> {
+        decode_hex(&self.blob)
+    }
+
+    /// Verifies that the sidecar is consistent with itself and belongs to `slot`: the blob
+    /// matches the KZG commitment and the commitment is included in the block body.
+    pub fn verify(&self, slot: u64) -> RaikoResult<()> {
+        let header = &self.signed_block_header.message;
+        if header.slot != slot {
+            return Err(RaikoError::RPC(format!(
+                "Blob sidecar {} is for slot {}, expected {slot}",
+                self.index, header.slot
+            )));
+        }
+
+        let blob = Blob::from_bytes(&self.blob_bytes()?)
+            .map_err(|e| RaikoError::Conversion(format!("Invalid blob: {e:?}")))?;
+        let commitment_bytes = decode_hex(&self.kzg_commitment)?;
+        let commitment = Bytes48::from_bytes(&commitment_bytes)
+            .map_err(|e| RaikoError::Conversion(format!("Invalid KZG commitment: {e:?}")))?;
+        let proof = Bytes48::from_bytes(&decode_hex(&self.kzg_proof)?)
+            .map_err(|e| RaikoError::Conversion(format!("Invalid KZG proof: {e:?}")))?;
+        let kzg_settings = Arc::clone(&*MAINNET_KZG_TRUSTED_SETUP);
+        let valid = KzgProof::verify_blob_kzg_proof(&blob, &commitment, &proof, &kzg_settings)
+            .map_err(|e| RaikoError::Conversion(format!("Could not verify KZG proof: {e:?}")))?;
+        if !valid {
+            return Err(RaikoError::RPC(format!(
+                "Blob sidecar {} does not match its KZG commitment",
+                self.index
+            )));
+        }
+
+        if !self.verify_inclusion_proof(&commitment_bytes) {
+            return Err(RaikoError::RPC(format!(
+                "Blob sidecar {} has an invalid commitment inclusion proof",
+                self.index
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the Merkle proof of the KZG commitment against the block body root.
+    fn verify_inclusion_proof(&self, commitment: &[u8]) -> bool {
+        if self.kzg_commitment_inclusion_proof.len() != KZG_COMMITMENT_INCLUSION_PROOF_DEPTH {
+            return false;
+        }
+
+        // the hash tree root of the 48 byte commitment is the root of its two chunks
+        let mut chunks = [0u8; 64];
+        chunks[..commitment.len()].copy_from_slice(commitment);
+        let mut value: [u8; 32] = Sha256::digest(chunks).into();
+
+        let index = KZG_COMMITMENT_SUBTREE_INDEX + self.index;
+        for (i, sibling) in self.kzg_commitment_inclusion_proof.iter().enumerate() {
+            let mut hasher = Sha256::new();
+            if (index >> i) & 1 == 1 {
+                hasher.update(sibling);
+                hasher.update(value);
+            } else {
+                hasher.update(value);
+                hasher.update(sibling);
+            }
+            value = hasher.finalize().into();
+        }
+
+        value == self.signed_block_header.message.body_root.0
+    }
+}
+
+/// A client for the beacon node API to fetch blob sidecars.
+pub struct BeaconClient {
+    url: String,
+}
+
+impl BeaconClient {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// Fetches all blob sidecars of the block at `slot` and verifies them.
+    pub async fn get_blob_sidecars(&self, slot: u64) -> RaikoResult<Vec<BlobSidecar>> {
+        let url = format!("{}/eth/v1/beacon/blob_sidecars/{slot}", self.url);
+        info!("Retrieve blob from {url}.");
+        let response = reqwest::get(&url)
+            .await
+            .map_err(|e| RaikoError::RPC(format!("Request {url} failed: {e}")))?;
+        if !response.status().is_success() {
+            warn!(
+                "Request {url} failed with status code: {}",
+                response.status()
+            );
+            return Err(RaikoError::RPC(format!(
+                "Request {url} failed with status code: {}",
+                response.status()
+            )));
+        }
+
+        let sidecars: BlobSidecarsResponse = response
+            .json()
+            .await
+            .map_err(|e| RaikoError::RPC(format!("Invalid blob sidecars response: {e}")))?;
+        for sidecar in &sidecars.data {
+            sidecar.verify(slot)?;
+        }
+        Ok(sidecars.data)
+    }
+
+    /// Fetches the blob with the given versioned hash from the block at `slot`.
+    pub async fn get_blob(&self, slot: u64, versioned_hash: B256) -> RaikoResult<Vec<u8>> {
+        let sidecars = self.get_blob_sidecars(slot).await?;
+        if sidecars.is_empty() {
+            return Err(RaikoError::RPC(format!(
+                "No blob data available for slot {slot} anymore"
+            )));
+        }
+        for sidecar in sidecars {
+            if sidecar.versioned_hash()? == versioned_hash {
+                return sidecar.blob_bytes();
+            }
+        }
+        Err(RaikoError::RPC(format!(
+            "Blob {versioned_hash} not found in slot {slot}"
+        )))
+    }
+}
+
+fn decode_hex(data: &str) -> RaikoResult<Vec<u8>> {
+    hex::decode(data.trim_start_matches("0x"))
+        .map_err(|e| RaikoError::Conversion(format!("Invalid hex data: {e}")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn empty_blob_sidecar(slot: u64) -> BlobSidecar {
+        // the commitment and proof of the zero blob are the point at infinity
+        let infinity = format!("0xc0{}", "00".repeat(47));
+        BlobSidecar {
+            index: 0,
+            blob: format!("0x{}", "00".repeat(131072)),
+            kzg_commitment: infinity.clone(),
+            kzg_proof: infinity,
+            signed_block_header: SignedBeaconBlockHeader {
+                message: BeaconBlockHeader {
+                    slot,
+                    proposer_index: 0,
+                    parent_root: B256::ZERO,
+                    state_root: B256::ZERO,
+                    body_root: B256::ZERO,
+                },
+                signature: String::new(),
+            },
+            kzg_commitment_inclusion_proof: vec![B256::ZERO; KZG_COMMITMENT_INCLUSION_PROOF_DEPTH],
+        }
+    }
+
+    #[test]
+    fn test_sidecar_versioned_hash() {
+        let sidecar = empty_blob_sidecar(1);
+        assert_eq!(
+            sidecar.versioned_hash().unwrap().to_string(),
+            "0x010657f37554c781402a22917dee2f75def7ab966d7b770905398eba3c444014"
+        );
+    }
+
+    #[test]
+    fn test_sidecar_verification() {
+        let sidecar = empty_blob_sidecar(1);
+        // wrong slot
+        assert!(sidecar.verify(2).is_err());
+        // the blob matches the commitment, but the commitment is not in the block body
+        assert!(sidecar.verify(1).is_err());
+    }
+}
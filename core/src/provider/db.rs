@@ -11,15 +11,25 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
-use std::{collections::HashSet, mem::take};
+use std::{
+    collections::HashSet,
+    mem::take,
+    sync::{Arc, Mutex},
+};
 
 use alloy_consensus::Header as AlloyConsensusHeader;
-use alloy_primitives::Bytes;
+use alloy_primitives::{Bytes, U64};
+use alloy_rpc_types::{EIP1186AccountProofResponse, EIP1186StorageProof};
 use raiko_lib::{
-    builder::OptimisticDatabase,
+    builder::{apply_state_changes, OptimisticDatabase},
     consts::ChainSpec,
-    mem_db::MemDb,
-    primitives::{Address, B256, U256},
+    input::StorageEntry,
+    mem_db::{AccountState, DbAccount, MemDb},
+    primitives::{
+        keccak::{keccak, KeccakCache},
+        mpt::{MptNode, StateAccount, EMPTY_ROOT},
+        Address, B256, U256,
+    },
     utils::to_header,
 };
 use revm::{
@@ -27,6 +37,7 @@ use revm::{
     Database, DatabaseCommit,
 };
 use tokio::runtime::Handle;
+use tracing::info;
 
 use crate::{
     interfaces::{RaikoError, RaikoResult},
@@ -34,6 +45,143 @@ use crate::{
     MerkleProof,
 };
 
+/// The number of most recent block hashes that are accessible to the EVM.
+const BLOCK_HASH_HISTORY: u64 = 256;
+
+/// The state left behind by a block, as far as it was read or written during its preflight.
+///
+/// The preflight of the next block starts from it, so that only accounts, storage and block
+/// hashes that were not touched before need to be fetched from the provider.
+#[derive(Clone, Debug, Default)]
+pub struct PostState {
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub db: MemDb,
+    pub headers: HashMap<u64, AlloyConsensusHeader>,
+    /// The tries of the witness with the changes of the block applied, which prove the
+    /// accounts and slots the block touched without fetching their proofs again.
+    pub tries: Option<StateTries>,
+}
+
+/// Tries of a state, verified against its state root, in which only the paths that were
+/// part of a witness are resolved.
+#[derive(Clone, Debug, Default)]
+pub struct StateTries {
+    pub state_trie: MptNode,
+    pub storage: std::collections::HashMap<Address, MptNode>,
+}
+
+impl StateTries {
+    /// Applies the changes in `db` to the tries of the parent state and returns the tries of
+    /// the resulting state, or `None` if they don't result in `state_root`.
+    pub fn apply(
+        db: &MemDb,
+        mut state_trie: MptNode,
+        mut storage: HashMap<Address, StorageEntry>,
+        state_root: B256,
+    ) -> Option<Self> {
+        apply_state_changes(
+            db,
+            &mut state_trie,
+            &mut storage,
+            &mut KeccakCache::default(),
+        )
+        .ok()?;
+        if state_trie.hash() != state_root {
+            return None;
+        }
+        let storage = storage
+            .into_iter()
+            .map(|(address, (storage_trie, _))| (address, storage_trie))
+            .collect();
+        Some(Self {
+            state_trie,
+            storage,
+        })
+    }
+
+    /// Returns the EIP-1186 proof, or `None` if any of its nodes isn't resolved.
+    pub fn proof(
+        &self,
+        address: &Address,
+        indices: &[U256],
+    ) -> Option<EIP1186AccountProofResponse> {
+        let address_key = keccak(address);
+        let account = self
+            .state_trie
+            .get_rlp::<StateAccount>(&address_key)
+            .ok()?
+            .unwrap_or_default();
+        let account_proof = self.state_trie.proof(&address_key).ok()?;
+
+        let empty_trie = MptNode::default();
+        let storage_trie = if account.storage_root == EMPTY_ROOT {
+            &empty_trie
+        } else {
+            // the trie of a changed account can be left over from before the change
+            self.storage
+                .get(address)
+                .filter(|storage_trie| storage_trie.hash() == account.storage_root)?
+        };
+        let mut storage_proof = Vec::with_capacity(indices.len());
+        for index in indices {
+            let key = B256::from(index.to_be_bytes::<32>());
+            let slot_key = keccak(key);
+            let value = storage_trie.get_rlp::<U256>(&slot_key).ok()?;
+            let proof = storage_trie.proof(&slot_key).ok()?;
+            storage_proof.push(EIP1186StorageProof {
+                key: key.into(),
+                value: value.unwrap_or_default(),
+                proof: proof.into_iter().map(Bytes::from).collect(),
+            });
+        }
+
+        Some(EIP1186AccountProofResponse {
+            address: *address,
+            balance: account.balance,
+            code_hash: account.code_hash,
+            nonce: U64::from(account.nonce),
+            storage_hash: account.storage_root,
+            account_proof: account_proof.into_iter().map(Bytes::from).collect(),
+            storage_proof,
+        })
+    }
+}
+
+/// The post-state of the most recently preflighted block of each network.
+///
+/// The tries aren't `Sync`, so the states are behind a mutex rather than a read-write lock
+/// for the cache to be shared between threads.
+#[derive(Clone, Debug, Default)]
+pub struct StateCache {
+    states: Arc<Mutex<std::collections::HashMap<String, PostState>>>,
+}
+
+impl StateCache {
+    /// Stores the post-state of a block, replacing the one of the previous block.
+    pub fn insert(&self, network: &str, state: PostState) {
+        if let Ok(mut states) = self.states.lock() {
+            states.insert(network.to_owned(), state);
+        }
+    }
+
+    /// Returns the cached post-state if it belongs to the given parent block, so it can
+    /// only be reused for the direct successor of the block on the same chain.
+    pub fn get_parent(
+        &self,
+        network: &str,
+        parent_number: u64,
+        parent_hash: B256,
+    ) -> Option<PostState> {
+        self.states
+            .lock()
+            .ok()?
+            .get(network)
+            .filter(|state| state.block_number == parent_number && state.block_hash == parent_hash)
+            .cloned()
+    }
+}
+
 pub struct ProviderDb<BDP: BlockDataProvider> {
     pub provider: BDP,
     pub block_number: u64,
@@ -47,10 +195,19 @@ pub struct ProviderDb<BDP: BlockDataProvider> {
     pub pending_accounts: HashSet<Address>,
     pub pending_slots: HashSet<(Address, U256)>,
     pub pending_block_hashes: HashSet<u64>,
+
+    /// The tries of the parent state, from the post-state of the parent block.
+    parent_tries: Option<StateTries>,
 }
 
 impl<BDP: BlockDataProvider> ProviderDb<BDP> {
-    pub async fn new(provider: BDP, chain_spec: ChainSpec, block_number: u64) -> RaikoResult<Self> {
+    /// Creates the database for the state at `block_number`, which is `parent_state` if given.
+    pub async fn new(
+        provider: BDP,
+        chain_spec: ChainSpec,
+        block_number: u64,
+        parent_state: Option<PostState>,
+    ) -> RaikoResult<Self> {
         let mut provider_db = ProviderDb {
             provider,
             block_number,
@@ -64,15 +221,35 @@ impl<BDP: BlockDataProvider> ProviderDb<BDP> {
             pending_accounts: HashSet::new(),
             pending_slots: HashSet::new(),
             pending_block_hashes: HashSet::new(),
+            parent_tries: None,
         };
+        if let Some(parent_state) = parent_state {
+            // Everything the previous block touched is served from its post-state, which
+            // moves into the initial state on access just like optimistically fetched data.
+            provider_db.staging_db = parent_state.db;
+            provider_db.initial_headers = parent_state.headers;
+            provider_db.parent_tries = parent_state.tries;
+        }
         if chain_spec.is_taiko() {
             // Get the 256 history block hashes from the provider at first time for anchor
             // transaction.
-            let start = block_number.saturating_sub(255);
-            let block_numbers = (start..=block_number)
-                .map(|block_number| (block_number, false))
-                .collect::<Vec<_>>();
-            let initial_history_blocks = provider_db.provider.get_blocks(&block_numbers).await?;
+            let start = block_number.saturating_sub(BLOCK_HASH_HISTORY - 1);
+            let mut block_numbers = Vec::new();
+            for block_number in start..=block_number {
+                match provider_db.staging_db.block_hashes.get(&block_number) {
+                    Some(block_hash) if provider_db.initial_headers.contains_key(&block_number) => {
+                        provider_db
+                            .initial_db
+                            .insert_block_hash(block_number, *block_hash);
+                    }
+                    _ => block_numbers.push((block_number, false)),
+                }
+            }
+            let initial_history_blocks = if block_numbers.is_empty() {
+                Vec::new()
+            } else {
+                provider_db.provider.get_blocks(&block_numbers).await?
+            };
             for block in initial_history_blocks {
                 let block_number: u64 = block
                     .header
@@ -94,14 +271,21 @@ impl<BDP: BlockDataProvider> ProviderDb<BDP> {
     }
 
     pub async fn get_proofs(&mut self) -> RaikoResult<(MerkleProof, MerkleProof, usize)> {
-        // Latest proof keys
-        let mut storage_keys = self.initial_db.storage_keys();
-        for (address, mut indices) in self.current_db.storage_keys() {
-            match storage_keys.get_mut(&address) {
-                Some(initial_indices) => initial_indices.append(&mut indices),
-                None => {
-                    storage_keys.insert(address, indices);
-                }
+        // Latest proof keys, only needed for what may have been deleted by the block
+        let mut storage_keys = std::collections::HashMap::new();
+        for (address, account) in &self.current_db.accounts {
+            let deleted_slots: Vec<U256> = match account.state {
+                // The whole storage trie is rebuilt, so nothing gets deleted from it
+                AccountState::StorageCleared => Vec::new(),
+                _ => account
+                    .storage
+                    .iter()
+                    .filter(|(_, value)| value.is_zero())
+                    .map(|(index, _)| *index)
+                    .collect(),
+            };
+            if account.state == AccountState::Deleted || !deleted_slots.is_empty() {
+                storage_keys.insert(*address, deleted_slots);
             }
         }
 
@@ -115,16 +299,34 @@ impl<BDP: BlockDataProvider> ProviderDb<BDP> {
         let num_latest_values: usize = storage_keys.values().map(|keys| keys.len()).sum();
         let num_storage_proofs = num_initial_values + num_latest_values;
 
-        // Initial proofs
-        let initial_proofs = self
+        // Initial proofs, only fetching the ones the tries of the parent block can't provide
+        let mut initial_proofs = MerkleProof::new();
+        let mut missing_keys = std::collections::HashMap::new();
+        for (address, indices) in self.initial_db.storage_keys() {
+            match self
+                .parent_tries
+                .as_ref()
+                .and_then(|tries| tries.proof(&address, &indices))
+            {
+                Some(proof) => {
+                    initial_proofs.insert(address, proof);
+                }
+                None => {
+                    missing_keys.insert(address, indices);
+                }
+            }
+        }
+        if !initial_proofs.is_empty() {
+            info!(
+                "Reusing the proofs of {} accounts from the parent block",
+                initial_proofs.len()
+            );
+        }
+        let fetched_proofs = self
             .provider
-            .get_merkle_proofs(
-                self.block_number,
-                self.initial_db.storage_keys(),
-                0,
-                num_storage_proofs,
-            )
+            .get_merkle_proofs(self.block_number, missing_keys, 0, num_storage_proofs)
             .await?;
+        initial_proofs.extend(fetched_proofs);
         let latest_proofs = self
             .provider
             .get_merkle_proofs(
@@ -167,6 +369,50 @@ impl<BDP: BlockDataProvider> ProviderDb<BDP> {
         Ok(headers)
     }
 
+    /// Returns the state after the block with the given header, which must have been
+    /// executed on top of this database.
+    pub fn post_state(&self, block_hash: B256, header: AlloyConsensusHeader) -> PostState {
+        let block_number = header.number;
+        let mut db = self.initial_db.clone();
+        for (address, account) in &self.current_db.accounts {
+            let post_account = db.accounts.entry(*address).or_default();
+            match account.state {
+                AccountState::Deleted => {
+                    // Looks the same as an account that never existed when fetched
+                    let code = Bytecode::new_raw(Bytes::new());
+                    *post_account = DbAccount {
+                        info: AccountInfo::new(U256::ZERO, 0, code.hash_slow(), code),
+                        state: AccountState::StorageCleared,
+                        storage: Default::default(),
+                    };
+                }
+                AccountState::StorageCleared => {
+                    *post_account = account.clone();
+                }
+                AccountState::Touched | AccountState::None => {
+                    post_account.info = account.info.clone();
+                    post_account.storage.extend(&account.storage);
+                }
+            }
+        }
+
+        // Only keep what is still accessible from the next block
+        let is_recent = |number: &u64| number + BLOCK_HASH_HISTORY > block_number;
+        db.block_hashes.insert(block_number, block_hash);
+        db.block_hashes.retain(|number, _| is_recent(number));
+        let mut headers = self.initial_headers.clone();
+        headers.insert(block_number, header);
+        headers.retain(|number, _| is_recent(number));
+
+        PostState {
+            block_number,
+            block_hash,
+            db,
+            headers,
+            tries: None,
+        }
+    }
+
     pub fn is_valid_run(&self) -> bool {
         self.pending_accounts.is_empty()
             && self.pending_slots.is_empty()
@@ -383,3 +629,71 @@ impl<BDP: BlockDataProvider> OptimisticDatabase for ProviderDb<BDP> {
         self.optimistic
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use raiko_lib::primitives::{keccak::KECCAK_EMPTY, mpt::proofs_to_tries};
+
+    use super::*;
+
+    fn account(nonce: u64) -> StateAccount {
+        StateAccount {
+            nonce,
+            balance: U256::ZERO,
+            storage_root: EMPTY_ROOT,
+            code_hash: KECCAK_EMPTY,
+        }
+    }
+
+    #[test]
+    fn test_state_tries() {
+        let addresses: Vec<Address> = (0..100u8).map(Address::with_last_byte).collect();
+        let mut state_trie = MptNode::default();
+        for (i, address) in addresses.iter().enumerate() {
+            state_trie
+                .insert_rlp(&keccak(address), account(i as u64))
+                .unwrap();
+        }
+        let parent = StateTries {
+            state_trie: state_trie.clone(),
+            storage: Default::default(),
+        };
+
+        // the witness of a block that only touches the first account
+        let touched = addresses[0];
+        let proofs = [(touched, parent.proof(&touched, &[]).unwrap())]
+            .into_iter()
+            .collect();
+        let (witness, storage) =
+            proofs_to_tries(state_trie.hash(), proofs, Default::default()).unwrap();
+        let mut db = MemDb::default();
+        db.accounts.insert(
+            touched,
+            DbAccount {
+                info: AccountInfo {
+                    nonce: 7,
+                    code_hash: KECCAK_EMPTY,
+                    ..Default::default()
+                },
+                state: AccountState::Touched,
+                storage: Default::default(),
+            },
+        );
+        state_trie.insert_rlp(&keccak(touched), account(7)).unwrap();
+
+        assert!(StateTries::apply(&db, witness.clone(), storage.clone(), B256::ZERO).is_none());
+        let tries = StateTries::apply(&db, witness, storage, state_trie.hash()).unwrap();
+
+        // the touched account is proven by the tries, the other ones have to be fetched
+        let proof = tries.proof(&touched, &[]).unwrap();
+        let expected = StateTries {
+            state_trie,
+            storage: Default::default(),
+        }
+        .proof(&touched, &[])
+        .unwrap();
+        assert_eq!(proof.nonce, U64::from(7));
+        assert_eq!(proof.account_proof, expected.account_proof);
+        assert!(tries.proof(&addresses[1], &[]).is_none());
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+};
+
+use alloy_consensus::{TxEip4844Variant, TxEnvelope};
+use alloy_primitives::{Address, Bloom, Bytes, Signature, TxKind, B256, B64, U256, U64};
+use alloy_rpc_types::{
+    Block, BlockTransactions, Header, Parity, Signature as RpcSignature, Transaction, Withdrawal,
+};
+use raiko_lib::{
+    primitives::{alloy_eips::eip2718::Decodable2718, mpt::MptNode, RlpBytes},
+    utils::{to_header, HeaderHasher},
+};
+use revm::primitives::AccountInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interfaces::{RaikoError, RaikoResult},
+    provider::BlockDataProvider,
+    MerkleProof,
+};
+
+/// The number of most recent payloads kept per network in the [PayloadStore].
+const MAX_PAYLOADS: usize = 256;
+
+/// Hash of the RLP encoding of an empty ommers list.
+const EMPTY_OMMERS_HASH: B256 =
+    alloy_primitives::b256!("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347");
+
+/// An execution payload as sent by the consensus client in `engine_newPayloadV1-3`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionPayload {
+    pub parent_hash: B256,
+    pub fee_recipient: Address,
+    pub state_root: B256,
+    pub receipts_root: B256,
+    pub logs_bloom: Bloom,
+    pub prev_randao: B256,
+    pub block_number: U64,
+    pub gas_limit: U64,
+    pub gas_used: U64,
+    pub timestamp: U64,
+    pub extra_data: Bytes,
+    pub base_fee_per_gas: U256,
+    pub block_hash: B256,
+    /// The EIP-2718 encoded transactions.
+    pub transactions: Vec<Bytes>,
+    /// Only present from `engine_newPayloadV2` on.
+    #[serde(default)]
+    pub withdrawals: Option<Vec<Withdrawal>>,
+    /// Only present from `engine_newPayloadV3` on.
+    #[serde(default)]
+    pub blob_gas_used: Option<U64>,
+    /// Only present from `engine_newPayloadV3` on.
+    #[serde(default)]
+    pub excess_blob_gas: Option<U64>,
+}
+
+impl ExecutionPayload {
+    /// Converts the payload into the RPC block representation used by the preflight.
+    ///
+    /// All roots that are not part of the payload are recomputed and the block hash is
+    /// checked against the resulting header, so a payload that doesn't match its claimed
+    /// hash is rejected.
+    pub fn into_block(self, parent_beacon_block_root: Option<B256>) -> RaikoResult<Block> {
+        let block_number = self.block_number.to::<u64>();
+
+        let mut tx_trie = MptNode::default();
+        let mut transactions = Vec::with_capacity(self.transactions.len());
+        for (i, raw_tx) in self.transactions.iter().enumerate() {
+            tx_trie
+                .insert_rlp_encoded(&i.to_rlp(), raw_tx.to_vec())
+                .map_err(|e| RaikoError::Conversion(format!("Invalid transaction trie: {e}")))?;
+            let tx = TxEnvelope::decode_2718(&mut raw_tx.as_ref()).map_err(|_| {
+                RaikoError::Conversion(format!("Invalid transaction {i} in payload"))
+            })?;
+            let mut tx = to_rpc_transaction(&tx)?;
+            tx.block_hash = Some(self.block_hash);
+            tx.block_number = Some(block_number);
+            tx.transaction_index = Some(i as u64);
+            transactions.push(tx);
+        }
+
+        let withdrawals_root = match &self.withdrawals {
+            Some(withdrawals) => {
+                let mut withdrawals_trie = MptNode::default();
+                for (i, withdrawal) in withdrawals.iter().enumerate() {
+                    withdrawals_trie
+                        .insert_rlp(&i.to_rlp(), withdrawal)
+                        .map_err(|e| {
+                            RaikoError::Conversion(format!("Invalid withdrawals trie: {e}"))
+                        })?;
+                }
+                Some(withdrawals_trie.hash())
+            }
+            None => None,
+        };
+
+        let header = Header {
+            hash: Some(self.block_hash),
+            parent_hash: self.parent_hash,
+            uncles_hash: EMPTY_OMMERS_HASH,
+            miner: self.fee_recipient,
+            state_root: self.state_root,
+            transactions_root: tx_trie.hash(),
+            receipts_root: self.receipts_root,
+            logs_bloom: self.logs_bloom,
+            number: Some(block_number),
+            gas_limit: self.gas_limit.to(),
+            gas_used: self.gas_used.to(),
+            timestamp: self.timestamp.to(),
+            extra_data: self.extra_data,
+            mix_hash: Some(self.prev_randao),
+            nonce: Some(B64::ZERO),
+            base_fee_per_gas: Some(self.base_fee_per_gas.to()),
+            withdrawals_root,
+            blob_gas_used: self.blob_gas_used.map(|gas| gas.to()),
+            excess_blob_gas: self.excess_blob_gas.map(|gas| gas.to()),
+            parent_beacon_block_root,
+            ..Default::default()
+        };
+        let hash = to_header(&header).hash();
+        if hash != self.block_hash {
+            return Err(RaikoError::Conversion(format!(
+                "Payload block hash mismatch: expected {}, got {hash}",
+                self.block_hash
+            )));
+        }
+
+        Ok(Block {
+            header,
+            transactions: BlockTransactions::Full(transactions),
+            withdrawals: self.withdrawals,
+            ..Default::default()
+        })
+    }
+}
+
+/// Converts a signed transaction into the RPC transaction representation.
+pub(crate) fn to_rpc_transaction(tx: &TxEnvelope) -> RaikoResult<Transaction> {
+    let recover_error = |_| RaikoError::Conversion("Could not recover tx sender".to_owned());
+    let (hash, signature, from) = match tx {
+        TxEnvelope::Legacy(tx) => (*tx.hash(), *tx.signature(), tx.recover_signer()),
+        TxEnvelope::Eip2930(tx) => (*tx.hash(), *tx.signature(), tx.recover_signer()),
+        TxEnvelope::Eip1559(tx) => (*tx.hash(), *tx.signature(), tx.recover_signer()),
+        TxEnvelope::Eip4844(tx) => (*tx.hash(), *tx.signature(), tx.recover_signer()),
+        _ => {
+            return Err(RaikoError::Conversion(
+                "Unsupported transaction type".to_owned(),
+            ))
+        }
+    };
+    let mut rpc_tx = Transaction {
+        hash,
+        from: from.map_err(recover_error)?,
+        signature: Some(to_rpc_signature(&signature)),
+        ..Default::default()
+    };
+
+    match tx {
+        TxEnvelope::Legacy(tx) => {
+            let tx = tx.tx();
+            rpc_tx.transaction_type = Some(0);
+            rpc_tx.chain_id = tx.chain_id;
+            rpc_tx.nonce = tx.nonce;
+            rpc_tx.gas_price = Some(tx.gas_price);
+            rpc_tx.gas = tx.gas_limit;
+            rpc_tx.to = to_address(tx.to);
+            rpc_tx.value = tx.value;
+            rpc_tx.input = tx.input.clone();
+        }
+        TxEnvelope::Eip2930(tx) => {
+            let tx = tx.tx();
+            rpc_tx.transaction_type = Some(1);
+            rpc_tx.chain_id = Some(tx.chain_id);
+            rpc_tx.nonce = tx.nonce;
+            rpc_tx.gas_price = Some(tx.gas_price);
+            rpc_tx.gas = tx.gas_limit;
+            rpc_tx.to = to_address(tx.to);
+            rpc_tx.value = tx.value;
+            rpc_tx.input = tx.input.clone();
+            rpc_tx.access_list = Some(tx.access_list.clone());
+        }
+        TxEnvelope::Eip1559(tx) => {
+            let tx = tx.tx();
+            rpc_tx.transaction_type = Some(2);
+            rpc_tx.chain_id = Some(tx.chain_id);
+            rpc_tx.nonce = tx.nonce;
+            rpc_tx.max_fee_per_gas = Some(tx.max_fee_per_gas);
+            rpc_tx.max_priority_fee_per_gas = Some(tx.max_priority_fee_per_gas);
+            rpc_tx.gas = tx.gas_limit;
+            rpc_tx.to = to_address(tx.to);
+            rpc_tx.value = tx.value;
+            rpc_tx.input = tx.input.clone();
+            rpc_tx.access_list = Some(tx.access_list.clone());
+        }
+        TxEnvelope::Eip4844(tx) => {
+            let tx = match tx.tx() {
+                TxEip4844Variant::TxEip4844(tx) => tx,
+                TxEip4844Variant::TxEip4844WithSidecar(tx) => &tx.tx,
+            };
+            rpc_tx.transaction_type = Some(3);
+            rpc_tx.chain_id = Some(tx.chain_id);
+            rpc_tx.nonce = tx.nonce;
+            rpc_tx.max_fee_per_gas = Some(tx.max_fee_per_gas);
+            rpc_tx.max_priority_fee_per_gas = Some(tx.max_priority_fee_per_gas);
+            rpc_tx.max_fee_per_blob_gas = Some(tx.max_fee_per_blob_gas);
+            rpc_tx.gas = tx.gas_limit;
+            rpc_tx.to = Some(tx.to);
+            rpc_tx.value = tx.value;
+            rpc_tx.input = tx.input.clone();
+            rpc_tx.access_list = Some(tx.access_list.clone());
+            rpc_tx.blob_versioned_hashes = Some(tx.blob_versioned_hashes.clone());
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(rpc_tx)
+}
+
+fn to_rpc_signature(signature: &Signature) -> RpcSignature {
+    RpcSignature {
+        r: signature.r(),
+        s: signature.s(),
+        v: U256::from(signature.v().to_u64()),
+        y_parity: Some(Parity(signature.v().y_parity())),
+    }
+}
+
+fn to_address(kind: TxKind) -> Option<Address> {
+    match kind {
+        TxKind::Call(to) => Some(to),
+        TxKind::Create => None,
+    }
+}
+
+/// Blocks received over the Engine API, which may not be queryable over JSON-RPC yet.
+///
+/// Only the most recent [MAX_PAYLOADS] blocks are kept for each network.
+#[derive(Clone, Debug, Default)]
+pub struct PayloadStore {
+    blocks: Arc<RwLock<HashMap<String, BTreeMap<u64, Block>>>>,
+}
+
+impl PayloadStore {
+    /// Stores a block, replacing any previous payload with the same number (e.g. on reorgs).
+    pub fn insert(&self, network: &str, block: Block) -> RaikoResult<()> {
+        let block_number = block
+            .header
+            .number
+            .ok_or_else(|| RaikoError::Conversion("Payload has no block number".to_owned()))?;
+        let mut blocks = self
+            .blocks
+            .write()
+            .map_err(|_| RaikoError::Preflight("Payload store poisoned".to_owned()))?;
+        let blocks = blocks.entry(network.to_owned()).or_default();
+        blocks.insert(block_number, block);
+        while blocks.len() > MAX_PAYLOADS {
+            blocks.pop_first();
+        }
+        Ok(())
+    }
+
+    /// Returns the stored block with the given number, if any.
+    pub fn get(&self, network: &str, block_number: u64) -> Option<Block> {
+        self.blocks
+            .read()
+            .ok()?
+            .get(network)?
+            .get(&block_number)
+            .cloned()
+    }
+}
+
+/// A block source that serves blocks received over the Engine API from the
+/// [PayloadStore] and falls back to the wrapped provider for everything else.
+///
+/// State is always read from the wrapped provider, which only requires the parent block to
+/// be available over JSON-RPC, not the block being proven.
+pub struct EngineBlockDataProvider<BDP: BlockDataProvider> {
+    inner: BDP,
+    payloads: PayloadStore,
+    network: String,
+}
+
+impl<BDP: BlockDataProvider> EngineBlockDataProvider<BDP> {
+    pub fn new(inner: BDP, payloads: PayloadStore, network: &str) -> Self {
+        Self {
+            inner,
+            payloads,
+            network: network.to_owned(),
+        }
+    }
+}
+
+impl<BDP: BlockDataProvider> BlockDataProvider for EngineBlockDataProvider<BDP> {
+    async fn get_blocks(&self, blocks_to_fetch: &[(u64, bool)]) -> RaikoResult<Vec<Block>> {
+        let mut blocks: HashMap<u64, Block> = HashMap::with_capacity(blocks_to_fetch.len());
+        let mut missing = Vec::new();
+        for (block_number, full) in blocks_to_fetch {
+            match self.payloads.get(&self.network, *block_number) {
+                Some(block) => {
+                    blocks.insert(*block_number, block);
+                }
+                None => missing.push((*block_number, *full)),
+            }
+        }
+
+        if !missing.is_empty() {
+            let fetched = self.inner.get_blocks(&missing).await?;
+            for ((block_number, _), block) in missing.into_iter().zip(fetched) {
+                blocks.insert(block_number, block);
+            }
+        }
+
+        blocks_to_fetch
+            .iter()
+            .map(|(block_number, _)| {
+                blocks.get(block_number).cloned().ok_or_else(|| {
+                    RaikoError::RPC(format!("Block {block_number} is not available"))
+                })
+            })
+            .collect()
+    }
+
+    async fn get_accounts(&self, accounts: &[Address]) -> RaikoResult<Vec<AccountInfo>> {
+        self.inner.get_accounts(accounts).await
+    }
+
+    async fn get_storage_values(&self, accounts: &[(Address, U256)]) -> RaikoResult<Vec<U256>> {
+        self.inner.get_storage_values(accounts).await
+    }
+
+    async fn get_merkle_proofs(
+        &self,
+        block_number: u64,
+        accounts: HashMap<Address, Vec<U256>>,
+        offset: usize,
+        num_storage_proofs: usize,
+    ) -> RaikoResult<MerkleProof> {
+        self.inner
+            .get_merkle_proofs(block_number, accounts, offset, num_storage_proofs)
+            .await
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use alloy_consensus::Header as AlloyConsensusHeader;
+use alloy_primitives::{Address, B256, U256};
+use alloy_rpc_types::{Block, BlockTransactions, Header};
+use raiko_lib::{
+    builder::{BlockBuilderStrategy, TaikoStrategy},
+    input::{GuestInput, InputLimits},
+    primitives::keccak::keccak,
+    utils::{generate_transactions, HeaderHasher},
+};
+use revm::primitives::AccountInfo;
+
+use crate::{
+    interfaces::{RaikoError, RaikoResult},
+    provider::{
+        db::StateTries, engine::to_rpc_transaction, snapshot::StateSnapshot, BlockDataProvider,
+    },
+    MerkleProof,
+};
+
+/// A provider that serves a block from its witness, e.g. the input of a fixture, so the
+/// preflight of the block can be run without a node.
+///
+/// The state before the block is served from the witness and the state after it from the
+/// witness with the block applied, so only the state the block touches is available. Only
+/// non-Taiko blocks are supported, as Taiko blocks also need the L1 chain.
+#[derive(Clone, Debug)]
+pub struct FixtureBlockDataProvider {
+    blocks: HashMap<u64, Block>,
+    parent: StateSnapshot,
+    post: StateSnapshot,
+}
+
+impl FixtureBlockDataProvider {
+    /// Creates the provider for the block of `input`, which is executed to get the state
+    /// after the block.
+    pub fn new(input: &GuestInput) -> RaikoResult<Self> {
+        if input.chain_spec.is_taiko() {
+            return Err(RaikoError::Preflight(
+                "Taiko blocks can't be served from their witness".to_owned(),
+            ));
+        }
+
+        let mut block = Block {
+            header: to_rpc_header(&input.block_header_reference),
+            withdrawals: input
+                .block_header_reference
+                .withdrawals_root
+                .map(|_| input.withdrawals.clone()),
+            ..Default::default()
+        };
+        let mut transactions = Vec::new();
+        let txs = generate_transactions(
+            &input.chain_spec,
+            false,
+            &input.taiko.tx_data,
+            None,
+            &InputLimits::default(),
+        )
+        .map_err(|e| RaikoError::Preflight(format!("Invalid tx list: {e}")))?;
+        for (i, tx) in txs.iter().enumerate() {
+            let mut tx = to_rpc_transaction(tx)?;
+            tx.block_hash = block.header.hash;
+            tx.block_number = Some(input.block_number);
+            tx.transaction_index = Some(i as u64);
+            transactions.push(tx);
+        }
+        block.transactions = BlockTransactions::Full(transactions);
+
+        let mut blocks = HashMap::new();
+        blocks.insert(input.block_number, block);
+        for header in std::iter::once(&input.parent_header).chain(&input.ancestor_headers) {
+            let block = Block {
+                header: to_rpc_header(header),
+                ..Default::default()
+            };
+            blocks.insert(header.number, block);
+        }
+
+        let contracts = input
+            .contracts
+            .iter()
+            .map(|code| (B256::from(keccak(code)), code.clone()))
+            .collect();
+        let parent = StateSnapshot {
+            block_number: input.parent_header.number,
+            block_hash: input.parent_header.hash(),
+            tries: StateTries {
+                state_trie: input.parent_state_trie.clone(),
+                storage: input
+                    .parent_storage
+                    .iter()
+                    .map(|(address, (storage_trie, _))| (*address, storage_trie.clone()))
+                    .collect(),
+            },
+            contracts,
+        };
+        let built = TaikoStrategy::build_state_from(input)
+            .map_err(|e| RaikoError::Preflight(format!("Failed to execute the block: {e}")))?;
+        let post = StateSnapshot::default().advance(input, built);
+
+        Ok(Self {
+            blocks,
+            parent,
+            post,
+        })
+    }
+}
+
+/// Converts a header into the RPC representation, the inverse of
+/// [raiko_lib::utils::to_header].
+fn to_rpc_header(header: &AlloyConsensusHeader) -> Header {
+    Header {
+        hash: Some(header.hash()),
+        parent_hash: header.parent_hash,
+        uncles_hash: header.ommers_hash,
+        miner: header.beneficiary,
+        state_root: header.state_root,
+        transactions_root: header.transactions_root,
+        receipts_root: header.receipts_root,
+        logs_bloom: header.logs_bloom,
+        difficulty: header.difficulty,
+        number: Some(header.number),
+        gas_limit: header.gas_limit,
+        gas_used: header.gas_used,
+        timestamp: header.timestamp,
+        extra_data: header.extra_data.clone(),
+        mix_hash: Some(header.mix_hash),
+        nonce: Some(header.nonce),
+        base_fee_per_gas: header.base_fee_per_gas,
+        withdrawals_root: header.withdrawals_root,
+        blob_gas_used: header.blob_gas_used,
+        excess_blob_gas: header.excess_blob_gas,
+        parent_beacon_block_root: header.parent_beacon_block_root,
+        ..Default::default()
+    }
+}
+
+impl BlockDataProvider for FixtureBlockDataProvider {
+    async fn get_blocks(&self, blocks_to_fetch: &[(u64, bool)]) -> RaikoResult<Vec<Block>> {
+        blocks_to_fetch
+            .iter()
+            .map(|(block_number, _)| {
+                self.blocks.get(block_number).cloned().ok_or_else(|| {
+                    RaikoError::RPC(format!("Block {block_number} is not part of the witness"))
+                })
+            })
+            .collect()
+    }
+
+    async fn get_accounts(&self, accounts: &[Address]) -> RaikoResult<Vec<AccountInfo>> {
+        accounts
+            .iter()
+            .map(|address| {
+                self.parent.account(address).ok_or_else(|| {
+                    RaikoError::RPC(format!("Account {address} is not part of the witness"))
+                })
+            })
+            .collect()
+    }
+
+    async fn get_storage_values(&self, accounts: &[(Address, U256)]) -> RaikoResult<Vec<U256>> {
+        accounts
+            .iter()
+            .map(|(address, index)| {
+                self.parent.storage_value(address, index).ok_or_else(|| {
+                    RaikoError::RPC(format!(
+                        "Storage slot {index} of {address} is not part of the witness"
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    async fn get_merkle_proofs(
+        &self,
+        block_number: u64,
+        accounts: HashMap<Address, Vec<U256>>,
+        _offset: usize,
+        _num_storage_proofs: usize,
+    ) -> RaikoResult<MerkleProof> {
+        let snapshot = [&self.parent, &self.post]
+            .into_iter()
+            .find(|snapshot| snapshot.block_number == block_number)
+            .ok_or_else(|| {
+                RaikoError::RPC(format!("The state of block {block_number} isn't available"))
+            })?;
+        accounts
+            .into_iter()
+            .map(|(address, indices)| {
+                let proof = snapshot.proof(&address, &indices).ok_or_else(|| {
+                    RaikoError::RPC(format!("Proof of {address} is not part of the witness"))
+                })?;
+                Ok((address, proof))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use raiko_lib::{
+        builder::{BlockBuilderStrategy, TaikoStrategy},
+        input::TaikoProverData,
+        utils::HeaderHasher,
+    };
+
+    use super::FixtureBlockDataProvider;
+    use crate::{
+        fixture::{Fixture, FIXTURES_DIR},
+        preflight::preflight,
+    };
+
+    /// The preflight over the witness of a fixture collects an input that builds the block.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fixture_preflight() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES_DIR);
+        for (path, fixture) in Fixture::load_dir(&dir).unwrap() {
+            let input = &fixture.input;
+            if input.chain_spec.is_taiko() {
+                continue;
+            }
+            let provider = FixtureBlockDataProvider::new(input).unwrap();
+            let preflight_input = preflight(
+                provider,
+                input.block_number,
+                input.chain_spec.clone(),
+                input.chain_spec.clone(),
+                TaikoProverData::default(),
+                None,
+            )
+            .await
+            .unwrap_or_else(|e| panic!("Preflight of {path:?} failed: {e}"));
+            assert_eq!(
+                preflight_input.block_hash_reference,
+                input.block_hash_reference
+            );
+            let (header, _) = TaikoStrategy::build_from(&preflight_input).unwrap();
+            assert_eq!(header.hash(), input.block_hash_reference);
+        }
+    }
+}
@@ -5,8 +5,12 @@ use std::collections::HashMap;
 
 use crate::{interfaces::RaikoResult, MerkleProof};
 
+pub mod beacon;
 pub mod db;
+pub mod engine;
+pub mod fixture;
 pub mod rpc;
+pub mod snapshot;
 
 #[allow(async_fn_in_trait)]
 pub trait BlockDataProvider {
@@ -0,0 +1,761 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use alloy_primitives::{Address, Bytes, B256, U256};
+use alloy_rpc_types::{Block, EIP1186AccountProofResponse};
+use raiko_lib::{
+    builder::BuiltState,
+    input::GuestInput,
+    primitives::{
+        keccak::{keccak, KECCAK_EMPTY},
+        mpt::{graft_nodes, StateAccount, EMPTY_ROOT},
+    },
+    utils::HeaderHasher,
+};
+use revm::primitives::{AccountInfo, Bytecode};
+
+use crate::{
+    interfaces::{RaikoError, RaikoResult},
+    provider::{db::StateTries, BlockDataProvider},
+    MerkleProof,
+};
+
+/// The state after a block, as far as it was witnessed by the preflights so far.
+///
+/// The tries only contain the paths that were accessed by one of the previous blocks, all
+/// other nodes are digests. Every node has been verified against the state root of the
+/// snapshot block, so the state can be served without asking the provider again.
+#[derive(Clone, Debug, Default)]
+pub struct StateSnapshot {
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub tries: StateTries,
+    /// Contract code by code hash.
+    pub contracts: HashMap<B256, Bytes>,
+}
+
+impl StateSnapshot {
+    /// Returns the snapshot of the state `built` by the block of `input`, extended with the
+    /// nodes that are still valid from `self`.
+    ///
+    /// `built` has to be the state the block was verified with, so a snapshot never
+    /// contains any state that doesn't belong to the canonical block. The unchanged subtries
+    /// are moved over from `self`, so only the paths changed by the block are new.
+    pub fn advance(self, input: &GuestInput, built: BuiltState) -> Self {
+        let is_parent = self.block_number + 1 == input.block_number
+            && self.block_hash == input.parent_header.hash();
+        let (previous, mut contracts) = if is_parent {
+            (self.tries, self.contracts)
+        } else {
+            (StateTries::default(), HashMap::new())
+        };
+
+        // Unchanged subtries keep their hash, so the nodes of the previous snapshot
+        // resolve the digests of the new tries wherever the state didn't change.
+        let StateTries {
+            state_trie: previous_state_trie,
+            mut storage,
+        } = previous;
+        let state_trie = graft_nodes(built.state_trie, previous_state_trie);
+        for (address, (storage_trie, _)) in built.storage {
+            let storage_trie = match storage.remove(&address) {
+                Some(previous) => graft_nodes(storage_trie, previous),
+                None => storage_trie,
+            };
+            storage.insert(address, storage_trie);
+        }
+        // Drop the storage of accounts that were deleted or changed without their storage
+        // trie being part of the witness.
+        storage.retain(|address, storage_trie| {
+            matches!(
+                state_trie.get_rlp::<StateAccount>(&keccak(address)),
+                Ok(Some(account)) if account.storage_root == storage_trie.hash()
+            )
+        });
+
+        contracts.extend(
+            input
+                .contracts
+                .iter()
+                .map(|code| (B256::from(keccak(code)), code.clone())),
+        );
+
+        Self {
+            block_number: input.block_number,
+            block_hash: built.header.hash(),
+            tries: StateTries {
+                state_trie,
+                storage,
+            },
+            contracts,
+        }
+    }
+
+    /// Returns the account, or `None` if it's not part of the snapshot.
+    pub(crate) fn account(&self, address: &Address) -> Option<AccountInfo> {
+        let account = self
+            .tries
+            .state_trie
+            .get_rlp::<StateAccount>(&keccak(address))
+            .ok()?
+            .unwrap_or_default();
+        let code = if account.code_hash.0 == KECCAK_EMPTY.0 {
+            Bytes::new()
+        } else {
+            self.contracts.get(&account.code_hash)?.clone()
+        };
+        let bytecode = Bytecode::new_raw(code);
+        Some(AccountInfo::new(
+            account.balance,
+            account.nonce,
+            bytecode.hash_slow(),
+            bytecode,
+        ))
+    }
+
+    /// Returns the storage value, or `None` if it's not part of the snapshot.
+    pub(crate) fn storage_value(&self, address: &Address, index: &U256) -> Option<U256> {
+        let account = self
+            .tries
+            .state_trie
+            .get_rlp::<StateAccount>(&keccak(address))
+            .ok()?
+            .unwrap_or_default();
+        if account.storage_root == EMPTY_ROOT {
+            return Some(U256::ZERO);
+        }
+        self.tries
+            .storage
+            .get(address)?
+            .get_rlp::<U256>(&keccak(index.to_be_bytes::<32>()))
+            .ok()
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Returns the EIP-1186 proof, or `None` if it's not part of the snapshot.
+    pub(crate) fn proof(
+        &self,
+        address: &Address,
+        indices: &[U256],
+    ) -> Option<EIP1186AccountProofResponse> {
+        self.tries.proof(address, indices)
+    }
+}
+
+/// A snapshot shared between the store and the providers of running preflights.
+///
+/// The tries cache the hashes of their nodes while being read, so they aren't `Sync` and
+/// every access goes through the mutex.
+pub type SharedSnapshot = Arc<Mutex<StateSnapshot>>;
+
+/// The most recent [StateSnapshot] of each network, which is advanced block by block when
+/// proving a range of blocks.
+#[derive(Clone, Debug, Default)]
+pub struct SnapshotStore {
+    snapshots: Arc<Mutex<HashMap<String, SharedSnapshot>>>,
+}
+
+impl SnapshotStore {
+    /// Returns the snapshot of the network, if any.
+    pub fn get(&self, network: &str) -> Option<SharedSnapshot> {
+        self.snapshots.lock().ok()?.get(network).cloned()
+    }
+
+    /// Advances the snapshot of the network to the state `built` by the block of `input`,
+    /// as returned by [crate::Raiko::get_output_with_state].
+    ///
+    /// The previous snapshot is only built upon if it belongs to the parent block,
+    /// otherwise the snapshot restarts from the witness of `input`. Older blocks are
+    /// ignored, so proving a block again doesn't throw away the progress of the range.
+    pub fn advance(&self, network: &str, input: &GuestInput, built: BuiltState) -> RaikoResult<()> {
+        let block_hash = built.header.hash();
+        if block_hash != input.block_hash_reference {
+            return Err(RaikoError::Preflight(format!(
+                "Snapshot block hash mismatch: expected {}, got {block_hash}",
+                input.block_hash_reference
+            )));
+        }
+
+        fn poisoned<T>(_: T) -> RaikoError {
+            RaikoError::Preflight("Snapshot store poisoned".to_owned())
+        }
+        let mut snapshots = self.snapshots.lock().map_err(poisoned)?;
+        let previous = snapshots.remove(network).unwrap_or_default();
+        {
+            let snapshot = previous.lock().map_err(poisoned)?;
+            if snapshot.block_number > input.block_number || snapshot.block_hash == block_hash {
+                drop(snapshot);
+                snapshots.insert(network.to_owned(), previous);
+                return Ok(());
+            }
+        }
+        // Providers of running preflights may still share the previous snapshot
+        let previous = match Arc::try_unwrap(previous) {
+            Ok(previous) => previous.into_inner().map_err(poisoned)?,
+            Err(previous) => previous.lock().map_err(poisoned)?.clone(),
+        };
+        let snapshot = previous.advance(input, built);
+        snapshots.insert(network.to_owned(), Arc::new(Mutex::new(snapshot)));
+        Ok(())
+    }
+}
+
+/// A provider that serves the state at `block_number` from the [StateSnapshot] of the
+/// network and only falls back to the wrapped provider for state that isn't part of it.
+///
+/// When proving a range of blocks, every block mostly touches state that one of the
+/// previous blocks already touched, so only a fraction of the accounts, storage slots
+/// and proofs of an independent preflight have to be fetched.
+pub struct SnapshotBlockDataProvider<BDP: BlockDataProvider> {
+    inner: BDP,
+    snapshot: RwLock<Option<SharedSnapshot>>,
+}
+
+impl<BDP: BlockDataProvider> SnapshotBlockDataProvider<BDP> {
+    /// Creates the provider for the state at `block_number`.
+    pub fn new(inner: BDP, store: &SnapshotStore, network: &str, block_number: u64) -> Self {
+        let snapshot = store.get(network).filter(|snapshot| {
+            snapshot
+                .lock()
+                .is_ok_and(|snapshot| snapshot.block_number == block_number)
+        });
+        Self {
+            inner,
+            snapshot: RwLock::new(snapshot),
+        }
+    }
+
+    /// Reads from the snapshot, or returns `None` if there is none. The lock is released
+    /// before returning, so it's never held across the requests to the wrapped provider.
+    fn with_snapshot<T>(&self, f: impl FnOnce(&StateSnapshot) -> T) -> Option<T> {
+        let snapshot = self.snapshot.read().ok()?.clone()?;
+        let snapshot = snapshot.lock().ok()?;
+        Some(f(&snapshot))
+    }
+}
+
+impl<BDP: BlockDataProvider> BlockDataProvider for SnapshotBlockDataProvider<BDP> {
+    async fn get_blocks(&self, blocks_to_fetch: &[(u64, bool)]) -> RaikoResult<Vec<Block>> {
+        let blocks = self.inner.get_blocks(blocks_to_fetch).await?;
+        // The snapshot can't be used if the chain was reorged since it was taken
+        let reorged = self.with_snapshot(|snapshot| {
+            blocks.iter().any(|block| {
+                block.header.number == Some(snapshot.block_number)
+                    && block.header.hash != Some(snapshot.block_hash)
+            })
+        });
+        if reorged == Some(true) {
+            if let Ok(mut snapshot) = self.snapshot.write() {
+                *snapshot = None;
+            }
+        }
+        Ok(blocks)
+    }
+
+    async fn get_accounts(&self, accounts: &[Address]) -> RaikoResult<Vec<AccountInfo>> {
+        let Some(mut result) = self.with_snapshot(|snapshot| {
+            accounts
+                .iter()
+                .map(|address| snapshot.account(address))
+                .collect::<Vec<_>>()
+        }) else {
+            return self.inner.get_accounts(accounts).await;
+        };
+        let missing: Vec<Address> = accounts
+            .iter()
+            .zip(&result)
+            .filter(|(_, account)| account.is_none())
+            .map(|(address, _)| *address)
+            .collect();
+        if !missing.is_empty() {
+            let mut fetched = self.inner.get_accounts(&missing).await?.into_iter();
+            for account in result.iter_mut().filter(|account| account.is_none()) {
+                *account = fetched.next();
+            }
+        }
+
+        result
+            .into_iter()
+            .map(|account| account.ok_or_else(|| RaikoError::RPC("No account".to_owned())))
+            .collect()
+    }
+
+    async fn get_storage_values(&self, accounts: &[(Address, U256)]) -> RaikoResult<Vec<U256>> {
+        let Some(mut result) = self.with_snapshot(|snapshot| {
+            accounts
+                .iter()
+                .map(|(address, index)| snapshot.storage_value(address, index))
+                .collect::<Vec<_>>()
+        }) else {
+            return self.inner.get_storage_values(accounts).await;
+        };
+        let missing: Vec<(Address, U256)> = accounts
+            .iter()
+            .zip(&result)
+            .filter(|(_, value)| value.is_none())
+            .map(|(slot, _)| *slot)
+            .collect();
+        if !missing.is_empty() {
+            let mut fetched = self.inner.get_storage_values(&missing).await?.into_iter();
+            for value in result.iter_mut().filter(|value| value.is_none()) {
+                *value = fetched.next();
+            }
+        }
+
+        result
+            .into_iter()
+            .map(|value| value.ok_or_else(|| RaikoError::RPC("No storage value".to_owned())))
+            .collect()
+    }
+
+    async fn get_merkle_proofs(
+        &self,
+        block_number: u64,
+        accounts: HashMap<Address, Vec<U256>>,
+        offset: usize,
+        num_storage_proofs: usize,
+    ) -> RaikoResult<MerkleProof> {
+        let from_snapshot = self.with_snapshot(|snapshot| {
+            if snapshot.block_number != block_number {
+                return None;
+            }
+            let mut proofs = MerkleProof::with_capacity(accounts.len());
+            let mut missing = HashMap::new();
+            for (address, indices) in &accounts {
+                match snapshot.proof(address, indices) {
+                    Some(proof) => {
+                        proofs.insert(*address, proof);
+                    }
+                    None => {
+                        missing.insert(*address, indices.clone());
+                    }
+                }
+            }
+            Some((proofs, missing))
+        });
+        let Some((mut proofs, missing)) = from_snapshot.flatten() else {
+            return self
+                .inner
+                .get_merkle_proofs(block_number, accounts, offset, num_storage_proofs)
+                .await;
+        };
+
+        if !missing.is_empty() {
+            let fetched = self
+                .inner
+                .get_merkle_proofs(block_number, missing, offset, num_storage_proofs)
+                .await?;
+            proofs.extend(fetched);
+        }
+        Ok(proofs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::Header as AlloyConsensusHeader;
+    use raiko_lib::primitives::mpt::{proofs_to_tries, MptNode};
+
+    use super::*;
+
+    const NETWORK: &str = "testnet";
+
+    fn address(i: u8) -> Address {
+        Address::with_last_byte(i)
+    }
+
+    fn slot_key(index: u64) -> [u8; 32] {
+        keccak(U256::from(index).to_be_bytes::<32>())
+    }
+
+    /// The state of 100 accounts with the nonces of `nonces`, or their index otherwise. The
+    /// first account holds `value` in the storage slots 1 to 20.
+    fn full_state(nonces: &[(u8, u64)], value: u64) -> StateTries {
+        let mut storage_trie = MptNode::default();
+        for index in 1..=20 {
+            storage_trie
+                .insert_rlp(&slot_key(index), U256::from(value))
+                .unwrap();
+        }
+        let mut state_trie = MptNode::default();
+        for i in 0..100u8 {
+            let nonce = nonces
+                .iter()
+                .find(|(j, _)| *j == i)
+                .map_or(i as u64, |(_, nonce)| *nonce);
+            let account = StateAccount {
+                nonce,
+                balance: U256::ZERO,
+                storage_root: if i == 0 {
+                    storage_trie.hash()
+                } else {
+                    EMPTY_ROOT
+                },
+                code_hash: KECCAK_EMPTY,
+            };
+            state_trie.insert_rlp(&keccak(address(i)), account).unwrap();
+        }
+        StateTries {
+            state_trie,
+            storage: [(address(0), storage_trie)].into_iter().collect(),
+        }
+    }
+
+    /// A block changing the state from `pre` to `post`, with the witness of the accounts
+    /// and storage slots in `touched`.
+    fn block(
+        number: u64,
+        parent_header: &AlloyConsensusHeader,
+        pre: &StateTries,
+        post: &StateTries,
+        touched: &[(u8, Vec<u64>)],
+    ) -> (GuestInput, BuiltState) {
+        let proofs = touched
+            .iter()
+            .map(|(i, indices)| {
+                let indices: Vec<U256> = indices.iter().copied().map(U256::from).collect();
+                (address(*i), pre.proof(&address(*i), &indices).unwrap())
+            })
+            .collect();
+        let (parent_state_trie, parent_storage) =
+            proofs_to_tries(pre.state_trie.hash(), proofs, Default::default()).unwrap();
+
+        let mut state_trie = parent_state_trie.clone();
+        let mut storage = parent_storage.clone();
+        for (i, indices) in touched {
+            let key = keccak(address(*i));
+            let account: StateAccount = post.state_trie.get_rlp(&key).unwrap().unwrap();
+            state_trie.insert_rlp(&key, account).unwrap();
+            for index in indices {
+                let value: U256 = post.storage[&address(*i)]
+                    .get_rlp(&slot_key(*index))
+                    .unwrap()
+                    .unwrap();
+                let (storage_trie, _) = storage.get_mut(&address(*i)).unwrap();
+                storage_trie.insert_rlp(&slot_key(*index), value).unwrap();
+            }
+        }
+        assert_eq!(state_trie.hash(), post.state_trie.hash());
+
+        let header = AlloyConsensusHeader {
+            number,
+            parent_hash: parent_header.hash(),
+            state_root: state_trie.hash(),
+            ..Default::default()
+        };
+        let input = GuestInput {
+            block_number: number,
+            block_hash_reference: header.hash(),
+            block_header_reference: header.clone(),
+            parent_header: parent_header.clone(),
+            parent_state_trie,
+            parent_storage,
+            ..Default::default()
+        };
+        let built = BuiltState {
+            header,
+            state_trie,
+            storage,
+            keccak_stats: Default::default(),
+        };
+        (input, built)
+    }
+
+    fn genesis(state: &StateTries) -> AlloyConsensusHeader {
+        AlloyConsensusHeader {
+            state_root: state.state_trie.hash(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_advance_grafts_onto_parent() {
+        let state0 = full_state(&[], 1);
+        let state1 = full_state(&[(1, 1000)], 1);
+        let state2 = full_state(&[(1, 1000), (2, 2000)], 1);
+
+        let (input, built) = block(
+            1,
+            &genesis(&state0),
+            &state0,
+            &state1,
+            &[(1, vec![]), (0, vec![1])],
+        );
+        let header = built.header.clone();
+        let snapshot = StateSnapshot::default().advance(&input, built);
+        assert_eq!(snapshot.block_number, 1);
+        assert_eq!(snapshot.block_hash, header.hash());
+        assert_eq!(snapshot.account(&address(1)).unwrap().nonce, 1000);
+        assert_eq!(
+            snapshot.storage_value(&address(0), &U256::from(1)),
+            Some(U256::from(1))
+        );
+        assert!(snapshot.account(&address(2)).is_none());
+
+        // the next block only witnesses the second account, the rest is grafted on
+        let (input, built) = block(2, &header, &state1, &state2, &[(2, vec![])]);
+        let snapshot = snapshot.advance(&input, built);
+        assert_eq!(snapshot.block_number, 2);
+        assert_eq!(snapshot.account(&address(1)).unwrap().nonce, 1000);
+        assert_eq!(snapshot.account(&address(2)).unwrap().nonce, 2000);
+        assert_eq!(
+            snapshot.storage_value(&address(0), &U256::from(1)),
+            Some(U256::from(1))
+        );
+        for i in [0, 1, 2] {
+            let indices = [U256::from(1)];
+            let proof = snapshot.proof(&address(i), &indices).unwrap();
+            let expected = state2.proof(&address(i), &indices).unwrap();
+            assert_eq!(proof.account_proof, expected.account_proof);
+            assert_eq!(proof.storage_proof, expected.storage_proof);
+        }
+        assert!(snapshot.account(&address(3)).is_none());
+    }
+
+    #[test]
+    fn test_advance_restarts_on_non_parent() {
+        let state0 = full_state(&[], 1);
+        let state1 = full_state(&[(1, 1000)], 1);
+        let state2 = full_state(&[(1, 1000), (2, 2000)], 1);
+
+        let (input, built) = block(1, &genesis(&state0), &state0, &state1, &[(1, vec![])]);
+        let header = built.header.clone();
+        let snapshot = StateSnapshot::default().advance(&input, built);
+
+        // a block on another parent doesn't build upon the snapshot
+        let other = AlloyConsensusHeader {
+            extra_data: Bytes::from_static(b"other"),
+            ..header
+        };
+        let (input, built) = block(2, &other, &state1, &state2, &[(2, vec![])]);
+        let snapshot = snapshot.advance(&input, built);
+        assert_eq!(snapshot.block_number, 2);
+        assert_eq!(snapshot.account(&address(2)).unwrap().nonce, 2000);
+        assert!(snapshot.account(&address(1)).is_none());
+    }
+
+    #[test]
+    fn test_advance_prunes_changed_storage() {
+        let state0 = full_state(&[], 1);
+        let state1 = full_state(&[(1, 1000)], 1);
+        let state2 = full_state(&[(1, 1000)], 2);
+
+        let (input, built) = block(
+            1,
+            &genesis(&state0),
+            &state0,
+            &state1,
+            &[(1, vec![]), (0, vec![1])],
+        );
+        let header = built.header.clone();
+        let snapshot = StateSnapshot::default().advance(&input, built);
+        assert!(snapshot.tries.storage.contains_key(&address(0)));
+
+        // the storage root changes without the storage trie being part of the witness
+        let (input, built) = block(2, &header, &state1, &state2, &[(0, vec![])]);
+        let snapshot = snapshot.advance(&input, built);
+        assert!(!snapshot.tries.storage.contains_key(&address(0)));
+        assert!(snapshot
+            .storage_value(&address(0), &U256::from(1))
+            .is_none());
+        assert!(snapshot.proof(&address(0), &[U256::from(1)]).is_none());
+        assert_eq!(snapshot.account(&address(1)).unwrap().nonce, 1000);
+    }
+
+    #[test]
+    fn test_store_advance() {
+        let state0 = full_state(&[], 1);
+        let state1 = full_state(&[(1, 1000)], 1);
+        let state2 = full_state(&[(1, 1000), (2, 2000)], 1);
+        let store = SnapshotStore::default();
+
+        let (input1, built1) = block(1, &genesis(&state0), &state0, &state1, &[(1, vec![])]);
+        let header = built1.header.clone();
+
+        // the built state has to be the one of the block
+        let mismatch = GuestInput {
+            block_hash_reference: B256::ZERO,
+            ..input1.clone()
+        };
+        assert!(store.advance(NETWORK, &mismatch, built1.clone()).is_err());
+        assert!(store.get(NETWORK).is_none());
+
+        store.advance(NETWORK, &input1, built1.clone()).unwrap();
+        let (input2, built2) = block(2, &header, &state1, &state2, &[(2, vec![])]);
+        store.advance(NETWORK, &input2, built2).unwrap();
+
+        // proving an older block again keeps the progress
+        store.advance(NETWORK, &input1, built1).unwrap();
+        let snapshot = store.get(NETWORK).unwrap();
+        let snapshot = snapshot.lock().unwrap();
+        assert_eq!(snapshot.block_number, 2);
+        assert_eq!(snapshot.account(&address(1)).unwrap().nonce, 1000);
+        assert_eq!(snapshot.account(&address(2)).unwrap().nonce, 2000);
+        assert!(store.get("other").is_none());
+    }
+
+    /// A node serving the full state of a block, which records the accounts it's asked for.
+    struct MockProvider {
+        state: StateSnapshot,
+        requested: Mutex<Vec<Address>>,
+    }
+
+    impl MockProvider {
+        fn new(block_number: u64, block_hash: B256, tries: StateTries) -> Self {
+            Self {
+                state: StateSnapshot {
+                    block_number,
+                    block_hash,
+                    tries,
+                    contracts: HashMap::new(),
+                },
+                requested: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn requested(&self) -> Vec<Address> {
+            std::mem::take(&mut self.requested.lock().unwrap())
+        }
+    }
+
+    impl BlockDataProvider for MockProvider {
+        async fn get_blocks(&self, blocks_to_fetch: &[(u64, bool)]) -> RaikoResult<Vec<Block>> {
+            let mut blocks = Vec::new();
+            for (block_number, _) in blocks_to_fetch {
+                let mut block = Block::default();
+                block.header.number = Some(*block_number);
+                block.header.hash = Some(self.state.block_hash);
+                blocks.push(block);
+            }
+            Ok(blocks)
+        }
+
+        async fn get_accounts(&self, accounts: &[Address]) -> RaikoResult<Vec<AccountInfo>> {
+            self.requested.lock().unwrap().extend(accounts);
+            Ok(accounts
+                .iter()
+                .map(|address| self.state.account(address).unwrap())
+                .collect())
+        }
+
+        async fn get_storage_values(&self, accounts: &[(Address, U256)]) -> RaikoResult<Vec<U256>> {
+            let mut requested = self.requested.lock().unwrap();
+            requested.extend(accounts.iter().map(|(address, _)| address));
+            Ok(accounts
+                .iter()
+                .map(|(address, index)| self.state.storage_value(address, index).unwrap())
+                .collect())
+        }
+
+        async fn get_merkle_proofs(
+            &self,
+            block_number: u64,
+            accounts: HashMap<Address, Vec<U256>>,
+            _offset: usize,
+            _num_storage_proofs: usize,
+        ) -> RaikoResult<MerkleProof> {
+            assert_eq!(block_number, self.state.block_number);
+            let mut requested = self.requested.lock().unwrap();
+            requested.extend(accounts.keys());
+            Ok(accounts
+                .into_iter()
+                .map(|(address, indices)| (address, self.state.proof(&address, &indices).unwrap()))
+                .collect())
+        }
+    }
+
+    /// The store with the snapshot after block 1, which witnessed the second account and the
+    /// first storage slot of the first account, and the node serving the full state.
+    fn snapshot_store() -> (SnapshotStore, MockProvider) {
+        let state0 = full_state(&[], 1);
+        let state1 = full_state(&[(1, 1000)], 1);
+        let (input, built) = block(
+            1,
+            &genesis(&state0),
+            &state0,
+            &state1,
+            &[(1, vec![]), (0, vec![1])],
+        );
+        let provider = MockProvider::new(1, built.header.hash(), state1);
+        let store = SnapshotStore::default();
+        store.advance(NETWORK, &input, built).unwrap();
+        (store, provider)
+    }
+
+    #[tokio::test]
+    async fn test_provider_falls_back_to_inner() {
+        let (store, inner) = snapshot_store();
+        let expected = MockProvider::new(1, B256::ZERO, inner.state.tries.clone());
+        let provider = SnapshotBlockDataProvider::new(inner, &store, NETWORK, 1);
+
+        let accounts = [address(1), address(2)];
+        assert_eq!(
+            provider.get_accounts(&accounts).await.unwrap(),
+            expected.get_accounts(&accounts).await.unwrap()
+        );
+        assert_eq!(provider.inner.requested(), [address(2)]);
+
+        let slots = [(address(0), U256::from(1)), (address(0), U256::from(2))];
+        assert_eq!(
+            provider.get_storage_values(&slots).await.unwrap(),
+            vec![U256::from(1); 2]
+        );
+        assert_eq!(provider.inner.requested(), [address(0)]);
+
+        let accounts: HashMap<_, _> = [
+            (address(0), vec![U256::from(1)]),
+            (address(1), vec![]),
+            (address(2), vec![]),
+        ]
+        .into_iter()
+        .collect();
+        let proofs = provider
+            .get_merkle_proofs(1, accounts.clone(), 0, 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            proofs,
+            expected
+                .get_merkle_proofs(1, accounts.clone(), 0, 0)
+                .await
+                .unwrap()
+        );
+        assert_eq!(provider.inner.requested(), [address(2)]);
+
+        // the snapshot only serves the proofs of its own block
+        let inner = MockProvider::new(2, B256::ZERO, expected.state.tries);
+        let provider = SnapshotBlockDataProvider::new(inner, &store, NETWORK, 1);
+        provider.get_merkle_proofs(2, accounts, 0, 0).await.unwrap();
+        assert_eq!(provider.inner.requested().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_provider_drops_snapshot_on_reorg() {
+        let (store, inner) = snapshot_store();
+        let block_hash = inner.state.block_hash;
+        let provider = SnapshotBlockDataProvider::new(inner, &store, NETWORK, 1);
+        provider.get_blocks(&[(1, false)]).await.unwrap();
+        provider.get_accounts(&[address(1)]).await.unwrap();
+        assert!(provider.inner.requested().is_empty());
+
+        // the node returns another block 1 than the snapshot was taken of
+        let (store, mut inner) = snapshot_store();
+        inner.state.block_hash = B256::repeat_byte(1);
+        let provider = SnapshotBlockDataProvider::new(inner, &store, NETWORK, 1);
+        provider.get_blocks(&[(1, false)]).await.unwrap();
+        provider.get_accounts(&[address(1)]).await.unwrap();
+        assert_eq!(provider.inner.requested(), [address(1)]);
+        assert_eq!(
+            store.get(NETWORK).unwrap().lock().unwrap().block_hash,
+            block_hash
+        );
+
+        // a snapshot of another block isn't used at all
+        let (store, inner) = snapshot_store();
+        let provider = SnapshotBlockDataProvider::new(inner, &store, NETWORK, 2);
+        provider.get_accounts(&[address(1)]).await.unwrap();
+        assert_eq!(provider.inner.requested(), [address(1)]);
+    }
+}
@@ -0,0 +1,247 @@
+//! EIP-712 signed metadata of proofs, which can be relayed to proof markets and used for
+//! off-chain settlement without trusting the channel the proof was received over.
+
+use std::{fmt, path::Path};
+
+use alloy_primitives::{keccak256, Address, Bytes, B256};
+use alloy_signer::Signer;
+use alloy_signer_wallet::LocalWallet;
+use alloy_sol_types::{eip712_domain, sol, Eip712Domain, SolStruct};
+use anyhow::{anyhow, Context};
+use raiko_core::interfaces::FeeTerms;
+use serde::{Deserialize, Serialize};
+
+use crate::interfaces::HostResult;
+
+/// The EIP-712 domain name of the proof metadata.
+pub const DOMAIN_NAME: &str = "Raiko";
+/// The EIP-712 domain version of the proof metadata.
+pub const DOMAIN_VERSION: &str = "3";
+
+sol! {
+    /// The metadata of a proof, as signed by the operator of the host.
+    #[derive(Debug, Serialize, Deserialize)]
+    struct ProofMetadata {
+        uint64 chainId;
+        uint64 blockNumber;
+        bytes32 blockHash;
+        bytes32 publicInputHash;
+        bytes32 proofHash;
+        string proofType;
+        address feeToken;
+        uint256 feeAmount;
+        bytes32 buildHash;
+    }
+}
+
+impl ProofMetadata {
+    /// Returns the metadata of a proof of the given block.
+    pub fn new(
+        chain_id: u64,
+        block_number: u64,
+        block_hash: B256,
+        public_input_hash: B256,
+        proof_hash: B256,
+        proof_type: &str,
+        fee: Option<&FeeTerms>,
+        build_hash: B256,
+    ) -> Self {
+        let fee = fee.cloned().unwrap_or_default();
+        Self {
+            chainId: chain_id,
+            blockNumber: block_number,
+            blockHash: block_hash,
+            publicInputHash: public_input_hash,
+            proofHash: proof_hash,
+            proofType: proof_type.to_owned(),
+            feeToken: fee.token,
+            feeAmount: fee.amount,
+            buildHash: build_hash,
+        }
+    }
+
+    /// Returns the EIP-712 domain the metadata is signed in, bound to the proven chain.
+    pub fn domain(&self) -> Eip712Domain {
+        eip712_domain! {
+            name: DOMAIN_NAME,
+            version: DOMAIN_VERSION,
+            chain_id: self.chainId,
+        }
+    }
+}
+
+/// Returns the hash the metadata binds the proof to, the keccak256 of the proof bytes.
+///
+/// Hex encoded proofs are hashed decoded, other proofs as they are returned. The hash is zero
+/// for proof types without a proof.
+pub fn proof_hash(proof: Option<&str>) -> B256 {
+    let Some(proof) = proof else {
+        return B256::ZERO;
+    };
+    match hex::decode(proof.strip_prefix("0x").unwrap_or(proof)) {
+        Ok(bytes) => keccak256(bytes),
+        Err(_) => keccak256(proof),
+    }
+}
+
+/// The signed metadata of a proof, ready to be relayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofAttestation {
+    pub metadata: ProofMetadata,
+    /// The EIP-712 hash that was signed.
+    pub signing_hash: B256,
+    /// The address of the operator key.
+    pub signer: Address,
+    /// The 65 byte `r || s || v` signature over `signing_hash`.
+    pub signature: Bytes,
+}
+
+/// Signs the metadata of proofs with the operator key.
+pub struct Attestor {
+    wallet: LocalWallet,
+}
+
+impl fmt::Debug for Attestor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Attestor")
+            .field("signer", &self.address())
+            .finish()
+    }
+}
+
+impl Attestor {
+    /// Reads the hex encoded private key from a file.
+    pub fn from_file(path: &Path) -> HostResult<Self> {
+        let key = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read the attestation key {}", path.display()))?;
+        let wallet = key
+            .trim()
+            .parse()
+            .map_err(|e| anyhow!("Invalid attestation key: {e}"))?;
+        Ok(Self { wallet })
+    }
+
+    pub fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    /// Signs the metadata.
+    pub async fn attest(&self, metadata: ProofMetadata) -> HostResult<ProofAttestation> {
+        let signing_hash = metadata.eip712_signing_hash(&metadata.domain());
+        let signature = self
+            .wallet
+            .sign_hash(&signing_hash)
+            .await
+            .map_err(|e| anyhow!("Could not sign the proof metadata: {e}"))?;
+        Ok(ProofAttestation {
+            metadata,
+            signing_hash,
+            signer: self.address(),
+            signature: signature.as_bytes().into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{b256, Signature, U256};
+
+    use super::*;
+
+    const README: &str = include_str!("../../README.md");
+
+    /// Returns the EIP-712 type of the metadata as documented in the README.
+    fn documented_type() -> String {
+        let start = README
+            .find("struct ProofMetadata {")
+            .expect("The README documents the metadata");
+        let fields: Vec<_> = README[start..]
+            .lines()
+            .skip(1)
+            .map(str::trim)
+            .take_while(|line| *line != "}")
+            .map(|line| line.trim_end_matches(';'))
+            .collect();
+        format!("ProofMetadata({})", fields.join(","))
+    }
+
+    #[test]
+    fn test_type_hash() {
+        assert_eq!(ProofMetadata::eip712_encode_type(), documented_type());
+        let metadata = ProofMetadata::new(
+            1,
+            1,
+            B256::ZERO,
+            B256::ZERO,
+            B256::ZERO,
+            "sgx",
+            None,
+            B256::ZERO,
+        );
+        assert_eq!(
+            metadata.eip712_type_hash(),
+            b256!("3fb290e501688a384472829a064012739888610bb276328ae4d459a67c0fc55c")
+        );
+    }
+
+    #[test]
+    fn test_proof_hash() {
+        assert_eq!(proof_hash(None), B256::ZERO);
+        assert_eq!(proof_hash(Some("0x0102")), keccak256([1u8, 2]));
+        assert_eq!(proof_hash(Some("0102")), keccak256([1u8, 2]));
+        assert_eq!(proof_hash(Some("not hex")), keccak256("not hex"));
+    }
+
+    #[tokio::test]
+    async fn test_attest() {
+        let attestor = Attestor {
+            wallet: "0000000000000000000000000000000000000000000000000000000000000001"
+                .parse()
+                .unwrap(),
+        };
+        let fee = FeeTerms {
+            token: Address::ZERO,
+            amount: U256::from(10_000_000_000_000_000u64),
+        };
+        let metadata = ProofMetadata::new(
+            167000,
+            100,
+            B256::repeat_byte(1),
+            B256::repeat_byte(2),
+            proof_hash(Some("0x0102")),
+            "sgx",
+            Some(&fee),
+            B256::repeat_byte(3),
+        );
+        let attestation = attestor.attest(metadata).await.unwrap();
+
+        assert_eq!(
+            attestation.signer,
+            "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf"
+                .parse::<Address>()
+                .unwrap()
+        );
+        assert_eq!(
+            attestation.signing_hash,
+            attestation
+                .metadata
+                .eip712_signing_hash(&attestation.metadata.domain())
+        );
+        let signature = Signature::try_from(&attestation.signature[..]).unwrap();
+        assert_eq!(
+            signature
+                .recover_address_from_prehash(&attestation.signing_hash)
+                .unwrap(),
+            attestation.signer
+        );
+
+        // The signature doesn't carry over to another proof
+        let mut other = attestation.metadata.clone();
+        other.proofHash = proof_hash(Some("0x0103"));
+        let other_hash = other.eip712_signing_hash(&other.domain());
+        assert_ne!(
+            signature.recover_address_from_prehash(&other_hash).unwrap(),
+            attestation.signer
+        );
+    }
+}
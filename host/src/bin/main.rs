@@ -1,7 +1,11 @@
 #![allow(incomplete_features)]
 use std::path::PathBuf;
 
-use raiko_host::{interfaces::HostResult, server::serve, ProverState};
+use clap::Parser;
+use raiko_host::{
+    capture, chain_spec, guest_build, interfaces::HostResult, server::serve, top, Cli, Command,
+    ProverState,
+};
 use tracing::info;
 use tracing_appender::{
     non_blocking::WorkerGuard,
@@ -11,17 +15,38 @@ use tracing_subscriber::FmtSubscriber;
 
 #[tokio::main]
 async fn main() -> HostResult<()> {
+    let mut opts = Cli::parse();
+    match opts.command.take() {
+        Some(Command::Top(args)) => {
+            // The JWT secret of the host can also be set in the config file
+            opts.merge_from_file()?;
+            return top::run(&opts, &args).await;
+        }
+        Some(Command::ChainSpec(args)) => {
+            // The chain spec path can also be set in the config file
+            opts.merge_from_file()?;
+            return chain_spec::run(&opts, &args);
+        }
+        Some(Command::Capture(args)) => {
+            opts.merge_from_file()?;
+            return capture::run(&opts, &args).await;
+        }
+        Some(Command::VerifyBuild(args)) => return guest_build::verify_build(&args),
+        None => {}
+    }
+
     env_logger::init();
-    let state = ProverState::init()?;
+    let state = ProverState::init_with_opts(opts)?;
+    let config = state.config();
     let _guard = subscribe_log(
-        &state.opts.log_path,
-        &state.opts.log_level,
-        state.opts.max_log,
+        &config.opts.log_path,
+        &config.opts.log_level,
+        config.opts.max_log,
     );
 
-    info!("Supported chains: {:?}", state.chain_specs);
-    info!("Start config:\n{:#?}", state.opts.proof_request_opt);
-    info!("Args:\n{:#?}", state.opts);
+    info!("Supported chains: {:?}", config.chain_specs);
+    info!("Start config:\n{:#?}", config.opts.proof_request_opt);
+    info!("Args:\n{:#?}", config.opts);
 
     serve(state).await?;
     Ok(())
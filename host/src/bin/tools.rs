@@ -0,0 +1,166 @@
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use clap::{Args, Parser, Subcommand};
+use raiko_core::{fixture::Fixture, inspect::WitnessStats, provider::rpc::RpcBlockDataProvider};
+use raiko_host::interfaces::{HostError, HostResult};
+use raiko_lib::{
+    consts::{ChainSpec, Eip1559Constants},
+    input::TaikoProverData,
+};
+use revm::primitives::SpecId;
+
+#[derive(Debug, Parser)]
+#[command(name = "raiko-tools", about = "Tooling around the taiko prover host")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Capture a block from a local devnet node (anvil, hardhat) as a test fixture
+    ImportFixture(ImportFixtureArgs),
+    /// Re-execute all fixtures in a directory
+    Replay(ReplayArgs),
+    /// Print statistics of the witness of a cached input or a fixture
+    Inspect(InspectArgs),
+}
+
+#[derive(Debug, Args)]
+struct ImportFixtureArgs {
+    #[arg(long, require_equals = true, default_value = "http://localhost:8545")]
+    /// RPC endpoint of the devnet node
+    rpc: String,
+
+    #[arg(long, require_equals = true)]
+    /// The block to capture
+    block_number: u64,
+
+    #[arg(long, require_equals = true, default_value = "31337")]
+    /// Chain id of the devnet
+    chain_id: u64,
+
+    #[arg(long, require_equals = true, default_value = "CANCUN")]
+    /// Hard fork the devnet runs from genesis
+    spec_id: String,
+
+    #[arg(long, require_equals = true)]
+    /// Name of the fixture [default: devnet-<block_number>]
+    name: Option<String>,
+
+    #[arg(long, require_equals = true, default_value = "core/fixtures")]
+    /// Directory to write the fixture to
+    output: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct ReplayArgs {
+    #[arg(long, require_equals = true, default_value = "fixtures")]
+    /// Directory with the fixtures to re-execute
+    dir: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct InspectArgs {
+    #[arg(long, require_equals = true)]
+    /// Path to the input, e.g. a file of the host's cache path
+    input: PathBuf,
+
+    #[arg(long)]
+    /// Read the input from a fixture instead
+    fixture: bool,
+}
+
+async fn import_fixture(args: ImportFixtureArgs) -> HostResult<()> {
+    if args.block_number == 0 {
+        return Err(HostError::InvalidRequestConfig(
+            "The genesis block cannot be captured".to_owned(),
+        ));
+    }
+    let spec_id: SpecId = serde_json::from_value(args.spec_id.clone().into()).map_err(|_| {
+        HostError::InvalidRequestConfig(format!("Unknown spec id {}", args.spec_id))
+    })?;
+    let mut chain_spec = ChainSpec::new_single(
+        "devnet".to_owned(),
+        args.chain_id,
+        spec_id,
+        Eip1559Constants::default(),
+        false,
+    );
+    chain_spec.rpc.clone_from(&args.rpc);
+
+    let name = args
+        .name
+        .unwrap_or_else(|| format!("devnet-{}", args.block_number));
+    let provider = RpcBlockDataProvider::new(&args.rpc, args.block_number - 1)?;
+    let mut fixture = Fixture::capture(
+        &name,
+        provider,
+        args.block_number,
+        chain_spec.clone(),
+        chain_spec,
+        TaikoProverData::default(),
+    )
+    .await?;
+    // Only blocks that can be re-executed are usable by the test suite
+    fixture.record();
+    if let Some(failure) = &fixture.failure {
+        return Err(HostError::Anyhow(anyhow::anyhow!(
+            "Block {} can't be re-executed: {failure}",
+            args.block_number
+        )));
+    }
+    let hash = fixture.input.block_hash_reference;
+    let path = fixture.save(&args.output)?;
+    println!(
+        "Wrote fixture for block {} ({hash}) to {path:?}",
+        args.block_number
+    );
+    Ok(())
+}
+
+fn replay(args: ReplayArgs) -> HostResult<()> {
+    let fixtures = Fixture::load_dir(&args.dir)?;
+    let mut failed = 0;
+    for (path, fixture) in &fixtures {
+        match fixture.check() {
+            Ok(hash) => println!("ok     {} ({hash})", fixture.name),
+            Err(e) => {
+                failed += 1;
+                println!("FAILED {} ({path:?}): {e}", fixture.name);
+                if let Some(failure) = &fixture.failure {
+                    println!("       captured with: {failure}");
+                }
+            }
+        }
+    }
+    println!("{} fixtures, {failed} failed", fixtures.len());
+    if failed > 0 {
+        return Err(HostError::Anyhow(anyhow::anyhow!(
+            "{failed} fixtures failed to replay"
+        )));
+    }
+    Ok(())
+}
+
+fn inspect(args: InspectArgs) -> HostResult<()> {
+    let input = if args.fixture {
+        Fixture::load(&args.input)?.input
+    } else {
+        let file = File::open(&args.input)?;
+        bincode::deserialize_from(BufReader::new(file))
+            .map_err(|e| HostError::Conversion(format!("Could not read the input: {e}")))?
+    };
+    println!("{}", WitnessStats::new(&input)?);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> HostResult<()> {
+    env_logger::init();
+    match Cli::parse().command {
+        Command::ImportFixture(args) => import_fixture(args).await,
+        Command::Replay(args) => replay(args),
+        Command::Inspect(args) => inspect(args),
+    }
+}
@@ -0,0 +1,121 @@
+//! `raiko-host capture`, to capture a block with its witness and expected public input as
+//! a compact fixture, e.g. to reproduce a block that fails to prove.
+
+use std::path::PathBuf;
+
+use alloy_primitives::{Address, B256};
+use anyhow::{anyhow, Context};
+use clap::Args;
+use raiko_core::{fixture::Fixture, provider::rpc::RpcBlockDataProvider};
+use raiko_lib::{consts::SupportedChainSpecs, input::TaikoProverData};
+
+use crate::{
+    interfaces::{HostError, HostResult},
+    Cli,
+};
+
+#[derive(Clone, Debug, Args)]
+pub struct CaptureArgs {
+    #[arg(long, require_equals = true)]
+    /// The block to capture
+    pub block: u64,
+
+    #[arg(long, require_equals = true)]
+    /// The network of the block [default: the network of the config file]
+    pub network: Option<String>,
+
+    #[arg(long, require_equals = true)]
+    /// The L1 network of the block [default: the L1 network of the config file]
+    pub l1_network: Option<String>,
+
+    #[arg(long, require_equals = true)]
+    /// The prover the block is proven for, part of the public input [default: the prover
+    /// of the config file]
+    pub prover: Option<Address>,
+
+    #[arg(long, require_equals = true)]
+    /// The graffiti the block is proven with, part of the public input [default: the
+    /// graffiti of the config file]
+    pub graffiti: Option<B256>,
+
+    #[arg(long, require_equals = true)]
+    /// Name of the fixture [default: <network>-<block>]
+    pub name: Option<String>,
+
+    #[arg(long, require_equals = true, default_value = "fixtures")]
+    /// Directory to write the fixture to
+    pub out: PathBuf,
+}
+
+/// Captures the block with the chain specs and the proof request options of `opts`.
+///
+/// The fixture is written before the block is executed, so blocks that fail are captured
+/// too, and written again with the public input or the error of the execution.
+pub async fn run(opts: &Cli, args: &CaptureArgs) -> HostResult<()> {
+    if args.block == 0 {
+        return Err(HostError::InvalidRequestConfig(
+            "The genesis block cannot be captured".to_owned(),
+        ));
+    }
+    let chain_specs = match &opts.chain_spec_path {
+        Some(path) => SupportedChainSpecs::merge_from_file(path.clone())
+            .map_err(|e| anyhow!("Could not read {}: {e}", path.display()))?,
+        None => SupportedChainSpecs::default(),
+    };
+    let config = &opts.proof_request_opt;
+    let network = args
+        .network
+        .clone()
+        .or_else(|| config.network.clone())
+        .context("Missing network")?;
+    let l1_network = args
+        .l1_network
+        .clone()
+        .or_else(|| config.l1_network.clone())
+        .context("Missing l1 network")?;
+    let prover = match (args.prover, &config.prover) {
+        (Some(prover), _) => prover,
+        (None, Some(prover)) => prover.parse().context("Invalid prover")?,
+        (None, None) => return Err(anyhow!("Missing prover").into()),
+    };
+    let graffiti = match (args.graffiti, &config.graffiti) {
+        (Some(graffiti), _) => graffiti,
+        (None, Some(graffiti)) => graffiti.parse().context("Invalid graffiti")?,
+        (None, None) => return Err(anyhow!("Missing graffiti").into()),
+    };
+
+    let chain_spec = chain_specs
+        .get_chain_spec(&network)
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported network".to_owned()))?;
+    let l1_chain_spec = chain_specs
+        .get_chain_spec(&l1_network)
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported l1 network".to_owned()))?;
+
+    let name = args
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("{network}-{}", args.block));
+    let provider = RpcBlockDataProvider::new(&chain_spec.rpc, args.block - 1)?;
+    let mut fixture = Fixture::capture(
+        &name,
+        provider,
+        args.block,
+        l1_chain_spec,
+        chain_spec,
+        TaikoProverData { prover, graffiti },
+    )
+    .await?;
+    let path = fixture.save_compact(&args.out)?;
+    println!(
+        "Wrote fixture for block {} ({}) to {path:?}",
+        args.block, fixture.input.block_hash_reference
+    );
+
+    fixture.record();
+    fixture.save_compact(&args.out)?;
+    match &fixture.failure {
+        None => println!("Recorded public input {:?}", fixture.public_input),
+        Some(failure) => println!("Recorded failure: {failure}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+//! `raiko-host chain-spec`, to inspect and validate the configured chain specs.
+
+use anyhow::anyhow;
+use clap::{Args, Subcommand};
+use raiko_lib::consts::{ChainSpec, ForkCondition, SupportedChainSpecs};
+
+use crate::{interfaces::HostResult, Cli};
+
+#[derive(Clone, Debug, Args)]
+pub struct ChainSpecArgs {
+    #[command(subcommand)]
+    pub command: ChainSpecCommand,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum ChainSpecCommand {
+    /// Print the effective fork schedule and contract addresses
+    Dump {
+        /// The networks to print, all if none are given
+        networks: Vec<String>,
+    },
+    /// Check the chain specs for inconsistencies, failing if any is found
+    Validate {
+        /// The networks to check, all if none are given
+        networks: Vec<String>,
+    },
+}
+
+/// Runs the chain spec command on the chain specs the host would use with `opts`.
+pub fn run(opts: &Cli, args: &ChainSpecArgs) -> HostResult<()> {
+    let chain_specs = match &opts.chain_spec_path {
+        Some(path) => SupportedChainSpecs::merge_from_file(path.clone())
+            .map_err(|e| anyhow!("Could not read {}: {e}", path.display()))?,
+        None => SupportedChainSpecs::default(),
+    };
+
+    match &args.command {
+        ChainSpecCommand::Dump { networks } => {
+            for spec in select(&chain_specs, networks)? {
+                dump(&spec);
+            }
+        }
+        ChainSpecCommand::Validate { networks } => {
+            let mut invalid = 0;
+            for spec in select(&chain_specs, networks)? {
+                let issues = spec.validate();
+                if issues.is_empty() {
+                    println!("{}: ok", spec.name);
+                    continue;
+                }
+                invalid += 1;
+                for issue in issues {
+                    println!("{}: {issue}", spec.name);
+                }
+            }
+            if invalid > 0 {
+                return Err(anyhow!("{invalid} chain specs are inconsistent").into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the chain specs of the networks, sorted by name.
+fn select(chain_specs: &SupportedChainSpecs, networks: &[String]) -> HostResult<Vec<ChainSpec>> {
+    let mut networks = if networks.is_empty() {
+        chain_specs.supported_networks()
+    } else {
+        networks.to_vec()
+    };
+    networks.sort();
+    networks
+        .iter()
+        .map(|network| {
+            chain_specs
+                .get_chain_spec(network)
+                .ok_or_else(|| anyhow!("Unsupported network {network}").into())
+        })
+        .collect()
+}
+
+fn dump(spec: &ChainSpec) {
+    println!("{} (chain id {})", spec.name, spec.chain_id);
+    println!("  taiko:            {}", spec.is_taiko);
+    println!("  rpc:              {}", spec.rpc);
+    println!(
+        "  beacon rpc:       {}",
+        spec.beacon_rpc.as_deref().unwrap_or("-")
+    );
+    println!("  genesis time:     {}", spec.genesis_time);
+    println!("  seconds per slot: {}", spec.seconds_per_slot);
+    println!("  max spec id:      {:?}", spec.max_spec_id);
+
+    println!("  forks:");
+    for (spec_id, fork) in &spec.hard_forks {
+        let condition = match fork {
+            ForkCondition::Block(block) => format!("block {block}"),
+            ForkCondition::Timestamp(timestamp) => format!("timestamp {timestamp}"),
+            ForkCondition::TBD => "not scheduled".to_owned(),
+        };
+        println!("    {:<16}{condition}", format!("{spec_id:?}"));
+    }
+
+    println!("  contracts:");
+    let address = |address: Option<_>| address.map_or("-".to_owned(), |a| format!("{a}"));
+    println!("    l1:             {}", address(spec.l1_contract));
+    println!("    l2:             {}", address(spec.l2_contract));
+    for (verifier, verifier_address) in &spec.verifier_address {
+        println!(
+            "    {:<16}{}",
+            format!("{verifier:?} verifier:"),
+            address(*verifier_address)
+        );
+    }
+    println!();
+}
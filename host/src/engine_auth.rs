@@ -0,0 +1,211 @@
+//! Authentication of the Engine API calls with the HS256 JWT shared with the consensus
+//! client, as specified in the Engine API authentication spec.
+//!
+//! The secret is the same 32 byte hex file (`jwt.hex`) the execution client is started
+//! with. Every call carries a fresh token whose `iat` claim has to be within
+//! [MAX_IAT_DRIFT_SECS] of the local time.
+
+use std::path::Path;
+
+use anyhow::{anyhow, ensure, Context};
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    interfaces::{HostError, HostResult},
+    jobs::unix_time,
+    ProverState,
+};
+
+/// Maximum difference between the `iat` claim of a token and the local time.
+pub const MAX_IAT_DRIFT_SECS: u64 = 60;
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+}
+
+#[derive(Deserialize)]
+struct JwtClaims {
+    iat: u64,
+}
+
+/// The secret the Engine API tokens are signed with.
+pub struct EngineJwt {
+    secret: [u8; 32],
+}
+
+impl std::fmt::Debug for EngineJwt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EngineJwt").finish_non_exhaustive()
+    }
+}
+
+impl EngineJwt {
+    pub fn new(secret: [u8; 32]) -> Self {
+        Self { secret }
+    }
+
+    /// Reads the hex encoded secret, with or without `0x` prefix, from a file.
+    pub fn from_file(path: &Path) -> HostResult<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read the Engine API secret {}", path.display()))?;
+        let content = content.trim();
+        let secret = hex::decode(content.strip_prefix("0x").unwrap_or(content))
+            .context("The Engine API secret is not hex encoded")?;
+        let secret = secret
+            .try_into()
+            .map_err(|_| anyhow!("The Engine API secret has to be 32 bytes"))?;
+        Ok(Self::new(secret))
+    }
+
+    /// Checks the signature and the `iat` claim of the token at the time `now`, in seconds
+    /// since the epoch.
+    pub fn validate(&self, token: &str, now: u64) -> anyhow::Result<()> {
+        let mut parts = token.split('.');
+        let (Some(header), Some(claims), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(anyhow!("Malformed token"));
+        };
+
+        let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).context("Malformed token");
+        let signing_input = &token[..header.len() + 1 + claims.len()];
+        let expected = hmac_sha256(&self.secret, signing_input.as_bytes());
+        ensure!(
+            keys_match(&expected, &decode(signature)?),
+            "Invalid token signature"
+        );
+
+        let header: JwtHeader = serde_json::from_slice(&decode(header)?)?;
+        ensure!(
+            header.alg == "HS256",
+            "Unsupported token algorithm {}",
+            header.alg
+        );
+        let claims: JwtClaims = serde_json::from_slice(&decode(claims)?)?;
+        ensure!(
+            claims.iat.abs_diff(now) <= MAX_IAT_DRIFT_SECS,
+            "Stale token, issued at {}",
+            claims.iat
+        );
+        Ok(())
+    }
+}
+
+/// An authenticated Engine API call.
+///
+/// With an Engine API secret the call needs a valid JWT, otherwise the JWT secret of the
+/// other routes is checked as a plain bearer token, if set.
+pub struct EngineAuth;
+
+#[async_trait]
+impl FromRequestParts<ProverState> for EngineAuth {
+    type Rejection = HostError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        prover_state: &ProverState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if let Some(engine_jwt) = &prover_state.engine_jwt {
+            let token = token
+                .ok_or_else(|| HostError::Unauthorized("Missing Engine API JWT".to_owned()))?;
+            engine_jwt
+                .validate(token, unix_time())
+                .map_err(|e| HostError::Unauthorized(e.to_string()))?;
+        } else if let Some(secret) = &prover_state.config().opts.jwt_secret {
+            if !token.is_some_and(|token| keys_match(secret.as_bytes(), token.as_bytes())) {
+                return Err(HostError::Unauthorized("Invalid bearer token".to_owned()));
+            }
+        }
+        Ok(EngineAuth)
+    }
+}
+
+/// HMAC-SHA256 as of RFC 2104.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner = Sha256::new()
+        .chain_update(block.map(|b| b ^ 0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(block.map(|b| b ^ 0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// Compares the signatures in constant time.
+fn keys_match(expected: &[u8], actual: &[u8]) -> bool {
+    expected.len() == actual.len()
+        && expected
+            .iter()
+            .zip(actual)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &[u8; 32], header: &str, claims: &str) -> String {
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header),
+            URL_SAFE_NO_PAD.encode(claims)
+        );
+        let signature = hmac_sha256(secret, signing_input.as_bytes());
+        format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature))
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let secret = [7u8; 32];
+        let jwt = EngineJwt::new(secret);
+        let header = r#"{"alg":"HS256","typ":"JWT"}"#;
+        let now = 1_700_000_000;
+
+        let token = sign(&secret, header, &format!(r#"{{"iat":{now}}}"#));
+        assert!(jwt.validate(&token, now + MAX_IAT_DRIFT_SECS).is_ok());
+        // stale
+        assert!(jwt.validate(&token, now + MAX_IAT_DRIFT_SECS + 1).is_err());
+        // signed with another secret
+        let token = sign(&[8u8; 32], header, &format!(r#"{{"iat":{now}}}"#));
+        assert!(jwt.validate(&token, now).is_err());
+        // unsigned
+        let token = sign(&secret, r#"{"alg":"none"}"#, &format!(r#"{{"iat":{now}}}"#));
+        assert!(jwt.validate(&token, now).is_err());
+        // without iat
+        let token = sign(&secret, header, "{}");
+        assert!(jwt.validate(&token, now).is_err());
+        assert!(jwt.validate("not a token", now).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+//! Build manifests of the guests, hashed into the metadata of every proof, and
+//! `raiko-host verify-build` to reproduce a guest from its manifest.
+//!
+//! The manifests are written by the guest builders next to the guest images. With the
+//! manifest of a proof, anyone can rebuild the guest from the same commit, features and
+//! toolchain, and check that it results in the image ID the proof was verified against.
+
+use std::{collections::HashMap, path::Path, path::PathBuf, process::Command};
+
+use alloy_primitives::{keccak256, B256};
+use anyhow::{anyhow, bail, ensure, Context};
+use clap::Args;
+use raiko_core::interfaces::ProofType;
+use raiko_pipeline::{
+    manifest::uncommitted_changes,
+    patches::{CYCLE_PROFILE_ENV, GUEST_FEATURES_ENV},
+    BuildManifest,
+};
+use tracing::warn;
+
+use crate::interfaces::HostResult;
+
+/// The manifest of a guest, with the hash it's referred to by in the proof metadata.
+#[derive(Debug, Clone)]
+pub struct GuestBuild {
+    pub manifest: BuildManifest,
+    /// The manifest file as published, which is what the hash is taken of.
+    pub json: String,
+    pub hash: B256,
+}
+
+/// The builds of the guests the host proves with, by proof type.
+#[derive(Debug, Clone, Default)]
+pub struct GuestBuilds(HashMap<String, GuestBuild>);
+
+impl GuestBuilds {
+    /// Reads the manifests configured in the `build_manifests` of the config file.
+    ///
+    /// Fails if a manifest is for another backend than its proof type, or, if the host is
+    /// built with the backend, for another image than the prover runs.
+    pub fn load(paths: &HashMap<String, PathBuf>) -> HostResult<Self> {
+        let mut builds = HashMap::with_capacity(paths.len());
+        for (proof_type, path) in paths {
+            let proof_type = proof_type.parse::<ProofType>()?;
+            let (manifest, json) = BuildManifest::read(path)?;
+            check_image(&proof_type, &manifest)
+                .with_context(|| format!("Invalid build manifest {}", path.display()))?;
+            if manifest.dirty {
+                warn!(
+                    "The {proof_type} guest was built with uncommitted changes, so its build \
+                     can't be reproduced"
+                );
+            }
+            let hash = manifest_hash(&json);
+            let build = GuestBuild {
+                manifest,
+                json,
+                hash,
+            };
+            builds.insert(proof_type.to_string(), build);
+        }
+        Ok(Self(builds))
+    }
+
+    pub fn get(&self, proof_type: &str) -> Option<&GuestBuild> {
+        self.0.get(proof_type)
+    }
+
+    /// Returns the hash of the manifest of the proof type, zero if it has none.
+    pub fn hash(&self, proof_type: &str) -> B256 {
+        self.get(proof_type).map_or(B256::ZERO, |build| build.hash)
+    }
+}
+
+/// Returns the hash of the manifest, the keccak256 of the manifest file.
+pub fn manifest_hash(json: &str) -> B256 {
+    keccak256(json)
+}
+
+/// Checks that the manifest is for the backend of the proof type and, if the host is built
+/// with the backend, for the image its prover runs.
+fn check_image(proof_type: &ProofType, manifest: &BuildManifest) -> anyhow::Result<()> {
+    let backend = match proof_type {
+        ProofType::Risc0 | ProofType::Sp1 => proof_type.to_string(),
+        // The other proof types have no image ID to check
+        _ => return Ok(()),
+    };
+    ensure!(
+        manifest.backend == backend,
+        "The manifest is for the {} backend, not {backend}",
+        manifest.backend
+    );
+    if let Some(image_id) = prover_image_id(proof_type) {
+        ensure!(
+            manifest.image_id == image_id,
+            "The manifest is for the image 0x{}, but the {backend} prover runs 0x{image_id}",
+            manifest.image_id
+        );
+    }
+    Ok(())
+}
+
+/// Returns the hex encoded image ID of the guest the prover of the proof type runs, in the
+/// format of the build manifests, if the host is built with the prover.
+fn prover_image_id(proof_type: &ProofType) -> Option<String> {
+    match proof_type {
+        ProofType::Risc0 => {
+            #[cfg(feature = "risc0")]
+            {
+                use risc0_driver::methods::risc0_guest::RISC0_GUEST_ID;
+                let image_id: Vec<u8> = RISC0_GUEST_ID
+                    .iter()
+                    .flat_map(|word| word.to_le_bytes())
+                    .collect();
+                Some(hex::encode(image_id))
+            }
+            #[cfg(not(feature = "risc0"))]
+            None
+        }
+        ProofType::Sp1 => {
+            #[cfg(feature = "sp1")]
+            {
+                Some(sp1_driver::guest_vkey().trim_start_matches("0x").to_owned())
+            }
+            #[cfg(not(feature = "sp1"))]
+            None
+        }
+        ProofType::Native | ProofType::Sgx | ProofType::Sandbox => None,
+    }
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct VerifyBuildArgs {
+    /// The build manifest of the guest, as published by the operator of the host
+    pub manifest: PathBuf,
+    /// The checkout of the repository to rebuild the guest in, which has to be at the
+    /// commit of the manifest. The guest images in it are overwritten.
+    #[arg(long, default_value = ".")]
+    pub source: PathBuf,
+    /// The `buildHash` of the proof metadata, checked against the hash of the manifest
+    #[arg(long)]
+    pub build_hash: Option<B256>,
+}
+
+/// Rebuilds the guest of the manifest and checks that the image ID matches.
+pub fn verify_build(args: &VerifyBuildArgs) -> HostResult<()> {
+    Ok(verify(args)?)
+}
+
+fn verify(args: &VerifyBuildArgs) -> anyhow::Result<()> {
+    let (expected, json) = BuildManifest::read(&args.manifest)?;
+    let hash = manifest_hash(&json);
+    println!(
+        "{} ({}) at {}",
+        expected.guest, expected.backend, expected.commit
+    );
+    println!("  manifest hash:    {hash}");
+    println!("  image id:         0x{}", expected.image_id);
+    if let Some(build_hash) = args.build_hash {
+        ensure!(
+            build_hash == hash,
+            "The manifest doesn't match the build hash {build_hash}"
+        );
+    }
+    ensure!(
+        !expected.dirty,
+        "The guest was built with uncommitted changes, so it can't be reproduced"
+    );
+
+    // The builders place the guests, and their manifests, in fixed directories
+    let (builder, dest) = match expected.backend.as_str() {
+        "risc0" => ("risc0-builder", "provers/risc0/driver/src/methods"),
+        "sp1" => ("sp1-builder", "provers/sp1/guest/elf"),
+        backend => bail!("Unsupported backend {backend}"),
+    };
+    ensure!(
+        expected.profile == "release",
+        "The builders only build release guests, the guest was built in {}",
+        expected.profile
+    );
+    let head = git(&args.source, &["rev-parse", "HEAD"])?;
+    ensure!(
+        head == expected.commit,
+        "The source is at {head}, check out {} first",
+        expected.commit
+    );
+    // The rebuild replaces the guests, so changes to them are expected
+    let changes = uncommitted_changes(&args.source).context("Could not run git status")?;
+    ensure!(
+        changes.is_empty(),
+        "The source has uncommitted changes:\n{changes}"
+    );
+
+    println!("Rebuilding with {builder}");
+    let mut cmd = Command::new("cargo");
+    cmd.args(["run", "--bin", builder])
+        .current_dir(&args.source)
+        .env_remove(CYCLE_PROFILE_ENV);
+    match &expected.features {
+        Some(features) => cmd.env(GUEST_FEATURES_ENV, features.join(",")),
+        None => cmd.env_remove(GUEST_FEATURES_ENV),
+    };
+    let status = cmd.status().context("Could not run the guest builder")?;
+    ensure!(status.success(), "The guest builder failed with {status}");
+
+    let rebuilt = BuildManifest::load(&BuildManifest::path(
+        &args.source.join(dest),
+        &expected.guest,
+    ))?;
+    // Differences in the environment are the likely cause of a different image
+    let differences = [
+        ("toolchain", &expected.toolchain, &rebuilt.toolchain),
+        ("target", &expected.target, &rebuilt.target),
+        (
+            "rust flags",
+            &expected.rust_flags.join(" "),
+            &rebuilt.rust_flags.join(" "),
+        ),
+        (
+            "c flags",
+            &expected.c_flags.join(" "),
+            &rebuilt.c_flags.join(" "),
+        ),
+    ];
+    for (name, expected, rebuilt) in differences {
+        if expected != rebuilt {
+            println!("  {name} differs: expected {expected:?}, rebuilt with {rebuilt:?}");
+        }
+    }
+    if rebuilt.image_id != expected.image_id {
+        return Err(anyhow!(
+            "The rebuilt image ID 0x{} doesn't match 0x{}",
+            rebuilt.image_id,
+            expected.image_id
+        ));
+    }
+    println!("  reproduced:       ok");
+    Ok(())
+}
+
+/// Returns the trimmed output of the git command in the repository.
+fn git(repo: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .context("Could not run git")?;
+    ensure!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the manifest to a new temporary directory and returns its path.
+    fn write(name: &str, manifest: &BuildManifest) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("raiko-build-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        manifest.write(&dir).unwrap();
+        BuildManifest::path(&dir, &manifest.guest)
+    }
+
+    /// Returns a manifest of the image the prover of the backend runs, if the host is built
+    /// with it.
+    fn manifest(backend: &str) -> BuildManifest {
+        let proof_type = backend.parse().unwrap();
+        BuildManifest {
+            guest: format!("{backend}-guest"),
+            backend: backend.to_owned(),
+            toolchain: "rustc 1.77.0".to_owned(),
+            target: "riscv32im-risc0-zkvm-elf".to_owned(),
+            profile: "release".to_owned(),
+            commit: "7679b8b".to_owned(),
+            image_id: prover_image_id(&proof_type).unwrap_or_else(|| "00ff".to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_manifest_hash() {
+        let path = write("hash", &manifest("risc0"));
+        let content = std::fs::read(&path).unwrap();
+        // The published file is hashed as it is, not a re-encoding of it
+        let builds =
+            GuestBuilds::load(&HashMap::from([("risc0".to_owned(), path.clone())])).unwrap();
+        assert_eq!(builds.hash("risc0"), keccak256(&content));
+        assert_ne!(
+            builds.hash("risc0"),
+            keccak256(serde_json::to_vec(&manifest("risc0")).unwrap())
+        );
+        assert_eq!(builds.get("risc0").unwrap().json.as_bytes(), content);
+        assert_eq!(builds.hash("sp1"), B256::ZERO);
+    }
+
+    #[test]
+    fn test_load() {
+        let dirty = BuildManifest {
+            dirty: true,
+            ..manifest("sp1")
+        };
+        let sp1 = write("load", &dirty);
+        let paths = HashMap::from([("SP1".to_owned(), sp1.clone())]);
+        // Dirty builds are loaded, but can't be verified
+        let builds = GuestBuilds::load(&paths).unwrap();
+        assert_eq!(builds.get("sp1").unwrap().manifest, dirty);
+
+        // The manifest of another backend
+        let paths = HashMap::from([("risc0".to_owned(), sp1.clone())]);
+        assert!(GuestBuilds::load(&paths).is_err());
+        // Proof types without an image only hash the manifest
+        let paths = HashMap::from([("sgx".to_owned(), sp1)]);
+        assert!(GuestBuilds::load(&paths).is_ok());
+        let paths = HashMap::from([("sp2".to_owned(), PathBuf::from("sp2.manifest.json"))]);
+        assert!(GuestBuilds::load(&paths).is_err());
+    }
+
+    #[cfg(feature = "risc0")]
+    #[test]
+    fn test_image_mismatch() {
+        // The image ID of the manifest is not the one of the embedded guest
+        let stale = BuildManifest {
+            image_id: "00ff".to_owned(),
+            ..manifest("risc0")
+        };
+        let path = write("mismatch", &stale);
+        let paths = HashMap::from([("risc0".to_owned(), path)]);
+        assert!(GuestBuilds::load(&paths).is_err());
+    }
+
+    #[test]
+    fn test_verify_checks_manifest() {
+        let path = write("verify", &manifest("risc0"));
+        let args = VerifyBuildArgs {
+            manifest: path.clone(),
+            source: PathBuf::from("."),
+            build_hash: Some(B256::repeat_byte(1)),
+        };
+        let error = verify(&args).unwrap_err().to_string();
+        assert!(error.contains("build hash"), "{error}");
+
+        let dirty = BuildManifest {
+            dirty: true,
+            ..manifest("risc0")
+        };
+        let path = write("verify-dirty", &dirty);
+        let args = VerifyBuildArgs {
+            manifest: path.clone(),
+            source: PathBuf::from("."),
+            build_hash: Some(manifest_hash(&std::fs::read_to_string(&path).unwrap())),
+        };
+        let error = verify(&args).unwrap_err().to_string();
+        assert!(error.contains("uncommitted changes"), "{error}");
+    }
+}
@@ -1,6 +1,6 @@
 use axum::response::IntoResponse;
-use raiko_core::interfaces::ProofType;
-use raiko_lib::prover::ProverError;
+use raiko_core::interfaces::{FeeTerms, ProofType};
+use raiko_lib::{input::InputLimitError, prover::ProverError};
 use utoipa::ToSchema;
 
 /// The standardized error returned by the Raiko host.
@@ -52,6 +52,32 @@ pub enum HostError {
     #[schema(value_type = Value)]
     FeatureNotSupportedError(ProofType),
 
+    /// For inputs that exceed the configured limits.
+    #[error("The input exceeds the limits: {0}")]
+    #[schema(value_type = Value)]
+    InputLimit(InputLimitError),
+
+    /// For proof requests whose fee is rejected by the pricing policy.
+    #[error("The fee was rejected: {0}")]
+    FeeRejected(String),
+
+    /// For proof requests whose fee is too low, with the fee the proof is generated for.
+    #[error("The fee is countered with {}", serde_json::to_string(.0).unwrap_or_default())]
+    #[schema(value_type = Value)]
+    FeeCountered(FeeTerms),
+
+    /// For requests without a valid API key or the permission for the route.
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    /// For proof requests exceeding the quota of the tenant.
+    #[error("The quota is exceeded: {0}")]
+    QuotaExceeded(String),
+
+    /// For requests of results that don't exist, or aren't visible to the tenant.
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     /// A catch-all error for any other error type.
     #[error("There was an unexpected error: {0}")]
     #[schema(value_type = Value)]
@@ -73,6 +99,15 @@ impl IntoResponse for HostError {
             HostError::FeatureNotSupportedError(t) => {
                 ("feature_not_supported_error".to_string(), t.to_string())
             }
+            HostError::InputLimit(e) => ("input_limit_error".to_string(), e.to_string()),
+            HostError::FeeRejected(e) => ("fee_rejected".to_string(), e),
+            HostError::FeeCountered(fee) => (
+                "fee_countered".to_string(),
+                serde_json::to_string(&fee).unwrap_or_default(),
+            ),
+            HostError::Unauthorized(e) => ("unauthorized".to_string(), e),
+            HostError::QuotaExceeded(e) => ("quota_exceeded".to_string(), e),
+            HostError::NotFound(e) => ("not_found".to_string(), e),
             HostError::Anyhow(e) => ("anyhow_error".to_string(), e.to_string()),
         };
         axum::Json(serde_json::json!({ "status": "error", "error": error, "message": message }))
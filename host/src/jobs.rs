@@ -0,0 +1,1160 @@
+//! Tracking of the proof requests served by the host, for the status API.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use raiko_core::interfaces::ProofRequest;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use utoipa::ToSchema;
+
+use crate::{
+    interfaces::{HostError, HostResult},
+    journal::{persisted, InterruptedJob, Journal, JournalEntry, Persisted},
+    market::ProofPackage,
+    tenants::Tenant,
+};
+
+/// Number of failed jobs that are kept for the status.
+const MAX_RECENT_FAILURES: usize = 32;
+/// Number of proof market submissions that are kept for the status.
+pub const MAX_RECENT_SUBMISSIONS: usize = 32;
+/// Number of results of finished jobs that are kept to be fetched by job id.
+pub const MAX_RECENT_RESULTS: usize = 32;
+/// Number of times a job interrupted by a crash is restarted, so that a job crashing the
+/// host doesn't do so forever.
+const MAX_RESTARTS: u32 = 3;
+
+/// The stage of a proof request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStage {
+    /// Waiting for one of the concurrency limit slots.
+    Queued,
+    /// Fetching the input of the block.
+    Preflight,
+    /// Executing the block natively to check the input.
+    Executing,
+    /// Running the prover backend.
+    Proving,
+}
+
+impl JobStage {
+    /// The share of the stages that have been completed when the stage starts.
+    pub fn progress(&self) -> f64 {
+        match self {
+            JobStage::Queued => 0.0,
+            JobStage::Preflight => 0.1,
+            JobStage::Executing => 0.4,
+            JobStage::Proving => 0.5,
+        }
+    }
+}
+
+/// A proof request that is queued or being served.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JobStatus {
+    pub id: u64,
+    pub tenant: String,
+    pub block_number: u64,
+    pub network: String,
+    pub proof_type: String,
+    pub stage: JobStage,
+    /// Rough share of the job that is done, between 0 and 1.
+    pub progress: f64,
+    /// Seconds since the job was received.
+    pub elapsed_secs: f64,
+    /// Seconds since the job entered its current stage.
+    pub stage_secs: f64,
+}
+
+/// A proof request that failed.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FailedJob {
+    pub job: JobStatus,
+    /// Unix timestamp of the failure.
+    pub failed_at: u64,
+    pub error: String,
+}
+
+/// The result of a finished proof request, which can be fetched by its job id, e.g. when
+/// the request was restarted after a crash.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JobResult {
+    pub id: u64,
+    pub tenant: String,
+    pub block_number: u64,
+    pub network: String,
+    pub proof_type: String,
+    /// Unix timestamp of the end of the job.
+    pub finished_at: u64,
+    #[serde(flatten)]
+    pub outcome: JobOutcome,
+}
+
+/// The outcome of a proof request, in the format of the responses of the proof route.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobOutcome {
+    Ok {
+        #[schema(value_type = Object)]
+        data: Value,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// The state of the submission of a proof to the proof market.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SubmissionState {
+    /// Sending the proof to the market, retried on failures.
+    Submitting,
+    /// Received by the market, which didn't decide on the proof yet.
+    Pending { id: String },
+    /// Accepted by the market.
+    Accepted { id: String },
+    /// Rejected by the market.
+    Rejected { id: Option<String>, reason: String },
+    /// The market couldn't be reached, or didn't decide in time.
+    Failed { error: String },
+}
+
+/// The submission of the proof of a finished job to the proof market.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SubmissionStatus {
+    pub job_id: u64,
+    pub tenant: String,
+    pub block_number: u64,
+    pub network: String,
+    pub proof_type: String,
+    pub state: SubmissionState,
+    /// Number of requests sent to the market so far, including status polls.
+    pub attempts: u32,
+    /// Unix timestamp of the last change.
+    pub updated_at: u64,
+}
+
+/// A submission as stored next to the journal, so it's resumed after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmissionRecord {
+    pub status: SubmissionStatus,
+    /// The proof, until the market received it.
+    pub package: Option<ProofPackage>,
+}
+
+impl SubmissionRecord {
+    /// Whether the market didn't decide on the proof yet.
+    pub fn in_flight(&self) -> bool {
+        matches!(
+            self.status.state,
+            SubmissionState::Submitting | SubmissionState::Pending { .. }
+        )
+    }
+}
+
+/// The usage of a prover backend by the jobs of all tenants.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BackendStatus {
+    pub proof_type: String,
+    /// Number of jobs currently proving with the backend.
+    pub proving: usize,
+    pub completed: u64,
+    pub failed: u64,
+    /// Share of the uptime during which the backend was proving, between 0 and 1.
+    pub utilization: f64,
+}
+
+/// The status of the host, as served by the status API.
+///
+/// Tenants that aren't operators only see their own jobs and failures.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HostStatus {
+    pub uptime_secs: f64,
+    pub concurrency_limit: usize,
+    /// Jobs waiting for a slot, oldest first.
+    pub queue: Vec<JobStatus>,
+    /// Jobs being served, oldest first.
+    pub jobs: Vec<JobStatus>,
+    pub backends: Vec<BackendStatus>,
+    /// The most recent failures, newest first.
+    pub failures: Vec<FailedJob>,
+    /// The most recent submissions to the proof market, newest first.
+    pub submissions: Vec<SubmissionStatus>,
+}
+
+struct Job {
+    tenant: String,
+    block_number: u64,
+    network: String,
+    proof_type: String,
+    stage: JobStage,
+    received: Instant,
+    stage_started: Instant,
+}
+
+impl Job {
+    fn new(request: &ProofRequest, tenant: &str, now: Instant) -> Self {
+        Self {
+            tenant: tenant.to_owned(),
+            block_number: request.block_number,
+            network: request.network.clone(),
+            proof_type: request.proof_type.to_string(),
+            stage: JobStage::Queued,
+            received: now,
+            stage_started: now,
+        }
+    }
+
+    fn status(&self, id: u64, now: Instant) -> JobStatus {
+        JobStatus {
+            id,
+            tenant: self.tenant.clone(),
+            block_number: self.block_number,
+            network: self.network.clone(),
+            proof_type: self.proof_type.clone(),
+            stage: self.stage,
+            progress: self.stage.progress(),
+            elapsed_secs: (now - self.received).as_secs_f64(),
+            stage_secs: (now - self.stage_started).as_secs_f64(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Backend {
+    proving: usize,
+    completed: u64,
+    failed: u64,
+    /// Time spent proving, excluding the current busy period.
+    busy_secs: f64,
+    busy_since: Option<Instant>,
+}
+
+impl Backend {
+    fn start_proving(&mut self, now: Instant) {
+        if self.proving == 0 {
+            self.busy_since = Some(now);
+        }
+        self.proving += 1;
+    }
+
+    fn stop_proving(&mut self, now: Instant) {
+        self.proving = self.proving.saturating_sub(1);
+        if self.proving == 0 {
+            if let Some(since) = self.busy_since.take() {
+                self.busy_secs += (now - since).as_secs_f64();
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Jobs {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+    backends: HashMap<String, Backend>,
+    failures: VecDeque<FailedJob>,
+    submissions: VecDeque<SubmissionStatus>,
+    /// The results of the most recently finished jobs, newest first.
+    results: VecDeque<JobResult>,
+    journal: Option<Journal>,
+    /// Jobs of the journal that still have to be restarted.
+    interrupted: Vec<InterruptedJob>,
+    /// Submissions of the journal that still have to be resumed.
+    interrupted_submissions: Vec<SubmissionRecord>,
+    concurrency_limit: usize,
+    /// Slots that are in use beyond a lowered concurrency limit, which are removed once
+    /// they are released.
+    excess_slots: usize,
+}
+
+impl Jobs {
+    /// Queues the entry to be written to the journal, if any. Failed writes are logged by
+    /// the journal, callers that need the entry to be persisted wait for the returned
+    /// receiver.
+    fn journal(&self, entry: JournalEntry) -> Option<Persisted> {
+        self.journal.as_ref().map(|journal| journal.append(entry))
+    }
+
+    fn store_result(&mut self, result: JobResult) {
+        if let Some(journal) = &self.journal {
+            journal.store_result(result.clone());
+        }
+        self.results.push_front(result);
+        self.results.truncate(MAX_RECENT_RESULTS);
+    }
+
+    fn record_failure(&mut self, job: JobStatus, error: String) {
+        let failed_at = unix_time();
+        self.failures.push_front(FailedJob {
+            job,
+            failed_at,
+            error,
+        });
+        self.failures.truncate(MAX_RECENT_FAILURES);
+    }
+}
+
+/// The proof requests of the host, which also enforces the concurrency limit on them.
+#[derive(Clone)]
+pub struct JobStore {
+    jobs: Arc<Mutex<Jobs>>,
+    slots: Arc<Semaphore>,
+    started: Instant,
+}
+
+impl std::fmt::Debug for JobStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobStore")
+            .field("concurrency_limit", &self.lock().concurrency_limit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl JobStore {
+    /// Creates a store that serves up to `concurrency_limit` jobs at once.
+    pub fn new(concurrency_limit: usize) -> Self {
+        let jobs = Jobs {
+            concurrency_limit,
+            ..Default::default()
+        };
+        Self {
+            jobs: Arc::new(Mutex::new(jobs)),
+            slots: Arc::new(Semaphore::new(concurrency_limit)),
+            started: Instant::now(),
+        }
+    }
+
+    /// Changes the number of jobs that are served at once.
+    ///
+    /// Running jobs are never interrupted, so a lower limit only takes full effect once
+    /// enough of them finished. Queued jobs stay queued.
+    pub fn set_concurrency_limit(&self, concurrency_limit: usize) {
+        let mut jobs = self.lock();
+        let current = jobs.concurrency_limit;
+        jobs.concurrency_limit = concurrency_limit;
+        if concurrency_limit >= current {
+            let added = concurrency_limit - current;
+            let reclaimed = added.min(jobs.excess_slots);
+            jobs.excess_slots -= reclaimed;
+            self.slots.add_permits(added - reclaimed);
+            return;
+        }
+
+        let mut removed = current - concurrency_limit;
+        while removed > 0 {
+            let Ok(slot) = self.slots.try_acquire() else {
+                break;
+            };
+            slot.forget();
+            removed -= 1;
+        }
+        jobs.excess_slots += removed;
+    }
+
+    /// Records all jobs in the journal at `path`, and loads the jobs that were interrupted
+    /// by the previous run of the host to be restarted with [Self::take_interrupted], and
+    /// the results that were stored.
+    ///
+    /// Jobs that were already restarted too often are recorded as failed instead.
+    pub fn with_journal(self, path: &Path) -> io::Result<Self> {
+        let (journal, state) = Journal::open(path)?;
+        let now = Instant::now();
+        {
+            let mut jobs = self.lock();
+            // Job ids stay unique across restarts, so results are never mixed up
+            let interrupted_ids = state.interrupted.iter().map(|job| job.id);
+            let result_ids = state.results.iter().map(|result| result.id);
+            let submission_ids = state.submissions.iter().map(|record| record.status.job_id);
+            jobs.next_id = interrupted_ids
+                .chain(result_ids)
+                .chain(submission_ids)
+                .map(|id| id + 1)
+                .max()
+                .unwrap_or(0);
+            jobs.journal = Some(journal);
+            jobs.results = state.results.into();
+            jobs.submissions = state
+                .submissions
+                .iter()
+                .take(MAX_RECENT_SUBMISSIONS)
+                .map(|record| record.status.clone())
+                .collect();
+            jobs.interrupted_submissions = state
+                .submissions
+                .into_iter()
+                .filter(SubmissionRecord::in_flight)
+                .collect();
+            for job in state.interrupted {
+                if job.attempt <= MAX_RESTARTS {
+                    jobs.interrupted.push(job);
+                    continue;
+                }
+                jobs.journal(JournalEntry::Finished { id: job.id });
+                let mut status = Job::new(&job.request, &job.tenant, now).status(job.id, now);
+                status.stage = job.stage;
+                let error = format!("Interrupted {} times, not restarted", job.attempt);
+                jobs.record_failure(status, error);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Registers the interrupted jobs again and returns them to be restarted.
+    pub fn take_interrupted(&self) -> Vec<(ProofRequest, JobHandle)> {
+        let now = Instant::now();
+        let mut jobs = self.lock();
+        let interrupted = std::mem::take(&mut jobs.interrupted);
+        interrupted
+            .into_iter()
+            .map(|job| {
+                jobs.jobs
+                    .insert(job.id, Job::new(&job.request, &job.tenant, now));
+                (job.request, self.handle(job.id, job.tenant))
+            })
+            .collect()
+    }
+
+    /// Returns the trackers of the submissions the market didn't decide on before the
+    /// previous run of the host stopped, to be resumed.
+    pub fn take_interrupted_submissions(&self) -> Vec<SubmissionTracker> {
+        let interrupted = std::mem::take(&mut self.lock().interrupted_submissions);
+        interrupted
+            .into_iter()
+            .map(|record| SubmissionTracker {
+                store: self.clone(),
+                submission: record.status,
+                package: record.package,
+            })
+            .collect()
+    }
+
+    /// Registers a queued job for the request of the tenant.
+    ///
+    /// Fails if the tenant already has as many jobs as its quota allows, or if the job
+    /// can't be written to the journal, because it wouldn't be restarted after a crash.
+    pub async fn enqueue(&self, request: &ProofRequest, tenant: &Tenant) -> HostResult<JobHandle> {
+        let (id, journaled) = self.register(request, tenant)?;
+        if let Some(journaled) = journaled {
+            if let Err(e) = persisted(journaled).await {
+                self.lock().jobs.remove(&id);
+                return Err(e.into());
+            }
+        }
+        Ok(self.handle(id, tenant.name.clone()))
+    }
+
+    /// Registers the job, returning its id and the receiver of its journal write.
+    fn register(
+        &self,
+        request: &ProofRequest,
+        tenant: &Tenant,
+    ) -> HostResult<(u64, Option<Persisted>)> {
+        let mut jobs = self.lock();
+        if let Some(max_jobs) = tenant.max_jobs {
+            let tenant_jobs = jobs
+                .jobs
+                .values()
+                .filter(|job| job.tenant == tenant.name)
+                .count();
+            if tenant_jobs >= max_jobs {
+                return Err(HostError::QuotaExceeded(format!(
+                    "Tenant {} already has {tenant_jobs} of {max_jobs} jobs",
+                    tenant.name
+                )));
+            }
+        }
+        let id = jobs.next_id;
+        let journaled = jobs.journal(JournalEntry::Enqueued {
+            id,
+            request: request.clone(),
+            tenant: tenant.name.clone(),
+            attempt: 0,
+        });
+        jobs.next_id += 1;
+        jobs.jobs
+            .insert(id, Job::new(request, &tenant.name, Instant::now()));
+        Ok((id, journaled))
+    }
+
+    fn handle(&self, id: u64, tenant: String) -> JobHandle {
+        JobHandle {
+            store: self.clone(),
+            id,
+            tenant,
+            slot: None,
+        }
+    }
+
+    /// Returns the current status of the jobs visible to the tenant.
+    pub fn status(&self, tenant: &Tenant) -> HostStatus {
+        let now = Instant::now();
+        let jobs = self.lock();
+        let uptime_secs = (now - self.started).as_secs_f64();
+        let visible = |job_tenant: &str| tenant.operator || job_tenant == tenant.name;
+
+        let (queue, jobs_status) = jobs
+            .jobs
+            .iter()
+            .filter(|(_, job)| visible(&job.tenant))
+            .map(|(id, job)| job.status(*id, now))
+            .partition(|job| job.stage == JobStage::Queued);
+
+        let mut backends: Vec<BackendStatus> = jobs
+            .backends
+            .iter()
+            .map(|(proof_type, backend)| {
+                let busy_secs = backend.busy_secs
+                    + backend
+                        .busy_since
+                        .map_or(0.0, |since| (now - since).as_secs_f64());
+                BackendStatus {
+                    proof_type: proof_type.clone(),
+                    proving: backend.proving,
+                    completed: backend.completed,
+                    failed: backend.failed,
+                    utilization: if uptime_secs > 0.0 {
+                        (busy_secs / uptime_secs).min(1.0)
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+        backends.sort_by(|a, b| a.proof_type.cmp(&b.proof_type));
+
+        HostStatus {
+            uptime_secs,
+            concurrency_limit: jobs.concurrency_limit,
+            queue,
+            jobs: jobs_status,
+            backends,
+            failures: jobs
+                .failures
+                .iter()
+                .filter(|failure| visible(&failure.job.tenant))
+                .cloned()
+                .collect(),
+            submissions: jobs
+                .submissions
+                .iter()
+                .filter(|submission| visible(&submission.tenant))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Returns the result of the finished job, if it belongs to the tenant.
+    pub fn result(&self, id: u64, tenant: &Tenant) -> HostResult<JobResult> {
+        let jobs = self.lock();
+        let visible = |job_tenant: &str| tenant.operator || job_tenant == tenant.name;
+        if let Some(result) = jobs.results.iter().find(|result| result.id == id) {
+            if visible(&result.tenant) {
+                return Ok(result.clone());
+            }
+        } else if jobs.jobs.get(&id).is_some_and(|job| visible(&job.tenant)) {
+            return Err(HostError::NotFound(format!("Job {id} is not finished yet")));
+        }
+        Err(HostError::NotFound(format!("No result for job {id}")))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Jobs> {
+        // The tracking is only informational, so a panic while holding the lock is ignored
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn set_stage(&self, id: u64, stage: JobStage) {
+        let now = Instant::now();
+        let mut jobs = self.lock();
+        if !jobs.jobs.contains_key(&id) {
+            return;
+        }
+        jobs.journal(JournalEntry::Stage { id, stage });
+        let Jobs { jobs, backends, .. } = &mut *jobs;
+        let Some(job) = jobs.get_mut(&id) else {
+            return;
+        };
+        let backend = backends.entry(job.proof_type.clone()).or_default();
+        if job.stage == JobStage::Proving {
+            backend.stop_proving(now);
+        }
+        if stage == JobStage::Proving {
+            backend.start_proving(now);
+        }
+        job.stage = stage;
+        job.stage_started = now;
+    }
+
+    fn record_submission(&self, record: SubmissionRecord) {
+        let mut jobs = self.lock();
+        let submission = &record.status;
+        if let Some(journal) = &jobs.journal {
+            journal.store_submission(record.clone());
+        }
+        let submissions = &mut jobs.submissions;
+        match submissions
+            .iter_mut()
+            .find(|recorded| recorded.job_id == submission.job_id)
+        {
+            Some(recorded) => *recorded = submission.clone(),
+            None => {
+                submissions.push_front(submission.clone());
+                submissions.truncate(MAX_RECENT_SUBMISSIONS);
+            }
+        }
+    }
+
+    fn release_slot(&self, slot: OwnedSemaphorePermit) {
+        let mut jobs = self.lock();
+        if jobs.excess_slots > 0 {
+            jobs.excess_slots -= 1;
+            slot.forget();
+        }
+    }
+
+    /// Removes the job, storing its result if it has one.
+    fn remove(&self, id: u64, result: Option<Result<Value, String>>) {
+        let now = Instant::now();
+        let mut jobs = self.lock();
+        let Some(job) = jobs.jobs.remove(&id) else {
+            return;
+        };
+        let status = job.status(id, now);
+        let error = match result {
+            Some(result) => {
+                let outcome = match &result {
+                    Ok(data) => JobOutcome::Ok { data: data.clone() },
+                    Err(message) => JobOutcome::Error {
+                        message: message.clone(),
+                    },
+                };
+                jobs.store_result(JobResult {
+                    id,
+                    tenant: status.tenant.clone(),
+                    block_number: status.block_number,
+                    network: status.network.clone(),
+                    proof_type: status.proof_type.clone(),
+                    finished_at: unix_time(),
+                    outcome,
+                });
+                result.err()
+            }
+            // A job that isn't finished was cancelled, e.g. because the client disconnected
+            None => Some("Cancelled".to_owned()),
+        };
+        // Once no job is left, nothing in the journal is needed anymore. The result is
+        // stored first, so it isn't lost to a crash in between.
+        if jobs.jobs.is_empty() && jobs.interrupted.is_empty() {
+            if let Some(journal) = &jobs.journal {
+                journal.clear();
+            }
+        } else {
+            jobs.journal(JournalEntry::Finished { id });
+        }
+
+        let backend = jobs.backends.entry(job.proof_type).or_default();
+        if job.stage == JobStage::Proving {
+            backend.stop_proving(now);
+        }
+        match error {
+            Some(error) => {
+                backend.failed += 1;
+                jobs.record_failure(status, error);
+            }
+            None => backend.completed += 1,
+        }
+    }
+}
+
+/// A registered job, which is removed from the store when finished or dropped.
+pub struct JobHandle {
+    store: JobStore,
+    id: u64,
+    tenant: String,
+    slot: Option<OwnedSemaphorePermit>,
+}
+
+impl JobHandle {
+    /// Waits until the job can be served within the concurrency limit.
+    pub async fn wait_for_slot(&mut self) {
+        if self.slot.is_none() {
+            self.slot = self.store.slots.clone().acquire_owned().await.ok();
+        }
+        self.set_stage(JobStage::Preflight);
+    }
+
+    /// The name of the tenant the job belongs to.
+    pub fn tenant(&self) -> &str {
+        &self.tenant
+    }
+
+    /// Starts recording the submission of the proof of the job, which usually continues
+    /// after the job is finished.
+    pub fn track_submission(&self, package: ProofPackage) -> Option<SubmissionTracker> {
+        let jobs = self.store.lock();
+        let job = jobs.jobs.get(&self.id)?;
+        let submission = SubmissionStatus {
+            job_id: self.id,
+            tenant: job.tenant.clone(),
+            block_number: job.block_number,
+            network: job.network.clone(),
+            proof_type: job.proof_type.clone(),
+            state: SubmissionState::Submitting,
+            attempts: 0,
+            updated_at: unix_time(),
+        };
+        drop(jobs);
+        let mut tracker = SubmissionTracker {
+            store: self.store.clone(),
+            submission,
+            package: Some(package),
+        };
+        tracker.update();
+        Some(tracker)
+    }
+
+    pub fn set_stage(&self, stage: JobStage) {
+        self.store.set_stage(self.id, stage);
+    }
+
+    /// Removes the job, storing its result to be fetched by the job id and recording the
+    /// error if it failed.
+    pub fn finish<T: Serialize, E: std::fmt::Display>(self, result: &Result<T, E>) {
+        let result = match result {
+            Ok(response) => serde_json::to_value(response).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        self.store.remove(self.id, Some(result));
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        // Without a result the job is recorded as cancelled
+        self.store.remove(self.id, None);
+        if let Some(slot) = self.slot.take() {
+            self.store.release_slot(slot);
+        }
+    }
+}
+
+/// Records the progress of a proof market submission in the status of the host, and in
+/// the journal if there is one.
+pub struct SubmissionTracker {
+    store: JobStore,
+    submission: SubmissionStatus,
+    /// The proof, until the market received it.
+    package: Option<ProofPackage>,
+}
+
+impl SubmissionTracker {
+    pub fn status(&self) -> &SubmissionStatus {
+        &self.submission
+    }
+
+    pub fn state(&self) -> &SubmissionState {
+        &self.submission.state
+    }
+
+    pub fn package(&self) -> Option<&ProofPackage> {
+        self.package.as_ref()
+    }
+
+    /// Counts a request sent to the market.
+    pub fn attempt(&mut self) {
+        self.submission.attempts += 1;
+        self.update();
+    }
+
+    /// Changes the state, the proof is dropped once it's no longer submitted.
+    pub fn set_state(&mut self, state: SubmissionState) {
+        if state != SubmissionState::Submitting {
+            self.package = None;
+        }
+        self.submission.state = state;
+        self.update();
+    }
+
+    fn update(&mut self) {
+        self.submission.updated_at = unix_time();
+        self.store.record_submission(SubmissionRecord {
+            status: self.submission.clone(),
+            package: self.package.clone(),
+        });
+    }
+}
+
+pub(crate) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use alloy_primitives::{Address, B256};
+    use raiko_core::interfaces::ProofType;
+
+    use super::*;
+    use crate::attestation::ProofMetadata;
+
+    fn request(block_number: u64) -> ProofRequest {
+        ProofRequest {
+            block_number,
+            network: "taiko_a7".to_owned(),
+            l1_network: "holesky".to_owned(),
+            graffiti: B256::ZERO,
+            prover: Address::ZERO,
+            proof_type: ProofType::Native,
+            fee: None,
+            prover_args: HashMap::new(),
+        }
+    }
+
+    fn package(block_number: u64) -> ProofPackage {
+        ProofPackage {
+            metadata: ProofMetadata::new(
+                167001,
+                block_number,
+                B256::ZERO,
+                B256::ZERO,
+                B256::ZERO,
+                "sp1",
+                None,
+                B256::ZERO,
+            ),
+            proof: Some("0x01".to_owned()),
+            quote: None,
+            attestation: None,
+            build: None,
+        }
+    }
+
+    fn tenant(name: &str, max_jobs: Option<usize>) -> Tenant {
+        Tenant {
+            name: name.to_owned(),
+            max_jobs,
+            operator: false,
+        }
+    }
+
+    async fn enqueue(store: &JobStore, block_number: u64) -> JobHandle {
+        store
+            .enqueue(&request(block_number), &Tenant::default())
+            .await
+            .unwrap()
+    }
+
+    /// Returns whether the job got a slot without waiting.
+    async fn gets_slot(job: &mut JobHandle) -> bool {
+        tokio::time::timeout(Duration::from_millis(50), job.wait_for_slot())
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_queue() {
+        let store = JobStore::new(1);
+        let mut first = enqueue(&store, 1).await;
+        let mut second = enqueue(&store, 2).await;
+        let status = store.status(&Tenant::default());
+        assert_eq!(status.queue.len(), 2);
+        assert!(status.jobs.is_empty());
+
+        assert!(gets_slot(&mut first).await);
+        assert!(!gets_slot(&mut second).await);
+        let status = store.status(&Tenant::default());
+        assert_eq!(status.queue.len(), 1);
+        assert_eq!(status.queue[0].block_number, 2);
+        assert_eq!(status.jobs[0].block_number, 1);
+        assert_eq!(status.jobs[0].stage, JobStage::Preflight);
+
+        // Finishing the first job frees its slot
+        first.finish::<(), String>(&Ok(()));
+        assert!(gets_slot(&mut second).await);
+        assert_eq!(store.status(&Tenant::default()).jobs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let store = JobStore::new(2);
+        let mut first = enqueue(&store, 1).await;
+        let mut second = enqueue(&store, 2).await;
+        let mut third = enqueue(&store, 3).await;
+        assert!(gets_slot(&mut first).await);
+        assert!(gets_slot(&mut second).await);
+
+        // Both slots are in use, so they are only removed once the jobs finish
+        store.set_concurrency_limit(1);
+        assert_eq!(store.lock().excess_slots, 1);
+        first.finish::<(), String>(&Ok(()));
+        assert!(!gets_slot(&mut third).await);
+        second.finish::<(), String>(&Ok(()));
+        assert!(gets_slot(&mut third).await);
+
+        // Raising the limit reclaims the excess slots first
+        store.set_concurrency_limit(3);
+        assert_eq!(store.lock().excess_slots, 0);
+        assert_eq!(store.slots.available_permits(), 2);
+        assert_eq!(store.status(&Tenant::default()).concurrency_limit, 3);
+    }
+
+    #[tokio::test]
+    async fn test_quota() {
+        let store = JobStore::new(1);
+        let alice = tenant("alice", Some(1));
+        let job = store.enqueue(&request(1), &alice).await.unwrap();
+        assert!(matches!(
+            store.enqueue(&request(2), &alice).await,
+            Err(HostError::QuotaExceeded(_))
+        ));
+        // Other tenants have their own quota
+        store
+            .enqueue(&request(2), &tenant("bob", Some(1)))
+            .await
+            .unwrap();
+        drop(job);
+        store.enqueue(&request(3), &alice).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_status_visibility() {
+        let store = JobStore::new(2);
+        let (alice, bob) = (tenant("alice", None), tenant("bob", None));
+        let alice_job = store.enqueue(&request(1), &alice).await.unwrap();
+        let bob_job = store.enqueue(&request(2), &bob).await.unwrap();
+        let _submission = alice_job.track_submission(package(1)).unwrap();
+        drop(bob_job);
+
+        let status = store.status(&alice);
+        assert_eq!(status.queue.len(), 1);
+        assert_eq!(status.queue[0].block_number, 1);
+        assert!(status.failures.is_empty());
+        assert_eq!(status.submissions.len(), 1);
+
+        let status = store.status(&bob);
+        assert!(status.queue.is_empty());
+        assert_eq!(status.failures.len(), 1);
+        assert_eq!(status.failures[0].job.block_number, 2);
+        assert!(status.submissions.is_empty());
+
+        // Operators see the jobs of all tenants
+        let status = store.status(&Tenant::default());
+        assert_eq!(status.queue.len(), 1);
+        assert_eq!(status.failures.len(), 1);
+        assert_eq!(status.submissions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_on_drop() {
+        let store = JobStore::new(1);
+        let job = enqueue(&store, 1).await;
+        job.set_stage(JobStage::Proving);
+        drop(job);
+
+        let status = store.status(&Tenant::default());
+        assert!(status.jobs.is_empty());
+        assert_eq!(status.failures.len(), 1);
+        assert_eq!(status.failures[0].error, "Cancelled");
+        assert_eq!(status.failures[0].job.stage, JobStage::Proving);
+        assert_eq!(status.backends[0].proving, 0);
+        assert_eq!(status.backends[0].failed, 1);
+        assert_eq!(status.backends[0].completed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_finish() {
+        let store = JobStore::new(1);
+        let job = enqueue(&store, 1).await;
+        job.finish::<(), String>(&Ok(()));
+        let job = enqueue(&store, 2).await;
+        job.finish::<(), String>(&Err("No witness".to_owned()));
+
+        let status = store.status(&Tenant::default());
+        assert_eq!(status.backends[0].completed, 1);
+        assert_eq!(status.backends[0].failed, 1);
+        assert_eq!(status.failures.len(), 1);
+        assert_eq!(status.failures[0].error, "No witness");
+        assert_eq!(status.failures[0].job.block_number, 2);
+    }
+
+    #[tokio::test]
+    async fn test_utilization() {
+        let store = JobStore::new(2);
+        let first = enqueue(&store, 1).await;
+        let second = enqueue(&store, 2).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        first.set_stage(JobStage::Proving);
+        second.set_stage(JobStage::Proving);
+        assert_eq!(store.status(&Tenant::default()).backends[0].proving, 2);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        first.finish::<(), String>(&Ok(()));
+        second.finish::<(), String>(&Ok(()));
+
+        // Overlapping jobs count once, and the time before proving doesn't count
+        let status = store.status(&Tenant::default());
+        let backend = &status.backends[0];
+        assert_eq!(backend.proving, 0);
+        assert_eq!(backend.completed, 2);
+        assert!(backend.utilization > 0.0 && backend.utilization < 1.0);
+        let busy_secs = store.lock().backends[&backend.proof_type].busy_secs;
+        assert!((0.02..status.uptime_secs - 0.02).contains(&busy_secs));
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_bounded() {
+        let store = JobStore::new(1);
+        for block_number in 0..MAX_RECENT_FAILURES as u64 + 8 {
+            drop(enqueue(&store, block_number).await);
+        }
+        let failures = store.status(&Tenant::default()).failures;
+        assert_eq!(failures.len(), MAX_RECENT_FAILURES);
+        // Newest first
+        assert_eq!(failures[0].job.block_number, MAX_RECENT_FAILURES as u64 + 7);
+    }
+
+    #[tokio::test]
+    async fn test_results() {
+        let store = JobStore::new(1);
+        let alice = tenant("alice", None);
+        let job = store.enqueue(&request(1), &alice).await.unwrap();
+        let running = enqueue(&store, 2).await;
+        job.finish::<_, String>(&Ok(serde_json::json!({ "proof": "0x01" })));
+        let job = enqueue(&store, 3).await;
+        job.finish::<(), _>(&Err("No witness"));
+        drop(enqueue(&store, 4).await);
+
+        let result = store.result(0, &alice).unwrap();
+        assert_eq!(result.block_number, 1);
+        assert_eq!(
+            result.outcome,
+            JobOutcome::Ok {
+                data: serde_json::json!({ "proof": "0x01" })
+            }
+        );
+        assert_eq!(
+            store.result(2, &Tenant::default()).unwrap().outcome,
+            JobOutcome::Error {
+                message: "No witness".to_owned()
+            }
+        );
+        // Only the tenant of the job and operators see the result
+        assert!(store.result(0, &Tenant::default()).is_ok());
+        assert!(store.result(0, &tenant("bob", None)).is_err());
+        assert!(store.result(2, &alice).is_err());
+        // Running and cancelled jobs have no result
+        let Err(HostError::NotFound(message)) = store.result(1, &Tenant::default()) else {
+            panic!("Running jobs have no result");
+        };
+        assert!(message.contains("not finished"), "{message}");
+        drop(running);
+        assert!(store.result(1, &Tenant::default()).is_err());
+        assert!(store.result(3, &Tenant::default()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restart() {
+        let dir = std::env::temp_dir().join(format!("raiko-jobs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("journal.jsonl");
+
+        // The host crashes while proving
+        let store = JobStore::new(1).with_journal(&path).unwrap();
+        let job = enqueue(&store, 1).await;
+        job.set_stage(JobStage::Proving);
+        std::mem::forget(job);
+
+        let store = JobStore::new(1).with_journal(&path).unwrap();
+        let mut restarted = store.take_interrupted();
+        assert_eq!(restarted.len(), 1);
+        let (request, job) = restarted.remove(0);
+        assert_eq!(request.block_number, 1);
+        job.finish::<_, String>(&Ok("proof"));
+        let job = enqueue(&store, 2).await;
+        assert_eq!(job.id, 1);
+        job.finish::<_, String>(&Ok("other proof"));
+        // The entries are written in order, so this also waits for the results
+        let written = store.lock().journal(JournalEntry::Stage {
+            id: u64::MAX,
+            stage: JobStage::Queued,
+        });
+        persisted(written.unwrap()).await.unwrap();
+
+        // The results survive another restart, and new jobs don't reuse their ids
+        let store = JobStore::new(1).with_journal(&path).unwrap();
+        assert!(store.take_interrupted().is_empty());
+        let result = store.result(0, &Tenant::default()).unwrap();
+        assert_eq!(
+            result.outcome,
+            JobOutcome::Ok {
+                data: serde_json::json!("proof")
+            }
+        );
+        assert_eq!(store.result(1, &Tenant::default()).unwrap().block_number, 2);
+        assert_eq!(enqueue(&store, 3).await.id, 2);
+    }
+
+    #[tokio::test]
+    async fn test_restart_submissions() {
+        let dir = std::env::temp_dir().join(format!("raiko-submissions-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("journal.jsonl");
+
+        // The host stops while a proof is sent and another one is decided on
+        let store = JobStore::new(2).with_journal(&path).unwrap();
+        let first = enqueue(&store, 1).await;
+        let second = enqueue(&store, 2).await;
+        let _submitting = first.track_submission(package(1)).unwrap();
+        let mut pending = second.track_submission(package(2)).unwrap();
+        pending.attempt();
+        pending.set_state(SubmissionState::Pending {
+            id: "p2".to_owned(),
+        });
+        first.finish::<_, String>(&Ok("proof"));
+        second.finish::<_, String>(&Ok("proof"));
+        let written = store.lock().journal(JournalEntry::Stage {
+            id: u64::MAX,
+            stage: JobStage::Queued,
+        });
+        persisted(written.unwrap()).await.unwrap();
+
+        let store = JobStore::new(1).with_journal(&path).unwrap();
+        let submissions = store.status(&Tenant::default()).submissions;
+        assert_eq!(submissions.len(), 2);
+        assert_eq!(submissions[0].job_id, 1);
+        assert_eq!(submissions[0].attempts, 1);
+        let resumed = store.take_interrupted_submissions();
+        let resumed: Vec<_> = resumed
+            .iter()
+            .map(|tracker| (tracker.state().clone(), tracker.package().is_some()))
+            .collect();
+        // Only the proof that the market didn't receive yet is kept
+        assert_eq!(
+            resumed,
+            [
+                (
+                    SubmissionState::Pending {
+                        id: "p2".to_owned()
+                    },
+                    false
+                ),
+                (SubmissionState::Submitting, true)
+            ]
+        );
+        assert!(store.take_interrupted_submissions().is_empty());
+        assert_eq!(enqueue(&store, 3).await.id, 2);
+    }
+}
@@ -0,0 +1,564 @@
+//! A write-ahead journal of the proof requests of the [JobStore](crate::jobs::JobStore),
+//! so that the requests interrupted by a crash are restarted instead of being lost.
+//!
+//! The results of the finished requests are kept next to the journal, in the
+//! `<journal>.results` directory, so the proofs of restarted requests can be fetched by
+//! their job id. The submissions to the proof market are kept in the
+//! `<journal>.submissions` directory, so the ones the market didn't decide on are resumed.
+//!
+//! The files are written by a dedicated thread, so the async handlers recording the jobs
+//! never wait for the disk.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use raiko_core::interfaces::ProofRequest;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tracing::warn;
+
+use crate::{
+    jobs::{JobResult, JobStage, SubmissionRecord, MAX_RECENT_RESULTS, MAX_RECENT_SUBMISSIONS},
+    tenants::DEFAULT_TENANT,
+};
+
+/// A change of the job store, written before the change is applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEntry {
+    /// A proof request was received, `attempt` counts the restarts after crashes.
+    Enqueued {
+        id: u64,
+        request: ProofRequest,
+        #[serde(default = "default_tenant")]
+        tenant: String,
+        attempt: u32,
+    },
+    /// A job entered a stage.
+    Stage { id: u64, stage: JobStage },
+    /// A job finished, successfully or not.
+    Finished { id: u64 },
+}
+
+/// A job that was not finished when the host stopped.
+#[derive(Debug, Clone)]
+pub struct InterruptedJob {
+    pub id: u64,
+    pub request: ProofRequest,
+    pub tenant: String,
+    /// The stage the job was interrupted in.
+    pub stage: JobStage,
+    /// The number of times the job was restarted, including this restart.
+    pub attempt: u32,
+}
+
+/// The jobs and results the previous run of the host left in the journal.
+#[derive(Debug, Default)]
+pub struct JournalState {
+    /// The interrupted jobs, in the order they were received.
+    pub interrupted: Vec<InterruptedJob>,
+    /// The stored results, newest first.
+    pub results: Vec<JobResult>,
+    /// The stored submissions, newest first.
+    pub submissions: Vec<SubmissionRecord>,
+}
+
+/// Resolves once a journal entry is persisted, or failed to be.
+pub type Persisted = oneshot::Receiver<io::Result<()>>;
+
+enum Command {
+    Append(JournalEntry, oneshot::Sender<io::Result<()>>),
+    Clear,
+    StoreResult(JobResult),
+    StoreSubmission(SubmissionRecord),
+}
+
+/// An append-only file of [JournalEntry]s, one JSON object per line, and the directory of
+/// the stored results.
+#[derive(Debug)]
+pub struct Journal {
+    commands: mpsc::Sender<Command>,
+}
+
+impl Journal {
+    /// Opens the journal at `path` and returns the jobs that were interrupted and the
+    /// results that were stored.
+    ///
+    /// The journal is compacted to only contain the interrupted jobs, each with its
+    /// attempt increased, so they are restarted again if the host crashes before they
+    /// finish.
+    pub fn open(path: &Path) -> io::Result<(Self, JournalState)> {
+        let mut jobs = BTreeMap::new();
+        if path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                // The last entry is incomplete if the host crashed while writing it
+                let Ok(entry) = serde_json::from_str::<JournalEntry>(&line?) else {
+                    continue;
+                };
+                match entry {
+                    JournalEntry::Enqueued {
+                        id,
+                        request,
+                        tenant,
+                        attempt,
+                    } => {
+                        let job = InterruptedJob {
+                            id,
+                            request,
+                            tenant,
+                            stage: JobStage::Queued,
+                            attempt,
+                        };
+                        jobs.insert(id, job);
+                    }
+                    JournalEntry::Stage { id, stage } => {
+                        if let Some(job) = jobs.get_mut(&id) {
+                            job.stage = stage;
+                        }
+                    }
+                    JournalEntry::Finished { id } => {
+                        jobs.remove(&id);
+                    }
+                }
+            }
+        }
+
+        let interrupted: Vec<InterruptedJob> = jobs
+            .into_values()
+            .map(|job| InterruptedJob {
+                attempt: job.attempt + 1,
+                ..job
+            })
+            .collect();
+
+        // Replace the journal atomically, so a crash while compacting loses nothing
+        let compacted = path.with_extension("tmp");
+        let mut file = File::create(&compacted)?;
+        for job in &interrupted {
+            write_entry(
+                &mut file,
+                &JournalEntry::Enqueued {
+                    id: job.id,
+                    request: job.request.clone(),
+                    tenant: job.tenant.clone(),
+                    attempt: job.attempt,
+                },
+            )?;
+        }
+        file.sync_all()?;
+        fs::rename(&compacted, path)?;
+
+        let results_dir = path.with_extension("results");
+        fs::create_dir_all(&results_dir)?;
+        let mut results = read_records(&results_dir)?;
+        results.truncate(MAX_RECENT_RESULTS);
+        let submissions_dir = path.with_extension("submissions");
+        fs::create_dir_all(&submissions_dir)?;
+        let submissions = read_records(&submissions_dir)?;
+
+        let file = OpenOptions::new().append(true).open(path)?;
+        let (commands, received) = mpsc::channel();
+        thread::Builder::new()
+            .name("journal".to_owned())
+            .spawn(move || write_journal(file, &results_dir, &submissions_dir, received))?;
+        let state = JournalState {
+            interrupted,
+            results,
+            submissions,
+        };
+        Ok((Self { commands }, state))
+    }
+
+    /// Queues the entry to be appended. The returned receiver resolves once it is
+    /// persisted, entries are persisted in the order they are queued.
+    pub fn append(&self, entry: JournalEntry) -> Persisted {
+        let (persisted, receiver) = oneshot::channel();
+        // A stopped writer drops the sender, which the receiver reports
+        let _ = self.commands.send(Command::Append(entry, persisted));
+        receiver
+    }
+
+    /// Queues the removal of all entries, which is only safe once all jobs are finished.
+    pub fn clear(&self) {
+        let _ = self.commands.send(Command::Clear);
+    }
+
+    /// Queues the result to be stored, replacing the oldest stored result once there
+    /// are [MAX_RECENT_RESULTS].
+    pub fn store_result(&self, result: JobResult) {
+        let _ = self.commands.send(Command::StoreResult(result));
+    }
+
+    /// Queues the submission to be stored, replacing its previous state. Once there are
+    /// more than [MAX_RECENT_SUBMISSIONS], the oldest ones the market decided on are
+    /// removed.
+    pub fn store_submission(&self, submission: SubmissionRecord) {
+        let _ = self.commands.send(Command::StoreSubmission(submission));
+    }
+}
+
+/// Waits for the persistence of an entry queued with [Journal::append].
+pub async fn persisted(receiver: Persisted) -> io::Result<()> {
+    receiver
+        .await
+        .unwrap_or_else(|_| Err(io::Error::other("The journal writer stopped")))
+}
+
+/// Writes the queued commands until the journal is dropped.
+fn write_journal(
+    mut file: File,
+    results_dir: &Path,
+    submissions_dir: &Path,
+    commands: mpsc::Receiver<Command>,
+) {
+    for command in commands {
+        match command {
+            Command::Append(entry, persisted) => {
+                let result = write_entry(&mut file, &entry).and_then(|()| file.sync_data());
+                if let Err(e) = &result {
+                    warn!("Could not journal {entry:?}: {e}");
+                }
+                let _ = persisted.send(result);
+            }
+            Command::Clear => {
+                if let Err(e) = file.set_len(0).and_then(|()| file.sync_data()) {
+                    warn!("Could not clear the journal: {e}");
+                }
+            }
+            Command::StoreResult(result) => {
+                let id = result.id;
+                if let Err(e) = write_result(results_dir, &result) {
+                    warn!("Could not store the result of job {id}: {e}");
+                }
+            }
+            Command::StoreSubmission(submission) => {
+                let id = submission.status.job_id;
+                if let Err(e) = write_submission(submissions_dir, &submission) {
+                    warn!("Could not store the submission of job {id}: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Jobs journaled before there were tenants belong to the default tenant.
+fn default_tenant() -> String {
+    DEFAULT_TENANT.to_owned()
+}
+
+fn write_entry(file: &mut File, entry: &JournalEntry) -> io::Result<()> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    file.write_all(&line)
+}
+
+/// Returns the job ids of the records in the directory with their files, in ascending
+/// order.
+fn record_files(dir: &Path) -> io::Result<BTreeMap<u64, PathBuf>> {
+    let mut files = BTreeMap::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let id = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".json"))
+            .and_then(|id| id.parse().ok());
+        if let Some(id) = id {
+            files.insert(id, path);
+        }
+    }
+    Ok(files)
+}
+
+/// Reads the stored records, newest first. Records that can't be read are skipped.
+fn read_records<T: DeserializeOwned>(dir: &Path) -> io::Result<Vec<T>> {
+    let files = record_files(dir)?;
+    Ok(files
+        .values()
+        .rev()
+        .filter_map(|path| read_record(path))
+        .collect())
+}
+
+fn read_record<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = fs::read(path).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// Writes the record of the job to `<id>.json`, replacing the previous one atomically.
+fn write_record<T: Serialize>(dir: &Path, id: u64, record: &T) -> io::Result<()> {
+    let path = dir.join(format!("{id}.json"));
+    let written = path.with_extension("tmp");
+    fs::write(&written, serde_json::to_vec(record)?)?;
+    fs::rename(&written, &path)
+}
+
+/// Writes the result and removes the oldest results beyond the limit.
+fn write_result(results_dir: &Path, result: &JobResult) -> io::Result<()> {
+    write_record(results_dir, result.id, result)?;
+    let files = record_files(results_dir)?;
+    for path in files.values().rev().skip(MAX_RECENT_RESULTS) {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Writes the submission and removes the oldest submissions beyond the limit, except the
+/// ones that still have to be resumed.
+fn write_submission(submissions_dir: &Path, submission: &SubmissionRecord) -> io::Result<()> {
+    write_record(submissions_dir, submission.status.job_id, submission)?;
+    let files = record_files(submissions_dir)?;
+    for path in files.values().rev().skip(MAX_RECENT_SUBMISSIONS) {
+        let in_flight =
+            read_record(path).is_some_and(|record: SubmissionRecord| record.in_flight());
+        if !in_flight {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy_primitives::{Address, B256};
+    use raiko_core::interfaces::ProofType;
+
+    use super::*;
+    use crate::jobs::{JobOutcome, SubmissionState, SubmissionStatus};
+
+    /// Returns the path of a journal in a new temporary directory.
+    fn journal_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("raiko-journal-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("journal.jsonl")
+    }
+
+    fn enqueued(id: u64) -> JournalEntry {
+        JournalEntry::Enqueued {
+            id,
+            request: ProofRequest {
+                block_number: 100 + id,
+                network: "taiko_a7".to_owned(),
+                l1_network: "holesky".to_owned(),
+                graffiti: B256::ZERO,
+                prover: Address::ZERO,
+                proof_type: ProofType::Native,
+                fee: None,
+                prover_args: HashMap::new(),
+            },
+            tenant: "alice".to_owned(),
+            attempt: 0,
+        }
+    }
+
+    fn result(id: u64) -> JobResult {
+        JobResult {
+            id,
+            tenant: "alice".to_owned(),
+            block_number: 100 + id,
+            network: "taiko_a7".to_owned(),
+            proof_type: "native".to_owned(),
+            finished_at: 0,
+            outcome: JobOutcome::Error {
+                message: "No witness".to_owned(),
+            },
+        }
+    }
+
+    fn submission(job_id: u64, state: SubmissionState) -> SubmissionRecord {
+        SubmissionRecord {
+            status: SubmissionStatus {
+                job_id,
+                tenant: "alice".to_owned(),
+                block_number: 100 + job_id,
+                network: "taiko_a7".to_owned(),
+                proof_type: "sp1".to_owned(),
+                state,
+                attempts: 1,
+                updated_at: 0,
+            },
+            package: None,
+        }
+    }
+
+    /// Appends the entries, returning once all are persisted.
+    async fn append(journal: &Journal, entries: Vec<JournalEntry>) {
+        for entry in entries {
+            persisted(journal.append(entry)).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let path = journal_path("replay");
+        let (journal, state) = Journal::open(&path).unwrap();
+        assert!(state.interrupted.is_empty());
+        append(
+            &journal,
+            vec![
+                enqueued(0),
+                enqueued(1),
+                enqueued(2),
+                JournalEntry::Stage {
+                    id: 1,
+                    stage: JobStage::Proving,
+                },
+                JournalEntry::Finished { id: 0 },
+            ],
+        )
+        .await;
+        drop(journal);
+        // A crash while writing leaves an incomplete entry behind
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"event":"finished","i"#).unwrap();
+
+        let (journal, state) = Journal::open(&path).unwrap();
+        let interrupted: Vec<_> = state
+            .interrupted
+            .iter()
+            .map(|job| (job.id, job.stage, job.attempt, job.tenant.as_str()))
+            .collect();
+        assert_eq!(
+            interrupted,
+            [
+                (1, JobStage::Proving, 1, "alice"),
+                (2, JobStage::Queued, 1, "alice")
+            ]
+        );
+        assert_eq!(state.interrupted[0].request.block_number, 101);
+
+        // Jobs that are interrupted again count another attempt
+        append(&journal, vec![JournalEntry::Finished { id: 2 }]).await;
+        drop(journal);
+        let (_, state) = Journal::open(&path).unwrap();
+        assert_eq!(state.interrupted.len(), 1);
+        assert_eq!(state.interrupted[0].id, 1);
+        assert_eq!(state.interrupted[0].attempt, 2);
+        // Restarted jobs start from scratch
+        assert_eq!(state.interrupted[0].stage, JobStage::Queued);
+    }
+
+    #[tokio::test]
+    async fn test_compaction() {
+        let path = journal_path("compaction");
+        let (journal, _) = Journal::open(&path).unwrap();
+        let mut entries = Vec::new();
+        for id in 0..10 {
+            entries.push(enqueued(id));
+            entries.push(JournalEntry::Stage {
+                id,
+                stage: JobStage::Preflight,
+            });
+            if id != 7 {
+                entries.push(JournalEntry::Finished { id });
+            }
+        }
+        append(&journal, entries).await;
+        drop(journal);
+
+        let (journal, state) = Journal::open(&path).unwrap();
+        assert_eq!(state.interrupted.len(), 1);
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<JournalEntry> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(matches!(
+            lines[..],
+            [JournalEntry::Enqueued {
+                id: 7,
+                attempt: 1,
+                ..
+            }]
+        ));
+        assert!(!path.with_extension("tmp").exists());
+
+        // Clearing removes the compacted entries too
+        journal.clear();
+        append(
+            &journal,
+            vec![JournalEntry::Stage {
+                id: 7,
+                stage: JobStage::Proving,
+            }],
+        )
+        .await;
+        drop(journal);
+        let (_, state) = Journal::open(&path).unwrap();
+        assert!(state.interrupted.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    }
+
+    #[test]
+    fn test_legacy_entry() {
+        let path = journal_path("legacy");
+        let mut entry = serde_json::to_value(enqueued(3)).unwrap();
+        entry.as_object_mut().unwrap().remove("tenant");
+        fs::write(&path, format!("{entry}\n")).unwrap();
+
+        let (_, state) = Journal::open(&path).unwrap();
+        assert_eq!(state.interrupted[0].tenant, DEFAULT_TENANT);
+    }
+
+    #[tokio::test]
+    async fn test_results() {
+        let path = journal_path("results");
+        let (journal, _) = Journal::open(&path).unwrap();
+        for id in 0..MAX_RECENT_RESULTS as u64 + 2 {
+            journal.store_result(result(id));
+        }
+        // The results are written in order with the entries
+        append(&journal, vec![enqueued(40)]).await;
+        drop(journal);
+
+        let (_, state) = Journal::open(&path).unwrap();
+        let ids: Vec<_> = state.results.iter().map(|result| result.id).collect();
+        let expected: Vec<_> = (2..MAX_RECENT_RESULTS as u64 + 2).rev().collect();
+        assert_eq!(ids, expected);
+        assert_eq!(
+            record_files(&path.with_extension("results")).unwrap().len(),
+            MAX_RECENT_RESULTS
+        );
+        assert_eq!(state.results[0].outcome, result(0).outcome);
+    }
+
+    #[tokio::test]
+    async fn test_submissions() {
+        let path = journal_path("submissions");
+        let (journal, _) = Journal::open(&path).unwrap();
+        let pending = SubmissionState::Pending {
+            id: "p0".to_owned(),
+        };
+        journal.store_submission(submission(0, SubmissionState::Submitting));
+        journal.store_submission(submission(0, pending.clone()));
+        for id in 1..MAX_RECENT_SUBMISSIONS as u64 + 2 {
+            let accepted = SubmissionState::Accepted {
+                id: format!("p{id}"),
+            };
+            journal.store_submission(submission(id, accepted));
+        }
+        append(&journal, vec![enqueued(40)]).await;
+        drop(journal);
+
+        // The oldest decided submission is removed, the pending one is kept to be resumed
+        let (_, state) = Journal::open(&path).unwrap();
+        let ids: Vec<_> = state
+            .submissions
+            .iter()
+            .map(|record| record.status.job_id)
+            .collect();
+        let mut expected: Vec<_> = (2..MAX_RECENT_SUBMISSIONS as u64 + 2).rev().collect();
+        expected.push(0);
+        assert_eq!(ids, expected);
+        assert_eq!(state.submissions.last().unwrap().status.state, pending);
+    }
+}
@@ -12,25 +12,59 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod attestation;
+pub mod capture;
+pub mod chain_spec;
+pub mod engine_auth;
+pub mod guest_build;
 pub mod interfaces;
+pub mod jobs;
+pub mod journal;
+pub mod market;
 pub mod metrics;
+pub mod pricing;
 pub mod server;
+pub mod tenants;
+pub mod top;
 
-use std::{alloc, path::PathBuf};
+use std::{
+    alloc,
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+};
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use cap::Cap;
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use raiko_core::{interfaces::ProofRequestOpt, merge};
-use raiko_lib::consts::SupportedChainSpecs;
+use raiko_lib::{consts::SupportedChainSpecs, input::InputLimits};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-
-use crate::interfaces::HostResult;
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+use crate::{
+    attestation::Attestor,
+    capture::CaptureArgs,
+    chain_spec::ChainSpecArgs,
+    engine_auth::EngineJwt,
+    guest_build::{GuestBuilds, VerifyBuildArgs},
+    interfaces::HostResult,
+    jobs::JobStore,
+    market::{Market, MarketConfig},
+    pricing::{Pricing, PricingConfig},
+    tenants::{validate_tenants, TenantConfig, TenantStores},
+    top::TopArgs,
+};
 
 #[global_allocator]
 static ALLOCATOR: Cap<alloc::System> = Cap::new(alloc::System, usize::MAX);
 
+fn default_engine_max_pending() -> usize {
+    16
+}
+
 fn default_address() -> String {
     "0.0.0.0:8080".to_string()
 }
@@ -104,6 +138,78 @@ pub struct Cli {
     #[arg(long, require_equals = true)]
     /// Set jwt secret for auth
     jwt_secret: Option<String>,
+
+    #[arg(long)]
+    /// Start proving blocks as soon as their payload is received over the Engine API
+    pub engine_prove: bool,
+
+    #[arg(long, require_equals = true)]
+    /// Path to the hex encoded secret shared with the consensus client, to authenticate the
+    /// Engine API calls with HS256 JWTs. Without it the Engine API uses the JWT secret of
+    /// the other routes
+    pub engine_jwt_secret_path: Option<PathBuf>,
+
+    #[arg(long, require_equals = true, default_value = "16")]
+    #[serde(default = "default_engine_max_pending")]
+    /// Maximum number of blocks from the Engine API waiting to be proven, the payloads
+    /// received beyond are stored but not proven
+    pub engine_max_pending: usize,
+
+    #[arg(long)]
+    /// On a block hash mismatch, find the first transaction whose execution diverges from
+    /// the `debug_traceBlockByNumber` traces of the node
+    pub find_divergence: bool,
+
+    #[arg(long, require_equals = true)]
+    /// Path to the journal of the proof requests, to restart the requests that were
+    /// interrupted by a crash
+    pub journal_path: Option<PathBuf>,
+
+    #[arg(long, require_equals = true)]
+    /// Path to a file with the hex encoded private key to sign the metadata of proofs
+    /// with (EIP-712)
+    pub attestation_key_path: Option<PathBuf>,
+
+    #[arg(skip)]
+    /// Fee requirements for proof requests, all fees are accepted if not set
+    pub pricing: Option<PricingConfig>,
+
+    #[arg(skip)]
+    /// The proof market finished proofs are submitted to, if any
+    pub market: Option<MarketConfig>,
+
+    #[command(flatten)]
+    /// Maximum sizes of the input, blocks exceeding them are rejected before proving
+    pub input_limits: InputLimitsOpt,
+
+    #[arg(skip)]
+    // Left out when empty, otherwise merging the command line options would replace the
+    // tenants of the config file
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    /// The tenants of the host with their API keys, all requests are served for a single
+    /// default tenant if empty
+    pub tenants: Vec<TenantConfig>,
+
+    #[arg(skip)]
+    /// The build manifests of the guests by proof type, hashed into the metadata of the
+    /// proofs
+    pub build_manifests: HashMap<String, PathBuf>,
+
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<Command>,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
+    /// Monitor the proof requests of a running host
+    Top(TopArgs),
+    /// Inspect and validate the chain specs
+    ChainSpec(ChainSpecArgs),
+    /// Capture a block with its witness and expected public input as a compact fixture
+    Capture(CaptureArgs),
+    /// Rebuild a guest from its build manifest and check the image ID
+    VerifyBuild(VerifyBuildArgs),
 }
 
 impl Cli {
@@ -112,7 +218,15 @@ impl Cli {
         let file = std::fs::File::open(&self.config_path)?;
         let reader = std::io::BufReader::new(file);
         let mut config: Value = serde_json::from_reader(reader)?;
-        let this = serde_json::to_value(&self)?;
+        let mut this = serde_json::to_value(&self)?;
+        // The options left at their default don't override the file, so the file can set
+        // them, e.g. the concurrency limit, and change them on a reload
+        let defaults = serde_json::to_value(serde_json::from_value::<Self>(Value::Object(
+            Default::default(),
+        ))?)?;
+        if let (Value::Object(this), Value::Object(defaults)) = (&mut this, &defaults) {
+            this.retain(|key, value| defaults.get(key) != Some(value));
+        }
         merge(&mut config, &this);
 
         *self = serde_json::from_value(config)?;
@@ -120,24 +234,115 @@ impl Cli {
     }
 }
 
+/// The [InputLimits] of the host, the limits of the guest for all that aren't set.
+#[derive(Default, Clone, Serialize, Deserialize, Debug, Args)]
+#[serde(default)]
+pub struct InputLimitsOpt {
+    #[arg(long, require_equals = true)]
+    /// Maximum number of transactions in a block, including the anchor transaction
+    pub max_transactions: Option<usize>,
+
+    #[arg(long, require_equals = true)]
+    /// Maximum size of the proposed tx list data in bytes
+    pub max_tx_data_size: Option<usize>,
+
+    #[arg(long, require_equals = true)]
+    /// Maximum size of the decompressed tx list in bytes
+    pub max_tx_list_size: Option<usize>,
+
+    #[arg(long, require_equals = true)]
+    /// Maximum number of resolved nodes over all tries of the witness
+    pub max_witness_nodes: Option<usize>,
+
+    #[arg(long, require_equals = true)]
+    /// Maximum number of contracts in the witness
+    pub max_contracts: Option<usize>,
+
+    #[arg(long, require_equals = true)]
+    /// Maximum number of blobs of a blob proposal
+    pub max_blobs: Option<usize>,
+}
+
+impl InputLimitsOpt {
+    pub fn limits(&self) -> InputLimits {
+        let default = InputLimits::default();
+        InputLimits {
+            max_transactions: self.max_transactions.unwrap_or(default.max_transactions),
+            max_tx_data_size: self.max_tx_data_size.unwrap_or(default.max_tx_data_size),
+            max_tx_list_size: self.max_tx_list_size.unwrap_or(default.max_tx_list_size),
+            max_witness_nodes: self.max_witness_nodes.unwrap_or(default.max_witness_nodes),
+            max_contracts: self.max_contracts.unwrap_or(default.max_contracts),
+            max_blobs: self.max_blobs.unwrap_or(default.max_blobs),
+        }
+    }
+}
+
+/// The options of the host that can be reloaded while it's running.
 #[derive(Debug, Clone)]
-pub struct ProverState {
+pub struct HostConfig {
     pub opts: Cli,
     pub chain_specs: SupportedChainSpecs,
+    pub pricing: Option<Pricing>,
+    pub market: Option<Market>,
+    pub builds: GuestBuilds,
 }
 
-impl ProverState {
-    pub fn init() -> HostResult<Self> {
-        // Read the command line arguments;
-        let mut opts = Cli::parse();
-        // Read the config file.
+impl HostConfig {
+    /// Reads the config file and the chain spec file on top of the command line options.
+    fn load(cli: &Cli) -> HostResult<Self> {
+        let mut opts = cli.clone();
         opts.merge_from_file()?;
+        validate_tenants(&opts.tenants)?;
+        if opts.concurrency_limit == 0 {
+            // No job would ever get a slot
+            return Err(anyhow!("The concurrency limit has to be at least 1").into());
+        }
 
         let chain_specs = if let Some(cs_path) = &opts.chain_spec_path {
-            SupportedChainSpecs::merge_from_file(cs_path.clone()).unwrap_or_default()
+            SupportedChainSpecs::merge_from_file(cs_path.clone())
+                .context("Could not read the chain spec file")?
         } else {
             SupportedChainSpecs::default()
         };
+        let pricing = opts.pricing.clone().map(Pricing::new);
+        let market = opts.market.clone().map(Market::new).transpose()?;
+        let builds = GuestBuilds::load(&opts.build_manifests)?;
+        Ok(Self {
+            opts,
+            chain_specs,
+            pricing,
+            market,
+            builds,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProverState {
+    /// The command line options, which the config file is merged into on every reload.
+    cli: Arc<Cli>,
+    config: Arc<RwLock<Arc<HostConfig>>>,
+    /// Held while reloading, so an older read of the files never replaces a newer one.
+    reloading: Arc<Mutex<()>>,
+    /// The payloads, state and snapshots cached for each tenant.
+    pub caches: TenantStores,
+    pub jobs: JobStore,
+    pub attestor: Option<Arc<Attestor>>,
+    pub engine_jwt: Option<Arc<EngineJwt>>,
+    /// The slots of the blocks from the Engine API that are waiting to be proven.
+    pub engine_slots: Arc<Semaphore>,
+}
+
+impl ProverState {
+    pub fn init() -> HostResult<Self> {
+        // Read the command line arguments;
+        Self::init_with_opts(Cli::parse())
+    }
+
+    pub fn init_with_opts(cli: Cli) -> HostResult<Self> {
+        // Read the config file.
+        let config = HostConfig::load(&cli)?;
+        let opts = &config.opts;
 
         // Check if the cache path exists and create it if it doesn't.
         if let Some(cache_path) = &opts.cache_path {
@@ -146,7 +351,104 @@ impl ProverState {
             }
         }
 
-        Ok(Self { opts, chain_specs })
+        let attestor = opts
+            .attestation_key_path
+            .as_deref()
+            .map(Attestor::from_file)
+            .transpose()?
+            .map(Arc::new);
+        let engine_jwt = opts
+            .engine_jwt_secret_path
+            .as_deref()
+            .map(EngineJwt::from_file)
+            .transpose()?
+            .map(Arc::new);
+        let mut jobs = JobStore::new(opts.concurrency_limit);
+        if let Some(journal_path) = &opts.journal_path {
+            jobs = jobs
+                .with_journal(journal_path)
+                .context("Could not open the job journal")?;
+        }
+        Ok(Self {
+            cli: Arc::new(cli),
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            reloading: Arc::default(),
+            caches: TenantStores::default(),
+            jobs,
+            attestor,
+            engine_jwt,
+            engine_slots: Arc::new(Semaphore::new(opts.engine_max_pending)),
+        })
+    }
+
+    /// Returns the current config. Requests keep using the config they started with, even
+    /// if it's reloaded in the meantime.
+    pub fn config(&self) -> Arc<HostConfig> {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Reads the config file and the chain spec file again, without affecting the queued
+    /// and running jobs.
+    ///
+    /// This updates the RPC endpoints and other chain spec overrides, the default proof
+    /// request options, the concurrency limit, the input limits, the pricing policy, the
+    /// tenants, the proof market and the build manifests.
+    /// The server address, the JWT secrets, the maximum of pending Engine API blocks and the
+    /// paths of the cache, the journal and the attestation key only change on a restart.
+    /// On failure the current config is kept.
+    ///
+    /// The files are read while blocking, so async callers run it with
+    /// [tokio::task::spawn_blocking].
+    pub fn reload(&self) -> HostResult<Arc<HostConfig>> {
+        let _reloading = self.reloading.lock().unwrap_or_else(|e| e.into_inner());
+        // The config is only locked to swap it, the requests keep reading it meanwhile
+        let mut config = HostConfig::load(&self.cli)?;
+        let current = self.config();
+
+        let (opts, fixed) = (&mut config.opts, &current.opts);
+        let changed = [
+            ("address", opts.address != fixed.address),
+            ("jwt_secret", opts.jwt_secret != fixed.jwt_secret),
+            ("cache_path", opts.cache_path != fixed.cache_path),
+            ("journal_path", opts.journal_path != fixed.journal_path),
+            (
+                "attestation_key_path",
+                opts.attestation_key_path != fixed.attestation_key_path,
+            ),
+            (
+                "engine_jwt_secret_path",
+                opts.engine_jwt_secret_path != fixed.engine_jwt_secret_path,
+            ),
+            (
+                "engine_max_pending",
+                opts.engine_max_pending != fixed.engine_max_pending,
+            ),
+        ];
+        for (name, _) in changed.iter().filter(|(_, changed)| *changed) {
+            warn!("Changing {name} requires a restart, keeping the current value");
+        }
+        opts.address.clone_from(&fixed.address);
+        opts.jwt_secret.clone_from(&fixed.jwt_secret);
+        opts.cache_path.clone_from(&fixed.cache_path);
+        opts.journal_path.clone_from(&fixed.journal_path);
+        opts.attestation_key_path
+            .clone_from(&fixed.attestation_key_path);
+        opts.engine_jwt_secret_path
+            .clone_from(&fixed.engine_jwt_secret_path);
+        opts.engine_max_pending = fixed.engine_max_pending;
+
+        self.jobs.set_concurrency_limit(opts.concurrency_limit);
+        self.caches.retain(&opts.tenants);
+        let config = Arc::new(config);
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        info!(
+            "Reloaded the config, supported chains: {:?}",
+            config.chain_specs
+        );
+        Ok(config)
     }
 }
 
@@ -172,3 +474,160 @@ mod memory {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use alloy_primitives::{Address, B256};
+    use raiko_core::interfaces::{ProofRequest, ProofType};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{jobs::JobHandle, tenants::Tenant};
+
+    fn write_config(path: &Path, config: &Value) {
+        std::fs::write(path, config.to_string()).unwrap();
+    }
+
+    /// Starts a host with `args` and `config` written to the config file, returning the path
+    /// of the file.
+    fn prover_state(name: &str, config: &Value, args: &[&str]) -> (ProverState, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("raiko-reload-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.json");
+        write_config(&config_path, config);
+        let config_arg = format!("--config-path={}", config_path.display());
+        let cli = Cli::parse_from(
+            ["raiko", config_arg.as_str()]
+                .into_iter()
+                .chain(args.iter().copied()),
+        );
+        (ProverState::init_with_opts(cli).unwrap(), config_path)
+    }
+
+    async fn enqueue(state: &ProverState, block_number: u64) -> JobHandle {
+        let request = ProofRequest {
+            block_number,
+            network: "taiko_a7".to_owned(),
+            l1_network: "holesky".to_owned(),
+            graffiti: B256::ZERO,
+            prover: Address::ZERO,
+            proof_type: ProofType::Native,
+            fee: None,
+            prover_args: HashMap::new(),
+        };
+        state
+            .jobs
+            .enqueue(&request, &Tenant::default())
+            .await
+            .unwrap()
+    }
+
+    /// Returns whether the job got a slot without waiting.
+    async fn gets_slot(job: &mut JobHandle) -> bool {
+        tokio::time::timeout(Duration::from_millis(50), job.wait_for_slot())
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_reload() {
+        let (state, config_path) = prover_state(
+            "reload",
+            &json!({
+                "concurrency_limit": 1,
+                "network": "taiko_a7",
+                "jwt_secret": "secret",
+            }),
+            &[],
+        );
+        let started = state.config();
+        assert_eq!(started.opts.concurrency_limit, 1);
+        assert_eq!(started.opts.address, default_address());
+
+        let mut first = enqueue(&state, 1).await;
+        let mut second = enqueue(&state, 2).await;
+        let mut third = enqueue(&state, 3).await;
+        assert!(gets_slot(&mut first).await);
+        assert!(!gets_slot(&mut second).await);
+
+        write_config(
+            &config_path,
+            &json!({
+                "concurrency_limit": 3,
+                "network": "ethereum",
+                "input_limits": { "max_tx_list_size": 1024 },
+                "address": "127.0.0.1:9000",
+                "jwt_secret": "other",
+                "engine_max_pending": 1,
+            }),
+        );
+        let config = state.reload().unwrap();
+        assert!(Arc::ptr_eq(&config, &state.config()));
+        assert_eq!(config.opts.concurrency_limit, 3);
+        assert_eq!(
+            config.opts.proof_request_opt.network.as_deref(),
+            Some("ethereum")
+        );
+        assert_eq!(config.opts.input_limits.max_tx_list_size, Some(1024));
+        // The options that require a restart keep their value
+        assert_eq!(config.opts.address, default_address());
+        assert_eq!(config.opts.jwt_secret.as_deref(), Some("secret"));
+        assert_eq!(config.opts.engine_max_pending, default_engine_max_pending());
+        // Running requests keep the config they started with
+        assert_eq!(started.opts.concurrency_limit, 1);
+
+        // The queued jobs are kept and get the new slots
+        let status = state.jobs.status(&Tenant::default());
+        assert_eq!(status.concurrency_limit, 3);
+        assert_eq!(status.queue.len(), 2);
+        assert!(gets_slot(&mut second).await);
+        assert!(gets_slot(&mut third).await);
+    }
+
+    #[tokio::test]
+    async fn test_reload_invalid() {
+        let (state, config_path) = prover_state("invalid", &json!({ "concurrency_limit": 2 }), &[]);
+        let current = state.config();
+        let _job = enqueue(&state, 1).await;
+
+        let invalid = [
+            "{".to_owned(),
+            json!({ "concurrency_limit": 0 }).to_string(),
+            json!({ "tenants": [{ "name": "../alice", "api_key": "key" }] }).to_string(),
+        ];
+        for config in invalid {
+            std::fs::write(&config_path, &config).unwrap();
+            assert!(state.reload().is_err(), "{config} was accepted");
+            assert!(Arc::ptr_eq(&current, &state.config()));
+            let status = state.jobs.status(&Tenant::default());
+            assert_eq!(status.concurrency_limit, 2);
+            assert_eq!(status.queue.len(), 1);
+        }
+        std::fs::remove_file(&config_path).unwrap();
+        assert!(state.reload().is_err());
+        assert!(Arc::ptr_eq(&current, &state.config()));
+    }
+
+    #[test]
+    fn test_command_line_precedence() {
+        let (state, config_path) = prover_state(
+            "precedence",
+            &json!({ "concurrency_limit": 2, "network": "taiko_a7", "max_log": 3 }),
+            &["--concurrency-limit=4", "--network=ethereum"],
+        );
+        let config = state.config();
+        let opts = &config.opts;
+        assert_eq!(opts.concurrency_limit, 4);
+        assert_eq!(opts.proof_request_opt.network.as_deref(), Some("ethereum"));
+        assert_eq!(opts.max_log, 3);
+
+        write_config(&config_path, &json!({ "concurrency_limit": 8 }));
+        let config = state.reload().unwrap();
+        let opts = &config.opts;
+        assert_eq!(opts.concurrency_limit, 4);
+        assert_eq!(opts.max_log, default_max_log());
+    }
+}
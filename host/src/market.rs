@@ -0,0 +1,501 @@
+//! Submission of finished proofs to an external proof market or aggregator.
+//!
+//! The market is expected to serve two routes:
+//! - `POST <url>/proofs` receives a [ProofPackage]
+//! - `GET <url>/proofs/<id>` returns the state of a submission
+//!
+//! Both answer with a [MarketReply].
+
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use raiko_core::interfaces::ProofType;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    attestation::{ProofAttestation, ProofMetadata},
+    interfaces::HostResult,
+    jobs::{SubmissionState, SubmissionTracker},
+};
+
+/// The configuration of the proof market, read from the `market` key of the config file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarketConfig {
+    /// The base URL of the market API.
+    pub url: String,
+    /// Path to a file with the bearer token for the market API, if it needs one.
+    pub token_path: Option<PathBuf>,
+    /// Number of times a proof is sent before the submission fails.
+    pub max_attempts: u32,
+    /// Delay before the first retry in milliseconds, doubled for every further retry.
+    pub retry_delay_ms: u64,
+    /// Interval between the status polls of a pending submission in milliseconds.
+    pub poll_interval_ms: u64,
+    /// Number of status polls before a pending submission fails.
+    pub max_polls: u32,
+}
+
+impl Default for MarketConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            token_path: None,
+            max_attempts: 5,
+            retry_delay_ms: 2_000,
+            poll_interval_ms: 10_000,
+            max_polls: 60,
+        }
+    }
+}
+
+/// Whether a market can verify the proofs of the type. Native and sandbox proofs prove
+/// nothing, so they are never submitted.
+pub fn is_verifiable(proof_type: &ProofType) -> bool {
+    !matches!(proof_type, ProofType::Native | ProofType::Sandbox)
+}
+
+/// A finished proof, as submitted to the market.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofPackage {
+    /// The public inputs of the proof.
+    pub metadata: ProofMetadata,
+    /// The proof, in the format of the prover backend.
+    pub proof: Option<String>,
+    /// The SGX quote, for SGX proofs.
+    pub quote: Option<String>,
+    /// The metadata signed by the operator, if enabled.
+    pub attestation: Option<ProofAttestation>,
+    /// The build manifest file of the guest, which hashes to the `buildHash` of the
+    /// metadata.
+    pub build: Option<String>,
+}
+
+/// The state of a submission, as reported by the market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+/// The answer of the market to a submission or a status poll.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketReply {
+    pub id: String,
+    pub status: MarketStatus,
+    /// Why the proof was rejected.
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl From<MarketReply> for SubmissionState {
+    fn from(reply: MarketReply) -> Self {
+        match reply.status {
+            MarketStatus::Pending => SubmissionState::Pending { id: reply.id },
+            MarketStatus::Accepted => SubmissionState::Accepted { id: reply.id },
+            MarketStatus::Rejected => SubmissionState::Rejected {
+                id: Some(reply.id),
+                reason: reply.reason.unwrap_or_default(),
+            },
+        }
+    }
+}
+
+/// A failed request to the market.
+enum MarketError {
+    /// The request may succeed if it's sent again, e.g. on a timeout or a server error.
+    Transient(String),
+    /// The market refused the request.
+    Permanent(String),
+}
+
+/// The client of the proof market.
+#[derive(Clone)]
+pub struct Market {
+    config: MarketConfig,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl std::fmt::Debug for Market {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Market")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Market {
+    pub fn new(config: MarketConfig) -> HostResult<Self> {
+        url::Url::parse(&config.url)
+            .with_context(|| format!("Invalid market url {:?}", config.url))?;
+        let token = config
+            .token_path
+            .as_ref()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .map(|token| token.trim().to_owned())
+                    .with_context(|| format!("Could not read the market token {}", path.display()))
+            })
+            .transpose()?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .context("Could not create the market client")?;
+        Ok(Self {
+            config,
+            token,
+            client,
+        })
+    }
+
+    /// Submits the proof in the background, and follows its state until the market
+    /// decides on it.
+    pub fn submit_in_background(&self, tracker: SubmissionTracker) {
+        let market = self.clone();
+        tokio::spawn(async move {
+            let block_number = tracker.status().block_number;
+            let state = market.submit(tracker).await;
+            match state {
+                SubmissionState::Accepted { id } => {
+                    info!("Proof of block {block_number} was accepted by the market as {id}")
+                }
+                state => {
+                    warn!("Submission of the proof of block {block_number} ended in {state:?}")
+                }
+            }
+        });
+    }
+
+    /// Submits the proof of the tracker unless the market already received it, retrying on
+    /// transient errors, and polls its state while it's pending. Every change is recorded
+    /// with the tracker, so a submission interrupted by a restart continues where it
+    /// stopped.
+    pub async fn submit(&self, mut tracker: SubmissionTracker) -> SubmissionState {
+        if *tracker.state() == SubmissionState::Submitting {
+            let state = match tracker.package().cloned() {
+                Some(package) => self.send_proof(&package, &mut tracker).await,
+                None => SubmissionState::Failed {
+                    error: "The proof to submit is missing".to_owned(),
+                },
+            };
+            tracker.set_state(state);
+        }
+        self.reconcile(tracker).await
+    }
+
+    /// Sends the proof until the market received or refused it, or the attempts are used
+    /// up.
+    async fn send_proof(
+        &self,
+        package: &ProofPackage,
+        tracker: &mut SubmissionTracker,
+    ) -> SubmissionState {
+        let url = format!("{}/proofs", self.config.url.trim_end_matches('/'));
+        let mut delay = Duration::from_millis(self.config.retry_delay_ms);
+        let mut state = SubmissionState::Failed {
+            error: "No submission attempt was made".to_owned(),
+        };
+        for attempt in 1..=self.config.max_attempts {
+            tracker.attempt();
+            state = match self.send(self.client.post(&url).json(package)).await {
+                Ok(reply) => reply.into(),
+                Err(MarketError::Permanent(reason)) => {
+                    SubmissionState::Rejected { id: None, reason }
+                }
+                Err(MarketError::Transient(error)) => {
+                    warn!("Submitting the proof failed (attempt {attempt}): {error}");
+                    if attempt < self.config.max_attempts {
+                        tokio::time::sleep(delay).await;
+                        delay *= 2;
+                    }
+                    SubmissionState::Failed { error }
+                }
+            };
+            if !matches!(state, SubmissionState::Failed { .. }) {
+                break;
+            }
+        }
+        state
+    }
+
+    /// Polls the state of a pending submission until the market decides on it.
+    async fn reconcile(&self, mut tracker: SubmissionTracker) -> SubmissionState {
+        let interval = Duration::from_millis(self.config.poll_interval_ms);
+        for _ in 0..self.config.max_polls {
+            let SubmissionState::Pending { id } = tracker.state().clone() else {
+                break;
+            };
+            tokio::time::sleep(interval).await;
+            tracker.attempt();
+            let url = format!("{}/proofs/{id}", self.config.url.trim_end_matches('/'));
+            match self.send(self.client.get(&url)).await {
+                Ok(reply) => tracker.set_state(reply.into()),
+                Err(MarketError::Permanent(reason)) => {
+                    tracker.set_state(SubmissionState::Rejected {
+                        id: Some(id),
+                        reason,
+                    })
+                }
+                Err(MarketError::Transient(error)) => {
+                    warn!("Polling the state of submission {id} failed: {error}")
+                }
+            }
+        }
+        if let SubmissionState::Pending { id } = tracker.state().clone() {
+            tracker.set_state(SubmissionState::Failed {
+                error: format!("The market didn't decide on submission {id} in time"),
+            });
+        }
+        tracker.state().clone()
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<MarketReply, MarketError> {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        let response = request
+            .send()
+            .await
+            .map_err(|e| MarketError::Transient(e.to_string()))?;
+        let status = response.status();
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(MarketError::Transient(format!(
+                "The market answered {status}"
+            )));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(MarketError::Permanent(format!("{status}: {body}")));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| MarketError::Permanent(format!("Invalid reply of the market: {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, VecDeque},
+        sync::{Arc, Mutex},
+    };
+
+    use alloy_primitives::{Address, B256};
+    use axum::{
+        extract::State,
+        http::{Method, StatusCode, Uri},
+        Json, Router,
+    };
+    use raiko_core::interfaces::ProofRequest;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        attestation::ProofMetadata,
+        jobs::{JobHandle, JobStore},
+        tenants::Tenant,
+    };
+
+    /// The requests a mock market received, and the replies it still has to send. The last
+    /// reply is repeated.
+    #[derive(Clone, Default)]
+    struct MockMarket {
+        requests: Arc<Mutex<Vec<String>>>,
+        replies: Arc<Mutex<VecDeque<(StatusCode, Value)>>>,
+    }
+
+    async fn reply(
+        State(mock): State<MockMarket>,
+        method: Method,
+        uri: Uri,
+    ) -> (StatusCode, Json<Value>) {
+        mock.requests
+            .lock()
+            .unwrap()
+            .push(format!("{method} {}", uri.path()));
+        let mut replies = mock.replies.lock().unwrap();
+        let (status, body) = if replies.len() > 1 {
+            replies.pop_front().unwrap()
+        } else {
+            replies[0].clone()
+        };
+        (status, Json(body))
+    }
+
+    /// Serves a mock market with the replies, returning the client of the market.
+    async fn serve(replies: Vec<(StatusCode, Value)>) -> (Market, MockMarket) {
+        let mock = MockMarket {
+            replies: Arc::new(Mutex::new(replies.into())),
+            ..Default::default()
+        };
+        let router = Router::new().fallback(reply).with_state(mock.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        let market = Market::new(MarketConfig {
+            url,
+            max_attempts: 3,
+            retry_delay_ms: 1,
+            poll_interval_ms: 1,
+            max_polls: 3,
+            ..Default::default()
+        })
+        .unwrap();
+        (market, mock)
+    }
+
+    fn requests(mock: &MockMarket) -> Vec<String> {
+        mock.requests.lock().unwrap().clone()
+    }
+
+    fn market_reply(id: &str, status: &str) -> (StatusCode, Value) {
+        (StatusCode::OK, json!({ "id": id, "status": status }))
+    }
+
+    async fn job(store: &JobStore) -> JobHandle {
+        let request = ProofRequest {
+            block_number: 1,
+            network: "taiko_a7".to_owned(),
+            l1_network: "holesky".to_owned(),
+            graffiti: B256::ZERO,
+            prover: Address::ZERO,
+            proof_type: ProofType::Sp1,
+            fee: None,
+            prover_args: HashMap::new(),
+        };
+        store.enqueue(&request, &Tenant::default()).await.unwrap()
+    }
+
+    fn package() -> ProofPackage {
+        ProofPackage {
+            metadata: ProofMetadata::new(
+                167001,
+                1,
+                B256::ZERO,
+                B256::ZERO,
+                B256::ZERO,
+                "sp1",
+                None,
+                B256::ZERO,
+            ),
+            proof: Some("0x01".to_owned()),
+            quote: None,
+            attestation: None,
+            build: None,
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(Market::new(MarketConfig::default()).is_err());
+        assert!(Market::new(MarketConfig {
+            url: "not a url".to_owned(),
+            ..Default::default()
+        })
+        .is_err());
+        assert!(is_verifiable(&ProofType::Sgx));
+        assert!(!is_verifiable(&ProofType::Native));
+        assert!(!is_verifiable(&ProofType::Sandbox));
+    }
+
+    #[tokio::test]
+    async fn test_submit() {
+        let (market, mock) = serve(vec![
+            (StatusCode::SERVICE_UNAVAILABLE, json!({})),
+            market_reply("p1", "pending"),
+            market_reply("p1", "pending"),
+            market_reply("p1", "accepted"),
+        ])
+        .await;
+        let store = JobStore::new(1);
+        let tracker = job(&store).await.track_submission(package()).unwrap();
+
+        let state = market.submit(tracker).await;
+        assert_eq!(
+            state,
+            SubmissionState::Accepted {
+                id: "p1".to_owned()
+            }
+        );
+        // The proof is sent again after the server error, then polled until accepted
+        assert_eq!(
+            requests(&mock),
+            [
+                "POST /proofs",
+                "POST /proofs",
+                "GET /proofs/p1",
+                "GET /proofs/p1"
+            ]
+        );
+        let submissions = store.status(&Tenant::default()).submissions;
+        assert_eq!(submissions[0].state, state);
+        assert_eq!(submissions[0].attempts, 4);
+    }
+
+    #[tokio::test]
+    async fn test_rejected() {
+        let (market, mock) = serve(vec![(StatusCode::BAD_REQUEST, json!("Invalid proof"))]).await;
+        let store = JobStore::new(1);
+        let tracker = job(&store).await.track_submission(package()).unwrap();
+
+        let SubmissionState::Rejected { id: None, reason } = market.submit(tracker).await else {
+            panic!("The proof was not rejected");
+        };
+        assert!(reason.contains("Invalid proof"), "{reason}");
+        // Refused proofs are not sent again
+        assert_eq!(requests(&mock), ["POST /proofs"]);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable() {
+        let (market, mock) = serve(vec![(StatusCode::SERVICE_UNAVAILABLE, json!({}))]).await;
+        let store = JobStore::new(1);
+        let tracker = job(&store).await.track_submission(package()).unwrap();
+
+        let state = market.submit(tracker).await;
+        assert!(matches!(state, SubmissionState::Failed { .. }), "{state:?}");
+        assert_eq!(requests(&mock).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile() {
+        let (market, mock) = serve(vec![market_reply("p1", "pending")]).await;
+        let store = JobStore::new(1);
+        let tracker = job(&store).await.track_submission(package()).unwrap();
+
+        // The market never decides
+        let SubmissionState::Failed { error } = market.submit(tracker).await else {
+            panic!("The submission did not fail");
+        };
+        assert!(error.contains("in time"), "{error}");
+        assert_eq!(requests(&mock).len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_resume() {
+        let (market, mock) = serve(vec![market_reply("p1", "accepted")]).await;
+        let store = JobStore::new(1);
+        // A submission the market received before a restart
+        let mut tracker = job(&store).await.track_submission(package()).unwrap();
+        tracker.set_state(SubmissionState::Pending {
+            id: "p1".to_owned(),
+        });
+        assert!(tracker.package().is_none());
+
+        let state = market.submit(tracker).await;
+        assert_eq!(
+            state,
+            SubmissionState::Accepted {
+                id: "p1".to_owned()
+            }
+        );
+        // Only the state is polled, the proof isn't sent again
+        assert_eq!(requests(&mock), ["GET /proofs/p1"]);
+    }
+}
@@ -2,10 +2,12 @@ use std::time::Duration;
 
 use lazy_static::lazy_static;
 use prometheus::{
-    labels, register_histogram_vec, register_int_counter_vec, register_int_gauge, HistogramVec,
-    IntCounterVec, IntGauge,
+    labels, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, HistogramVec, IntCounter, IntCounterVec, IntGauge,
 };
 use raiko_core::interfaces::ProofType;
+use raiko_lib::primitives::keccak::KeccakCacheStats;
+use tracing::debug;
 
 lazy_static! {
     pub static ref HOST_REQ_COUNT: IntCounterVec = register_int_counter_vec!(
@@ -56,11 +58,33 @@ lazy_static! {
         &["block_id", "success"]
     )
     .unwrap();
+    pub static ref TENANT_REQ_COUNT: IntCounterVec = register_int_counter_vec!(
+        "tenant_request_count",
+        "the number of proof requests sent by this tenant",
+        &["tenant"]
+    )
+    .unwrap();
+    pub static ref TENANT_PROOF_COUNT: IntCounterVec = register_int_counter_vec!(
+        "tenant_proof_count",
+        "the number of finished proof requests of this tenant",
+        &["tenant", "success"]
+    )
+    .unwrap();
     pub static ref CONCURRENT_REQUESTS: IntGauge = register_int_gauge!(
         "concurrent_requests",
         "number of requests currently being processed"
     )
     .unwrap();
+    pub static ref KECCAK_CACHE_HIT_COUNT: IntCounter = register_int_counter!(
+        "keccak_cache_hit_count",
+        "the number of keccak hashes served from the cache during block building"
+    )
+    .unwrap();
+    pub static ref KECCAK_CACHE_MISS_COUNT: IntCounter = register_int_counter!(
+        "keccak_cache_miss_count",
+        "the number of keccak hashes computed during block building with the cache enabled"
+    )
+    .unwrap();
 }
 
 /// Increase the count of requests currently being processed.
@@ -124,6 +148,24 @@ pub fn inc_guest_error(guest: &ProofType, block_id: u64) {
     GUEST_PROOF_ERROR_COUNT.with(&labels).inc();
 }
 
+/// Increment the request count for the given tenant.
+pub fn inc_tenant_req_count(tenant: &str) {
+    let labels = labels! {
+        "tenant" => tenant,
+    };
+    TENANT_REQ_COUNT.with(&labels).inc();
+}
+
+/// Increment the count of finished proof requests for the given tenant.
+pub fn inc_tenant_proof_count(tenant: &str, success: bool) {
+    let success = success.to_string();
+    let labels = labels! {
+        "tenant" => tenant,
+        "success" => &success,
+    };
+    TENANT_PROOF_COUNT.with(&labels).inc();
+}
+
 /// Convert a duration to a float with 3 decimal places (seconds,milliseconds).
 fn duration_to_f64(d: Duration) -> f64 {
     (d.as_secs_f64() * 1_000.0).round() / 1_000.0
@@ -167,3 +209,15 @@ pub fn observe_total_time(block_id: u64, time: Duration, success: bool) {
     };
     TOTAL_TIME.with(&labels).observe(duration_to_f64(time));
 }
+
+/// Record the keccak cache usage of block building.
+pub fn observe_keccak_cache(stats: KeccakCacheStats) {
+    debug!(
+        "Keccak cache: {} hits, {} misses ({:.1}% hit rate)",
+        stats.hits,
+        stats.misses,
+        stats.hit_rate() * 100.0
+    );
+    KECCAK_CACHE_HIT_COUNT.inc_by(stats.hits);
+    KECCAK_CACHE_MISS_COUNT.inc_by(stats.misses);
+}
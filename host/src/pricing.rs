@@ -0,0 +1,300 @@
+//! Pricing policies deciding on the fee offered with a proof request, so the host can run
+//! unattended against a proof market.
+
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use alloy_primitives::{Address, U256};
+use raiko_core::interfaces::{FeeTerms, ProofRequest};
+use serde::{Deserialize, Serialize};
+
+use crate::interfaces::{HostError, HostResult};
+
+/// The decision on the fee offered for a proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PricingDecision {
+    /// The proof is generated for the offered fee.
+    Accept,
+    /// The proof is not generated for any fee.
+    Reject { reason: String },
+    /// The proof is only generated for the given fee.
+    Counter { fee: FeeTerms },
+}
+
+/// Decides on the fee offered with a proof request, given the cost of the proof.
+pub trait PricingPolicy: Debug + Send + Sync {
+    fn decide(&self, request: &ProofRequest, cost: Option<U256>) -> PricingDecision;
+}
+
+/// The configuration of the [MarginPolicy], read from the `pricing` key of the config
+/// file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PricingConfig {
+    /// The token fees have to be paid in, the zero address for ether.
+    pub token: Address,
+    /// The estimated cost of a proof by proof type. Proof types without a cost are
+    /// rejected.
+    pub costs: HashMap<String, ProofCost>,
+    /// The margin over the cost the fee has to include, in percent.
+    pub margin_percent: u64,
+    /// Whether to counter fees that are too low with the minimum fee instead of rejecting.
+    pub counter: bool,
+}
+
+impl PricingConfig {
+    /// Returns the estimated cost of proving the request for a block that used `gas_used`.
+    pub fn cost(&self, request: &ProofRequest, gas_used: U256) -> Option<U256> {
+        self.costs
+            .get(&request.proof_type.to_string())
+            .map(|cost| cost.estimate(gas_used))
+    }
+}
+
+/// The estimated cost of the proofs of a proof type, in the smallest unit of the token.
+///
+/// The cycles of a proof are only known once the block is executed, but they scale with
+/// the gas used by the block, which is known before the request is accepted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProofCost {
+    /// The cost of proving a block that used no gas.
+    pub base: U256,
+    /// The cost added for every million gas used by the block.
+    pub per_million_gas: U256,
+}
+
+impl ProofCost {
+    /// Returns the estimated cost of proving a block that used `gas_used`.
+    pub fn estimate(&self, gas_used: U256) -> U256 {
+        let gas_cost = self.per_million_gas.saturating_mul(gas_used) / U256::from(1_000_000);
+        self.base.saturating_add(gas_cost)
+    }
+}
+
+/// Accepts all fees that cover the cost of the proof plus a fixed margin.
+#[derive(Debug, Clone)]
+pub struct MarginPolicy {
+    pub token: Address,
+    pub margin_percent: u64,
+    pub counter: bool,
+}
+
+impl From<&PricingConfig> for MarginPolicy {
+    fn from(config: &PricingConfig) -> Self {
+        Self {
+            token: config.token,
+            margin_percent: config.margin_percent,
+            counter: config.counter,
+        }
+    }
+}
+
+impl MarginPolicy {
+    /// Returns the lowest fee that is accepted for a proof of the given cost, saturating
+    /// at [U256::MAX] so the fee never drops below the cost.
+    pub fn min_fee(&self, cost: U256) -> U256 {
+        let percent = U256::from(self.margin_percent.saturating_add(100));
+        cost.checked_mul(percent)
+            .map_or(U256::MAX, |fee| fee / U256::from(100))
+    }
+}
+
+impl PricingPolicy for MarginPolicy {
+    fn decide(&self, request: &ProofRequest, cost: Option<U256>) -> PricingDecision {
+        let Some(cost) = cost else {
+            return PricingDecision::Reject {
+                reason: format!("No cost estimate for {} proofs", request.proof_type),
+            };
+        };
+        let min_fee = FeeTerms {
+            token: self.token,
+            amount: self.min_fee(cost),
+        };
+
+        let reason = match &request.fee {
+            Some(fee) if fee.token != self.token => {
+                format!("Fees are only accepted in {}", self.token)
+            }
+            Some(fee) if fee.amount >= min_fee.amount => return PricingDecision::Accept,
+            Some(fee) => format!("Fee {} is below {}", fee.amount, min_fee.amount),
+            None => "No fee offered".to_owned(),
+        };
+        if self.counter {
+            PricingDecision::Counter { fee: min_fee }
+        } else {
+            PricingDecision::Reject { reason }
+        }
+    }
+}
+
+/// The pricing policy of the host together with the cost estimates of the backends.
+#[derive(Debug, Clone)]
+pub struct Pricing {
+    pub config: PricingConfig,
+    pub policy: Arc<dyn PricingPolicy>,
+}
+
+impl Pricing {
+    /// Returns the pricing with the [MarginPolicy] of the config.
+    pub fn new(config: PricingConfig) -> Self {
+        let policy = Arc::new(MarginPolicy::from(&config));
+        Self { config, policy }
+    }
+
+    /// Fails if the fee offered with the request is not accepted for a block that used
+    /// `gas_used`.
+    pub fn check(&self, request: &ProofRequest, gas_used: U256) -> HostResult<()> {
+        match self
+            .policy
+            .decide(request, self.config.cost(request, gas_used))
+        {
+            PricingDecision::Accept => Ok(()),
+            PricingDecision::Reject { reason } => Err(HostError::FeeRejected(reason)),
+            PricingDecision::Counter { fee } => Err(HostError::FeeCountered(fee)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use raiko_core::interfaces::ProofType;
+
+    use super::*;
+
+    fn request(fee: Option<FeeTerms>) -> ProofRequest {
+        ProofRequest {
+            block_number: 1,
+            network: "taiko_a7".to_owned(),
+            l1_network: "holesky".to_owned(),
+            graffiti: B256::ZERO,
+            prover: Address::ZERO,
+            proof_type: ProofType::Sgx,
+            fee,
+            prover_args: HashMap::new(),
+        }
+    }
+
+    fn fee(token: Address, amount: u64) -> Option<FeeTerms> {
+        Some(FeeTerms {
+            token,
+            amount: U256::from(amount),
+        })
+    }
+
+    fn policy(counter: bool) -> MarginPolicy {
+        MarginPolicy {
+            token: Address::ZERO,
+            margin_percent: 20,
+            counter,
+        }
+    }
+
+    #[test]
+    fn test_min_fee() {
+        assert_eq!(policy(false).min_fee(U256::from(1000)), U256::from(1200));
+        assert_eq!(policy(false).min_fee(U256::ZERO), U256::ZERO);
+        // Rounded down
+        assert_eq!(policy(false).min_fee(U256::from(9)), U256::from(10));
+        assert_eq!(policy(false).min_fee(U256::MAX), U256::MAX);
+        assert_eq!(
+            policy(false).min_fee(U256::MAX / U256::from(120) + U256::from(1)),
+            U256::MAX
+        );
+        assert_eq!(
+            policy(false).min_fee(U256::MAX / U256::from(120)),
+            U256::MAX / U256::from(120) * U256::from(120) / U256::from(100)
+        );
+
+        let margin = MarginPolicy {
+            margin_percent: u64::MAX,
+            ..policy(false)
+        };
+        assert_eq!(margin.min_fee(U256::from(100)), U256::from(u64::MAX));
+    }
+
+    #[test]
+    fn test_estimate() {
+        let cost = ProofCost {
+            base: U256::from(1000),
+            per_million_gas: U256::from(500),
+        };
+        assert_eq!(cost.estimate(U256::ZERO), U256::from(1000));
+        assert_eq!(cost.estimate(U256::from(3_000_000)), U256::from(2500));
+        assert_eq!(cost.estimate(U256::MAX), U256::MAX);
+
+        let config = PricingConfig {
+            costs: HashMap::from([("sgx".to_owned(), cost)]),
+            ..Default::default()
+        };
+        let mut risc0 = request(None);
+        risc0.proof_type = ProofType::Risc0;
+        assert_eq!(
+            config.cost(&request(None), U256::from(1_000_000)),
+            Some(U256::from(1500))
+        );
+        assert_eq!(config.cost(&risc0, U256::from(1_000_000)), None);
+    }
+
+    #[test]
+    fn test_decide() {
+        let cost = Some(U256::from(1000));
+        let reject = policy(false);
+        let token = Address::repeat_byte(1);
+        assert_eq!(
+            reject.decide(&request(fee(Address::ZERO, 1200)), cost),
+            PricingDecision::Accept
+        );
+        assert_eq!(
+            reject.decide(&request(fee(Address::ZERO, 5000)), cost),
+            PricingDecision::Accept
+        );
+        for fee in [fee(Address::ZERO, 1199), fee(token, 5000), None] {
+            assert!(matches!(
+                reject.decide(&request(fee), cost),
+                PricingDecision::Reject { .. }
+            ));
+        }
+        // Without a cost estimate nothing is accepted, not even countered
+        assert!(matches!(
+            policy(true).decide(&request(fee(Address::ZERO, 5000)), None),
+            PricingDecision::Reject { .. }
+        ));
+
+        let counter = policy(true);
+        let min_fee = FeeTerms {
+            token: Address::ZERO,
+            amount: U256::from(1200),
+        };
+        for fee in [fee(Address::ZERO, 1199), fee(token, 5000), None] {
+            assert_eq!(
+                counter.decide(&request(fee), cost),
+                PricingDecision::Counter {
+                    fee: min_fee.clone()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let pricing = Pricing::new(PricingConfig {
+            costs: HashMap::from([(
+                "sgx".to_owned(),
+                ProofCost {
+                    base: U256::ZERO,
+                    per_million_gas: U256::from(1000),
+                },
+            )]),
+            margin_percent: 20,
+            ..Default::default()
+        });
+        let request = request(fee(Address::ZERO, 1200));
+        assert!(pricing.check(&request, U256::from(1_000_000)).is_ok());
+        // The same fee doesn't cover the cost of a bigger block
+        assert!(matches!(
+            pricing.check(&request, U256::from(2_000_000)),
+            Err(HostError::FeeRejected(_))
+        ));
+    }
+}
@@ -14,18 +14,22 @@ use tower_http::{
     validate_request::ValidateRequestHeaderLayer,
 };
 
-use crate::ProverState;
+use crate::{tenants::API_KEY_HEADER, ProverState};
 
 mod v1;
 
-pub fn create_router(concurrency_limit: usize, jwt_secret: Option<&str>) -> Router<ProverState> {
+pub(crate) use v1::restart_interrupted_jobs;
+
+pub fn create_router(jwt_secret: Option<&str>) -> Router<ProverState> {
     let cors = CorsLayer::new()
         .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
         .allow_headers([
             header::ORIGIN,
             header::ORIGIN,
             header::ACCEPT,
+            header::AUTHORIZATION,
             HeaderName::from_static("x-requested-with"),
+            HeaderName::from_static(API_KEY_HEADER),
         ])
         .allow_origin(cors::Any);
     let compression = CompressionLayer::new();
@@ -34,9 +38,14 @@ pub fn create_router(concurrency_limit: usize, jwt_secret: Option<&str>) -> Rout
 
     let trace = TraceLayer::new_for_http();
 
-    let v1_api = v1::create_router(concurrency_limit);
+    let mut v1_api = v1::create_router();
+    if let Some(jwt_secret) = jwt_secret {
+        v1_api = v1_api.layer(ValidateRequestHeaderLayer::bearer(jwt_secret));
+    }
+    // The Engine API checks the tokens of the consensus client itself
+    let v1_api = v1_api.nest("/engine", v1::create_engine_router());
 
-    let router = Router::new()
+    Router::new()
         .nest("/v1", v1_api.clone())
         .merge(v1_api)
         .layer(middleware)
@@ -44,14 +53,7 @@ pub fn create_router(concurrency_limit: usize, jwt_secret: Option<&str>) -> Rout
         .layer(trace)
         .fallback(|uri: Uri| async move {
             (StatusCode::NOT_FOUND, format!("No handler found for {uri}"))
-        });
-
-    if let Some(jwt_secret) = jwt_secret {
-        let auth = ValidateRequestHeaderLayer::bearer(jwt_secret);
-        router.layer(auth)
-    } else {
-        router
-    }
+        })
 }
 
 pub fn create_docs() -> utoipa::openapi::OpenApi {
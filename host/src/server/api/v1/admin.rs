@@ -0,0 +1,195 @@
+use axum::{debug_handler, extract::State, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::{
+    interfaces::{HostError, HostResult},
+    tenants::Tenant,
+    ProverState,
+};
+
+/// The config that is in effect after a reload.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReloadResponse {
+    pub concurrency_limit: usize,
+    /// The networks with a chain spec.
+    pub networks: Vec<String>,
+    /// Whether the fees of proof requests are checked by a pricing policy.
+    pub pricing: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/reload",
+    tag = "Admin",
+    responses (
+        (status = 200, description = "The config was reloaded", body = ReloadResponse),
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Reload the config
+///
+/// Reads the config file and the chain spec file again, like sending `SIGHUP` to the host.
+/// Queued and running proof requests are kept. The current config stays in effect if the
+/// files can't be read. Only allowed for operators, and only if the host authenticates its
+/// clients with a JWT secret or tenants.
+async fn reload_handler(
+    State(prover_state): State<ProverState>,
+    tenant: Tenant,
+) -> HostResult<Json<ReloadResponse>> {
+    let current = prover_state.config();
+    if current.opts.jwt_secret.is_none() && current.opts.tenants.is_empty() {
+        // Every client would be an operator
+        return Err(HostError::Unauthorized(
+            "The admin routes require a JWT secret or tenants".to_owned(),
+        ));
+    }
+    tenant.require_operator()?;
+    let config = tokio::task::spawn_blocking(move || prover_state.reload()).await??;
+    let mut networks = config.chain_specs.supported_networks();
+    networks.sort();
+    Ok(Json(ReloadResponse {
+        concurrency_limit: config.opts.concurrency_limit,
+        networks,
+        pricing: config.pricing.is_some(),
+    }))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(reload_handler))]
+struct Docs;
+
+pub fn create_docs() -> utoipa::openapi::OpenApi {
+    Docs::openapi()
+}
+
+pub fn create_router() -> Router<ProverState> {
+    Router::new().route("/reload", post(reload_handler))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+    use clap::Parser;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{server::api::create_router, tenants::API_KEY_HEADER, Cli};
+
+    /// Returns the router of a host started with `config` written to a new temporary
+    /// directory, and the path of the config file.
+    fn router(name: &str, config: &Value) -> (Router, ProverState, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("raiko-admin-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.json");
+        std::fs::write(&config_path, config.to_string()).unwrap();
+        let cli = Cli::parse_from([
+            "raiko".to_owned(),
+            format!("--config-path={}", config_path.display()),
+        ]);
+        let prover_state = ProverState::init_with_opts(cli).unwrap();
+        let jwt_secret = prover_state.config().opts.jwt_secret.clone();
+        let router = create_router(jwt_secret.as_deref()).with_state(prover_state.clone());
+        (router, prover_state, config_path)
+    }
+
+    /// Posts to /admin/reload with the headers, returning the status and the JSON body.
+    async fn reload(router: &Router, headers: &[(&str, &str)]) -> (StatusCode, Value) {
+        let mut request = Request::post("/admin/reload");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let response = router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_reload_requires_authentication() {
+        // Without a JWT secret or tenants every client would be an operator
+        let (router, prover_state, config_path) =
+            router("unauthenticated", &json!({ "concurrency_limit": 2 }));
+        std::fs::write(&config_path, json!({ "concurrency_limit": 4 }).to_string()).unwrap();
+        let (status, body) = reload(&router, &[]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["error"], "unauthorized");
+        assert_eq!(prover_state.config().opts.concurrency_limit, 2);
+    }
+
+    #[tokio::test]
+    async fn test_reload_jwt() {
+        let config = json!({ "concurrency_limit": 2, "jwt_secret": "secret" });
+        let (router, prover_state, config_path) = router("jwt", &config);
+        std::fs::write(
+            &config_path,
+            json!({ "concurrency_limit": 4, "network": "ethereum" }).to_string(),
+        )
+        .unwrap();
+
+        let (status, _) = reload(&router, &[]).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) =
+            reload(&router, &[(header::AUTHORIZATION.as_str(), "Bearer other")]).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(prover_state.config().opts.concurrency_limit, 2);
+
+        let (status, body) = reload(
+            &router,
+            &[(header::AUTHORIZATION.as_str(), "Bearer secret")],
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["concurrency_limit"], 4);
+        assert_eq!(prover_state.config().opts.concurrency_limit, 4);
+        // The JWT secret is kept until a restart
+        let (status, _) = reload(
+            &router,
+            &[(header::AUTHORIZATION.as_str(), "Bearer secret")],
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_reload_operator() {
+        let config = json!({
+            "concurrency_limit": 2,
+            "tenants": [
+                { "name": "alice", "api_key": "alice-key", "operator": true },
+                { "name": "bob", "api_key": "bob-key" },
+            ],
+        });
+        let (router, prover_state, config_path) = router("operator", &config);
+        let mut reloaded = config.clone();
+        reloaded["concurrency_limit"] = json!(4);
+        std::fs::write(&config_path, reloaded.to_string()).unwrap();
+
+        for headers in [vec![], vec![(API_KEY_HEADER, "bob-key")]] {
+            let (_, body) = reload(&router, &headers).await;
+            assert_eq!(body["error"], "unauthorized");
+        }
+        assert_eq!(prover_state.config().opts.concurrency_limit, 2);
+
+        let (_, body) = reload(&router, &[(API_KEY_HEADER, "alice-key")]).await;
+        assert_eq!(body["concurrency_limit"], 4);
+        assert_eq!(prover_state.config().opts.concurrency_limit, 4);
+
+        // An invalid config keeps the current one
+        std::fs::write(&config_path, json!({ "concurrency_limit": 0 }).to_string()).unwrap();
+        let (_, body) = reload(&router, &[(API_KEY_HEADER, "alice-key")]).await;
+        assert_eq!(body["error"], "anyhow_error");
+        assert_eq!(prover_state.config().opts.concurrency_limit, 4);
+    }
+}
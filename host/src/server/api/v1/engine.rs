@@ -0,0 +1,149 @@
+use alloy_primitives::B256;
+use axum::{
+    debug_handler,
+    extract::{Query, State},
+    routing::post,
+    Json, Router,
+};
+use raiko_core::provider::engine::ExecutionPayload;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{info, warn};
+use utoipa::{IntoParams, OpenApi};
+
+use crate::{
+    engine_auth::EngineAuth,
+    server::api::v1::{
+        jsonrpc::{self, parse_param, JsonRpcRequest, RpcError},
+        proof::{enqueue_proof, parse_proof_request},
+    },
+    tenants::Tenant,
+    ProverState,
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EngineQuery {
+    /// The network the payloads belong to. Defaults to the configured network.
+    network: Option<String>,
+}
+
+/// Stores the payload of an `engine_newPayloadV1-3` call and, if enabled, starts proving
+/// the block right away.
+fn new_payload(
+    prover_state: &ProverState,
+    tenant: &Tenant,
+    network: &str,
+    params: &Value,
+    with_beacon_root: bool,
+) -> Result<Value, RpcError> {
+    let payload: ExecutionPayload = parse_param(params, 0)?;
+    let parent_beacon_block_root: Option<B256> = if with_beacon_root {
+        Some(parse_param(params, 2)?)
+    } else {
+        None
+    };
+
+    let block_hash = payload.block_hash;
+    let block = payload
+        .into_block(parent_beacon_block_root)
+        .map_err(|e| RpcError::invalid_params(e.to_string()))?;
+    let block_number = block.header.number.unwrap_or_default();
+    prover_state
+        .caches
+        .get(&tenant.name)
+        .payloads
+        .insert(network, block)
+        .map_err(|e| RpcError::server(e.to_string()))?;
+    info!("Received payload for block {block_number} ({block_hash}) on {network}");
+
+    let host_config = prover_state.config();
+    if host_config.opts.engine_prove {
+        // Payloads can arrive faster than they are proven, so only a bounded number of
+        // blocks is kept waiting
+        let Ok(slot) = prover_state.engine_slots.clone().try_acquire_owned() else {
+            warn!(
+                "Not proving block {block_number}, {} blocks from the Engine API are pending",
+                host_config.opts.engine_max_pending
+            );
+            return Ok(accepted());
+        };
+        let (prover_state, tenant) = (prover_state.clone(), tenant.clone());
+        let req = serde_json::json!({ "block_number": block_number, "network": network });
+        tokio::spawn(async move {
+            let _slot = slot;
+            // The operator proves its own blocks, so there is no fee to price
+            let result = match parse_proof_request(&host_config, &req) {
+                Ok(proof_request) => enqueue_proof(prover_state, &tenant, proof_request).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!("Proving block {block_number} from the Engine API failed: {e}");
+            }
+        });
+    }
+
+    Ok(accepted())
+}
+
+/// The payload was not executed by us, so we can only acknowledge it.
+fn accepted() -> Value {
+    serde_json::json!({
+        "status": "ACCEPTED",
+        "latestValidHash": null,
+        "validationError": null,
+    })
+}
+
+#[utoipa::path(post, path = "/engine",
+    tag = "Engine",
+    request_body = JsonRpcRequest,
+    params(EngineQuery),
+    responses (
+        (status = 200, description = "JSON-RPC response with the payload status")
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Receive new payloads over the Engine API.
+///
+/// Accepts `engine_newPayloadV1`, `engine_newPayloadV2` and `engine_newPayloadV3` calls, e.g.
+/// mirrored by an Engine API multiplexer in front of the locally attached execution client.
+/// Received blocks are used for proof requests without waiting for them to be queryable over
+/// JSON-RPC.
+///
+/// The calls are authenticated with the HS256 JWT of the Engine API if an Engine API secret
+/// is configured.
+async fn engine_handler(
+    State(prover_state): State<ProverState>,
+    _auth: EngineAuth,
+    tenant: Tenant,
+    Query(query): Query<EngineQuery>,
+    Json(req): Json<JsonRpcRequest>,
+) -> Json<Value> {
+    let network = query
+        .network
+        .or_else(|| prover_state.config().opts.proof_request_opt.network.clone())
+        .unwrap_or_default();
+
+    let result = match req.method.as_str() {
+        "engine_newPayloadV1" | "engine_newPayloadV2" => {
+            new_payload(&prover_state, &tenant, &network, &req.params, false)
+        }
+        "engine_newPayloadV3" => new_payload(&prover_state, &tenant, &network, &req.params, true),
+        method => Err(RpcError::method_not_found(method)),
+    };
+
+    jsonrpc::response(req.id, result)
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(engine_handler))]
+struct Docs;
+
+pub fn create_docs() -> utoipa::openapi::OpenApi {
+    Docs::openapi()
+}
+
+pub fn create_router() -> Router<ProverState> {
+    Router::new().route("/", post(engine_handler))
+}
@@ -0,0 +1,83 @@
+use axum::Json;
+use serde::Deserialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// JSON-RPC error code for unknown methods.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for malformed parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+/// JSON-RPC error code for requests that are valid but cannot be served.
+pub const SERVER_ERROR: i64 = -32000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+/// A JSON-RPC request.
+pub struct JsonRpcRequest {
+    #[serde(default)]
+    #[schema(value_type = Value)]
+    /// The request id, echoed in the response.
+    pub id: Value,
+    /// The method to call.
+    pub method: String,
+    #[serde(default)]
+    #[schema(value_type = Value)]
+    /// The positional method parameters.
+    pub params: Value,
+}
+
+#[derive(Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn method_not_found(method: &str) -> Self {
+        Self {
+            code: METHOD_NOT_FOUND,
+            message: format!("Method {method} not supported"),
+        }
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.into(),
+        }
+    }
+
+    pub fn server(message: impl Into<String>) -> Self {
+        Self {
+            code: SERVER_ERROR,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::server(e.to_string())
+    }
+}
+
+/// Deserializes the positional parameter at `index`.
+pub fn parse_param<T: for<'de> Deserialize<'de>>(
+    params: &Value,
+    index: usize,
+) -> Result<T, RpcError> {
+    let param = params.get(index).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(param)
+        .map_err(|e| RpcError::invalid_params(format!("Invalid parameter {index}: {e}")))
+}
+
+/// Wraps the result of a call into a JSON-RPC response.
+pub fn response(id: Value, result: Result<Value, RpcError>) -> Json<Value> {
+    Json(match result {
+        Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(RpcError { code, message }) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }),
+    })
+}
@@ -2,16 +2,22 @@ use axum::{response::IntoResponse, Router};
 use raiko_lib::input::GuestOutput;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tower::ServiceBuilder;
 use utoipa::{OpenApi, ToSchema};
 use utoipa_scalar::{Scalar, Servable};
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::{interfaces::HostError, ProverState};
+use crate::{attestation::ProofAttestation, interfaces::HostError, ProverState};
 
+mod admin;
+mod engine;
 mod health;
+mod jsonrpc;
 mod metrics;
 mod proof;
+mod status;
+mod witness;
+
+pub(crate) use proof::restart_interrupted_jobs;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -36,13 +42,27 @@ mod proof;
             crate::interfaces::HostError,
             GuestOutputDoc,
             ProofResponse,
+            jsonrpc::JsonRpcRequest,
             Status,
+            crate::jobs::HostStatus,
+            crate::jobs::JobStatus,
+            crate::jobs::JobStage,
+            crate::jobs::FailedJob,
+            crate::jobs::JobResult,
+            crate::jobs::JobOutcome,
+            crate::jobs::BackendStatus,
+            crate::jobs::SubmissionStatus,
+            crate::jobs::SubmissionState,
+            admin::ReloadResponse,
         )
     ),
     tags(
         (name = "Proving", description = "Routes that handle proving requests"),
         (name = "Health", description = "Routes that report the server health status"),
-        (name = "Metrics", description = "Routes that give detailed insight into the server")
+        (name = "Metrics", description = "Routes that give detailed insight into the server"),
+        (name = "Witness", description = "Routes that serve witness data from the input cache"),
+        (name = "Engine", description = "Routes that receive blocks over the Engine API"),
+        (name = "Admin", description = "Routes that manage the running server")
     )
 )]
 /// The root API struct which is generated from the `OpenApi` derive macro.
@@ -58,6 +78,10 @@ pub struct ProofResponse {
     proof: Option<String>,
     /// The quote.
     quote: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    /// The metadata of the proof signed by the operator, if enabled.
+    attestation: Option<ProofAttestation>,
 }
 
 impl IntoResponse for ProofResponse {
@@ -107,6 +131,10 @@ pub fn create_docs() -> utoipa::openapi::OpenApi {
         health::create_docs(),
         metrics::create_docs(),
         proof::create_docs(),
+        status::create_docs(),
+        witness::create_docs(),
+        engine::create_docs(),
+        admin::create_docs(),
     ]
     .into_iter()
     .fold(Docs::openapi(), |mut doc, sub_doc| {
@@ -115,19 +143,23 @@ pub fn create_docs() -> utoipa::openapi::OpenApi {
     })
 }
 
-pub fn create_router(concurrency_limit: usize) -> Router<ProverState> {
+/// The Engine API routes, which are authenticated separately from the other routes.
+pub fn create_engine_router() -> Router<ProverState> {
+    engine::create_router()
+}
+
+pub fn create_router() -> Router<ProverState> {
     let docs = create_docs();
 
     Router::new()
-        // Only add the concurrency limit to the proof route. We want to still be able to call
-        // healthchecks and metrics to have insight into the system.
-        .nest(
-            "/proof",
-            proof::create_router()
-                .layer(ServiceBuilder::new().concurrency_limit(concurrency_limit)),
-        )
+        // The concurrency limit of the proof route is enforced by the job store, so that
+        // queued requests show up in the status.
+        .nest("/proof", proof::create_router())
         .nest("/health", health::create_router())
         .nest("/metrics", metrics::create_router())
+        .nest("/status", status::create_router())
+        .nest("/witness", witness::create_router())
+        .nest("/admin", admin::create_router())
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", docs.clone()))
         .merge(Scalar::with_url("/scalar", docs))
 }
@@ -1,32 +1,47 @@
-use std::{fs::File, path::PathBuf};
+use std::{fs::File, path::PathBuf, sync::Arc};
 
-use axum::{debug_handler, extract::State, routing::post, Json, Router};
+use alloy_primitives::U256;
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+    routing::{get, post},
+    Json, Router,
+};
 use raiko_core::{
+    divergence::find_divergence,
     interfaces::{ProofRequest, RaikoError},
-    provider::rpc::RpcBlockDataProvider,
+    provider::{
+        engine::EngineBlockDataProvider, rpc::RpcBlockDataProvider,
+        snapshot::SnapshotBlockDataProvider, BlockDataProvider,
+    },
     Raiko,
 };
 use raiko_lib::{
-    input::{get_input_path, GuestInput},
+    input::{get_input_path, GuestInput, GuestOutput},
+    utils::HeaderHasher,
     Measurement,
 };
 use serde_json::Value;
-use tracing::{debug, info};
+use tracing::{debug, error, info, warn};
 use utoipa::OpenApi;
 
 use crate::{
+    attestation::{proof_hash, ProofMetadata},
     interfaces::{HostError, HostResult},
+    jobs::{JobHandle, JobResult, JobStage, SubmissionState},
+    market::{is_verifiable, ProofPackage},
     memory,
     metrics::{
         dec_current_req, inc_current_req, inc_guest_error, inc_guest_req_count, inc_guest_success,
-        inc_host_error, inc_host_req_count, observe_guest_time, observe_prepare_input_time,
-        observe_total_time,
+        inc_host_error, inc_host_req_count, inc_tenant_proof_count, inc_tenant_req_count,
+        observe_guest_time, observe_keccak_cache, observe_prepare_input_time, observe_total_time,
     },
     server::api::v1::ProofResponse,
-    ProverState,
+    tenants::{tenant_cache_path, Tenant, TenantCaches},
+    HostConfig, ProverState,
 };
 
-fn get_cached_input(
+pub(crate) fn get_cached_input(
     cache_path: &Option<PathBuf>,
     block_number: u64,
     network: &str,
@@ -49,6 +64,7 @@ fn set_cached_input(
     let Some(dir) = cache_path.as_ref() else {
         return Ok(());
     };
+    std::fs::create_dir_all(dir)?;
 
     let path = get_input_path(dir, block_number, network);
 
@@ -63,31 +79,172 @@ fn set_cached_input(
     bincode::serialize_into(file, input).map_err(|e| HostError::Anyhow(e.into()))
 }
 
-async fn handle_proof(
-    ProverState {
-        opts,
-        chain_specs: support_chain_specs,
-    }: ProverState,
+/// Logs the first transaction of the block whose execution diverges from the node.
+async fn log_divergence(rpc: &str, input: &GuestInput) {
+    let divergence = match RpcBlockDataProvider::new(rpc, input.block_number - 1) {
+        Ok(provider) => find_divergence(&provider, input).await,
+        Err(e) => Err(e),
+    };
+    match divergence {
+        Ok(Some(divergence)) => error!(
+            "Block {} diverges from the node at {divergence}",
+            input.block_number
+        ),
+        Ok(None) => info!(
+            "No transaction of block {} diverges from the node",
+            input.block_number
+        ),
+        Err(e) => warn!(
+            "Could not compare block {} with the node: {e}",
+            input.block_number
+        ),
+    }
+}
+
+pub(crate) async fn handle_proof(
+    prover_state: ProverState,
+    tenant: Tenant,
     req: Value,
 ) -> HostResult<ProofResponse> {
+    inc_tenant_req_count(&tenant.name);
+    let host_config = prover_state.config();
+    let proof_request = parse_proof_request(&host_config, &req)?;
+    if let Some(pricing) = &host_config.pricing {
+        let gas_used = block_gas_used(&host_config, &proof_request).await?;
+        pricing.check(&proof_request, gas_used)?;
+    }
+    enqueue_proof(prover_state, &tenant, proof_request).await
+}
+
+/// Returns the gas used by the block of the request, which the cost of its proof scales
+/// with.
+async fn block_gas_used(
+    host_config: &HostConfig,
+    proof_request: &ProofRequest,
+) -> HostResult<U256> {
+    let chain_spec = host_config
+        .chain_specs
+        .get_chain_spec(&proof_request.network)
+        .ok_or_else(|| HostError::InvalidRequestConfig("Unsupported raiko network".to_string()))?;
+    let provider = RpcBlockDataProvider::new(
+        &chain_spec.rpc,
+        proof_request.block_number.saturating_sub(1),
+    )?;
+    let blocks = provider
+        .get_blocks(&[(proof_request.block_number, false)])
+        .await?;
+    let block = blocks.first().ok_or_else(|| {
+        HostError::InvalidRequestConfig(format!("Block {} not found", proof_request.block_number))
+    })?;
+    Ok(U256::from(block.header.gas_used))
+}
+
+/// Constructs the proof request from the request of the client and the configured
+/// defaults.
+pub(crate) fn parse_proof_request(
+    host_config: &HostConfig,
+    req: &Value,
+) -> HostResult<ProofRequest> {
     // Override the existing proof request config from the config file and command line
-    // options with the request from the client.
-    let mut config = opts.proof_request_opt.clone();
-    config.merge(&req)?;
+    // options with the request from the client. Only the client offers a fee, a default
+    // fee would let every request skip the pricing.
+    let mut config = host_config.opts.proof_request_opt.clone();
+    config.fee = None;
+    config.merge(req)?;
 
     // Construct the actual proof request from the available configs.
-    let proof_request = ProofRequest::try_from(config)?;
+    Ok(ProofRequest::try_from(config)?)
+}
+
+/// Queues the proof request of the tenant, without checking its fee, and waits for the
+/// proof.
+pub(crate) async fn enqueue_proof(
+    prover_state: ProverState,
+    tenant: &Tenant,
+    proof_request: ProofRequest,
+) -> HostResult<ProofResponse> {
+    // Only requests that passed the pricing are counted
     inc_host_req_count(proof_request.block_number);
     inc_guest_req_count(&proof_request.proof_type, proof_request.block_number);
+    let job = prover_state.jobs.enqueue(&proof_request, tenant).await?;
+    run_job(prover_state, proof_request, job).await
+}
+
+/// Restarts the jobs of the journal that were interrupted by the previous run of the host,
+/// and resumes their submissions to the proof market.
+pub(crate) fn restart_interrupted_jobs(prover_state: &ProverState) {
+    let market = prover_state.config().market.clone();
+    for mut tracker in prover_state.jobs.take_interrupted_submissions() {
+        let status = tracker.status();
+        info!(
+            "Resuming the submission of the proof of block {} on {}",
+            status.block_number, status.network
+        );
+        match &market {
+            Some(market) => market.submit_in_background(tracker),
+            None => tracker.set_state(SubmissionState::Failed {
+                error: "No proof market is configured anymore".to_owned(),
+            }),
+        }
+    }
+
+    for (proof_request, job) in prover_state.jobs.take_interrupted() {
+        info!(
+            "Restarting the interrupted proof of block {} on {}",
+            proof_request.block_number, proof_request.network
+        );
+        let prover_state = prover_state.clone();
+        tokio::spawn(async move {
+            let block_number = proof_request.block_number;
+            if let Err(e) = run_job(prover_state, proof_request, job).await {
+                warn!("Restarted proof of block {block_number} failed: {e}");
+            }
+        });
+    }
+}
+
+async fn run_job(
+    prover_state: ProverState,
+    proof_request: ProofRequest,
+    mut job: JobHandle,
+) -> HostResult<ProofResponse> {
+    job.wait_for_slot().await;
+    // A job that was queued during a reload already uses the new config
+    let host_config = prover_state.config();
+    let result = prove_request(prover_state, host_config, proof_request, &job).await;
+    inc_tenant_proof_count(job.tenant(), result.is_ok());
+    job.finish(&result);
+    result
+}
 
+async fn prove_request(
+    ProverState {
+        caches, attestor, ..
+    }: ProverState,
+    host_config: Arc<HostConfig>,
+    proof_request: ProofRequest,
+    job: &JobHandle,
+) -> HostResult<ProofResponse> {
+    let HostConfig {
+        opts,
+        chain_specs: support_chain_specs,
+        ..
+    } = &*host_config;
     info!(
         "# Generating proof for block {} on {}",
         proof_request.block_number, proof_request.network
     );
 
-    // Check for a cached input for the given request config.
+    // Check for a cached input for the given request config, the inputs of each tenant are
+    // cached separately.
+    let cache_path = tenant_cache_path(&opts.cache_path, job.tenant());
+    let TenantCaches {
+        payloads,
+        state_cache,
+        snapshots,
+    } = caches.get(job.tenant());
     let cached_input = get_cached_input(
-        &opts.cache_path,
+        &cache_path,
         proof_request.block_number,
         &proof_request.network.to_string(),
     );
@@ -103,31 +260,60 @@ async fn handle_proof(
     // Execute the proof generation.
     let total_time = Measurement::start("", false);
 
+    let input_limits = opts.input_limits.limits();
     let raiko = Raiko::new(
         l1_chain_spec.clone(),
         taiko_chain_spec.clone(),
         proof_request.clone(),
-    );
+    )
+    .with_state_cache(state_cache)
+    .with_input_limits(input_limits);
     let input = if let Some(cached_input) = cached_input {
         debug!("Using cached input");
         cached_input
     } else {
         memory::reset_stats();
         let measurement = Measurement::start("Generating input...", false);
-        let provider = RpcBlockDataProvider::new(
-            &taiko_chain_spec.rpc.clone(),
+        let provider = SnapshotBlockDataProvider::new(
+            EngineBlockDataProvider::new(
+                RpcBlockDataProvider::new(
+                    &taiko_chain_spec.rpc.clone(),
+                    proof_request.block_number - 1,
+                )?,
+                payloads,
+                &proof_request.network,
+            ),
+            &snapshots,
+            &proof_request.network,
             proof_request.block_number - 1,
-        )?;
+        );
         let input = raiko.generate_input(provider).await?;
         let input_time = measurement.stop_with("=> Input generated");
         observe_prepare_input_time(proof_request.block_number, input_time, true);
         memory::print_stats("Input generation peak memory used: ");
         input
     };
+    input_limits.check(&input).map_err(HostError::InputLimit)?;
+    job.set_stage(JobStage::Executing);
     memory::reset_stats();
-    let output = raiko.get_output(&input)?;
+    let (output, state) = match raiko.get_output_with_state(&input) {
+        Ok(output) => output,
+        Err(e) => {
+            if opts.find_divergence {
+                log_divergence(&taiko_chain_spec.rpc, &input).await;
+            }
+            return Err(e.into());
+        }
+    };
     memory::print_stats("Guest program peak memory used: ");
+    observe_keccak_cache(state.keccak_stats);
 
+    // The next block of a range can then be served mostly from the snapshot
+    if let Err(e) = snapshots.advance(&proof_request.network, &input, state) {
+        warn!("Could not advance the state snapshot: {e}");
+    }
+
+    job.set_stage(JobStage::Proving);
     memory::reset_stats();
     let measurement = Measurement::start("Generating proof...", false);
     let proof = raiko.prove(input.clone(), &output).await.map_err(|e| {
@@ -159,13 +345,48 @@ async fn handle_proof(
 
     // Cache the input for future use.
     set_cached_input(
-        &opts.cache_path,
+        &cache_path,
         proof_request.block_number,
         &proof_request.network.to_string(),
         &input,
     )?;
 
-    ProofResponse::try_from(proof)
+    let mut response = ProofResponse::try_from(proof)?;
+    let GuestOutput::Success { header, hash } = &output else {
+        return Ok(response);
+    };
+    let proof_type = proof_request.proof_type.to_string();
+    let build = host_config.builds.get(&proof_type);
+    let metadata = ProofMetadata::new(
+        taiko_chain_spec.chain_id,
+        proof_request.block_number,
+        header.hash(),
+        *hash,
+        proof_hash(response.proof.as_deref()),
+        &proof_type,
+        proof_request.fee.as_ref(),
+        host_config.builds.hash(&proof_type),
+    );
+    if let Some(attestor) = attestor {
+        response.attestation = Some(attestor.attest(metadata.clone()).await?);
+    }
+    let market = host_config
+        .market
+        .as_ref()
+        .filter(|_| is_verifiable(&proof_request.proof_type));
+    if let Some(market) = market {
+        let package = ProofPackage {
+            metadata,
+            proof: response.proof.clone(),
+            quote: response.quote.clone(),
+            attestation: response.attestation.clone(),
+            build: build.map(|build| build.json.clone()),
+        };
+        if let Some(tracker) = job.track_submission(package) {
+            market.submit_in_background(tracker);
+        }
+    }
+    Ok(response)
 }
 
 #[utoipa::path(post, path = "/proof",
@@ -184,19 +405,48 @@ async fn handle_proof(
 /// - sgx - uses the sgx environment to construct a block and produce proof of execution
 /// - sp1 - uses the sp1 prover
 /// - risc0 - uses the risc0 prover
+/// - sandbox - returns a fake proof after a simulated delay, for integration tests
+///
+/// If tenants are configured, the API key of the tenant has to be sent in the `X-Api-Key`
+/// header.
 async fn proof_handler(
     State(prover_state): State<ProverState>,
+    tenant: Tenant,
     Json(req): Json<Value>,
 ) -> HostResult<ProofResponse> {
     inc_current_req();
-    handle_proof(prover_state, req).await.map_err(|e| {
+    handle_proof(prover_state, tenant, req).await.map_err(|e| {
         dec_current_req();
         e
     })
 }
 
+#[utoipa::path(get, path = "/proof/{id}",
+    tag = "Proving",
+    params(
+        ("id" = u64, Path, description = "The id of the job, as listed in the status")
+    ),
+    responses (
+        (status = 200, description = "The result of the finished proof request", body = JobResult)
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Get the result of a finished proof request.
+///
+/// The results of the most recent proof requests are kept, including the requests that
+/// were restarted after a crash, whose clients are gone. The job id of a request is listed
+/// in the status while it is queued or running. Tenants that aren't operators only get the
+/// results of their own requests.
+async fn result_handler(
+    State(prover_state): State<ProverState>,
+    tenant: Tenant,
+    Path(id): Path<u64>,
+) -> HostResult<Json<JobResult>> {
+    prover_state.jobs.result(id, &tenant).map(Json)
+}
+
 #[derive(OpenApi)]
-#[openapi(paths(proof_handler))]
+#[openapi(paths(proof_handler, result_handler))]
 struct Docs;
 
 pub fn create_docs() -> utoipa::openapi::OpenApi {
@@ -204,5 +454,7 @@ pub fn create_docs() -> utoipa::openapi::OpenApi {
 }
 
 pub fn create_router() -> Router<ProverState> {
-    Router::new().route("/", post(proof_handler))
+    Router::new()
+        .route("/", post(proof_handler))
+        .route("/:id", get(result_handler))
 }
@@ -0,0 +1,37 @@
+use axum::{debug_handler, extract::State, routing::get, Json, Router};
+use utoipa::OpenApi;
+
+use crate::{jobs::HostStatus, tenants::Tenant, ProverState};
+
+#[utoipa::path(
+    get,
+    path = "/status",
+    tag = "Metrics",
+    responses (
+        (status = 200, description = "The status of the proof requests", body = HostStatus),
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Get the status of the proof requests
+///
+/// Lists the queued and running proof requests with their stage, the utilization of the
+/// prover backends and the most recent failures. Tenants that aren't operators only see
+/// their own requests.
+async fn status_handler(
+    State(prover_state): State<ProverState>,
+    tenant: Tenant,
+) -> Json<HostStatus> {
+    Json(prover_state.jobs.status(&tenant))
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(status_handler))]
+struct Docs;
+
+pub fn create_docs() -> utoipa::openapi::OpenApi {
+    Docs::openapi()
+}
+
+pub fn create_router() -> Router<ProverState> {
+    Router::new().route("/", get(status_handler))
+}
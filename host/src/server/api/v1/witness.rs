@@ -0,0 +1,557 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use alloy_primitives::{Address, Bytes, B256, U256, U64};
+use axum::{
+    debug_handler,
+    extract::{Query, State},
+    routing::post,
+    Json, Router,
+};
+use raiko_lib::{
+    input::GuestInput,
+    primitives::{
+        keccak::keccak,
+        mpt::{MptNode, MptNodeData, MptNodeReference, StateAccount, EMPTY_ROOT},
+    },
+    utils::HeaderHasher,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::{
+    server::api::v1::{
+        jsonrpc::{self, parse_param, JsonRpcRequest, RpcError},
+        proof::get_cached_input,
+    },
+    tenants::{tenant_cache_path, Tenant},
+    ProverState,
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WitnessQuery {
+    /// The network the witness belongs to. Defaults to the configured network.
+    network: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+/// An EIP-1186 account proof.
+struct AccountProof {
+    address: Address,
+    balance: U256,
+    code_hash: B256,
+    nonce: U64,
+    storage_hash: B256,
+    account_proof: Vec<Bytes>,
+    storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Debug, Serialize)]
+/// An EIP-1186 storage proof.
+struct StorageProof {
+    key: B256,
+    value: U256,
+    proof: Vec<Bytes>,
+}
+
+#[derive(Debug, Default, Serialize)]
+/// All the data required to execute a block statelessly.
+struct ExecutionWitness {
+    /// The trie nodes of the parent state and the touched storage tries, by node hash.
+    state: BTreeMap<B256, Bytes>,
+    /// The bytecode of all touched contracts, by code hash.
+    codes: BTreeMap<B256, Bytes>,
+    /// The RLP encoded parent header followed by the ancestor headers.
+    headers: Vec<Bytes>,
+}
+
+/// Loads the cached input for the given block and verifies that the witness is consistent
+/// with the block headers it commits to.
+fn load_verified_input(
+    cache_path: &Option<PathBuf>,
+    network: &str,
+    block_number: u64,
+) -> Result<GuestInput, RpcError> {
+    if cache_path.is_none() {
+        return Err(RpcError::server("No witness cache configured"));
+    }
+    let input = get_cached_input(cache_path, block_number, network)
+        .ok_or_else(|| RpcError::server(format!("No witness cached for block {block_number}")))?;
+
+    if input.parent_header.hash() != input.block_header_reference.parent_hash {
+        return Err(RpcError::server(
+            "Cached parent header does not match the block",
+        ));
+    }
+    if input.parent_state_trie.hash() != input.parent_header.state_root {
+        return Err(RpcError::server(
+            "Cached state trie does not match the parent state root",
+        ));
+    }
+    for (address, (storage_trie, _)) in &input.parent_storage {
+        let storage_root = get_account(&input.parent_state_trie, address)?.storage_root;
+        if storage_trie.hash() != storage_root {
+            return Err(RpcError::server(format!(
+                "Cached storage trie for {address} does not match the storage root"
+            )));
+        }
+    }
+
+    Ok(input)
+}
+
+fn get_account(state_trie: &MptNode, address: &Address) -> Result<StateAccount, RpcError> {
+    state_trie
+        .get_rlp::<StateAccount>(&keccak(address))
+        .map(Option::unwrap_or_default)
+        .map_err(|_| RpcError::server(format!("Account {address} is not in the witness")))
+}
+
+fn parse_block_number(params: &Value, index: usize) -> Result<u64, RpcError> {
+    match params.get(index) {
+        Some(Value::Number(number)) => number.as_u64(),
+        Some(Value::String(number)) => number
+            .strip_prefix("0x")
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok()),
+        _ => None,
+    }
+    .ok_or_else(|| RpcError::invalid_params("Only explicit block numbers are supported"))
+}
+
+/// Serves `eth_getProof` for the state after `block_number`, which is the parent state of
+/// the next block.
+fn get_proof(
+    cache_path: &Option<PathBuf>,
+    network: &str,
+    params: &Value,
+) -> Result<AccountProof, RpcError> {
+    let address: Address = parse_param(params, 0)?;
+    let slots: Vec<U256> = parse_param(params, 1)?;
+    let block_number = parse_block_number(params, 2)?;
+
+    let next_block = block_number
+        .checked_add(1)
+        .ok_or_else(|| RpcError::invalid_params("Block number out of range"))?;
+    let input = load_verified_input(cache_path, network, next_block)?;
+    let account = get_account(&input.parent_state_trie, &address)?;
+    let account_proof = input
+        .parent_state_trie
+        .proof(&keccak(address))
+        .map_err(|_| RpcError::server(format!("Account {address} is not in the witness")))?;
+
+    let storage_trie = input.parent_storage.get(&address).map(|(trie, _)| trie);
+    let mut storage_proof = Vec::with_capacity(slots.len());
+    for slot in slots {
+        let key = B256::from(slot.to_be_bytes::<32>());
+        let (value, proof) = match storage_trie {
+            Some(trie) => {
+                let slot_key = keccak(key);
+                match (trie.get_rlp::<U256>(&slot_key), trie.proof(&slot_key)) {
+                    (Ok(value), Ok(proof)) => Some((value.unwrap_or_default(), proof)),
+                    _ => None,
+                }
+            }
+            // the slots of accounts without any storage are trivially proven
+            None if account.storage_root == EMPTY_ROOT => Some((U256::ZERO, vec![])),
+            None => None,
+        }
+        .ok_or_else(|| {
+            RpcError::server(format!("Slot {key} of {address} is not in the witness"))
+        })?;
+        storage_proof.push(StorageProof {
+            key,
+            value,
+            proof: proof.into_iter().map(Bytes::from).collect(),
+        });
+    }
+
+    Ok(AccountProof {
+        address,
+        balance: account.balance,
+        code_hash: account.code_hash,
+        nonce: U64::from(account.nonce),
+        storage_hash: account.storage_root,
+        account_proof: account_proof.into_iter().map(Bytes::from).collect(),
+        storage_proof,
+    })
+}
+
+/// Serves `debug_executionWitness` for `block_number`, i.e. the parent state required to
+/// execute it.
+fn get_execution_witness(
+    cache_path: &Option<PathBuf>,
+    network: &str,
+    params: &Value,
+) -> Result<ExecutionWitness, RpcError> {
+    let block_number = parse_block_number(params, 0)?;
+    let input = load_verified_input(cache_path, network, block_number)?;
+
+    let mut witness = ExecutionWitness::default();
+    collect_nodes(&input.parent_state_trie, true, &mut witness.state);
+    for (storage_trie, _) in input.parent_storage.values() {
+        collect_nodes(storage_trie, true, &mut witness.state);
+    }
+    witness.codes = input
+        .contracts
+        .iter()
+        .map(|code| (keccak(code).into(), code.clone()))
+        .collect();
+    witness.headers = std::iter::once(&input.parent_header)
+        .chain(input.ancestor_headers.iter())
+        .map(|header| alloy_rlp::encode(header).into())
+        .collect();
+
+    Ok(witness)
+}
+
+/// Collects all resolved nodes of the trie that are referenced by hash.
+fn collect_nodes(node: &MptNode, is_root: bool, nodes: &mut BTreeMap<B256, Bytes>) {
+    if matches!(node.as_data(), MptNodeData::Null | MptNodeData::Digest(_)) {
+        return;
+    }
+    if is_root || matches!(node.reference(), MptNodeReference::Digest(_)) {
+        nodes.insert(node.hash(), alloy_rlp::encode(node).into());
+    }
+    match node.as_data() {
+        MptNodeData::Branch(children) => {
+            for child in children.iter().flatten() {
+                collect_nodes(child, false, nodes);
+            }
+        }
+        MptNodeData::Extension(_, child) => collect_nodes(child, false, nodes),
+        _ => {}
+    }
+}
+
+#[utoipa::path(post, path = "/witness",
+    tag = "Witness",
+    request_body = JsonRpcRequest,
+    params(WitnessQuery),
+    responses (
+        (status = 200, description = "JSON-RPC response with the requested witness data")
+    )
+)]
+#[debug_handler(state = ProverState)]
+/// Serve witness data from the input cache.
+///
+/// Accepts a JSON-RPC request for one of the following methods, answered from the
+/// verified inputs cached by previous proof requests:
+/// - `eth_getProof` - EIP-1186 account and storage proofs for the state after a block
+/// - `debug_executionWitness` - the trie nodes, bytecode and headers needed to execute a block
+async fn witness_handler(
+    State(prover_state): State<ProverState>,
+    tenant: Tenant,
+    Query(query): Query<WitnessQuery>,
+    Json(req): Json<JsonRpcRequest>,
+) -> Json<Value> {
+    let config = prover_state.config();
+    let network = query
+        .network
+        .or_else(|| config.opts.proof_request_opt.network.clone())
+        .unwrap_or_default();
+    // Only the inputs cached for the proofs of the tenant are served
+    let cache_path = tenant_cache_path(&config.opts.cache_path, &tenant.name);
+
+    let JsonRpcRequest { id, method, params } = req;
+    // The cached input is read and verified while blocking
+    let result = tokio::task::spawn_blocking(move || match method.as_str() {
+        "eth_getProof" => get_proof(&cache_path, &network, &params)
+            .and_then(|proof| Ok(serde_json::to_value(proof)?)),
+        "debug_executionWitness" => get_execution_witness(&cache_path, &network, &params)
+            .and_then(|witness| Ok(serde_json::to_value(witness)?)),
+        method => Err(RpcError::method_not_found(method)),
+    })
+    .await
+    .unwrap_or_else(|e| Err(RpcError::server(format!("Witness request failed: {e}"))));
+
+    jsonrpc::response(id, result)
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(witness_handler))]
+struct Docs;
+
+pub fn create_docs() -> utoipa::openapi::OpenApi {
+    Docs::openapi()
+}
+
+pub fn create_router() -> Router<ProverState> {
+    Router::new().route("/", post(witness_handler))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, str::FromStr};
+
+    use alloy_consensus::Header as AlloyConsensusHeader;
+    use clap::Parser;
+    use raiko_lib::input::get_input_path;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        server::api::v1::jsonrpc::{INVALID_PARAMS, SERVER_ERROR},
+        tenants::DEFAULT_TENANT,
+        Cli,
+    };
+
+    const NETWORK: &str = "testnet";
+
+    fn contract() -> Address {
+        Address::repeat_byte(1)
+    }
+
+    fn code() -> Bytes {
+        Bytes::from_static(&[0x60, 0x00])
+    }
+
+    fn slot_key(slot: u64) -> [u8; 32] {
+        keccak(U256::from(slot).to_be_bytes::<32>())
+    }
+
+    /// The input of `block_number` with the witness of a contract with three storage slots
+    /// and of an account without storage.
+    fn input(block_number: u64) -> GuestInput {
+        let mut storage_trie = MptNode::default();
+        for slot in 1..=3 {
+            storage_trie
+                .insert_rlp(&slot_key(slot), U256::from(slot * 10))
+                .unwrap();
+        }
+        let mut state_trie = MptNode::default();
+        let contract_account = StateAccount {
+            nonce: 1,
+            balance: U256::from(100),
+            storage_root: storage_trie.hash(),
+            code_hash: keccak(code()).into(),
+        };
+        state_trie
+            .insert_rlp(&keccak(contract()), contract_account)
+            .unwrap();
+        let account = StateAccount {
+            nonce: 2,
+            ..Default::default()
+        };
+        state_trie
+            .insert_rlp(&keccak(Address::repeat_byte(2)), account)
+            .unwrap();
+
+        let parent_header = AlloyConsensusHeader {
+            number: block_number - 1,
+            state_root: state_trie.hash(),
+            ..Default::default()
+        };
+        let block_header_reference = AlloyConsensusHeader {
+            number: block_number,
+            parent_hash: parent_header.hash(),
+            ..Default::default()
+        };
+        GuestInput {
+            block_number,
+            block_header_reference,
+            parent_header,
+            parent_state_trie: state_trie,
+            parent_storage: [(contract(), (storage_trie, vec![]))].into_iter().collect(),
+            contracts: vec![code()],
+            ..Default::default()
+        }
+    }
+
+    /// Returns the state of a host with the tenants alice and bob, caching the inputs in a
+    /// new temporary directory.
+    fn prover_state(name: &str) -> (ProverState, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("raiko-witness-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.json");
+        let config = json!({
+            "tenants": [
+                { "name": "alice", "api_key": "alice-key" },
+                { "name": "bob", "api_key": "bob-key" },
+            ],
+        });
+        std::fs::write(&config_path, config.to_string()).unwrap();
+        let cache_path = dir.join("cache");
+        let cli = Cli::parse_from([
+            "raiko".to_owned(),
+            format!("--config-path={}", config_path.display()),
+            format!("--cache-path={}", cache_path.display()),
+        ]);
+        (ProverState::init_with_opts(cli).unwrap(), cache_path)
+    }
+
+    fn cache_input(cache_path: &Path, tenant: &str, input: &GuestInput) {
+        let dir = tenant_cache_path(&Some(cache_path.to_owned()), tenant).unwrap();
+        std::fs::create_dir_all(&dir).unwrap();
+        let file =
+            std::fs::File::create(get_input_path(&dir, input.block_number, NETWORK)).unwrap();
+        bincode::serialize_into(file, input).unwrap();
+    }
+
+    async fn call(state: &ProverState, tenant: &str, method: &str, params: Value) -> Value {
+        let tenant = Tenant {
+            name: tenant.to_owned(),
+            max_jobs: None,
+            operator: false,
+        };
+        let query = WitnessQuery {
+            network: Some(NETWORK.to_owned()),
+        };
+        let req = JsonRpcRequest {
+            id: json!(1),
+            method: method.to_owned(),
+            params,
+        };
+        let Json(response) =
+            witness_handler(State(state.clone()), tenant, Query(query), Json(req)).await;
+        assert_eq!(response["id"], json!(1));
+        response
+    }
+
+    fn from_value<T: for<'de> Deserialize<'de>>(value: &Value) -> T {
+        serde_json::from_value(value.clone()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_proof() {
+        let (state, cache_path) = prover_state("proof");
+        let input = input(10);
+        cache_input(&cache_path, "alice", &input);
+        let storage_root = input.parent_storage[&contract()].0.hash();
+
+        // the state after block 9 is the parent state of block 10
+        let params = json!([contract(), ["0x1", "0x4"], "0x9"]);
+        let response = call(&state, "alice", "eth_getProof", params).await;
+        let proof = &response["result"];
+        assert_eq!(from_value::<U64>(&proof["nonce"]), U64::from(1));
+        assert_eq!(from_value::<U256>(&proof["balance"]), U256::from(100));
+        assert_eq!(from_value::<B256>(&proof["storageHash"]), storage_root);
+        let account_proof: Vec<Bytes> = from_value(&proof["accountProof"]);
+        assert_eq!(
+            B256::from(keccak(&account_proof[0])),
+            input.parent_header.state_root
+        );
+        assert_eq!(
+            account_proof,
+            input
+                .parent_state_trie
+                .proof(&keccak(contract()))
+                .unwrap()
+                .into_iter()
+                .map(Bytes::from)
+                .collect::<Vec<_>>()
+        );
+
+        let storage_proof = proof["storageProof"].as_array().unwrap();
+        let values: Vec<U256> = storage_proof
+            .iter()
+            .map(|slot| from_value(&slot["value"]))
+            .collect();
+        assert_eq!(values, [U256::from(10), U256::ZERO]);
+        for slot in storage_proof {
+            let nodes: Vec<Bytes> = from_value(&slot["proof"]);
+            assert_eq!(B256::from(keccak(&nodes[0])), storage_root);
+        }
+
+        // the slots of an account without storage are proven by its storage root
+        let params = json!([Address::repeat_byte(2), ["0x1"], 9]);
+        let response = call(&state, "alice", "eth_getProof", params).await;
+        let proof = &response["result"];
+        assert_eq!(from_value::<B256>(&proof["storageHash"]), EMPTY_ROOT);
+        assert_eq!(proof["storageProof"][0]["proof"], json!([]));
+
+        // only the parent state of cached blocks is served
+        let params = json!([contract(), [], "0xa"]);
+        let response = call(&state, "alice", "eth_getProof", params).await;
+        assert_eq!(response["error"]["code"], json!(SERVER_ERROR));
+
+        let params = json!([contract(), [], format!("{:#x}", u64::MAX)]);
+        let response = call(&state, "alice", "eth_getProof", params).await;
+        assert_eq!(response["error"]["code"], json!(INVALID_PARAMS));
+    }
+
+    #[tokio::test]
+    async fn test_get_execution_witness() {
+        let (state, cache_path) = prover_state("execution");
+        let input = input(10);
+        cache_input(&cache_path, "alice", &input);
+
+        let response = call(&state, "alice", "debug_executionWitness", json!(["0xa"])).await;
+        let witness = &response["result"];
+        let nodes: BTreeMap<String, Bytes> = from_value(&witness["state"]);
+        let nodes: BTreeMap<B256, Bytes> = nodes
+            .into_iter()
+            .map(|(hash, node)| (B256::from_str(&hash).unwrap(), node))
+            .collect();
+        for (hash, node) in &nodes {
+            assert_eq!(B256::from(keccak(node)), *hash);
+        }
+        // the roots of the state trie and the storage trie and their children
+        assert!(nodes.contains_key(&input.parent_header.state_root));
+        assert!(nodes.contains_key(&input.parent_storage[&contract()].0.hash()));
+
+        let codes: BTreeMap<String, Bytes> = from_value(&witness["codes"]);
+        assert_eq!(codes.into_values().collect::<Vec<_>>(), [code()]);
+        let headers: Vec<Bytes> = from_value(&witness["headers"]);
+        assert_eq!(
+            headers,
+            [Bytes::from(alloy_rlp::encode(&input.parent_header))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tampered_cache() {
+        let (state, cache_path) = prover_state("tampered");
+
+        // an account that isn't part of the parent state
+        let mut tampered = input(10);
+        tampered
+            .parent_state_trie
+            .insert_rlp(&keccak(Address::repeat_byte(3)), StateAccount::default())
+            .unwrap();
+        cache_input(&cache_path, "alice", &tampered);
+
+        // a storage slot that isn't part of the storage of the contract
+        let mut tampered = input(11);
+        let (storage_trie, _) = tampered.parent_storage.get_mut(&contract()).unwrap();
+        storage_trie
+            .insert_rlp(&slot_key(4), U256::from(40))
+            .unwrap();
+        cache_input(&cache_path, "alice", &tampered);
+
+        // a parent header that isn't the parent of the block
+        let mut tampered = input(12);
+        tampered.parent_header.gas_used = 1;
+        cache_input(&cache_path, "alice", &tampered);
+
+        for block_number in [10, 11, 12] {
+            let params = json!([block_number]);
+            let response = call(&state, "alice", "debug_executionWitness", params).await;
+            assert_eq!(response["error"]["code"], json!(SERVER_ERROR));
+            assert!(response.get("result").is_none());
+
+            let params = json!([contract(), ["0x1"], block_number - 1]);
+            let response = call(&state, "alice", "eth_getProof", params).await;
+            assert_eq!(response["error"]["code"], json!(SERVER_ERROR));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_other_tenant() {
+        let (state, cache_path) = prover_state("tenant");
+        cache_input(&cache_path, "alice", &input(10));
+
+        let response = call(&state, "alice", "debug_executionWitness", json!([10])).await;
+        assert!(response["result"].is_object());
+        // neither bob nor the default tenant see the inputs cached for alice
+        for tenant in ["bob", DEFAULT_TENANT] {
+            let response = call(&state, tenant, "debug_executionWitness", json!([10])).await;
+            assert_eq!(response["error"]["code"], json!(SERVER_ERROR));
+            let params = json!([contract(), [], 9]);
+            let response = call(&state, tenant, "eth_getProof", params).await;
+            assert_eq!(response["error"]["code"], json!(SERVER_ERROR));
+        }
+    }
+}
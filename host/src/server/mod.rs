@@ -2,28 +2,58 @@ use std::{net::SocketAddr, str::FromStr};
 
 use anyhow::Context;
 use tokio::net::TcpListener;
-use tracing::debug;
+use tracing::{debug, error, info};
 
-use crate::{interfaces::HostError, server::api::create_router, ProverState};
+use crate::{
+    interfaces::HostError,
+    server::api::{create_router, restart_interrupted_jobs},
+    ProverState,
+};
 
 pub mod api;
 
 /// Starts the proverd server.
 pub async fn serve(state: ProverState) -> anyhow::Result<()> {
-    let addr = SocketAddr::from_str(&state.opts.address)
-        .map_err(|_| HostError::InvalidAddress(state.opts.address.clone()))?;
+    let config = state.config();
+    let addr = SocketAddr::from_str(&config.opts.address)
+        .map_err(|_| HostError::InvalidAddress(config.opts.address.clone()))?;
     let listener = TcpListener::bind(addr).await?;
 
     debug!("Listening on: {}", listener.local_addr()?);
 
-    let router = create_router(
-        state.opts.concurrency_limit,
-        state.opts.jwt_secret.as_deref(),
-    )
-    .with_state(state);
+    restart_interrupted_jobs(&state);
+    reload_on_hangup(&state)?;
+
+    let router = create_router(config.opts.jwt_secret.as_deref()).with_state(state);
     axum::serve(listener, router)
         .await
         .context("Server couldn't serve")?;
 
     Ok(())
 }
+
+/// Reloads the config whenever the host receives `SIGHUP`.
+#[cfg(unix)]
+fn reload_on_hangup(state: &ProverState) -> anyhow::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup()).context("Could not listen for SIGHUP")?;
+    let state = state.clone();
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("Received SIGHUP, reloading the config");
+            let reloading = state.clone();
+            match tokio::task::spawn_blocking(move || reloading.reload()).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => error!("Could not reload the config: {e}"),
+                Err(e) => error!("Could not reload the config: {e}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn reload_on_hangup(_state: &ProverState) -> anyhow::Result<()> {
+    Ok(())
+}
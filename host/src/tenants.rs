@@ -0,0 +1,295 @@
+//! Tenants sharing a host, each identified by its API key.
+//!
+//! The jobs, the cached inputs and the quota of a tenant are kept apart from the other
+//! tenants, so one deployment can serve several independent teams. A host without
+//! configured tenants serves every request as the [DEFAULT_TENANT].
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, ensure};
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use raiko_core::provider::{db::StateCache, engine::PayloadStore, snapshot::SnapshotStore};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    interfaces::{HostError, HostResult},
+    ProverState,
+};
+
+/// The tenant of all requests if no tenants are configured.
+pub const DEFAULT_TENANT: &str = "default";
+/// The header the API key of the tenant is sent in.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// A tenant of the host, read from the `tenants` list of the config file.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TenantConfig {
+    /// The name of the tenant, also used as the directory of its cached inputs.
+    pub name: String,
+    pub api_key: String,
+    /// Maximum number of queued and running jobs, unlimited if not set.
+    pub max_jobs: Option<usize>,
+    /// Whether the tenant sees the jobs of all tenants and may manage the host.
+    pub operator: bool,
+}
+
+impl std::fmt::Debug for TenantConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The config is logged on startup, so the key is left out
+        f.debug_struct("TenantConfig")
+            .field("name", &self.name)
+            .field("max_jobs", &self.max_jobs)
+            .field("operator", &self.operator)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Checks that the tenants can be told apart by their API key and their name is usable as
+/// a directory name.
+pub fn validate_tenants(tenants: &[TenantConfig]) -> anyhow::Result<()> {
+    for (i, tenant) in tenants.iter().enumerate() {
+        let name = &tenant.name;
+        ensure!(
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            "Invalid tenant name {name:?}, only letters, digits, '-' and '_' are allowed"
+        );
+        ensure!(
+            name != DEFAULT_TENANT,
+            "The tenant name {name:?} is reserved"
+        );
+        ensure!(!tenant.api_key.is_empty(), "Tenant {name} has no API key");
+        for other in &tenants[..i] {
+            if other.name == *name {
+                bail!("Tenant {name} is configured twice");
+            }
+            if other.api_key == tenant.api_key {
+                bail!("Tenants {} and {name} share an API key", other.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The tenant a request is served for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tenant {
+    pub name: String,
+    pub max_jobs: Option<usize>,
+    pub operator: bool,
+}
+
+impl Default for Tenant {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_TENANT.to_owned(),
+            max_jobs: None,
+            operator: true,
+        }
+    }
+}
+
+impl From<&TenantConfig> for Tenant {
+    fn from(config: &TenantConfig) -> Self {
+        Self {
+            name: config.name.clone(),
+            max_jobs: config.max_jobs,
+            operator: config.operator,
+        }
+    }
+}
+
+impl Tenant {
+    /// Returns the tenant with the API key, or the default tenant if no tenants are
+    /// configured.
+    pub fn authenticate(tenants: &[TenantConfig], api_key: Option<&str>) -> HostResult<Self> {
+        if tenants.is_empty() {
+            return Ok(Self::default());
+        }
+        let api_key =
+            api_key.ok_or_else(|| HostError::Unauthorized("Missing API key".to_owned()))?;
+        tenants
+            .iter()
+            .find(|tenant| keys_match(&tenant.api_key, api_key))
+            .map(Self::from)
+            .ok_or_else(|| HostError::Unauthorized("Unknown API key".to_owned()))
+    }
+
+    /// Fails unless the tenant may manage the host.
+    pub fn require_operator(&self) -> HostResult<()> {
+        if self.operator {
+            Ok(())
+        } else {
+            Err(HostError::Unauthorized(format!(
+                "Tenant {} is not an operator",
+                self.name
+            )))
+        }
+    }
+}
+
+/// Returns the directory the inputs of the tenant are cached in.
+///
+/// The default tenant uses the cache directory itself, so the cache of a host is kept
+/// when it starts out without tenants.
+pub fn tenant_cache_path(cache_path: &Option<PathBuf>, tenant: &str) -> Option<PathBuf> {
+    let cache_path = cache_path.as_ref()?;
+    if tenant == DEFAULT_TENANT {
+        Some(cache_path.clone())
+    } else {
+        Some(cache_path.join(tenant))
+    }
+}
+
+/// The in-memory caches of the witness data of a tenant.
+#[derive(Debug, Clone, Default)]
+pub struct TenantCaches {
+    pub payloads: PayloadStore,
+    pub state_cache: StateCache,
+    pub snapshots: SnapshotStore,
+}
+
+/// The [TenantCaches] of every tenant, so no tenant is served the blocks and state fetched
+/// for another.
+#[derive(Debug, Clone, Default)]
+pub struct TenantStores {
+    caches: Arc<Mutex<HashMap<String, TenantCaches>>>,
+}
+
+impl TenantStores {
+    /// Returns the caches of the tenant, created on first use.
+    pub fn get(&self, tenant: &str) -> TenantCaches {
+        let mut caches = self.caches.lock().unwrap_or_else(|e| e.into_inner());
+        caches.entry(tenant.to_owned()).or_default().clone()
+    }
+
+    /// Drops the caches of the tenants that are no longer configured.
+    pub fn retain(&self, tenants: &[TenantConfig]) {
+        let mut caches = self.caches.lock().unwrap_or_else(|e| e.into_inner());
+        caches.retain(|name, _| {
+            (tenants.is_empty() && name == DEFAULT_TENANT)
+                || tenants.iter().any(|tenant| tenant.name == *name)
+        });
+    }
+}
+
+/// Compares the keys in constant time, so the time of a failed attempt tells nothing about
+/// the configured keys.
+fn keys_match(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len()
+        && expected
+            .bytes()
+            .zip(actual.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[async_trait]
+impl FromRequestParts<ProverState> for Tenant {
+    type Rejection = HostError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        prover_state: &ProverState,
+    ) -> Result<Self, Self::Rejection> {
+        let api_key = parts
+            .headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok());
+        Tenant::authenticate(&prover_state.config().opts.tenants, api_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_rpc_types::Block;
+
+    use super::*;
+
+    fn config(name: &str, api_key: &str) -> TenantConfig {
+        TenantConfig {
+            name: name.to_owned(),
+            api_key: api_key.to_owned(),
+            max_jobs: Some(2),
+            operator: false,
+        }
+    }
+
+    #[test]
+    fn test_authenticate() {
+        // Without tenants every request is served for the default tenant
+        assert_eq!(Tenant::authenticate(&[], None).unwrap(), Tenant::default());
+        assert_eq!(
+            Tenant::authenticate(&[], Some("key")).unwrap(),
+            Tenant::default()
+        );
+
+        let tenants = [config("alice", "alice-key"), config("bob", "bob-key")];
+        let bob = Tenant::authenticate(&tenants, Some("bob-key")).unwrap();
+        assert_eq!(bob.name, "bob");
+        assert_eq!(bob.max_jobs, Some(2));
+        assert!(bob.require_operator().is_err());
+        for api_key in [None, Some(""), Some("bob-ke"), Some("carol-key")] {
+            assert!(matches!(
+                Tenant::authenticate(&tenants, api_key),
+                Err(HostError::Unauthorized(_))
+            ));
+        }
+        assert!(Tenant::default().require_operator().is_ok());
+    }
+
+    #[test]
+    fn test_validate_tenants() {
+        assert!(validate_tenants(&[]).is_ok());
+        assert!(validate_tenants(&[config("alice", "a"), config("bob-2_b", "b")]).is_ok());
+        for name in ["", "../alice", "al ice", DEFAULT_TENANT] {
+            assert!(validate_tenants(&[config(name, "a")]).is_err(), "{name}");
+        }
+        assert!(validate_tenants(&[config("alice", "")]).is_err());
+        assert!(validate_tenants(&[config("alice", "a"), config("alice", "b")]).is_err());
+        assert!(validate_tenants(&[config("alice", "a"), config("bob", "a")]).is_err());
+    }
+
+    #[test]
+    fn test_tenant_cache_path() {
+        let cache_path = Some(PathBuf::from("/cache"));
+        assert_eq!(tenant_cache_path(&None, "alice"), None);
+        assert_eq!(
+            tenant_cache_path(&cache_path, DEFAULT_TENANT),
+            Some(PathBuf::from("/cache"))
+        );
+        assert_eq!(
+            tenant_cache_path(&cache_path, "alice"),
+            Some(PathBuf::from("/cache/alice"))
+        );
+    }
+
+    #[test]
+    fn test_stores() {
+        let stores = TenantStores::default();
+        let mut block = Block::default();
+        block.header.number = Some(1);
+        stores
+            .get("alice")
+            .payloads
+            .insert("taiko_a7", block)
+            .unwrap();
+        assert!(stores.get("alice").payloads.get("taiko_a7", 1).is_some());
+        // Other tenants don't see the payload
+        assert!(stores.get("bob").payloads.get("taiko_a7", 1).is_none());
+
+        stores.retain(&[config("alice", "a")]);
+        let caches = stores.caches.lock().unwrap();
+        assert_eq!(caches.keys().collect::<Vec<_>>(), ["alice"]);
+        drop(caches);
+        stores.retain(&[]);
+        assert!(stores.caches.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+//! `raiko-host top`, a terminal UI showing the status of a running host.
+
+use std::{
+    io::{self, Stdout},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use clap::Args;
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        event::{self, Event, KeyCode, KeyModifiers},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+    Frame, Terminal,
+};
+
+use crate::{
+    interfaces::HostResult,
+    jobs::{FailedJob, HostStatus, JobStatus},
+    tenants::API_KEY_HEADER,
+    Cli,
+};
+
+#[derive(Clone, Debug, Args)]
+pub struct TopArgs {
+    #[arg(long, require_equals = true, default_value = "http://localhost:8080")]
+    /// URL of the host to monitor
+    pub url: String,
+
+    #[arg(long, require_equals = true, default_value = "1000")]
+    /// Refresh interval in milliseconds
+    pub interval: u64,
+
+    #[arg(long, require_equals = true, env = "RAIKO_API_KEY")]
+    /// API key of the tenant to monitor, the jobs of all tenants are shown for operators
+    pub api_key: Option<String>,
+}
+
+/// Shows the status of the host at `args.url` until `q` is pressed.
+pub async fn run(opts: &Cli, args: &TopArgs) -> HostResult<()> {
+    let client = reqwest::Client::new();
+    let mut request = client.get(format!("{}/v1/status", args.url.trim_end_matches('/')));
+    if let Some(jwt_secret) = &opts.jwt_secret {
+        request = request.bearer_auth(jwt_secret);
+    }
+    if let Some(api_key) = &args.api_key {
+        request = request.header(API_KEY_HEADER, api_key);
+    }
+
+    let mut terminal = enter_terminal().context("Could not set up the terminal")?;
+    let result = monitor(&mut terminal, &request, &args.url, args.interval).await;
+    leave_terminal(&mut terminal).context("Could not restore the terminal")?;
+    result
+}
+
+async fn monitor(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    request: &reqwest::RequestBuilder,
+    url: &str,
+    interval: u64,
+) -> HostResult<()> {
+    let interval = Duration::from_millis(interval);
+    loop {
+        let status = fetch_status(request).await;
+        terminal.draw(|frame| draw(frame, url, &status))?;
+
+        // Handle key presses until the next refresh
+        let refresh = Instant::now() + interval;
+        while let Some(timeout) = refresh.checked_duration_since(Instant::now()) {
+            if !event::poll(timeout)? {
+                break;
+            }
+            if let Event::Key(key) = event::read()? {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) || ctrl_c {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+async fn fetch_status(request: &reqwest::RequestBuilder) -> Result<HostStatus, String> {
+    let request = request
+        .try_clone()
+        .ok_or_else(|| "Invalid request".to_owned())?;
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    let response = response.error_for_status().map_err(|e| e.to_string())?;
+    response.json().await.map_err(|e| e.to_string())
+}
+
+fn enter_terminal() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    Terminal::new(CrosstermBackend::new(stdout))
+}
+
+fn leave_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()
+}
+
+fn draw(frame: &mut Frame, url: &str, status: &Result<HostStatus, String>) {
+    let status = match status {
+        Ok(status) => status,
+        Err(e) => {
+            let text = vec![
+                Line::from(format!("Could not fetch the status of {url}")),
+                Line::from(e.as_str()),
+                Line::from(""),
+                Line::from("Press q to quit"),
+            ];
+            let paragraph = Paragraph::new(text)
+                .style(Style::default().fg(Color::Red))
+                .block(block("raiko top"));
+            frame.render_widget(paragraph, frame.size());
+            return;
+        }
+    };
+
+    let areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Percentage(35),
+            Constraint::Percentage(20),
+            Constraint::Length(status.backends.len() as u16 + 3),
+            Constraint::Min(5),
+        ])
+        .split(frame.size());
+
+    draw_summary(frame, areas[0], url, status);
+    draw_jobs(frame, areas[1], "Running", &status.jobs);
+    draw_jobs(frame, areas[2], "Queue", &status.queue);
+    draw_backends(frame, areas[3], status);
+    draw_failures(frame, areas[4], &status.failures);
+}
+
+fn draw_summary(frame: &mut Frame, area: Rect, url: &str, status: &HostStatus) {
+    let summary = format!(
+        "{url}  up {}  running {}/{}  queued {}  (q to quit)",
+        duration(status.uptime_secs),
+        status.jobs.len(),
+        status.concurrency_limit,
+        status.queue.len(),
+    );
+    frame.render_widget(Paragraph::new(summary).block(block("raiko top")), area);
+}
+
+fn draw_jobs(frame: &mut Frame, area: Rect, title: &str, jobs: &[JobStatus]) {
+    let rows = jobs.iter().map(|job| {
+        Row::new(vec![
+            Cell::from(job.id.to_string()),
+            Cell::from(job.tenant.clone()),
+            Cell::from(job.block_number.to_string()),
+            Cell::from(job.network.clone()),
+            Cell::from(job.proof_type.clone()),
+            Cell::from(format!("{:?}", job.stage).to_lowercase()),
+            Cell::from(progress_bar(job.progress)),
+            Cell::from(duration(job.stage_secs)),
+            Cell::from(duration(job.elapsed_secs)),
+        ])
+    });
+    let widths = [
+        Constraint::Length(6),
+        Constraint::Length(12),
+        Constraint::Length(10),
+        Constraint::Length(16),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(16),
+        Constraint::Length(10),
+        Constraint::Length(10),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header(&[
+            "id", "tenant", "block", "network", "prover", "stage", "progress", "in stage", "total",
+        ]))
+        .block(block(&format!("{title} ({})", jobs.len())));
+    frame.render_widget(table, area);
+}
+
+fn draw_backends(frame: &mut Frame, area: Rect, status: &HostStatus) {
+    let rows = status.backends.iter().map(|backend| {
+        Row::new(vec![
+            Cell::from(backend.proof_type.clone()),
+            Cell::from(backend.proving.to_string()),
+            Cell::from(backend.completed.to_string()),
+            Cell::from(backend.failed.to_string()),
+            Cell::from(progress_bar(backend.utilization)),
+        ])
+    });
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(8),
+        Constraint::Length(16),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header(&[
+            "prover",
+            "proving",
+            "completed",
+            "failed",
+            "utilization",
+        ]))
+        .block(block("Backends"));
+    frame.render_widget(table, area);
+}
+
+fn draw_failures(frame: &mut Frame, area: Rect, failures: &[FailedJob]) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default();
+    let rows = failures.iter().map(|failure| {
+        let ago = now.saturating_sub(failure.failed_at) as f64;
+        Row::new(vec![
+            Cell::from(format!("{} ago", duration(ago))),
+            Cell::from(failure.job.block_number.to_string()),
+            Cell::from(failure.job.network.clone()),
+            Cell::from(failure.job.proof_type.clone()),
+            Cell::from(format!("{:?}", failure.job.stage).to_lowercase()),
+            Cell::from(failure.error.clone()),
+        ])
+        .style(Style::default().fg(Color::Red))
+    });
+    let widths = [
+        Constraint::Length(12),
+        Constraint::Length(10),
+        Constraint::Length(16),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Min(20),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header(&[
+            "failed", "block", "network", "prover", "stage", "error",
+        ]))
+        .block(block("Recent failures"));
+    frame.render_widget(table, area);
+}
+
+fn block(title: &str) -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .title(title.to_owned())
+}
+
+fn header(titles: &[&'static str]) -> Row<'static> {
+    Row::new(titles.to_vec()).style(Style::default().add_modifier(Modifier::BOLD))
+}
+
+/// Renders a share between 0 and 1 as a bar with the percentage.
+fn progress_bar(share: f64) -> String {
+    const WIDTH: usize = 10;
+    let share = share.clamp(0.0, 1.0);
+    let filled = (share * WIDTH as f64).round() as usize;
+    format!(
+        "{}{} {:>3.0}%",
+        "█".repeat(filled),
+        "░".repeat(WIDTH - filled),
+        share * 100.0
+    )
+}
+
+fn duration(secs: f64) -> String {
+    let secs = secs as u64;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
@@ -0,0 +1,20 @@
+//! Decoding of the tx list of a block proposed with blob data.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use raiko_lib::utils::{
+    decode_blob_data, decode_transactions, zlib_decompress_data, BLOB_DATA_CAPACITY,
+};
+
+fuzz_target!(|data: &[u8]| {
+    // blobs always have the full size, the remainder is zero padded
+    let mut blob = data[..data.len().min(BLOB_DATA_CAPACITY)].to_vec();
+    blob.resize(BLOB_DATA_CAPACITY, 0);
+
+    let compressed = decode_blob_data(&blob);
+    if let Ok(tx_list) = zlib_decompress_data(&compressed) {
+        decode_transactions(&tx_list);
+    }
+    // the compressed data can also come directly from the calldata
+    let _ = zlib_decompress_data(data);
+});
@@ -0,0 +1,18 @@
+//! Decoding of block headers, e.g. the parent and ancestor headers of the guest input.
+#![no_main]
+
+use alloy_consensus::Header as AlloyConsensusHeader;
+use libfuzzer_sys::fuzz_target;
+use raiko_lib::{utils::HeaderHasher, RlpBytes};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(header) = AlloyConsensusHeader::decode_bytes(data) {
+        // the block hash is computed over the re-encoded header
+        let encoded = alloy_rlp::encode(&header);
+        assert_eq!(
+            AlloyConsensusHeader::decode_bytes(&encoded).unwrap(),
+            header
+        );
+        header.hash();
+    }
+});
@@ -0,0 +1,21 @@
+//! Decoding of Merkle Patricia Trie nodes, as in the account and storage proofs of the
+//! guest input.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use raiko_lib::primitives::mpt::{keccak, MptNode, RlpBytes};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(node) = MptNode::decode(data) else {
+        return;
+    };
+    // the re-encoding determines the node hash, so it must be stable
+    let encoded = node.to_rlp();
+    assert_eq!(MptNode::decode(&encoded).unwrap().to_rlp(), encoded);
+    node.hash();
+
+    // lookups in an arbitrary node must fail gracefully
+    let key = keccak(data);
+    let _ = node.get(&key);
+    let _ = node.proof(&key);
+});
@@ -0,0 +1,23 @@
+//! Decoding of transactions, as in the tx list of a Taiko block and in the block bodies.
+#![no_main]
+
+use alloy_consensus::TxEnvelope;
+use alloy_eips::eip2718::Decodable2718;
+use libfuzzer_sys::fuzz_target;
+use raiko_lib::{
+    builder::execute::recover_sender, primitives::Rlp2718Bytes, utils::decode_transactions,
+};
+
+fuzz_target!(|data: &[u8]| {
+    // a single EIP-2718 envelope, as stored in the transaction trie
+    if let Ok(tx) = TxEnvelope::decode_2718(&mut &data[..]) {
+        let encoded = tx.to_rlp_2718();
+        assert_eq!(TxEnvelope::decode_2718(&mut &encoded[..]).unwrap(), tx);
+        let _ = recover_sender(&tx);
+    }
+
+    // an RLP list of transactions, invalid lists decode to no transactions
+    for tx in decode_transactions(data) {
+        let _ = recover_sender(&tx);
+    }
+});
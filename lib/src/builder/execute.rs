@@ -28,6 +28,7 @@ use revm::{
     },
     taiko, Database, DatabaseCommit, Evm, JournaledState,
 };
+use thiserror_no_std::Error as ThisError;
 cfg_if::cfg_if! {
     if #[cfg(feature = "tracer")] {
         use std::{fs::{OpenOptions, File}, io::{BufWriter, Write}, sync::{Arc, Mutex}};
@@ -35,15 +36,15 @@ cfg_if::cfg_if! {
     }
 }
 
-use super::{OptimisticDatabase, TxExecStrategy};
+use super::{trace::TxTrace, OptimisticDatabase, TxExecStrategy};
 use crate::{
     builder::BlockBuilder,
     clear_line,
     consts::GWEI_TO_WEI,
     guest_mem_forget, inplace_print,
     primitives::{
-        alloy_eips::eip4788::SYSTEM_ADDRESS, mpt::MptNode, receipt::Receipt, Bloom, Rlp2718Bytes,
-        RlpBytes,
+        alloy_eips::eip4788::SYSTEM_ADDRESS, keccak::keccak, mpt::MptNode, receipt::Receipt, Bloom,
+        Rlp2718Bytes, RlpBytes, B256,
     },
     print_duration,
     time::{AddAssign, Duration, Instant},
@@ -88,7 +89,7 @@ impl TxExecStrategy for TkoTxExecStrategy {
         // generate the transactions from the tx list
         // For taiko blocks, insert the anchor tx as the first transaction
         let anchor_tx = if chain_spec.is_taiko() {
-            Some(serde_json::from_str(&block_builder.input.taiko.anchor_tx.clone()).unwrap())
+            Some(&block_builder.input.taiko.anchor_tx)
         } else {
             None
         };
@@ -97,7 +98,8 @@ impl TxExecStrategy for TkoTxExecStrategy {
             block_builder.input.taiko.block_proposed.meta.blobUsed,
             &block_builder.input.taiko.tx_data,
             anchor_tx,
-        );
+            &block_builder.limits,
+        )?;
 
         // Setup the EVM environment
         let evm = Evm::builder().with_db(block_builder.db.take().unwrap());
@@ -169,13 +171,16 @@ impl TxExecStrategy for TkoTxExecStrategy {
         let mut cumulative_gas_used = 0u64;
         let mut blob_gas_used = 0_u64;
 
+        // recover all senders upfront, which doesn't depend on the execution
+        let measurement = Measurement::start("Recovering senders...", true);
+        let senders = recover_senders(&transactions)?;
+        measurement.stop();
+
         // process all the transactions
-        let mut tx_trie = MptNode::default();
-        let mut receipt_trie = MptNode::default();
-        // track the actual tx number to use in the tx/receipt trees as the key
-        let mut actual_tx_no = 0usize;
+        let mut included_txs = Vec::with_capacity(transactions.len());
+        let mut receipts = Vec::with_capacity(transactions.len());
         let num_transactions = transactions.len();
-        for (tx_no, tx) in take(&mut transactions).into_iter().enumerate() {
+        for (tx_no, (tx, sender)) in take(&mut transactions).into_iter().zip(senders).enumerate() {
             inplace_print(&format!("\rprocessing tx {tx_no}/{num_transactions}..."));
 
             #[cfg(feature = "tracer")]
@@ -183,7 +188,7 @@ impl TxExecStrategy for TkoTxExecStrategy {
                 &mut evm.context.external,
                 chain_id,
                 block_builder.input.block_number,
-                actual_tx_no,
+                included_txs.len(),
             );
 
             // anchor transaction always the first transaction
@@ -191,7 +196,7 @@ impl TxExecStrategy for TkoTxExecStrategy {
 
             // setup the EVM environment
             let tx_env = &mut evm.env_mut().tx;
-            fill_eth_tx_env(tx_env, &tx)?;
+            fill_eth_tx_env(tx_env, &tx, sender)?;
             // Set and check some taiko specific values
             if chain_spec.is_taiko() {
                 // set if the tx is the anchor tx
@@ -314,21 +319,19 @@ impl TxExecStrategy for TkoTxExecStrategy {
                 result.logs().iter().map(|log| log.clone().into()).collect(),
             );
 
+            if let Some(tx_traces) = &mut block_builder.tx_traces {
+                tx_traces.push(TxTrace::new(tx_hash(&tx), gas_used, &state));
+            }
+
             // update the state
             evm.context.evm.db.commit(state);
 
             // accumulate logs to the block bloom filter
             logs_bloom.accrue_bloom(&receipt.payload.logs_bloom);
 
-            // Add receipt and tx to tries
-            let trie_key = alloy_rlp::encode(actual_tx_no);
-            // Add to tx trie
-            tx_trie.insert_rlp_encoded(&trie_key, tx.to_rlp_2718())?;
-            // Add to receipt trie
-            receipt_trie.insert_rlp(&trie_key, receipt)?;
-
-            // If we got here it means the tx is not invalid
-            actual_tx_no += 1;
+            // If we got here it means the tx is not invalid, so it goes into the tries
+            included_txs.push(tx);
+            receipts.push(receipt);
 
             tx_misc_duration.add_assign(start.elapsed());
         }
@@ -336,6 +339,12 @@ impl TxExecStrategy for TkoTxExecStrategy {
         print_duration("Tx transact time: ", tx_transact_duration);
         print_duration("Tx misc time: ", tx_misc_duration);
 
+        let measurement = Measurement::start("Building tx and receipt tries...", true);
+        let (tx_trie, receipt_trie) = build_tx_and_receipt_tries(&included_txs, &receipts)?;
+        guest_mem_forget(included_txs);
+        guest_mem_forget(receipts);
+        measurement.stop();
+
         let mut db = &mut evm.context.evm.db;
 
         // process withdrawals unconditionally after any transactions
@@ -377,16 +386,98 @@ impl TxExecStrategy for TkoTxExecStrategy {
     }
 }
 
-pub fn fill_eth_tx_env(tx_env: &mut TxEnv, tx: &TxEnvelope) -> Result<(), Error> {
+/// Error returned when the sender of a transaction can't be recovered.
+#[derive(Debug, ThisError, PartialEq, Eq)]
+pub enum RecoverError {
+    /// Returned for transaction types the builder doesn't support.
+    #[error("unsupported transaction type {0}")]
+    UnsupportedTxType(u8),
+}
+
+impl From<RecoverError> for anyhow::Error {
+    fn from(error: RecoverError) -> Self {
+        // `anyhow!` would convert the error with this impl again
+        anyhow::Error::msg(error)
+    }
+}
+
+/// Recovers the senders of the transactions, [Address::ZERO] for invalid signatures.
+pub fn recover_senders(transactions: &[TxEnvelope]) -> Result<Vec<Address>, RecoverError> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        transactions.par_iter().map(recover_sender).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    transactions.iter().map(recover_sender).collect()
+}
+
+/// Recovers the sender of the transaction, [Address::ZERO] for an invalid signature.
+pub fn recover_sender(tx: &TxEnvelope) -> Result<Address, RecoverError> {
+    // TODO(Brecht): use optimized recover
+    let signer = match tx {
+        TxEnvelope::Legacy(tx) => tx.recover_signer(),
+        TxEnvelope::Eip2930(tx) => tx.recover_signer(),
+        TxEnvelope::Eip1559(tx) => tx.recover_signer(),
+        TxEnvelope::Eip4844(tx) => tx.recover_signer(),
+        _ => return Err(RecoverError::UnsupportedTxType(tx.tx_type() as u8)),
+    };
+    Ok(signer.unwrap_or_default())
+}
+
+/// Returns the hash of the transaction.
+fn tx_hash(tx: &TxEnvelope) -> B256 {
+    keccak(tx.to_rlp_2718()).into()
+}
+
+/// Builds the tx and receipt tries of the included transactions. Both tries, and the
+/// encoding of their values, are built in parallel if enabled.
+pub fn build_tx_and_receipt_tries(
+    transactions: &[TxEnvelope],
+    receipts: &[Receipt],
+) -> Result<(MptNode, MptNode)> {
+    let build_tx_trie = || build_trie(transactions, |tx: &TxEnvelope| tx.to_rlp_2718());
+    let build_receipt_trie =
+        || build_trie(receipts, |receipt: &Receipt| alloy_rlp::encode(receipt));
+
+    #[cfg(feature = "parallel")]
+    let (tx_trie, receipt_trie) = rayon::join(build_tx_trie, build_receipt_trie);
+    #[cfg(not(feature = "parallel"))]
+    let (tx_trie, receipt_trie) = (build_tx_trie(), build_receipt_trie());
+
+    Ok((tx_trie?, receipt_trie?))
+}
+
+/// Builds a trie keyed by the RLP encoded index of each value.
+fn build_trie<T: Sync>(
+    values: &[T],
+    encode: impl Fn(&T) -> Vec<u8> + Send + Sync,
+) -> Result<MptNode> {
+    #[cfg(feature = "parallel")]
+    let encoded: Vec<Vec<u8>> = {
+        use rayon::prelude::*;
+        values.par_iter().map(encode).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let encoded = values.iter().map(encode);
+
+    let mut trie = MptNode::default();
+    for (i, value) in encoded.into_iter().enumerate() {
+        trie.insert_rlp_encoded(&alloy_rlp::encode(i), value)?;
+    }
+    Ok(trie)
+}
+
+/// Fills the tx environment from the transaction, sent by the recovered `caller`.
+pub fn fill_eth_tx_env(tx_env: &mut TxEnv, tx: &TxEnvelope, caller: Address) -> Result<(), Error> {
     // Clear values that may not be set
     tx_env.access_list.clear();
     tx_env.blob_hashes.clear();
     tx_env.max_fee_per_blob_gas.take();
+    tx_env.caller = caller;
     // Get the data from the tx
-    // TODO(Brecht): use optimized recover
     match tx {
         TxEnvelope::Legacy(tx) => {
-            tx_env.caller = tx.recover_signer().unwrap_or_default();
             let tx = tx.tx();
             tx_env.gas_limit = tx.gas_limit.try_into().unwrap();
             tx_env.gas_price = tx.gas_price.try_into().unwrap();
@@ -403,7 +494,6 @@ pub fn fill_eth_tx_env(tx_env: &mut TxEnv, tx: &TxEnvelope) -> Result<(), Error>
             tx_env.access_list.clear();
         }
         TxEnvelope::Eip2930(tx) => {
-            tx_env.caller = tx.recover_signer().unwrap_or_default();
             let tx = tx.tx();
             tx_env.gas_limit = tx.gas_limit.try_into().unwrap();
             tx_env.gas_price = tx.gas_price.try_into().unwrap();
@@ -420,7 +510,6 @@ pub fn fill_eth_tx_env(tx_env: &mut TxEnv, tx: &TxEnvelope) -> Result<(), Error>
             tx_env.access_list = tx.access_list.flattened();
         }
         TxEnvelope::Eip1559(tx) => {
-            tx_env.caller = tx.recover_signer().unwrap_or_default();
             let tx = tx.tx();
             tx_env.gas_limit = tx.gas_limit.try_into().unwrap();
             tx_env.gas_price = tx.max_fee_per_gas.try_into().unwrap();
@@ -437,7 +526,6 @@ pub fn fill_eth_tx_env(tx_env: &mut TxEnv, tx: &TxEnvelope) -> Result<(), Error>
             tx_env.access_list = tx.access_list.flattened();
         }
         TxEnvelope::Eip4844(tx) => {
-            tx_env.caller = tx.recover_signer().unwrap_or_default();
             let tx = tx.tx().tx();
             tx_env.gas_limit = tx.gas_limit.try_into().unwrap();
             tx_env.gas_price = tx.max_fee_per_gas.try_into().unwrap();
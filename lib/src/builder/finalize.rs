@@ -15,15 +15,19 @@
 use core::mem;
 
 use alloy_consensus::Header as AlloyConsensusHeader;
-use anyhow::Result;
-use revm::{Database, DatabaseCommit};
+use anyhow::{Context, Result};
+use revm::{
+    primitives::{Address, HashMap},
+    Database, DatabaseCommit,
+};
 
 use crate::{
-    builder::BlockBuilder,
+    builder::{BlockBuilder, BuiltState},
     guest_mem_forget,
+    input::StorageEntry,
     mem_db::{AccountState, MemDb},
     primitives::{
-        keccak::keccak,
+        keccak::KeccakCache,
         mpt::{MptNode, StateAccount},
     },
 };
@@ -33,76 +37,106 @@ where
     D: Database + DatabaseCommit,
     <D as Database>::Error: core::fmt::Debug,
 {
-    fn finalize(block_builder: BlockBuilder<D>) -> Result<(AlloyConsensusHeader, MptNode)>;
+    fn finalize(block_builder: BlockBuilder<D>) -> Result<(AlloyConsensusHeader, MptNode)> {
+        let state = Self::finalize_state(block_builder)?;
+        guest_mem_forget(state.storage);
+        Ok((state.header, state.state_trie))
+    }
+
+    /// Finalizes the block and also returns the updated storage tries of the input.
+    fn finalize_state(block_builder: BlockBuilder<D>) -> Result<BuiltState>;
 }
 
 pub struct MemDbBlockFinalizeStrategy {}
 
 impl BlockFinalizeStrategy<MemDb> for MemDbBlockFinalizeStrategy {
-    fn finalize(mut block_builder: BlockBuilder<MemDb>) -> Result<(AlloyConsensusHeader, MptNode)> {
+    fn finalize_state(mut block_builder: BlockBuilder<MemDb>) -> Result<BuiltState> {
         let db: MemDb = block_builder.db.take().expect("DB not initialized");
+        let keccak_cache = &mut block_builder.keccak_cache;
 
         // apply state updates
         let mut state_trie = mem::take(&mut block_builder.input.parent_state_trie);
-        for (address, account) in &db.accounts {
-            // if the account has not been touched, it can be ignored
-            if account.state == AccountState::None {
-                continue;
-            }
+        apply_state_changes(
+            &db,
+            &mut state_trie,
+            &mut block_builder.input.parent_storage,
+            keccak_cache,
+        )?;
 
-            // compute the index of the current account in the state trie
-            let state_trie_index = keccak(address);
+        // update result header with the new state root
+        let mut header = block_builder.header.take().expect("Header not initialized");
+        header.state_root = state_trie.hash_with_cache(keccak_cache);
+        let keccak_stats = keccak_cache.stats();
 
-            // remove deleted accounts from the state trie
-            if account.state == AccountState::Deleted {
-                state_trie.delete(&state_trie_index)?;
-                continue;
-            }
+        // Leak memory, save cycles
+        let storage = mem::take(&mut block_builder.input.parent_storage);
+        guest_mem_forget(block_builder);
 
-            // otherwise, compute the updated storage root for that account
-            let state_storage = &account.storage;
-            let storage_root = {
-                // getting a mutable reference is more efficient than calling remove
-                // every account must have an entry, even newly created accounts
-                let (storage_trie, _) = block_builder
-                    .input
-                    .parent_storage
-                    .get_mut(address)
-                    .expect("Address not found in storage");
-                // for cleared accounts always start from the empty trie
-                if account.state == AccountState::StorageCleared {
-                    storage_trie.clear();
-                }
+        Ok(BuiltState {
+            header,
+            state_trie,
+            storage,
+            keccak_stats,
+        })
+    }
+}
 
-                // apply all new storage entries for the current account (address)
-                for (key, value) in state_storage {
-                    let storage_trie_index = keccak(key.to_be_bytes::<32>());
-                    if value.is_zero() {
-                        storage_trie.delete(&storage_trie_index)?;
-                    } else {
-                        storage_trie.insert_rlp(&storage_trie_index, *value)?;
-                    }
-                }
+/// Applies the changes of the touched accounts in `db` to the state trie and the storage
+/// tries of the parent state.
+pub fn apply_state_changes(
+    db: &MemDb,
+    state_trie: &mut MptNode,
+    parent_storage: &mut HashMap<Address, StorageEntry>,
+    keccak_cache: &mut KeccakCache,
+) -> Result<()> {
+    for (address, account) in &db.accounts {
+        // if the account has not been touched, it can be ignored
+        if account.state == AccountState::None {
+            continue;
+        }
 
-                storage_trie.hash()
-            };
+        // compute the index of the current account in the state trie
+        let state_trie_index = keccak_cache.keccak(address);
 
-            let state_account = StateAccount {
-                nonce: account.info.nonce,
-                balance: account.info.balance,
-                storage_root,
-                code_hash: account.info.code_hash,
-            };
-            state_trie.insert_rlp(&state_trie_index, state_account)?;
+        // remove deleted accounts from the state trie
+        if account.state == AccountState::Deleted {
+            state_trie.delete(&state_trie_index)?;
+            continue;
         }
 
-        // update result header with the new state root
-        let mut header = block_builder.header.take().expect("Header not initialized");
-        header.state_root = state_trie.hash();
+        // otherwise, compute the updated storage root for that account
+        let state_storage = &account.storage;
+        let storage_root = {
+            // getting a mutable reference is more efficient than calling remove
+            // every account must have an entry, even newly created accounts
+            let (storage_trie, _) = parent_storage
+                .get_mut(address)
+                .with_context(|| format!("Address {address} not found in storage"))?;
+            // for cleared accounts always start from the empty trie
+            if account.state == AccountState::StorageCleared {
+                storage_trie.clear();
+            }
 
-        // Leak memory, save cycles
-        guest_mem_forget(block_builder);
+            // apply all new storage entries for the current account (address)
+            for (key, value) in state_storage {
+                let storage_trie_index = keccak_cache.keccak(key.to_be_bytes::<32>());
+                if value.is_zero() {
+                    storage_trie.delete(&storage_trie_index)?;
+                } else {
+                    storage_trie.insert_rlp(&storage_trie_index, *value)?;
+                }
+            }
+
+            storage_trie.hash_with_cache(keccak_cache)
+        };
 
-        Ok((header, state_trie))
+        let state_account = StateAccount {
+            nonce: account.info.nonce,
+            balance: account.info.balance,
+            storage_root,
+            code_hash: account.info.code_hash,
+        };
+        state_trie.insert_rlp(&state_trie_index, state_account)?;
     }
+    Ok(())
 }
@@ -14,9 +14,9 @@
 
 use core::mem;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use revm::{
-    primitives::{AccountInfo, Bytecode, HashMap, B256},
+    primitives::{AccountInfo, Address, Bytecode, HashMap, B256},
     Database, DatabaseCommit,
 };
 
@@ -24,12 +24,9 @@ use crate::{
     builder::BlockBuilder,
     consts::MAX_BLOCK_HASH_AGE,
     guest_mem_forget,
+    input::StorageEntry,
     mem_db::{AccountState, DbAccount, MemDb},
-    primitives::{
-        keccak::{keccak, KECCAK_EMPTY},
-        mpt::StateAccount,
-        Bytes,
-    },
+    primitives::{keccak::KECCAK_EMPTY, mpt::StateAccount, trie_bloom::TrieBloom, Bytes},
     utils::HeaderHasher,
 };
 
@@ -45,41 +42,49 @@ pub struct MemDbInitStrategy {}
 
 impl DbInitStrategy<MemDb> for MemDbInitStrategy {
     fn initialize_database(mut block_builder: BlockBuilder<MemDb>) -> Result<BlockBuilder<MemDb>> {
+        let keccak_cache = &mut block_builder.keccak_cache;
+
         // Verify state trie root
-        if block_builder.input.parent_state_trie.hash()
-            != block_builder.input.parent_header.state_root
-        {
+        let state_root = block_builder
+            .input
+            .parent_state_trie
+            .hash_with_cache(keccak_cache);
+        if state_root != block_builder.input.parent_header.state_root {
             bail!(
-                "Invalid state trie: expected {}, got {}",
+                "Invalid state trie: expected {}, got {state_root}",
                 block_builder.input.parent_header.state_root,
-                block_builder.input.parent_state_trie.hash()
             );
         }
 
         // hash all the contract code
         let contracts: HashMap<B256, Bytes> = mem::take(&mut block_builder.input.contracts)
             .into_iter()
-            .map(|bytes| (keccak(&bytes).into(), bytes))
+            .map(|bytes| (keccak_cache.keccak(&bytes), bytes))
             .collect();
 
+        // Compute all storage roots, which are independent of each other
+        let storage_roots = storage_roots(&mut block_builder.input.parent_storage);
+
         // Load account data into db
+        let state_bloom = TrieBloom::new(&block_builder.input.parent_state_trie);
         let mut accounts = HashMap::with_capacity(block_builder.input.parent_storage.len());
         for (address, (storage_trie, slots)) in &mut block_builder.input.parent_storage {
             // consume the slots, as they are no longer needed afterwards
             let slots = mem::take(slots);
 
             // load the account from the state trie or empty if it does not exist
-            let state_account = block_builder
-                .input
-                .parent_state_trie
-                .get_rlp::<StateAccount>(&keccak(address))?
+            let state_account = state_bloom
+                .get_rlp::<StateAccount>(
+                    &block_builder.input.parent_state_trie,
+                    &keccak_cache.keccak(address),
+                )?
                 .unwrap_or_default();
             // Verify storage trie root
-            if storage_trie.hash() != state_account.storage_root {
+            let storage_root = storage_roots[address];
+            if storage_root != state_account.storage_root {
                 bail!(
-                    "Invalid storage trie for {address:?}: expected {}, got {}",
+                    "Invalid storage trie for {address:?}: expected {}, got {storage_root}",
                     state_account.storage_root,
-                    storage_trie.hash()
                 );
             }
 
@@ -90,16 +95,21 @@ impl DbInitStrategy<MemDb> for MemDbInitStrategy {
             } else {
                 let bytes = contracts
                     .get(&code_hash)
-                    .expect("Contract not found")
+                    .with_context(|| format!("Contract {code_hash} not found"))?
                     .clone();
                 Bytecode::new_raw(bytes)
             };
 
             // load storage reads
             let mut storage = HashMap::with_capacity(slots.len());
+            let storage_bloom = if slots.is_empty() {
+                TrieBloom::pass_through()
+            } else {
+                TrieBloom::new(storage_trie)
+            };
             for slot in slots {
-                let value: crate::primitives::U256 = storage_trie
-                    .get_rlp(&keccak(slot.to_be_bytes::<32>()))?
+                let value: crate::primitives::U256 = storage_bloom
+                    .get_rlp(storage_trie, &keccak_cache.keccak(slot.to_be_bytes::<32>()))?
                     .unwrap_or_default();
                 storage.insert(slot, value);
             }
@@ -155,3 +165,27 @@ impl DbInitStrategy<MemDb> for MemDbInitStrategy {
         }))
     }
 }
+
+/// Computes the storage root of every account, in parallel if enabled.
+///
+/// Every node of the tries is only hashed once, so the tries are hashed without the keccak
+/// cache, which couldn't be shared between the threads anyway.
+fn storage_roots(parent_storage: &mut HashMap<Address, StorageEntry>) -> HashMap<Address, B256> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        // the tries are only `Send`, so they are handed out mutably to the threads
+        let roots: Vec<_> = parent_storage
+            .iter_mut()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|(address, (storage_trie, _))| (*address, storage_trie.hash()))
+            .collect();
+        roots.into_iter().collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    parent_storage
+        .iter()
+        .map(|(address, (storage_trie, _))| (*address, storage_trie.hash()))
+        .collect()
+}
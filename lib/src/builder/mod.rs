@@ -14,25 +14,33 @@
 
 use alloy_consensus::Header as AlloyConsensusHeader;
 use anyhow::Result;
-use revm::{Database, DatabaseCommit};
+use revm::{
+    primitives::{Address, HashMap},
+    Database, DatabaseCommit,
+};
 
-pub use self::execute::TkoTxExecStrategy;
+pub use self::{execute::TkoTxExecStrategy, finalize::apply_state_changes};
 use crate::{
     builder::{
         finalize::{BlockFinalizeStrategy, MemDbBlockFinalizeStrategy},
         initialize::{DbInitStrategy, MemDbInitStrategy},
         prepare::{HeaderPrepStrategy, TaikoHeaderPrepStrategy},
+        trace::TxTrace,
     },
     consts::ChainSpec,
-    input::GuestInput,
+    input::{GuestInput, InputLimits, StorageEntry},
     mem_db::MemDb,
-    primitives::mpt::MptNode,
+    primitives::{
+        keccak::{KeccakCache, KeccakCacheStats},
+        mpt::MptNode,
+    },
 };
 
 pub mod execute;
 mod finalize;
 mod initialize;
 pub mod prepare;
+pub mod trace;
 
 /// Optimistic database
 #[allow(async_fn_in_trait)]
@@ -44,6 +52,17 @@ pub trait OptimisticDatabase {
     fn is_optimistic(&self) -> bool;
 }
 
+/// The result of a block build, with the resulting state.
+#[derive(Clone, Debug)]
+pub struct BuiltState {
+    pub header: AlloyConsensusHeader,
+    pub state_trie: MptNode,
+    /// The updated storage tries of all accounts in the input
+    pub storage: HashMap<Address, StorageEntry>,
+    /// The keccak cache usage of the build
+    pub keccak_stats: KeccakCacheStats,
+}
+
 /// A generic builder for building a block.
 #[derive(Clone, Debug)]
 pub struct BlockBuilder<D> {
@@ -51,6 +70,11 @@ pub struct BlockBuilder<D> {
     pub(crate) input: GuestInput,
     pub(crate) db: Option<D>,
     pub(crate) header: Option<AlloyConsensusHeader>,
+    pub(crate) keccak_cache: KeccakCache,
+    /// The limits the decoded parts of the input are checked against.
+    pub(crate) limits: InputLimits,
+    /// The results of the included transactions, only recorded if enabled.
+    pub(crate) tx_traces: Option<Vec<TxTrace>>,
 }
 
 impl<D> BlockBuilder<D>
@@ -65,9 +89,29 @@ where
             db: None,
             header: None,
             input: input.clone(),
+            keccak_cache: KeccakCache::default(),
+            limits: InputLimits::default(),
+            tx_traces: None,
         }
     }
 
+    /// Checks the decoded parts of the input against `limits` instead of the defaults.
+    pub fn with_limits(mut self, limits: InputLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Records the result of every included transaction during the execution.
+    pub fn with_tx_traces(mut self) -> Self {
+        self.tx_traces = Some(Vec::new());
+        self
+    }
+
+    /// Returns the recorded results of the included transactions.
+    pub fn take_tx_traces(&mut self) -> Vec<TxTrace> {
+        self.tx_traces.take().unwrap_or_default()
+    }
+
     /// Sets the database instead of initializing it from the input.
     pub fn with_db(mut self, db: D) -> Self {
         self.db = Some(db);
@@ -94,6 +138,12 @@ where
         T::finalize(self)
     }
 
+    /// Finalizes the block building and returns the header, the state trie and the
+    /// storage tries of all accounts in the input.
+    pub fn finalize_state<T: BlockFinalizeStrategy<D>>(self) -> Result<BuiltState> {
+        T::finalize_state(self)
+    }
+
     /// Returns a reference to the database.
     pub fn db(&self) -> Option<&D> {
         self.db.as_ref()
@@ -120,6 +170,34 @@ pub trait BlockBuilderStrategy {
             .execute_transactions::<Self::TxExecStrategy>()?
             .finalize::<Self::BlockFinalizeStrategy>()
     }
+
+    /// Builds a block from the given input and also returns the storage tries of the
+    /// resulting state.
+    fn build_state_from(input: &GuestInput) -> Result<BuiltState> {
+        Self::build_state_with_limits(input, InputLimits::default())
+    }
+
+    /// Builds a block like [BlockBuilderStrategy::build_state_from], checking the decoded
+    /// parts of the input against `limits`.
+    fn build_state_with_limits(input: &GuestInput, limits: InputLimits) -> Result<BuiltState> {
+        BlockBuilder::<MemDb>::new(input)
+            .with_limits(limits)
+            .initialize_database::<Self::DbInitStrategy>()?
+            .prepare_header::<Self::HeaderPrepStrategy>()?
+            .execute_transactions::<Self::TxExecStrategy>()?
+            .finalize_state::<Self::BlockFinalizeStrategy>()
+    }
+
+    /// Executes the transactions of the given input and returns the result of every
+    /// included transaction, without finalizing the block.
+    fn trace_from(input: &GuestInput) -> Result<Vec<TxTrace>> {
+        BlockBuilder::<MemDb>::new(input)
+            .with_tx_traces()
+            .initialize_database::<Self::DbInitStrategy>()?
+            .prepare_header::<Self::HeaderPrepStrategy>()?
+            .execute_transactions::<Self::TxExecStrategy>()
+            .map(|mut block_builder| block_builder.take_tx_traces())
+    }
 }
 
 /// The [BlockBuilderStrategy] for building a Taiko block.
@@ -0,0 +1,60 @@
+//! Per-transaction results of the block execution, to find where the execution diverges
+//! from the execution of a node.
+
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+
+use alloy_primitives::{Address, B256, U256};
+use revm::primitives::{Account, HashMap};
+use serde::{Deserialize, Serialize};
+
+/// The state of an account after a transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountTrace {
+    pub balance: U256,
+    pub nonce: u64,
+    /// Set if the account was destroyed by the transaction.
+    pub deleted: bool,
+    /// The storage slots changed by the transaction, with their new values.
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// The gas used and the state changes of a single included transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxTrace {
+    pub tx_hash: B256,
+    pub gas_used: u64,
+    /// All accounts touched by the transaction.
+    pub accounts: BTreeMap<Address, AccountTrace>,
+}
+
+impl TxTrace {
+    /// Records the result of a transaction from the state changes of the EVM.
+    pub fn new(tx_hash: B256, gas_used: u64, state: &HashMap<Address, Account>) -> Self {
+        let accounts = state
+            .iter()
+            .filter(|(_, account)| account.is_touched())
+            .map(|(address, account)| {
+                let storage = account
+                    .storage
+                    .iter()
+                    .filter(|(_, slot)| slot.is_changed())
+                    .map(|(key, slot)| (*key, slot.present_value()))
+                    .collect();
+                let trace = AccountTrace {
+                    balance: account.info.balance,
+                    nonce: account.info.nonce,
+                    deleted: account.is_selfdestructed(),
+                    storage,
+                };
+                (*address, trace)
+            })
+            .collect();
+        Self {
+            tx_hash,
+            gas_used,
+            accounts,
+        }
+    }
+}
@@ -22,6 +22,7 @@ use anyhow::{anyhow, bail, Result};
 use revm::primitives::SpecId;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use thiserror_no_std::Error as ThisError;
 
 #[cfg(not(feature = "std"))]
 use crate::no_std::*;
@@ -53,14 +54,42 @@ impl Default for SupportedChainSpecs {
     fn default() -> Self {
         let deserialized: Vec<ChainSpec> =
             serde_json::from_str(DEFAULT_CHAIN_SPECS).unwrap_or_default();
-        let chain_spec_list = deserialized
-            .into_iter()
-            .map(|cs| (cs.name.clone(), cs))
-            .collect::<HashMap<String, ChainSpec>>();
+        let chain_spec_list =
+            chain_spec_map(deserialized).expect("The default chain specs are unambiguous");
         SupportedChainSpecs(chain_spec_list)
     }
 }
 
+/// Maps the chain specs by network name, failing if a network is listed twice, as only one
+/// of its specs would be used.
+fn chain_spec_map(chain_specs: Vec<ChainSpec>) -> Result<HashMap<String, ChainSpec>> {
+    let mut chain_spec_map = HashMap::with_capacity(chain_specs.len());
+    for cs in chain_specs {
+        if chain_spec_map.contains_key(&cs.name) {
+            bail!("The network {} is listed twice", cs.name);
+        }
+        chain_spec_map.insert(cs.name.clone(), cs);
+    }
+    check_chain_ids(chain_spec_map.values())?;
+    Ok(chain_spec_map)
+}
+
+/// Fails if two networks have the same chain id, as blocks are also matched to their spec by
+/// chain id.
+fn check_chain_ids<'a>(chain_specs: impl IntoIterator<Item = &'a ChainSpec>) -> Result<()> {
+    let mut networks = HashMap::new();
+    for cs in chain_specs {
+        if let Some(other) = networks.insert(cs.chain_id, &cs.name) {
+            bail!(
+                "The networks {other} and {} have the same chain id {}",
+                cs.name,
+                cs.chain_id
+            );
+        }
+    }
+    Ok(())
+}
+
 impl SupportedChainSpecs {
     #[cfg(feature = "std")]
     pub fn merge_from_file(file_path: PathBuf) -> Result<SupportedChainSpecs> {
@@ -69,13 +98,11 @@ impl SupportedChainSpecs {
         let reader = std::io::BufReader::new(file);
         let config: Value = serde_json::from_reader(reader)?;
         let chain_spec_list: Vec<ChainSpec> = serde_json::from_value(config)?;
-        let new_chain_specs = chain_spec_list
-            .into_iter()
-            .map(|cs| (cs.name.clone(), cs))
-            .collect::<HashMap<String, ChainSpec>>();
+        let new_chain_specs = chain_spec_map(chain_spec_list)?;
 
         // override known specs
         known_chain_specs.0.extend(new_chain_specs);
+        check_chain_ids(known_chain_specs.0.values())?;
         Ok(known_chain_specs)
     }
 
@@ -231,6 +258,91 @@ impl ChainSpec {
     pub fn network(&self) -> String {
         self.name.clone()
     }
+
+    /// Returns the inconsistencies of the spec that would make blocks fail to build.
+    pub fn validate(&self) -> Vec<ChainSpecIssue> {
+        let mut issues = Vec::new();
+
+        if self.spec_id(0, self.genesis_time).is_none() {
+            issues.push(ChainSpecIssue::NoGenesisFork);
+        }
+        for (spec_id, fork) in &self.hard_forks {
+            if *spec_id > self.max_spec_id && *fork != ForkCondition::TBD {
+                issues.push(ChainSpecIssue::ForkAboveMax(*spec_id));
+            }
+        }
+        // Every fork has to activate at or after all the forks it builds upon
+        let forks: Vec<_> = self.hard_forks.iter().collect();
+        for (i, (later, later_fork)) in forks.iter().enumerate() {
+            for (earlier, earlier_fork) in &forks[..i] {
+                if activates_before(later_fork, earlier_fork) {
+                    issues.push(ChainSpecIssue::ForkOutOfOrder {
+                        earlier: **earlier,
+                        later: **later,
+                    });
+                }
+            }
+        }
+
+        if self.rpc.is_empty() {
+            issues.push(ChainSpecIssue::MissingRpc);
+        }
+        if self.seconds_per_slot == 0 {
+            issues.push(ChainSpecIssue::ZeroSlotTime);
+        }
+        if self.is_taiko {
+            if self.l1_contract.is_none() {
+                issues.push(ChainSpecIssue::MissingContract("l1_contract"));
+            }
+            if self.l2_contract.is_none() {
+                issues.push(ChainSpecIssue::MissingContract("l2_contract"));
+            }
+        } else {
+            // Blobs of proposals are fetched from the beacon node of the L1
+            let cancun = self.hard_forks.get(&SpecId::CANCUN);
+            if cancun.is_some_and(|fork| *fork != ForkCondition::TBD) && self.beacon_rpc.is_none() {
+                issues.push(ChainSpecIssue::MissingBeaconRpc);
+            }
+        }
+        issues
+    }
+}
+
+/// Returns `true` if `fork` is guaranteed to activate before `other`.
+fn activates_before(fork: &ForkCondition, other: &ForkCondition) -> bool {
+    match (fork, other) {
+        (ForkCondition::Block(a), ForkCondition::Block(b)) => a < b,
+        (ForkCondition::Timestamp(a), ForkCondition::Timestamp(b)) => a < b,
+        // Timestamp based forks follow the block based ones since Shanghai
+        (ForkCondition::Block(_), ForkCondition::Timestamp(_)) => true,
+        (ForkCondition::Timestamp(_), ForkCondition::Block(_)) => false,
+        (ForkCondition::TBD, _) => false,
+        (_, ForkCondition::TBD) => true,
+    }
+}
+
+/// An inconsistency of a [ChainSpec].
+#[derive(Debug, ThisError, Clone, PartialEq, Eq)]
+pub enum ChainSpecIssue {
+    /// No fork is active at genesis, so early blocks have no spec.
+    #[error("no fork is active at genesis")]
+    NoGenesisFork,
+    /// A fork is scheduled but not supported by `max_spec_id`.
+    #[error("{0:?} is scheduled above the max spec id")]
+    ForkAboveMax(SpecId),
+    /// A fork activates before a fork it builds upon, e.g. Cancun before Shanghai.
+    #[error("{later:?} activates before {earlier:?}")]
+    ForkOutOfOrder { earlier: SpecId, later: SpecId },
+    #[error("no rpc configured")]
+    MissingRpc,
+    #[error("seconds_per_slot is zero")]
+    ZeroSlotTime,
+    /// A Taiko chain is missing the address of one of its protocol contracts.
+    #[error("{0} is not configured")]
+    MissingContract(&'static str),
+    /// A chain with Cancun is missing the beacon node to fetch blobs from.
+    #[error("Cancun is scheduled without a beacon_rpc")]
+    MissingBeaconRpc,
 }
 
 // network enum here either has fixed setting or need known patch fix
@@ -273,6 +385,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn validate_chain_spec() {
+        let chain_specs = SupportedChainSpecs::default();
+        for network in chain_specs.supported_networks() {
+            let spec = chain_specs.get_chain_spec(&network).unwrap();
+            assert_eq!(spec.validate(), vec![], "{network}");
+        }
+
+        let mut spec = chain_specs
+            .get_chain_spec(&Network::Holesky.to_string())
+            .unwrap();
+        spec.hard_forks
+            .insert(SpecId::CANCUN, ForkCondition::Timestamp(1_600_000_000));
+        spec.hard_forks
+            .insert(SpecId::PRAGUE, ForkCondition::Block(0));
+        assert_eq!(
+            spec.validate(),
+            vec![
+                ChainSpecIssue::ForkAboveMax(SpecId::PRAGUE),
+                ChainSpecIssue::ForkOutOfOrder {
+                    earlier: SpecId::SHANGHAI,
+                    later: SpecId::CANCUN
+                },
+                ChainSpecIssue::ForkOutOfOrder {
+                    earlier: SpecId::SHANGHAI,
+                    later: SpecId::PRAGUE
+                },
+                ChainSpecIssue::ForkOutOfOrder {
+                    earlier: SpecId::CANCUN,
+                    later: SpecId::PRAGUE
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_chain_specs() {
+        let chain_specs: Vec<ChainSpec> = serde_json::from_str(DEFAULT_CHAIN_SPECS).unwrap();
+        assert!(chain_spec_map(chain_specs.clone()).is_ok());
+
+        let mut duplicate_name = chain_specs.clone();
+        let mut spec = duplicate_name[0].clone();
+        spec.chain_id = 424242;
+        duplicate_name.push(spec);
+        let err = chain_spec_map(duplicate_name).unwrap_err();
+        assert!(err.to_string().contains("listed twice"), "{err}");
+
+        let mut duplicate_chain_id = chain_specs;
+        let mut spec = duplicate_chain_id[0].clone();
+        spec.name = "ethereum_fork".to_string();
+        duplicate_chain_id.push(spec);
+        let err = chain_spec_map(duplicate_chain_id).unwrap_err();
+        assert!(err.to_string().contains("same chain id"), "{err}");
+    }
+
     #[ignore]
     #[test]
     fn serde_chain_spec() {
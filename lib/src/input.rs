@@ -20,15 +20,21 @@ use alloy_rpc_types::Withdrawal as AlloyWithdrawal;
 use alloy_sol_types::{sol, SolCall};
 use anyhow::{anyhow, Result};
 use revm::primitives::HashMap;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_with::serde_as;
+use thiserror_no_std::Error as ThisError;
 
 #[cfg(not(feature = "std"))]
 use crate::no_std::*;
 use crate::{
     consts::ChainSpec,
-    primitives::{mpt::MptNode, Address, Bytes, B256, U256},
+    primitives::{
+        compact::CompactTxEip1559,
+        mpt::{MptNode, MptNodeData},
+        Address, Bytes, B256, U256,
+    },
     serde_with::{RlpBytes, RlpHexBytes},
+    utils::BLOB_DATA_CAPACITY,
 };
 
 /// Represents the state of an account's storage.
@@ -90,8 +96,10 @@ pub struct TaikoGuestInput {
     /// header
     #[serde_as(as = "RlpBytes")]
     pub l1_header: AlloyConsensusHeader,
+    /// The transaction list as proposed, hashed into the public input
     pub tx_data: Vec<u8>,
-    pub anchor_tx: String,
+    /// The anchor transaction, which isn't part of the proposed transaction list
+    pub anchor_tx: CompactTxEip1559,
     pub block_proposed: BlockProposed,
     pub prover_data: TaikoProverData,
     pub tx_blob_hash: Option<B256>,
@@ -206,6 +214,302 @@ sol! {
     function proveBlock(uint64 blockId, bytes calldata input) {}
 }
 
+/// Maximum sizes of the untrusted parts of a [GuestInput].
+///
+/// The guest enforces the default limits while it reads the input, so that adversarial
+/// inputs fail with an [InputLimitError] instead of exhausting the guest memory. The host
+/// can be configured with lower limits to reject such blocks before proving.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputLimits {
+    /// Maximum number of transactions in the block, including the anchor transaction.
+    pub max_transactions: usize,
+    /// Maximum size of the proposed tx list data, either calldata or a blob.
+    pub max_tx_data_size: usize,
+    /// Maximum size of the tx list after decompressing the tx list data.
+    pub max_tx_list_size: usize,
+    /// Maximum number of resolved nodes over the state trie and all storage tries.
+    pub max_witness_nodes: usize,
+    /// Maximum number of contracts whose code is part of the witness.
+    pub max_contracts: usize,
+    /// Maximum number of blobs the tx list data of a blob proposal spans.
+    pub max_blobs: usize,
+}
+
+impl Default for InputLimits {
+    fn default() -> Self {
+        Self {
+            max_transactions: 16_384,
+            max_tx_data_size: BLOB_DATA_CAPACITY,
+            max_tx_list_size: 1 << 24,
+            max_witness_nodes: 1 << 20,
+            max_contracts: 1 << 16,
+            max_blobs: 1,
+        }
+    }
+}
+
+/// Error returned when a part of the input exceeds its [InputLimits].
+#[derive(Debug, ThisError, PartialEq, Eq)]
+pub enum InputLimitError {
+    /// Returned when the tx list decodes to more transactions than allowed.
+    #[error("too many transactions: {count} > {max}")]
+    TooManyTransactions { count: usize, max: usize },
+    /// Returned when the proposed tx list data is larger than allowed.
+    #[error("tx list data too large: {size} > {max} bytes")]
+    TxDataTooLarge { size: usize, max: usize },
+    /// Returned when the tx list data decompresses to more bytes than allowed.
+    #[error("decompressed tx list larger than {max} bytes")]
+    TxListTooLarge { max: usize },
+    /// Returned when the witness contains more trie nodes than allowed.
+    #[error("too many witness nodes: {count} > {max}")]
+    TooManyWitnessNodes { count: usize, max: usize },
+    /// Returned when a trie of the witness is nested deeper than any valid trie.
+    #[error("trie nested deeper than {max} nodes")]
+    TrieTooDeep { max: usize },
+    /// Returned when the witness contains more contracts than allowed.
+    #[error("too many contracts: {count} > {max}")]
+    TooManyContracts { count: usize, max: usize },
+    /// Returned when the block proposal uses more blobs than allowed.
+    #[error("too many blobs: {count} > {max}")]
+    TooManyBlobs { count: usize, max: usize },
+}
+
+impl From<InputLimitError> for anyhow::Error {
+    fn from(error: InputLimitError) -> Self {
+        // `anyhow!` would convert the error with this impl again
+        anyhow::Error::msg(error)
+    }
+}
+
+impl InputLimits {
+    /// Checks all limits that can be checked without decoding the tx list.
+    pub fn check(&self, input: &GuestInput) -> Result<(), InputLimitError> {
+        self.check_tx_data(
+            input.taiko.tx_data.len(),
+            input.taiko.block_proposed.meta.blobUsed,
+        )?;
+        self.check_contracts(input.contracts.len())?;
+        let storage_nodes = input
+            .parent_storage
+            .values()
+            .map(|(trie, _)| trie.size())
+            .sum::<usize>();
+        self.check_witness_nodes(input.parent_state_trie.size() + storage_nodes)
+    }
+
+    pub fn check_transactions(&self, count: usize) -> Result<(), InputLimitError> {
+        if count > self.max_transactions {
+            return Err(InputLimitError::TooManyTransactions {
+                count,
+                max: self.max_transactions,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn check_witness_nodes(&self, count: usize) -> Result<(), InputLimitError> {
+        if count > self.max_witness_nodes {
+            return Err(InputLimitError::TooManyWitnessNodes {
+                count,
+                max: self.max_witness_nodes,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn check_contracts(&self, count: usize) -> Result<(), InputLimitError> {
+        if count > self.max_contracts {
+            return Err(InputLimitError::TooManyContracts {
+                count,
+                max: self.max_contracts,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_tx_data(&self, size: usize, blob_used: bool) -> Result<(), InputLimitError> {
+        if blob_used {
+            let count = size.div_ceil(BLOB_DATA_CAPACITY);
+            if count > self.max_blobs {
+                return Err(InputLimitError::TooManyBlobs {
+                    count,
+                    max: self.max_blobs,
+                });
+            }
+        }
+        if size > self.max_tx_data_size {
+            return Err(InputLimitError::TxDataTooLarge {
+                size,
+                max: self.max_tx_data_size,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The maximum depth of a trie: a branch or an extension and a branch for each of the 64
+/// nibbles of a key, and the leaf.
+const MAX_TRIE_DEPTH: usize = 2 * 64 + 1;
+
+/// A trie node without its children, which are written after it in pre-order, so that a
+/// trie can be read one node at a time.
+#[derive(Debug, Serialize, Deserialize)]
+enum TrieNodeSection {
+    Null,
+    /// A branch with the children set in the bit mask.
+    Branch(u16),
+    Leaf(Vec<u8>, Vec<u8>),
+    Extension(Vec<u8>),
+    Digest(B256),
+}
+
+fn write_trie(node: &MptNode, writer: &mut impl InputWriter) {
+    match node.as_data() {
+        MptNodeData::Null => writer.write(&TrieNodeSection::Null),
+        MptNodeData::Branch(children) => {
+            let mask = children
+                .iter()
+                .enumerate()
+                .filter(|(_, child)| child.is_some())
+                .fold(0u16, |mask, (i, _)| mask | (1 << i));
+            writer.write(&TrieNodeSection::Branch(mask));
+            for child in children.iter().flatten() {
+                write_trie(child, writer);
+            }
+        }
+        MptNodeData::Leaf(key, value) => {
+            writer.write(&TrieNodeSection::Leaf(key.clone(), value.clone()))
+        }
+        MptNodeData::Extension(prefix, child) => {
+            writer.write(&TrieNodeSection::Extension(prefix.clone()));
+            write_trie(child, writer);
+        }
+        MptNodeData::Digest(digest) => writer.write(&TrieNodeSection::Digest(*digest)),
+    }
+}
+
+/// Reads a trie written with [write_trie], adding its resolved nodes to `witness_nodes`.
+fn read_trie(
+    reader: &mut impl InputReader,
+    limits: &InputLimits,
+    witness_nodes: &mut usize,
+    depth: usize,
+) -> Result<MptNode, InputLimitError> {
+    if depth > MAX_TRIE_DEPTH {
+        return Err(InputLimitError::TrieTooDeep {
+            max: MAX_TRIE_DEPTH,
+        });
+    }
+    let node: TrieNodeSection = reader.read();
+    if !matches!(node, TrieNodeSection::Null | TrieNodeSection::Digest(_)) {
+        *witness_nodes += 1;
+        limits.check_witness_nodes(*witness_nodes)?;
+    }
+    let data = match node {
+        TrieNodeSection::Null => MptNodeData::Null,
+        TrieNodeSection::Branch(mask) => {
+            let mut children: [Option<Box<MptNode>>; 16] = Default::default();
+            for (i, child) in children.iter_mut().enumerate() {
+                if mask & (1 << i) != 0 {
+                    *child = Some(Box::new(read_trie(
+                        reader,
+                        limits,
+                        witness_nodes,
+                        depth + 1,
+                    )?));
+                }
+            }
+            MptNodeData::Branch(children)
+        }
+        TrieNodeSection::Leaf(key, value) => MptNodeData::Leaf(key, value),
+        TrieNodeSection::Extension(prefix) => {
+            let child = read_trie(reader, limits, witness_nodes, depth + 1)?;
+            MptNodeData::Extension(prefix, Box::new(child))
+        }
+        TrieNodeSection::Digest(digest) => MptNodeData::Digest(digest),
+    };
+    Ok(data.into())
+}
+
+/// A sink for the sections of a [GuestInput] written by [write_input].
+pub trait InputWriter {
+    fn write<T: Serialize>(&mut self, value: &T);
+}
+
+/// A source for the sections of a [GuestInput] read by [read_input].
+pub trait InputReader {
+    fn read<T: DeserializeOwned>(&mut self) -> T;
+}
+
+/// Writes the input in sections, so that the guest can read it with [read_input] without
+/// having the complete serialized input in memory at once.
+///
+/// The tx list data, the tries, the bytecode and the storage, which make up most of the
+/// input, are written after the remaining input: the tx list data after its size, the
+/// tries one node at a time and the bytecode and storage one entry at a time.
+pub fn write_input(mut input: GuestInput, writer: &mut impl InputWriter) {
+    let tx_data = core::mem::take(&mut input.taiko.tx_data);
+    let parent_state_trie = core::mem::take(&mut input.parent_state_trie);
+    let contracts = core::mem::take(&mut input.contracts);
+    let parent_storage = core::mem::take(&mut input.parent_storage);
+
+    writer.write(&input);
+    writer.write(&tx_data.len());
+    writer.write(&tx_data);
+    write_trie(&parent_state_trie, writer);
+    writer.write(&contracts.len());
+    for contract in &contracts {
+        writer.write(contract);
+    }
+    writer.write(&parent_storage.len());
+    for (address, (storage_trie, slots)) in &parent_storage {
+        writer.write(&(address, slots));
+        write_trie(storage_trie, writer);
+    }
+}
+
+/// Reads an input written with [write_input], deserializing the large sections piece by
+/// piece.
+///
+/// The limits are checked before each section they apply to is read, so nothing is
+/// allocated for a section that exceeds them.
+pub fn read_input(
+    reader: &mut impl InputReader,
+    limits: &InputLimits,
+) -> Result<GuestInput, InputLimitError> {
+    let mut input: GuestInput = reader.read();
+
+    let blob_used = input.taiko.block_proposed.meta.blobUsed;
+    let tx_data_size: usize = reader.read();
+    limits.check_tx_data(tx_data_size, blob_used)?;
+    input.taiko.tx_data = reader.read();
+    // the size is only a hint for the limits, the data itself has to be within them too
+    limits.check_tx_data(input.taiko.tx_data.len(), blob_used)?;
+
+    let mut witness_nodes = 0;
+    input.parent_state_trie = read_trie(reader, limits, &mut witness_nodes, 0)?;
+
+    let num_contracts: usize = reader.read();
+    limits.check_contracts(num_contracts)?;
+    input.contracts.reserve_exact(num_contracts);
+    for _ in 0..num_contracts {
+        input.contracts.push(reader.read());
+    }
+
+    // every storage trie belongs to at least one account leaf
+    let num_accounts: usize = reader.read();
+    limits.check_witness_nodes(num_accounts)?;
+    input.parent_storage.reserve(num_accounts);
+    for _ in 0..num_accounts {
+        let (address, slots): (Address, Vec<U256>) = reader.read();
+        let storage_trie = read_trie(reader, limits, &mut witness_nodes, 0)?;
+        input.parent_storage.insert(address, (storage_trie, slots));
+    }
+
+    Ok(input)
+}
+
 #[cfg(feature = "std")]
 use std::path::Path;
 
@@ -218,10 +522,174 @@ pub fn get_input_path(dir: &Path, block_number: u64, network: &str) -> PathBuf {
 mod tests {
     extern crate alloc;
     use super::*;
+    use crate::primitives::keccak::keccak;
 
     #[test]
     fn input_serde_roundtrip() {
         let input = GuestInput::default();
         let _: GuestInput = bincode::deserialize(&bincode::serialize(&input).unwrap()).unwrap();
     }
+
+    struct Sections(alloc::collections::VecDeque<Vec<u8>>);
+
+    impl InputWriter for Sections {
+        fn write<T: Serialize>(&mut self, value: &T) {
+            self.0.push_back(bincode::serialize(value).unwrap());
+        }
+    }
+
+    impl InputReader for Sections {
+        fn read<T: DeserializeOwned>(&mut self) -> T {
+            bincode::deserialize(&self.0.pop_front().unwrap()).unwrap()
+        }
+    }
+
+    fn trie(n: u64) -> MptNode {
+        let mut trie = MptNode::default();
+        for i in 0..n {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        trie
+    }
+
+    #[test]
+    fn input_sections_roundtrip() {
+        let mut input = GuestInput::default();
+        input.taiko.tx_data = vec![1, 2, 3];
+        input.parent_state_trie = trie(64);
+        input.contracts = vec![
+            Bytes::from_static(&[0x60, 0x00]),
+            Bytes::from_static(&[0xfe]),
+        ];
+        input
+            .parent_storage
+            .insert(Address::repeat_byte(1), (trie(3), vec![U256::from(1)]));
+        // only the path to a single key, the other subtries are digests
+        let storage_trie =
+            MptNode::decode(&trie(64).proof(&keccak(0u64.to_be_bytes())).unwrap()[0]).unwrap();
+        input
+            .parent_storage
+            .insert(Address::repeat_byte(2), (storage_trie, vec![]));
+
+        let mut sections = Sections(Default::default());
+        write_input(input.clone(), &mut sections);
+
+        let read = read_input(&mut sections, &InputLimits::default()).unwrap();
+        assert!(sections.0.is_empty());
+        assert_eq!(read.taiko.tx_data, input.taiko.tx_data);
+        assert_eq!(read.parent_state_trie, input.parent_state_trie);
+        assert_eq!(
+            read.parent_state_trie.hash(),
+            input.parent_state_trie.hash()
+        );
+        assert_eq!(read.contracts, input.contracts);
+        assert_eq!(read.parent_storage, input.parent_storage);
+    }
+
+    #[test]
+    fn input_limits() {
+        let mut input = GuestInput::default();
+        input.taiko.tx_data = vec![0; 1024];
+        input.parent_state_trie = trie(8);
+        input.contracts = vec![Bytes::from_static(&[0xfe]); 2];
+        let limits = InputLimits {
+            max_tx_data_size: 1024,
+            max_witness_nodes: input.parent_state_trie.size(),
+            max_contracts: 2,
+            ..Default::default()
+        };
+        assert_eq!(limits.check(&input), Ok(()));
+
+        input.taiko.tx_data.push(0);
+        assert_eq!(
+            limits.check(&input),
+            Err(InputLimitError::TxDataTooLarge {
+                size: 1025,
+                max: 1024
+            })
+        );
+        input.taiko.tx_data.pop();
+
+        input.contracts.push(Bytes::new());
+        assert_eq!(
+            limits.check(&input),
+            Err(InputLimitError::TooManyContracts { count: 3, max: 2 })
+        );
+        input.contracts.pop();
+
+        // the limits are enforced while reading the input
+        input.parent_storage.insert(
+            Address::repeat_byte(1),
+            (input.parent_state_trie.clone(), vec![]),
+        );
+        let mut sections = Sections(Default::default());
+        write_input(input, &mut sections);
+        assert!(matches!(
+            read_input(&mut sections, &limits),
+            Err(InputLimitError::TooManyWitnessNodes { .. })
+        ));
+    }
+
+    #[test]
+    fn input_limits_blobs() {
+        let limits = InputLimits {
+            max_tx_data_size: 2 * BLOB_DATA_CAPACITY,
+            ..Default::default()
+        };
+        let mut input = GuestInput::default();
+        input.taiko.tx_data = vec![0; BLOB_DATA_CAPACITY + 1];
+        // calldata isn't split into blobs
+        assert_eq!(limits.check(&input), Ok(()));
+
+        input.taiko.block_proposed.meta.blobUsed = true;
+        assert_eq!(
+            limits.check(&input),
+            Err(InputLimitError::TooManyBlobs { count: 2, max: 1 })
+        );
+    }
+
+    #[test]
+    fn input_limits_before_decoding() {
+        let mut input = GuestInput::default();
+        input.taiko.tx_data = vec![0; 1025];
+        input.contracts = vec![Bytes::from_static(&[0xfe]); 3];
+        let mut sections = Sections(Default::default());
+        write_input(input.clone(), &mut sections);
+
+        // the tx list data isn't decoded if its size exceeds the limit
+        let limits = InputLimits {
+            max_tx_data_size: 1024,
+            ..Default::default()
+        };
+        let mut read = Sections(sections.0.clone());
+        assert!(matches!(
+            read_input(&mut read, &limits),
+            Err(InputLimitError::TxDataTooLarge { .. })
+        ));
+        assert_eq!(read.0.len(), sections.0.len() - 2);
+
+        // neither are the contracts
+        let limits = InputLimits {
+            max_contracts: 2,
+            ..Default::default()
+        };
+        let mut read = Sections(sections.0.clone());
+        assert!(matches!(
+            read_input(&mut read, &limits),
+            Err(InputLimitError::TooManyContracts { count: 3, max: 2 })
+        ));
+        assert_eq!(read.0.len(), 3 + 1);
+
+        // nested deeper than any valid trie
+        let mut sections = Sections(Default::default());
+        for _ in 0..=MAX_TRIE_DEPTH {
+            sections.write(&TrieNodeSection::Extension(vec![0x00]));
+        }
+        assert_eq!(
+            read_trie(&mut sections, &InputLimits::default(), &mut 0, 0),
+            Err(InputLimitError::TrieTooDeep {
+                max: MAX_TRIE_DEPTH
+            })
+        );
+    }
 }
@@ -171,11 +171,21 @@ where
 }
 
 pub mod serde_with {
-    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use core::{fmt, marker::PhantomData};
+
+    use serde::{
+        de::{SeqAccess, Visitor},
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
     use serde_with::{DeserializeAs, SerializeAs};
 
     use super::RlpBytes as _;
 
+    /// Serializes a value as its RLP encoding.
+    ///
+    /// The encoding is written as a byte string, so formats that support it can hand out
+    /// the bytes borrowed from the input buffer during deserialization, which the value
+    /// is then decoded from without an intermediate copy.
     pub struct RlpBytes {}
 
     impl<T> SerializeAs<T> for RlpBytes
@@ -187,7 +197,7 @@ pub mod serde_with {
             S: Serializer,
         {
             let bytes = alloy_rlp::encode(source);
-            bytes.serialize(serializer)
+            serializer.serialize_bytes(&bytes)
         }
     }
 
@@ -199,8 +209,33 @@ pub mod serde_with {
         where
             D: Deserializer<'de>,
         {
-            let bytes = <Vec<u8>>::deserialize(deserializer)?;
-            T::decode_bytes(bytes).map_err(serde::de::Error::custom)
+            deserializer.deserialize_bytes(RlpBytesVisitor(PhantomData))
+        }
+    }
+
+    struct RlpBytesVisitor<T>(PhantomData<T>);
+
+    impl<'de, T> Visitor<'de> for RlpBytesVisitor<T>
+    where
+        T: alloy_rlp::Decodable,
+    {
+        type Value = T;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("RLP encoded bytes")
+        }
+
+        fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<T, E> {
+            T::decode_bytes(v).map_err(E::custom)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+            // self-describing formats like JSON encode bytes as a sequence
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            self.visit_bytes(&bytes)
         }
     }
 
@@ -0,0 +1,317 @@
+//! Compact representation of the anchor transaction in the guest input.
+//!
+//! The RPC types encode signatures and access lists as big integers and nested vectors,
+//! which inflates the serialized input and costs cycles to parse in the guest. The types
+//! here only use fixed-size byte arrays and small inline lists.
+//!
+//! The anchor transaction is the only transaction the witness carries in decoded form.
+//! All other transactions are part of `tx_data`, which has to stay the exact bytes the
+//! proposal committed to, as its blob or calldata hash is part of the public input. Their
+//! signatures and access lists are decoded from those bytes in the guest and can't be
+//! re-encoded.
+
+use alloy_consensus::{SignableTransaction, Signed, TxEip1559};
+use alloy_eips::eip2930::{AccessList, AccessListItem};
+use alloy_primitives::{Address, Bytes, Signature, TxKind, B256, U256};
+use alloy_rpc_types::Transaction as AlloyTransaction;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+
+/// An ECDSA signature as its 32-byte `r` and `s` values and the y-parity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactSignature {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+    pub y_parity: bool,
+}
+
+impl From<&Signature> for CompactSignature {
+    fn from(signature: &Signature) -> Self {
+        Self {
+            r: signature.r().to_be_bytes(),
+            s: signature.s().to_be_bytes(),
+            y_parity: signature.v().y_parity(),
+        }
+    }
+}
+
+impl TryFrom<&CompactSignature> for Signature {
+    type Error = anyhow::Error;
+
+    fn try_from(signature: &CompactSignature) -> Result<Self> {
+        Signature::from_rs_and_parity(
+            U256::from_be_bytes(signature.r),
+            U256::from_be_bytes(signature.s),
+            signature.y_parity,
+        )
+        .map_err(|e| anyhow!("Invalid signature: {e}"))
+    }
+}
+
+/// The storage keys accessed of a single account, inline for up to two keys.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactAccessListItem {
+    pub address: Address,
+    pub storage_keys: SmallVec<[B256; 2]>,
+}
+
+/// An EIP-2930 access list, inline for a single account.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactAccessList(pub SmallVec<[CompactAccessListItem; 1]>);
+
+impl CompactAccessList {
+    /// Returns the access list in the format used by the EVM.
+    pub fn flattened(&self) -> Vec<(Address, Vec<U256>)> {
+        self.0
+            .iter()
+            .map(|item| {
+                let keys = item
+                    .storage_keys
+                    .iter()
+                    .map(|key| U256::from_be_bytes(key.0))
+                    .collect();
+                (item.address, keys)
+            })
+            .collect()
+    }
+}
+
+impl From<&AccessList> for CompactAccessList {
+    fn from(access_list: &AccessList) -> Self {
+        Self(
+            access_list
+                .0
+                .iter()
+                .map(|item| CompactAccessListItem {
+                    address: item.address,
+                    storage_keys: item.storage_keys.iter().copied().collect(),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl From<&CompactAccessList> for AccessList {
+    fn from(access_list: &CompactAccessList) -> Self {
+        AccessList(
+            access_list
+                .0
+                .iter()
+                .map(|item| AccessListItem {
+                    address: item.address,
+                    storage_keys: item.storage_keys.to_vec(),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A signed EIP-1559 transaction, e.g. the anchor transaction of a Taiko block.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactTxEip1559 {
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub gas_limit: u128,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    /// `None` for contract creations.
+    pub to: Option<Address>,
+    pub value: U256,
+    pub access_list: CompactAccessList,
+    pub input: Bytes,
+    pub signature: CompactSignature,
+}
+
+impl CompactTxEip1559 {
+    /// Returns the signed transaction, with its hash computed from the content.
+    pub fn to_signed(&self) -> Result<Signed<TxEip1559>> {
+        let tx = TxEip1559 {
+            chain_id: self.chain_id,
+            nonce: self.nonce,
+            gas_limit: self.gas_limit,
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: self.max_priority_fee_per_gas,
+            to: match self.to {
+                Some(to) => TxKind::Call(to),
+                None => TxKind::Create,
+            },
+            value: self.value,
+            access_list: (&self.access_list).into(),
+            input: self.input.clone(),
+        };
+        Ok(tx.into_signed((&self.signature).try_into()?))
+    }
+}
+
+impl From<&Signed<TxEip1559>> for CompactTxEip1559 {
+    fn from(signed: &Signed<TxEip1559>) -> Self {
+        let tx = signed.tx();
+        Self {
+            chain_id: tx.chain_id,
+            nonce: tx.nonce,
+            gas_limit: tx.gas_limit,
+            max_fee_per_gas: tx.max_fee_per_gas,
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+            to: match tx.to {
+                TxKind::Call(to) => Some(to),
+                TxKind::Create => None,
+            },
+            value: tx.value,
+            access_list: (&tx.access_list).into(),
+            input: tx.input.clone(),
+            signature: signed.signature().into(),
+        }
+    }
+}
+
+impl TryFrom<&AlloyTransaction> for CompactTxEip1559 {
+    type Error = anyhow::Error;
+
+    fn try_from(tx: &AlloyTransaction) -> Result<Self> {
+        let signature = tx.signature.as_ref().context("Missing signature")?;
+        let y_parity = signature.y_parity.context("Missing signature parity")?.0;
+        Ok(Self {
+            chain_id: tx.chain_id.context("Missing chain id")?,
+            nonce: tx.nonce,
+            gas_limit: tx.gas,
+            max_fee_per_gas: tx.max_fee_per_gas.context("Missing max fee per gas")?,
+            max_priority_fee_per_gas: tx
+                .max_priority_fee_per_gas
+                .context("Missing max priority fee per gas")?,
+            to: tx.to,
+            value: tx.value,
+            access_list: tx
+                .access_list
+                .as_ref()
+                .map(CompactAccessList::from)
+                .unwrap_or_default(),
+            input: tx.input.clone(),
+            signature: CompactSignature {
+                r: signature.r.to_be_bytes(),
+                s: signature.s.to_be_bytes(),
+                y_parity,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_tx_roundtrip() {
+        let tx = TxEip1559 {
+            chain_id: 167008,
+            nonce: 7,
+            gas_limit: 250_000,
+            max_fee_per_gas: 10_000_000,
+            max_priority_fee_per_gas: 0,
+            to: TxKind::Call(Address::repeat_byte(0x11)),
+            value: U256::ZERO,
+            access_list: AccessList(vec![AccessListItem {
+                address: Address::repeat_byte(0x22),
+                storage_keys: vec![B256::repeat_byte(1), B256::repeat_byte(2), B256::ZERO],
+            }]),
+            input: Bytes::from(vec![0xda, 0x69, 0xd3, 0xdb]),
+        };
+        let signature = Signature::from_rs_and_parity(U256::from(1), U256::from(2), true).unwrap();
+        let signed = tx.into_signed(signature);
+
+        let compact = CompactTxEip1559::from(&signed);
+        let decoded: CompactTxEip1559 =
+            bincode::deserialize(&bincode::serialize(&compact).unwrap()).unwrap();
+        assert_eq!(decoded, compact);
+
+        let restored = decoded.to_signed().unwrap();
+        assert_eq!(restored.hash(), signed.hash());
+        assert_eq!(restored.signature(), signed.signature());
+        assert_eq!(
+            decoded.access_list.flattened(),
+            signed.tx().access_list.flattened()
+        );
+    }
+
+    mod differential {
+        use alloy_consensus::TxEnvelope;
+        use alloy_eips::eip2718::Encodable2718;
+        use proptest::{collection::vec, prelude::*};
+
+        use super::*;
+
+        fn arb_access_list() -> impl Strategy<Value = AccessList> {
+            let item = (any::<[u8; 20]>(), vec(any::<[u8; 32]>(), 0..4)).prop_map(
+                |(address, storage_keys)| AccessListItem {
+                    address: address.into(),
+                    storage_keys: storage_keys.into_iter().map(B256::from).collect(),
+                },
+            );
+            vec(item, 0..3).prop_map(AccessList)
+        }
+
+        fn arb_tx() -> impl Strategy<Value = TxEip1559> {
+            (
+                (
+                    any::<u64>(),
+                    any::<u64>(),
+                    any::<u64>(),
+                    any::<u128>(),
+                    any::<u128>(),
+                ),
+                (
+                    proptest::option::of(any::<[u8; 20]>()),
+                    any::<[u8; 32]>(),
+                    arb_access_list(),
+                    vec(any::<u8>(), 0..128),
+                ),
+            )
+                .prop_map(
+                    |(
+                        (chain_id, nonce, gas_limit, max_fee_per_gas, max_priority_fee_per_gas),
+                        (to, value, access_list, input),
+                    )| TxEip1559 {
+                        chain_id,
+                        nonce,
+                        gas_limit: gas_limit.into(),
+                        max_fee_per_gas,
+                        max_priority_fee_per_gas,
+                        to: to.map_or(TxKind::Create, |to| TxKind::Call(to.into())),
+                        value: U256::from_be_bytes(value),
+                        access_list,
+                        input: input.into(),
+                    },
+                )
+        }
+
+        proptest! {
+            /// The anchor tx restored from its compact form must encode and hash exactly
+            /// like the transaction alloy decodes from the block.
+            #[test]
+            fn compact_tx_matches_alloy(
+                tx in arb_tx(),
+                r in any::<[u8; 32]>(),
+                s in any::<[u8; 32]>(),
+                y_parity in any::<bool>(),
+            ) {
+                let signature = Signature::from_rs_and_parity(
+                    U256::from_be_bytes(r),
+                    U256::from_be_bytes(s),
+                    y_parity,
+                )
+                .unwrap();
+                let signed = tx.into_signed(signature);
+                let restored = CompactTxEip1559::from(&signed).to_signed().unwrap();
+
+                prop_assert_eq!(restored.hash(), signed.hash());
+                prop_assert_eq!(
+                    TxEnvelope::from(restored).encoded_2718(),
+                    TxEnvelope::from(signed).encoded_2718()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,379 @@
+//! Helpers for working with EIP-4337 user operations.
+//!
+//! User operations are bundled into `handleOps` calls to an entry point contract. These
+//! helpers recover the user operations from the transactions of a block, compute their
+//! hashes the same way the entry point does, and create inclusion proofs for the bundle
+//! transactions so user operations can be tied to a proven block.
+
+use alloy_consensus::TxEnvelope;
+use alloy_primitives::{address, keccak256, Address, Bytes, TxKind, B256, U256};
+use alloy_sol_types::{sol, SolCall, SolValue};
+use anyhow::Result;
+
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::primitives::{mpt::MptNode, Rlp2718Bytes, RlpBytes};
+
+/// The address of the v0.6 entry point.
+pub const ENTRY_POINT_V06: Address = address!("5FF137D4b0FDCD49DcA30c7CF57E578a026d2789");
+/// The address of the v0.7 entry point.
+pub const ENTRY_POINT_V07: Address = address!("0000000071727De22E5E9d8BAf0edAc6f37da032");
+
+mod v06 {
+    use super::sol;
+
+    sol! {
+        #[derive(Debug, Default, PartialEq, Eq)]
+        struct UserOperation {
+            address sender;
+            uint256 nonce;
+            bytes initCode;
+            bytes callData;
+            uint256 callGasLimit;
+            uint256 verificationGasLimit;
+            uint256 preVerificationGas;
+            uint256 maxFeePerGas;
+            uint256 maxPriorityFeePerGas;
+            bytes paymasterAndData;
+            bytes signature;
+        }
+
+        #[derive(Debug)]
+        function handleOps(UserOperation[] ops, address beneficiary) external;
+    }
+}
+
+mod v07 {
+    use super::sol;
+
+    sol! {
+        #[derive(Debug, Default, PartialEq, Eq)]
+        struct PackedUserOperation {
+            address sender;
+            uint256 nonce;
+            bytes initCode;
+            bytes callData;
+            bytes32 accountGasLimits;
+            uint256 preVerificationGas;
+            bytes32 gasFees;
+            bytes paymasterAndData;
+            bytes signature;
+        }
+
+        #[derive(Debug)]
+        function handleOps(PackedUserOperation[] ops, address beneficiary) external;
+    }
+}
+
+pub use v06::UserOperation;
+pub use v07::PackedUserOperation;
+
+impl UserOperation {
+    /// Returns the hash of the user operation as computed by the v0.6 `entry_point`.
+    pub fn hash(&self, entry_point: Address, chain_id: u64) -> B256 {
+        let packed = (
+            self.sender,
+            self.nonce,
+            keccak256(&self.initCode),
+            keccak256(&self.callData),
+            self.callGasLimit,
+            self.verificationGasLimit,
+            self.preVerificationGas,
+            self.maxFeePerGas,
+            self.maxPriorityFeePerGas,
+            keccak256(&self.paymasterAndData),
+        )
+            .abi_encode();
+        user_op_hash(&packed, entry_point, chain_id)
+    }
+}
+
+impl PackedUserOperation {
+    /// Returns the hash of the user operation as computed by the v0.7 `entry_point`.
+    pub fn hash(&self, entry_point: Address, chain_id: u64) -> B256 {
+        let packed = (
+            self.sender,
+            self.nonce,
+            keccak256(&self.initCode),
+            keccak256(&self.callData),
+            self.accountGasLimits,
+            self.preVerificationGas,
+            self.gasFees,
+            keccak256(&self.paymasterAndData),
+        )
+            .abi_encode();
+        user_op_hash(&packed, entry_point, chain_id)
+    }
+
+    /// The gas limit of the verification step, packed into the high 128 bits of
+    /// `accountGasLimits`.
+    pub fn verification_gas_limit(&self) -> u128 {
+        high_u128(&self.accountGasLimits)
+    }
+
+    /// The gas limit of the execution step, packed into the low 128 bits of
+    /// `accountGasLimits`.
+    pub fn call_gas_limit(&self) -> u128 {
+        low_u128(&self.accountGasLimits)
+    }
+
+    /// The max priority fee per gas, packed into the high 128 bits of `gasFees`.
+    pub fn max_priority_fee_per_gas(&self) -> u128 {
+        high_u128(&self.gasFees)
+    }
+
+    /// The max fee per gas, packed into the low 128 bits of `gasFees`.
+    pub fn max_fee_per_gas(&self) -> u128 {
+        low_u128(&self.gasFees)
+    }
+}
+
+fn user_op_hash(packed: &[u8], entry_point: Address, chain_id: u64) -> B256 {
+    keccak256((keccak256(packed), entry_point, U256::from(chain_id)).abi_encode())
+}
+
+fn high_u128(value: &B256) -> u128 {
+    u128::from_be_bytes(value[..16].try_into().unwrap())
+}
+
+fn low_u128(value: &B256) -> u128 {
+    u128::from_be_bytes(value[16..].try_into().unwrap())
+}
+
+/// A user operation included in a bundle transaction of a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludedUserOperation {
+    /// The index of the bundle transaction in the block.
+    pub tx_index: usize,
+    /// The hash of the bundle transaction.
+    pub tx_hash: B256,
+    /// The index of the user operation within the bundle.
+    pub op_index: usize,
+    /// The entry point that handled the bundle.
+    pub entry_point: Address,
+    /// The hash of the user operation.
+    pub user_op_hash: B256,
+    /// The account that sent the user operation.
+    pub sender: Address,
+    /// The nonce of the user operation.
+    pub nonce: U256,
+}
+
+/// Returns all user operations bundled in direct `handleOps` calls to the v0.6 or v0.7 entry
+/// point in the given block transactions.
+///
+/// Bundles submitted through intermediary contracts are not detected.
+pub fn find_user_operations(
+    transactions: &[TxEnvelope],
+    chain_id: u64,
+) -> Vec<IncludedUserOperation> {
+    let mut user_ops = Vec::new();
+    for (tx_index, tx) in transactions.iter().enumerate() {
+        let Some((to, tx_hash, input)) = call_data(tx) else {
+            continue;
+        };
+        let ops: Vec<(Address, U256, B256)> = if to == ENTRY_POINT_V06 {
+            let Ok(call) = v06::handleOpsCall::abi_decode(input, true) else {
+                continue;
+            };
+            call.ops
+                .iter()
+                .map(|op| (op.sender, op.nonce, op.hash(to, chain_id)))
+                .collect()
+        } else if to == ENTRY_POINT_V07 {
+            let Ok(call) = v07::handleOpsCall::abi_decode(input, true) else {
+                continue;
+            };
+            call.ops
+                .iter()
+                .map(|op| (op.sender, op.nonce, op.hash(to, chain_id)))
+                .collect()
+        } else {
+            continue;
+        };
+
+        user_ops.extend(ops.into_iter().enumerate().map(
+            |(op_index, (sender, nonce, user_op_hash))| IncludedUserOperation {
+                tx_index,
+                tx_hash,
+                op_index,
+                entry_point: to,
+                user_op_hash,
+                sender,
+                nonce,
+            },
+        ));
+    }
+    user_ops
+}
+
+/// Returns the proof of the transaction at `tx_index` in the transactions trie of the block.
+///
+/// Together with the proven block header, this proves that the bundle transaction (and
+/// with it the user operation) was included in the block.
+pub fn transaction_inclusion_proof(
+    transactions: &[TxEnvelope],
+    tx_index: usize,
+) -> Result<Vec<Bytes>> {
+    let mut tx_trie = MptNode::default();
+    for (i, tx) in transactions.iter().enumerate() {
+        tx_trie.insert_rlp_encoded(&i.to_rlp(), tx.to_rlp_2718())?;
+    }
+    Ok(tx_trie
+        .proof(&tx_index.to_rlp())?
+        .into_iter()
+        .map(Bytes::from)
+        .collect())
+}
+
+/// Returns the call target, hash and input of a transaction, or `None` for contract
+/// creations.
+fn call_data(tx: &TxEnvelope) -> Option<(Address, B256, &[u8])> {
+    let (to, hash, input) = match tx {
+        TxEnvelope::Legacy(tx) => (tx.tx().to, tx.hash(), &tx.tx().input),
+        TxEnvelope::Eip2930(tx) => (tx.tx().to, tx.hash(), &tx.tx().input),
+        TxEnvelope::Eip1559(tx) => (tx.tx().to, tx.hash(), &tx.tx().input),
+        TxEnvelope::Eip4844(tx) => (
+            TxKind::Call(tx.tx().tx().to),
+            tx.hash(),
+            &tx.tx().tx().input,
+        ),
+        _ => return None,
+    };
+    match to {
+        TxKind::Call(to) => Some((to, *hash, input.as_ref())),
+        TxKind::Create => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::{SignableTransaction, TxEip1559};
+    use alloy_primitives::{b256, Signature};
+
+    use super::*;
+    use crate::primitives::mpt::{mpt_from_proof, parse_proof};
+
+    fn user_operation(nonce: u64) -> UserOperation {
+        UserOperation {
+            sender: address!("1111111111111111111111111111111111111111"),
+            nonce: U256::from(nonce),
+            initCode: Bytes::new(),
+            callData: Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef]),
+            callGasLimit: U256::from(100_000),
+            verificationGasLimit: U256::from(200_000),
+            preVerificationGas: U256::from(50_000),
+            maxFeePerGas: U256::from(1_000_000_000),
+            maxPriorityFeePerGas: U256::from(1_000_000),
+            paymasterAndData: Bytes::new(),
+            signature: Bytes::new(),
+        }
+    }
+
+    fn bundle_tx(nonce: u64, to: Address, input: Vec<u8>) -> TxEnvelope {
+        let tx = TxEip1559 {
+            chain_id: 1,
+            nonce,
+            gas_limit: 1_000_000,
+            max_fee_per_gas: 1_000_000_000,
+            max_priority_fee_per_gas: 1_000_000,
+            to: TxKind::Call(to),
+            value: U256::ZERO,
+            access_list: Default::default(),
+            input: input.into(),
+        };
+        let signature = Signature::from_rs_and_parity(U256::from(1), U256::from(1), false).unwrap();
+        tx.into_signed(signature).into()
+    }
+
+    #[test]
+    fn test_user_op_hash_depends_on_domain() {
+        let op = user_operation(0);
+        let hash = op.hash(ENTRY_POINT_V06, 1);
+        assert_ne!(hash, op.hash(ENTRY_POINT_V07, 1));
+        assert_ne!(hash, op.hash(ENTRY_POINT_V06, 167000));
+        assert_ne!(hash, user_operation(1).hash(ENTRY_POINT_V06, 1));
+    }
+
+    #[test]
+    fn test_known_answers() {
+        // The selectors of `handleOps` in the published entry point ABIs
+        assert_eq!(v06::handleOpsCall::SELECTOR, [0x1f, 0xad, 0x94, 0x8c]);
+        assert_eq!(v07::handleOpsCall::SELECTOR, [0x76, 0x5e, 0x82, 0x7f]);
+
+        // Computed independently of alloy, with a standalone keccak256 and ABI encoder
+        assert_eq!(
+            user_operation(0).hash(ENTRY_POINT_V06, 1),
+            b256!("fc7d3dc489b04d99b181c67734b924951c66a03b9befa52ed8cadf458a4abccf")
+        );
+        let op = PackedUserOperation {
+            sender: address!("1111111111111111111111111111111111111111"),
+            nonce: U256::ZERO,
+            initCode: Bytes::new(),
+            callData: Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef]),
+            accountGasLimits: b256!(
+                "00000000000000000000000000030d40000000000000000000000000000186a0"
+            ),
+            preVerificationGas: U256::from(50_000),
+            gasFees: b256!("000000000000000000000000000f42400000000000000000000000003b9aca00"),
+            paymasterAndData: Bytes::new(),
+            signature: Bytes::new(),
+        };
+        assert_eq!(
+            op.hash(ENTRY_POINT_V07, 1),
+            b256!("f34579e1819a7c7f611d5c83025e25e992b5a9bbc90cda0abfebc5aeec538349")
+        );
+    }
+
+    #[test]
+    fn test_packed_gas_fields() {
+        let op = PackedUserOperation {
+            accountGasLimits: b256!(
+                "00000000000000000000000000030d40000000000000000000000000000186a0"
+            ),
+            gasFees: b256!("000000000000000000000000000f42400000000000000000000000003b9aca00"),
+            ..Default::default()
+        };
+        assert_eq!(op.verification_gas_limit(), 200_000);
+        assert_eq!(op.call_gas_limit(), 100_000);
+        assert_eq!(op.max_priority_fee_per_gas(), 1_000_000);
+        assert_eq!(op.max_fee_per_gas(), 1_000_000_000);
+    }
+
+    #[test]
+    fn test_find_user_operations() {
+        let ops = vec![user_operation(0), user_operation(1)];
+        let bundle = v06::handleOpsCall {
+            ops: ops.clone(),
+            beneficiary: Address::ZERO,
+        }
+        .abi_encode();
+        let transactions = vec![
+            bundle_tx(0, Address::ZERO, vec![]),
+            bundle_tx(1, ENTRY_POINT_V06, bundle),
+        ];
+
+        let found = find_user_operations(&transactions, 1);
+        assert_eq!(found.len(), 2);
+        for (i, op) in found.iter().enumerate() {
+            assert_eq!(op.tx_index, 1);
+            assert_eq!(op.op_index, i);
+            assert_eq!(op.user_op_hash, ops[i].hash(ENTRY_POINT_V06, 1));
+        }
+
+        // the bundle transaction can be proven against the transactions root
+        let proof = transaction_inclusion_proof(&transactions, 1).unwrap();
+        let proof_trie = mpt_from_proof(&parse_proof(&proof).unwrap()).unwrap();
+        let mut tx_trie = MptNode::default();
+        for (i, tx) in transactions.iter().enumerate() {
+            tx_trie
+                .insert_rlp_encoded(&i.to_rlp(), tx.to_rlp_2718())
+                .unwrap();
+        }
+        assert_eq!(proof_trie.hash(), tx_trie.hash());
+        assert_eq!(
+            proof_trie.get(&1usize.to_rlp()).unwrap(),
+            Some(transactions[1].to_rlp_2718().as_slice())
+        );
+    }
+}
@@ -13,8 +13,12 @@
 // limitations under the License.
 
 use alloy_primitives::{b256, B256};
+use revm_primitives::HashMap;
 use sha3::{Digest, Keccak256};
 
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+
 /// Represents the Keccak-256 hash of an empty byte slice.
 ///
 /// This is a constant value and can be used as a default or placeholder
@@ -36,3 +40,70 @@ pub fn keccak(data: impl AsRef<[u8]>) -> [u8; 32] {
     // std::hint::black_box(sha2::Sha256::digest(&data));
     Keccak256::digest(data).into()
 }
+
+/// Memoizes Keccak-256 hashes by the hashed content.
+///
+/// Items like account addresses, storage keys and trie nodes are often hashed several times
+/// during a single block build. The cache is only active with the `keccak-cache` feature,
+/// otherwise it hashes the data every time.
+#[derive(Clone, Debug, Default)]
+pub struct KeccakCache {
+    hashes: HashMap<Vec<u8>, B256>,
+    stats: KeccakCacheStats,
+}
+
+impl KeccakCache {
+    /// Returns the Keccak-256 hash of `data`, from the cache if it was hashed before.
+    pub fn keccak(&mut self, data: impl AsRef<[u8]>) -> B256 {
+        let data = data.as_ref();
+        if !cfg!(feature = "keccak-cache") {
+            return keccak(data).into();
+        }
+        if let Some(hash) = self.hashes.get(data) {
+            self.stats.hits += 1;
+            return *hash;
+        }
+        self.stats.misses += 1;
+        let hash = B256::from(keccak(data));
+        self.hashes.insert(data.to_vec(), hash);
+        hash
+    }
+
+    /// Returns the hit and miss counts of the cache.
+    pub fn stats(&self) -> KeccakCacheStats {
+        self.stats
+    }
+}
+
+/// Hit and miss counts of a [KeccakCache].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeccakCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl KeccakCacheStats {
+    /// Returns the share of lookups served from the cache.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+#[cfg(all(test, feature = "keccak-cache"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keccak_cache() {
+        let mut cache = KeccakCache::default();
+        assert_eq!(cache.keccak(b""), KECCAK_EMPTY);
+        assert_eq!(cache.keccak(b""), KECCAK_EMPTY);
+        assert_eq!(cache.keccak([1u8]), B256::from(keccak([1u8])));
+        assert_eq!(cache.stats(), KeccakCacheStats { hits: 1, misses: 2 });
+    }
+}
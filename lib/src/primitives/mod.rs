@@ -18,11 +18,14 @@ extern crate core;
 
 pub use alloc::{vec, vec::Vec};
 
+pub mod compact;
+pub mod eip4337;
 pub mod eip4844;
 pub mod keccak;
 pub mod mpt;
 pub mod receipt;
 pub mod signature;
+pub mod trie_bloom;
 
 #[cfg(feature = "c-kzg")]
 pub use c_kzg as kzg;
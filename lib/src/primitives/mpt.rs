@@ -20,15 +20,16 @@ use core::{
 };
 
 use alloy_primitives::{b256, TxNumber, B256, U256};
-use alloy_rlp::Encodable;
+use alloy_rlp::{Decodable, Encodable};
 use alloy_rlp_derive::{RlpDecodable, RlpEncodable, RlpMaxEncodedLen};
 use alloy_rpc_types::EIP1186AccountProofResponse;
 use anyhow::{Context, Result};
 use revm_primitives::{Address, HashMap};
-use rlp::{Decodable, DecoderError, Prototype, Rlp};
 use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
 
+use crate::primitives::keccak::KeccakCache;
+
 pub type StorageEntry = (MptNode, Vec<U256>);
 
 /// Represents an Ethereum account within the state trie.
@@ -151,14 +152,25 @@ pub enum Error {
     /// Occurs when a value is unexpectedly found in a branch node.
     #[error("branch node with value")]
     ValueInBranch,
+    /// Occurs when an empty value is inserted, which would be indistinguishable from a
+    /// missing key.
+    #[error("empty value")]
+    EmptyValue,
+    /// Occurs when a proof node that is not the root is embedded instead of hashed. The
+    /// associated value is the index of the node in the proof.
+    #[error("proof node {0} is not referenced by hash")]
+    UnhashedProofNode(usize),
+    /// Occurs when a proof node doesn't reference its successor. The associated value is
+    /// the index of the node in the proof.
+    #[error("proof node {0} does not reference the successor")]
+    UnreferencedProofNode(usize),
+    /// Occurs when the tries built from proofs don't match the expected root.
+    #[error("root mismatch: expected {expected}, got {actual}")]
+    RootMismatch { expected: B256, actual: B256 },
     /// Represents errors related to the RLP encoding and decoding using the `alloy_rlp`
     /// library.
     #[error("RLP error")]
     Rlp(#[from] alloy_rlp::Error),
-    /// Represents errors related to the RLP encoding and decoding, specifically legacy
-    /// errors.
-    #[error("RLP error")]
-    LegacyRlp(#[from] DecoderError),
 }
 
 /// Represents the various types of data that can be stored within a node in the sparse
@@ -282,57 +294,89 @@ impl Encodable for MptNode {
 /// Provides decoding functionalities for the [MptNode] type.
 ///
 /// This implementation allows for the deserialization of an RLP-encoded [MptNode] back
-/// into its original form. The decoding is done based on the prototype of the RLP data,
-/// ensuring that the node is reconstructed accurately.
-///
-/// **Note**: This implementation is still using the older RLP library and needs to be
-/// migrated to `alloy_rlp` in the future.
-// TODO: migrate to alloy_rlp
+/// into its original form. The node is decoded directly from the borrowed input buffer:
+/// child nodes are decoded from sub-slices of the buffer, and only the paths and values
+/// that end up being stored in the trie are copied.
 impl Decodable for MptNode {
-    /// Decodes an RLP-encoded node from the provided `rlp` buffer.
+    /// Decodes an RLP-encoded node from the provided `buf` and advances it past the node.
     ///
-    /// The method handles different RLP prototypes and reconstructs the `MptNode` based
-    /// on the encoded data. If the RLP data does not match any known prototype or if
-    /// there's an error during decoding, an error is returned.
-    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
-        match rlp.prototype()? {
-            Prototype::Null | Prototype::Data(0) => Ok(MptNodeData::Null.into()),
-            Prototype::List(2) => {
-                let path: Vec<u8> = rlp.val_at(0)?;
-                let prefix = path[0];
+    /// If the RLP data does not match any known node type or if there's an error during
+    /// decoding, an error is returned.
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let header = alloy_rlp::Header::decode(buf)?;
+        let (mut payload, rest) = buf.split_at(header.payload_length);
+        *buf = rest;
+
+        if !header.list {
+            return match payload.len() {
+                0 => Ok(MptNodeData::Null.into()),
+                32 => Ok(MptNodeData::Digest(B256::from_slice(payload)).into()),
+                _ => Err(alloy_rlp::Error::Custom("invalid node reference")),
+            };
+        }
+
+        let mut items: [&[u8]; 17] = Default::default();
+        let mut len = 0;
+        while !payload.is_empty() {
+            if len == items.len() {
+                return Err(alloy_rlp::Error::Custom("invalid number of node items"));
+            }
+            items[len] = rlp_item(&mut payload)?;
+            len += 1;
+        }
+
+        match len {
+            2 => {
+                let path = rlp_string(items[0])?;
+                let prefix = *path.first().ok_or(alloy_rlp::Error::InputTooShort)?;
                 if (prefix & (2 << 4)) == 0 {
-                    let node: MptNode = Decodable::decode(&rlp.at(1)?)?;
-                    Ok(MptNodeData::Extension(path, Box::new(node)).into())
+                    let node: MptNode = Decodable::decode(&mut &items[1][..])?;
+                    Ok(MptNodeData::Extension(path.to_vec(), Box::new(node)).into())
                 } else {
-                    Ok(MptNodeData::Leaf(path, rlp.val_at(1)?).into())
+                    let value = rlp_string(items[1])?;
+                    Ok(MptNodeData::Leaf(path.to_vec(), value.to_vec()).into())
                 }
             }
-            Prototype::List(17) => {
+            17 => {
                 let mut node_list = Vec::with_capacity(16);
-                for node_rlp in rlp.iter().take(16) {
-                    match node_rlp.prototype()? {
-                        Prototype::Null | Prototype::Data(0) => {
-                            node_list.push(None);
-                        }
-                        _ => node_list.push(Some(Box::new(Decodable::decode(&node_rlp)?))),
+                for mut item in items.into_iter().take(16) {
+                    if item == [alloy_rlp::EMPTY_STRING_CODE] {
+                        node_list.push(None);
+                    } else {
+                        node_list.push(Some(Box::new(Decodable::decode(&mut item)?)));
                     }
                 }
-                let value: Vec<u8> = rlp.val_at(16)?;
-                if value.is_empty() {
+                if rlp_string(items[16])?.is_empty() {
                     Ok(MptNodeData::Branch(node_list.try_into().unwrap()).into())
                 } else {
-                    Err(DecoderError::Custom("branch node with value"))
+                    Err(alloy_rlp::Error::Custom("branch node with value"))
                 }
             }
-            Prototype::Data(32) => {
-                let bytes: Vec<u8> = rlp.as_val()?;
-                Ok(MptNodeData::Digest(B256::from_slice(&bytes)).into())
-            }
-            _ => Err(DecoderError::RlpIncorrectListLen),
+            _ => Err(alloy_rlp::Error::Custom("invalid number of node items")),
         }
     }
 }
 
+/// Splits the next RLP item, including its header, off the front of `buf`.
+fn rlp_item<'a>(buf: &mut &'a [u8]) -> alloy_rlp::Result<&'a [u8]> {
+    let start = *buf;
+    let header = alloy_rlp::Header::decode(buf)?;
+    let len = start.len() - buf.len() + header.payload_length;
+    let (item, rest) = start.split_at(len);
+    *buf = rest;
+    Ok(item)
+}
+
+/// Returns the payload of the RLP string `item` without copying it.
+fn rlp_string(item: &[u8]) -> alloy_rlp::Result<&[u8]> {
+    let mut buf = item;
+    let header = alloy_rlp::Header::decode(&mut buf)?;
+    if header.list {
+        return Err(alloy_rlp::Error::UnexpectedList);
+    }
+    Ok(&buf[..header.payload_length])
+}
+
 /// Represents a node in the sparse Merkle Patricia Trie (MPT).
 ///
 /// The [MptNode] type encapsulates the data and functionalities associated with a node in
@@ -354,7 +398,15 @@ impl MptNode {
     /// This method allows for the deserialization of a previously serialized [MptNode].
     #[inline]
     pub fn decode(bytes: impl AsRef<[u8]>) -> Result<MptNode, Error> {
-        rlp::decode(bytes.as_ref()).map_err(Error::from)
+        let mut buf = bytes.as_ref();
+        if buf.is_empty() {
+            return Ok(MptNodeData::Null.into());
+        }
+        let node = <MptNode as Decodable>::decode(&mut buf)?;
+        if !buf.is_empty() {
+            return Err(alloy_rlp::Error::Custom("Trailing data").into());
+        }
+        Ok(node)
     }
 
     /// Retrieves the underlying data of the node.
@@ -393,6 +445,51 @@ impl MptNode {
         }
     }
 
+    /// Computes the hash of the node like [MptNode::hash], but looks up the hashes of all
+    /// nodes that need to be (re)hashed in `cache` first.
+    pub fn hash_with_cache(&self, cache: &mut KeccakCache) -> B256 {
+        match self.data {
+            MptNodeData::Null => EMPTY_ROOT,
+            _ => match self.reference_with_cache(cache) {
+                MptNodeReference::Digest(digest) => digest,
+                MptNodeReference::Bytes(bytes) => cache.keccak(bytes),
+            },
+        }
+    }
+
+    fn reference_with_cache(&self, cache: &mut KeccakCache) -> MptNodeReference {
+        if let Some(reference) = self.cached_reference.borrow().as_ref() {
+            return reference.clone();
+        }
+
+        // compute the children first, so that encoding this node uses their cached references
+        match &self.data {
+            MptNodeData::Branch(children) => {
+                for child in children.iter().flatten() {
+                    child.reference_with_cache(cache);
+                }
+            }
+            MptNodeData::Extension(_, child) => {
+                child.reference_with_cache(cache);
+            }
+            _ => {}
+        }
+
+        let reference = match &self.data {
+            MptNodeData::Null | MptNodeData::Digest(_) => self.calc_reference(),
+            _ => {
+                let encoded = alloy_rlp::encode(self);
+                if encoded.len() < 32 {
+                    MptNodeReference::Bytes(encoded)
+                } else {
+                    MptNodeReference::Digest(cache.keccak(encoded))
+                }
+            }
+        };
+        *self.cached_reference.borrow_mut() = Some(reference.clone());
+        reference
+    }
+
     /// Encodes the [MptNodeReference] of this node into the `out` buffer.
     fn reference_encode(&self, out: &mut dyn alloy_rlp::BufMut) {
         match self.reference() {
@@ -511,6 +608,51 @@ impl MptNode {
         }
     }
 
+    /// Returns the EIP-1186 proof for the given key.
+    ///
+    /// The proof consists of the RLP-encodings of all nodes on the path to the key that
+    /// are referenced by hash, starting with the root. Nodes embedded in their parent are
+    /// not included separately. The proof is an exclusion proof if the key is not present.
+    pub fn proof(&self, key: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        let mut proof = Vec::new();
+        if !self.is_empty() {
+            self.proof_internal(&to_nibs(key), true, &mut proof)?;
+        }
+        Ok(proof)
+    }
+
+    fn proof_internal(
+        &self,
+        key_nibs: &[u8],
+        is_root: bool,
+        proof: &mut Vec<Vec<u8>>,
+    ) -> Result<(), Error> {
+        if let MptNodeData::Digest(digest) = &self.data {
+            return Err(Error::NodeNotResolved(*digest));
+        }
+        if is_root || matches!(self.reference(), MptNodeReference::Digest(_)) {
+            proof.push(alloy_rlp::encode(self));
+        }
+
+        match &self.data {
+            MptNodeData::Branch(nodes) => {
+                if let Some((i, tail)) = key_nibs.split_first() {
+                    if let Some(ref node) = nodes[*i as usize] {
+                        return node.proof_internal(tail, false, proof);
+                    }
+                }
+                Ok(())
+            }
+            MptNodeData::Extension(prefix, node) => {
+                if let Some(tail) = key_nibs.strip_prefix(prefix_nibs(prefix).as_slice()) {
+                    return node.proof_internal(tail, false, proof);
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Removes a key from the trie.
     ///
     /// This method attempts to remove a key-value pair from the trie. If the key is
@@ -633,7 +775,9 @@ impl MptNode {
     /// the value and returns `false`.
     #[inline]
     pub fn insert(&mut self, key: &[u8], value: Vec<u8>) -> Result<bool, Error> {
-        assert!(!value.is_empty(), "value must not be empty");
+        if value.is_empty() {
+            return Err(Error::EmptyValue);
+        }
         self.insert_internal(&to_nibs(key), value)
     }
 
@@ -810,10 +954,7 @@ impl MptNode {
                 })
                 .collect(),
             MptNodeData::Leaf(_, data) => {
-                vec![format!(
-                    "{nibs} -> {:?}",
-                    T::decode(&mut &data[..]).unwrap()
-                )]
+                vec![format!("{nibs} -> {:?}", T::decode(&mut &data[..]))]
             }
             MptNodeData::Extension(_, node) => node
                 .debug_rlp::<T>()
@@ -889,7 +1030,7 @@ fn lcp(a: &[u8], b: &[u8]) -> usize {
     cmp::min(a.len(), b.len())
 }
 
-fn prefix_nibs(prefix: &[u8]) -> Vec<u8> {
+pub(crate) fn prefix_nibs(prefix: &[u8]) -> Vec<u8> {
     let (extension, tail) = prefix.split_first().unwrap();
     // the first bit of the first nibble denotes the parity
     let is_odd = extension & (1 << 4) != 0;
@@ -907,16 +1048,13 @@ fn prefix_nibs(prefix: &[u8]) -> Vec<u8> {
 }
 
 /// Parses proof bytes into a vector of MPT nodes.
-pub fn parse_proof(proof: &[impl AsRef<[u8]>]) -> Result<Vec<MptNode>> {
-    Ok(proof
-        .iter()
-        .map(MptNode::decode)
-        .collect::<Result<Vec<_>, _>>()?)
+pub fn parse_proof(proof: &[impl AsRef<[u8]>]) -> Result<Vec<MptNode>, Error> {
+    proof.iter().map(MptNode::decode).collect()
 }
 
 /// Creates a Merkle Patricia trie from an EIP-1186 proof.
 /// For inclusion proofs the returned trie contains exactly one leaf with the value.
-pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode> {
+pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode, Error> {
     let mut next: Option<MptNode> = None;
     for (i, node) in proof_nodes.iter().enumerate().rev() {
         // there is nothing to replace for the last node
@@ -927,7 +1065,7 @@ pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode> {
 
         // the next node must have a digest reference
         let MptNodeReference::Digest(ref child_ref) = replacement.reference() else {
-            panic!("node {} in proof is not referenced by hash", i + 1);
+            return Err(Error::UnhashedProofNode(i + 1));
         };
         // find the child that references the next node
         let resolved: MptNode = match node.as_data().clone() {
@@ -937,19 +1075,19 @@ pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode> {
                 ) {
                     *child = Box::new(replacement);
                 } else {
-                    panic!("node {i} does not reference the successor");
+                    return Err(Error::UnreferencedProofNode(i));
                 }
                 MptNodeData::Branch(children).into()
             }
             MptNodeData::Extension(prefix, child) => {
-                assert!(
-                    matches!(child.as_data(), MptNodeData::Digest(d) if d == child_ref),
-                    "node {i} does not reference the successor"
-                );
+                if !matches!(child.as_data(), MptNodeData::Digest(d) if d == child_ref) {
+                    return Err(Error::UnreferencedProofNode(i));
+                }
                 MptNodeData::Extension(prefix, Box::new(replacement)).into()
             }
+            // nodes without children can't reference the successor
             MptNodeData::Null | MptNodeData::Leaf(_, _) | MptNodeData::Digest(_) => {
-                panic!("node {i} has no children to replace");
+                return Err(Error::UnreferencedProofNode(i));
             }
         };
 
@@ -961,10 +1099,10 @@ pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode> {
 }
 
 /// Verifies that the given proof is a valid proof of exclusion for the given key.
-pub fn is_not_included(key: &[u8], proof_nodes: &[MptNode]) -> Result<bool> {
-    let proof_trie = mpt_from_proof(proof_nodes).unwrap();
+pub fn is_not_included(key: &[u8], proof_nodes: &[MptNode]) -> Result<bool, Error> {
+    let proof_trie = mpt_from_proof(proof_nodes)?;
     // for valid proofs, the get must not fail
-    let value = proof_trie.get(key).unwrap();
+    let value = proof_trie.get(key)?;
 
     Ok(value.is_none())
 }
@@ -1002,6 +1140,52 @@ pub fn resolve_nodes(root: &MptNode, node_store: &HashMap<MptNodeReference, MptN
     trie
 }
 
+/// Resolves the digests of `trie` with the subtries at the same position in `previous`,
+/// e.g. the trie before some of its keys were changed.
+///
+/// The subtries are moved instead of copied, so only the paths that differ between the
+/// two tries are visited.
+pub fn graft_nodes(trie: MptNode, previous: MptNode) -> MptNode {
+    let MptNode {
+        data,
+        cached_reference,
+    } = trie;
+    let data = match (data, previous.data) {
+        (MptNodeData::Digest(digest), data) => {
+            let previous = MptNode {
+                data,
+                cached_reference: previous.cached_reference,
+            };
+            if !matches!(previous.as_data(), MptNodeData::Digest(_)) && previous.hash() == digest {
+                return previous;
+            }
+            MptNodeData::Digest(digest)
+        }
+        (MptNodeData::Branch(children), MptNodeData::Branch(previous_children)) => {
+            let children: Vec<_> = children
+                .into_iter()
+                .zip(previous_children)
+                .map(|(child, previous)| match (child, previous) {
+                    (Some(child), Some(previous)) => Some(Box::new(graft_nodes(*child, *previous))),
+                    (child, _) => child,
+                })
+                .collect();
+            MptNodeData::Branch(children.try_into().unwrap())
+        }
+        (
+            MptNodeData::Extension(prefix, child),
+            MptNodeData::Extension(previous_prefix, previous),
+        ) if prefix == previous_prefix => {
+            MptNodeData::Extension(prefix, Box::new(graft_nodes(*child, *previous)))
+        }
+        (data, _) => data,
+    };
+    MptNode {
+        data,
+        cached_reference,
+    }
+}
+
 /// Returns a list of all possible nodes that can be created by shortening the path of the
 /// given node.
 /// When nodes in an MPT are deleted, leaves or extensions may be extended. To still be
@@ -1030,6 +1214,11 @@ pub fn shorten_node_path(node: &MptNode) -> Vec<MptNode> {
     res
 }
 
+/// Builds the tries from the proofs of the parent state.
+///
+/// `proofs` are the proofs of the state after the block. They are only needed for the
+/// accounts and slots that may have been deleted, to resolve the nodes that get shortened
+/// by the deletion.
 pub fn proofs_to_tries(
     state_root: B256,
     parent_proofs: HashMap<Address, EIP1186AccountProofResponse>,
@@ -1045,8 +1234,10 @@ pub fn proofs_to_tries(
     let mut state_nodes = HashMap::new();
     let mut state_root_node = MptNode::default();
     for (address, proof) in parent_proofs {
-        let proof_nodes = parse_proof(&proof.account_proof).unwrap();
-        mpt_from_proof(&proof_nodes).unwrap();
+        let proof_nodes = parse_proof(&proof.account_proof)
+            .with_context(|| format!("invalid account proof for {address}"))?;
+        mpt_from_proof(&proof_nodes)
+            .with_context(|| format!("invalid account proof for {address}"))?;
 
         // the first node in the proof is the root
         if let Some(node) = proof_nodes.first() {
@@ -1057,10 +1248,12 @@ pub fn proofs_to_tries(
             state_nodes.insert(node.reference(), node);
         }
 
-        let fini_proofs = proofs.get(&address).unwrap();
+        let fini_proofs = proofs.get(&address);
 
         // assure that addresses can be deleted from the state trie
-        add_orphaned_leafs(address, &fini_proofs.account_proof, &mut state_nodes)?;
+        if let Some(fini_proofs) = fini_proofs {
+            add_orphaned_leafs(address, &fini_proofs.account_proof, &mut state_nodes)?;
+        }
 
         // if no slots are provided, return the trie only consisting of the storage root
         let storage_root = proof.storage_hash;
@@ -1073,8 +1266,10 @@ pub fn proofs_to_tries(
         let mut storage_nodes = HashMap::new();
         let mut storage_root_node = MptNode::default();
         for storage_proof in &proof.storage_proof {
-            let proof_nodes = parse_proof(&storage_proof.proof).unwrap();
-            mpt_from_proof(&proof_nodes).unwrap();
+            let proof_nodes = parse_proof(&storage_proof.proof)
+                .with_context(|| format!("invalid storage proof for {address}"))?;
+            mpt_from_proof(&proof_nodes)
+                .with_context(|| format!("invalid storage proof for {address}"))?;
 
             // the first node in the proof is the root
             if let Some(node) = proof_nodes.first() {
@@ -1087,7 +1282,7 @@ pub fn proofs_to_tries(
         }
 
         // assure that slots can be deleted from the storage trie
-        for storage_proof in &fini_proofs.storage_proof {
+        for storage_proof in fini_proofs.iter().flat_map(|proofs| &proofs.storage_proof) {
             add_orphaned_leafs(
                 storage_proof.key.0 .0,
                 &storage_proof.proof,
@@ -1096,7 +1291,8 @@ pub fn proofs_to_tries(
         }
         // create the storage trie, from all the relevant nodes
         let storage_trie = resolve_nodes(&storage_root_node, &storage_nodes);
-        assert_eq!(storage_trie.hash(), storage_root);
+        check_root(&storage_trie, storage_root)
+            .with_context(|| format!("invalid storage proofs for {address}"))?;
 
         // convert the slots to a vector of U256
         let slots = proof
@@ -1107,11 +1303,20 @@ pub fn proofs_to_tries(
         storage.insert(address, (storage_trie, slots));
     }
     let state_trie = resolve_nodes(&state_root_node, &state_nodes);
-    assert_eq!(state_trie.hash(), state_root);
+    check_root(&state_trie, state_root).context("invalid account proofs")?;
 
     Ok((state_trie, storage))
 }
 
+/// Checks that the trie has the expected root hash.
+fn check_root(trie: &MptNode, expected: B256) -> Result<(), Error> {
+    let actual = trie.hash();
+    if actual != expected {
+        return Err(Error::RootMismatch { expected, actual });
+    }
+    Ok(())
+}
+
 /// Adds all the leaf nodes of non-inclusion proofs to the nodes.
 fn add_orphaned_leafs(
     key: impl AsRef<[u8]>,
@@ -1121,10 +1326,11 @@ fn add_orphaned_leafs(
     if !proof.is_empty() {
         let proof_nodes = parse_proof(proof).context("invalid proof encoding")?;
         if is_not_included(&keccak(key), &proof_nodes)? {
-            // add the leaf node to the nodes
-            let leaf = proof_nodes.last().unwrap();
-            for node in shorten_node_path(leaf) {
-                nodes_by_reference.insert(node.reference(), node);
+            // add the leaf node to the nodes, a non-empty proof always has a last node
+            if let Some(leaf) = proof_nodes.last() {
+                for node in shorten_node_path(leaf) {
+                    nodes_by_reference.insert(node.reference(), node);
+                }
             }
         }
     }
@@ -1365,6 +1571,125 @@ mod tests {
         assert!(trie.is_empty());
     }
 
+    #[test]
+    pub fn test_proof() {
+        const N: usize = 512;
+
+        let mut trie = MptNode::default();
+        for i in 0..N {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+
+        for i in 0..N {
+            // inclusion proof
+            let key = keccak(i.to_be_bytes());
+            let proof_nodes = parse_proof(&trie.proof(&key).unwrap()).unwrap();
+            let proof_trie = mpt_from_proof(&proof_nodes).unwrap();
+            assert_eq!(proof_trie.hash(), trie.hash());
+            assert_eq!(proof_trie.get_rlp(&key).unwrap(), Some(i));
+
+            // exclusion proof
+            let key = keccak((i + N).to_be_bytes());
+            let proof_nodes = parse_proof(&trie.proof(&key).unwrap()).unwrap();
+            assert!(is_not_included(&key, &proof_nodes).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_invalid_proof() {
+        const N: usize = 512;
+
+        let mut trie = MptNode::default();
+        for i in 0..N {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        let key = keccak(0usize.to_be_bytes());
+        let mut proof_nodes = parse_proof(&trie.proof(&key).unwrap()).unwrap();
+        assert!(proof_nodes.len() > 2);
+
+        // a node that doesn't reference its successor is rejected instead of panicking
+        proof_nodes.remove(1);
+        assert!(matches!(
+            mpt_from_proof(&proof_nodes),
+            Err(Error::UnreferencedProofNode(0))
+        ));
+        assert!(is_not_included(&key, &proof_nodes).is_err());
+
+        assert!(matches!(trie.insert(&key, vec![]), Err(Error::EmptyValue)));
+    }
+
+    #[test]
+    pub fn test_graft_nodes() {
+        const N: usize = 512;
+
+        let mut trie = MptNode::default();
+        for i in 0..N {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+
+        // a trie only consisting of the proof of a single key
+        let key = keccak(0usize.to_be_bytes());
+        let proof_nodes = parse_proof(&trie.proof(&key).unwrap()).unwrap();
+        let mut partial = mpt_from_proof(&proof_nodes).unwrap();
+        assert!(partial.get(&keccak(1usize.to_be_bytes())).is_err());
+
+        // changing the key keeps all other subtries, which are taken from the previous trie
+        partial.insert_rlp(&key, N).unwrap();
+        let hash = partial.hash();
+        let resolved = graft_nodes(partial, trie);
+        assert_eq!(resolved.hash(), hash);
+        assert_eq!(resolved.get_rlp(&key).unwrap(), Some(N));
+        for i in 1..N {
+            assert_eq!(resolved.get_rlp(&keccak(i.to_be_bytes())).unwrap(), Some(i));
+        }
+    }
+
+    #[test]
+    pub fn test_decode() {
+        let mut trie = MptNode::default();
+        for (k, v) in [
+            ("cat", "kitten"),
+            ("dog", "puppy"),
+            ("duck", "duckling"),
+            ("horse", "stallion"),
+        ] {
+            trie.insert(k.as_bytes(), v.as_bytes().to_vec()).unwrap();
+        }
+        let decoded = MptNode::decode(trie.to_rlp()).unwrap();
+        assert_eq!(decoded.hash(), trie.hash());
+
+        let leaf: MptNode = MptNodeData::Leaf(vec![0x20, 0x0f], b"puppy".to_vec()).into();
+        let decoded = MptNode::decode(leaf.to_rlp()).unwrap();
+        assert_eq!(decoded.as_data(), leaf.as_data());
+        assert!(MptNode::decode(Vec::<u8>::new()).unwrap().is_empty());
+
+        // trailing data
+        let mut encoded = trie.to_rlp();
+        encoded.push(alloy_rlp::EMPTY_STRING_CODE);
+        assert!(MptNode::decode(&encoded).is_err());
+        // branch with value
+        let mut branch = vec![0xd1];
+        branch.extend([alloy_rlp::EMPTY_STRING_CODE; 16]);
+        branch.push(0x01);
+        assert!(MptNode::decode(&branch).is_err());
+        // truncated node
+        let encoded = trie.to_rlp();
+        assert!(MptNode::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    #[test]
+    pub fn test_hash_with_cache() {
+        let mut trie = MptNode::default();
+        for i in 0..512usize {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        let reference = trie.clone();
+        assert_eq!(
+            trie.hash_with_cache(&mut KeccakCache::default()),
+            reference.hash()
+        );
+    }
+
     #[test]
     pub fn test_index_trie() {
         const N: usize = 512;
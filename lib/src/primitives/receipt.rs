@@ -226,4 +226,67 @@ mod tests {
 
         assert_eq!(data, expected);
     }
+
+    mod differential {
+        use alloy_consensus::{
+            Receipt as AlloyReceipt, ReceiptEnvelope, ReceiptWithBloom as AlloyReceiptWithBloom,
+        };
+        use alloy_eips::eip2718::Encodable2718;
+        use alloy_primitives::Log as AlloyLog;
+        use proptest::{collection::vec, prelude::*};
+
+        use super::*;
+
+        fn arb_logs() -> impl Strategy<Value = Vec<Log>> {
+            let log = (
+                any::<[u8; 20]>(),
+                vec(any::<[u8; 32]>(), 0..=4),
+                vec(any::<u8>(), 0..64),
+            )
+                .prop_map(|(address, topics, data)| Log {
+                    address: address.into(),
+                    topics: topics.into_iter().map(B256::from).collect(),
+                    data: data.into(),
+                });
+            vec(log, 0..4)
+        }
+
+        proptest! {
+            /// The receipts trie must be built from the same bytes as alloy (and therefore
+            /// reth) would use, otherwise the receipts root silently diverges.
+            #[test]
+            fn receipt_encoding_matches_alloy(
+                tx_type in 0u8..4,
+                success in any::<bool>(),
+                cumulative_gas_used in any::<u64>(),
+                logs in arb_logs(),
+            ) {
+                let receipt =
+                    Receipt::new(tx_type, success, U256::from(cumulative_gas_used), logs.clone());
+
+                let alloy_receipt = AlloyReceiptWithBloom {
+                    receipt: AlloyReceipt {
+                        status: success,
+                        cumulative_gas_used: cumulative_gas_used.into(),
+                        logs: logs
+                            .into_iter()
+                            .map(|log| AlloyLog::new_unchecked(log.address, log.topics, log.data))
+                            .collect(),
+                    },
+                    logs_bloom: receipt.payload.logs_bloom,
+                };
+                let envelope = match tx_type {
+                    0 => ReceiptEnvelope::Legacy(alloy_receipt),
+                    1 => ReceiptEnvelope::Eip2930(alloy_receipt),
+                    2 => ReceiptEnvelope::Eip1559(alloy_receipt),
+                    _ => ReceiptEnvelope::Eip4844(alloy_receipt),
+                };
+
+                let mut encoded = Vec::new();
+                receipt.encode(&mut encoded);
+                prop_assert_eq!(encoded.len(), receipt.length());
+                prop_assert_eq!(encoded, envelope.encoded_2718());
+            }
+        }
+    }
 }
@@ -0,0 +1,222 @@
+//! Bloom filters over the keys of a partially resolved Merkle Patricia Trie.
+
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::primitives::mpt::{prefix_nibs, to_nibs, Error, MptNode, MptNodeData};
+
+/// Number of filter bits that are set for each recorded path.
+const NUM_HASHES: u64 = 4;
+/// Number of filter bits per recorded path, for a false positive rate of about 0.25%.
+const BITS_PER_PATH: usize = 16;
+/// Paths are only recorded up to this number of nibbles.
+const MAX_PATH_LEN: usize = 128;
+
+/// A filter answering whether a trie lookup can be decided without traversing the trie.
+///
+/// The filter records the full path of every leaf and the path of every unresolved node.
+/// A key that is neither one of the leaf paths nor starts with one of the digest paths is
+/// provably not part of the trie, so most negative lookups (e.g. fresh storage slots) are
+/// answered by a few bit tests instead of walking down to the exclusion point. All other
+/// keys still need the lookup, which also reports unresolved nodes as before.
+///
+/// The filter is only active with the `trie-bloom` feature, otherwise every key needs the
+/// lookup.
+#[derive(Clone, Debug)]
+pub struct TrieBloom {
+    bits: Vec<u64>,
+    /// Bit `i` is set if a path of `i` nibbles was recorded.
+    path_lens: u128,
+    /// Set if no key can be excluded, e.g. because the filter is disabled.
+    pass_through: bool,
+}
+
+impl TrieBloom {
+    /// Builds the filter of the given trie.
+    pub fn new(trie: &MptNode) -> Self {
+        if !cfg!(feature = "trie-bloom") {
+            return Self::pass_through();
+        }
+
+        let mut paths = Vec::new();
+        collect_paths(trie, &mut Vec::new(), &mut paths);
+        if paths.iter().any(|path| path.len() >= MAX_PATH_LEN) {
+            return Self::pass_through();
+        }
+
+        let num_bits = (paths.len() * BITS_PER_PATH).max(64);
+        let mut bloom = Self {
+            bits: vec![0; num_bits.div_ceil(64)],
+            path_lens: 0,
+            pass_through: false,
+        };
+        for path in &paths {
+            bloom.path_lens |= 1 << path.len();
+            let hash = finish(
+                path.iter().fold(FNV_OFFSET, |hash, nib| step(hash, *nib)),
+                path.len(),
+            );
+            bloom.insert(hash);
+        }
+        bloom
+    }
+
+    /// Returns a filter that requires a lookup for every key.
+    pub fn pass_through() -> Self {
+        Self {
+            bits: Vec::new(),
+            path_lens: 0,
+            pass_through: true,
+        }
+    }
+
+    /// Returns `false` if the key is provably not in the trie. If `true` is returned, the
+    /// trie has to be queried.
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        if self.pass_through {
+            return true;
+        }
+
+        // check every prefix of the key that has the length of a recorded path
+        let nibs = to_nibs(key);
+        let mut hash = FNV_OFFSET;
+        for len in 0..=nibs.len().min(MAX_PATH_LEN - 1) {
+            if len > 0 {
+                hash = step(hash, nibs[len - 1]);
+            }
+            if self.path_lens & (1 << len) != 0 && self.contains(finish(hash, len)) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Retrieves the RLP-decoded value of the key, without querying the trie if the key is
+    /// provably not in it.
+    pub fn get_rlp<T: alloy_rlp::Decodable>(
+        &self,
+        trie: &MptNode,
+        key: &[u8],
+    ) -> Result<Option<T>, Error> {
+        if self.may_contain(key) {
+            trie.get_rlp(key)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn insert(&mut self, hash: u64) {
+        for index in self.indices(hash) {
+            self.bits[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    fn contains(&self, hash: u64) -> bool {
+        self.indices(hash)
+            .all(|index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    fn indices(&self, hash: u64) -> impl Iterator<Item = usize> {
+        // double hashing, with an odd step so that all bits are reachable
+        let num_bits = (self.bits.len() * 64) as u64;
+        let step = (hash >> 32) | 1;
+        (0..NUM_HASHES).map(move |i| {
+            let index = hash.wrapping_add(i.wrapping_mul(step)) % num_bits;
+            index as usize
+        })
+    }
+}
+
+/// Collects the full paths of all leaves and the paths of all unresolved nodes.
+fn collect_paths(node: &MptNode, path: &mut Vec<u8>, paths: &mut Vec<Vec<u8>>) {
+    match node.as_data() {
+        MptNodeData::Null => {}
+        MptNodeData::Branch(children) => {
+            for (i, child) in children.iter().enumerate() {
+                if let Some(child) = child {
+                    path.push(i as u8);
+                    collect_paths(child, path, paths);
+                    path.pop();
+                }
+            }
+        }
+        MptNodeData::Leaf(prefix, _) => {
+            let mut leaf_path = path.clone();
+            leaf_path.extend(prefix_nibs(prefix));
+            paths.push(leaf_path);
+        }
+        MptNodeData::Extension(prefix, child) => {
+            let len = path.len();
+            path.extend(prefix_nibs(prefix));
+            collect_paths(child, path, paths);
+            path.truncate(len);
+        }
+        MptNodeData::Digest(_) => paths.push(path.clone()),
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Adds a nibble to the FNV-1a hash of a path.
+fn step(hash: u64, nib: u8) -> u64 {
+    (hash ^ u64::from(nib)).wrapping_mul(FNV_PRIME)
+}
+
+/// Mixes the length into the hash of a path, so that paths of different lengths don't
+/// share their bits.
+fn finish(hash: u64, len: usize) -> u64 {
+    let mut hash = hash ^ (len as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^ (hash >> 33)
+}
+
+#[cfg(all(test, feature = "trie-bloom"))]
+mod tests {
+    use super::*;
+    use crate::primitives::mpt::{keccak, mpt_from_proof, parse_proof};
+
+    #[test]
+    fn test_trie_bloom() {
+        const N: usize = 512;
+
+        let mut trie = MptNode::default();
+        for i in 0..N {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+
+        // all keys of the trie must be looked up
+        let bloom = TrieBloom::new(&trie);
+        for i in 0..N {
+            let key = keccak(i.to_be_bytes());
+            assert_eq!(bloom.get_rlp(&trie, &key).unwrap(), Some(i));
+        }
+        // most absent keys are excluded by the filter alone
+        let excluded = (N..2 * N)
+            .filter(|i| !bloom.may_contain(&keccak(i.to_be_bytes())))
+            .count();
+        assert!(excluded > N * 9 / 10);
+
+        // keys below unresolved nodes must never be excluded
+        let proof = trie.proof(&keccak(0usize.to_be_bytes())).unwrap();
+        let partial = mpt_from_proof(&parse_proof(&proof).unwrap()).unwrap();
+        let bloom = TrieBloom::new(&partial);
+        for i in 0..2 * N {
+            let key = keccak(i.to_be_bytes());
+            if !bloom.may_contain(&key) {
+                assert_eq!(partial.get(&key).unwrap(), None);
+            }
+        }
+        assert!(partial.get(&keccak(1usize.to_be_bytes())).is_err());
+        assert!(bloom.may_contain(&keccak(1usize.to_be_bytes())));
+    }
+
+    #[test]
+    fn test_empty_trie_bloom() {
+        let bloom = TrieBloom::new(&MptNode::default());
+        assert!(!bloom.may_contain(&keccak([0u8])));
+
+        let bloom = TrieBloom::new(&MptNodeData::Digest(keccak([0u8]).into()).into());
+        assert!(bloom.may_contain(&keccak([0u8])));
+    }
+}
@@ -4,9 +4,9 @@ use std::io::Read;
 use std::io::Write;
 
 use alloy_consensus::{Header as AlloyConsensusHeader, Signed, TxEip1559, TxEnvelope};
-use alloy_primitives::{uint, Address, Signature, TxKind, U256};
+use alloy_primitives::{uint, Address, TxKind, U256};
 use alloy_rlp::{Decodable, Encodable};
-use alloy_rpc_types::{Header as AlloyHeader, Transaction as AlloyTransaction};
+use alloy_rpc_types::Header as AlloyHeader;
 use anyhow::{anyhow, bail, ensure, Context, Result};
 use lazy_static::lazy_static;
 use libflate::zlib::Decoder as zlibDecoder;
@@ -16,8 +16,8 @@ use libflate::zlib::Encoder as zlibEncoder;
 use crate::no_std::*;
 use crate::{
     consts::{ChainSpec, Network},
-    input::{decode_anchor, GuestInput},
-    primitives::{keccak256, B256},
+    input::{decode_anchor, GuestInput, InputLimitError, InputLimits},
+    primitives::{compact::CompactTxEip1559, keccak256, B256},
 };
 
 pub const ANCHOR_GAS_LIMIT: u64 = 250_000;
@@ -38,7 +38,7 @@ lazy_static! {
 }
 
 pub fn decode_transactions(tx_list: &[u8]) -> Vec<TxEnvelope> {
-    Vec::<TxEnvelope>::decode(&mut &tx_list.to_owned()[..]).unwrap_or_else(|e| {
+    Vec::<TxEnvelope>::decode(&mut &tx_list[..]).unwrap_or_else(|e| {
         // If decoding fails we need to make an empty block
         println!("decode_transactions not successful: {e:?}, use empty tx_list");
         vec![]
@@ -50,80 +50,110 @@ fn validate_calldata_tx_list(tx_list: &[u8]) -> bool {
     tx_list.len() <= CALL_DATA_CAPACITY
 }
 
-fn get_tx_list(chain_spec: &ChainSpec, is_blob_data: bool, tx_list: &[u8]) -> Vec<u8> {
+/// Returns the number of items of an RLP list from the item headers, without decoding the
+/// items themselves.
+fn count_list_items(list: &[u8]) -> Result<usize> {
+    let buf = &mut &list[..];
+    let header = alloy_rlp::Header::decode(buf)?;
+    ensure!(
+        header.list && header.payload_length <= buf.len(),
+        "not an RLP list"
+    );
+    let mut payload = &buf[..header.payload_length];
+    let mut count = 0;
+    while !payload.is_empty() {
+        let header = alloy_rlp::Header::decode(&mut payload)?;
+        ensure!(
+            header.payload_length <= payload.len(),
+            "list item exceeds the list"
+        );
+        payload = &payload[header.payload_length..];
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Decompresses the tx list data, which results in an empty tx list if the data is
+/// malformed. Only exceeding the size limit fails the block.
+fn decompress_tx_list(data: &[u8], limits: &InputLimits) -> Result<Vec<u8>, InputLimitError> {
+    match zlib_decompress_data(data, limits.max_tx_list_size) {
+        Ok(tx_list) => Ok(tx_list),
+        Err(e) => match e.downcast::<InputLimitError>() {
+            Ok(e) => Err(e),
+            Err(_) => Ok(Vec::new()),
+        },
+    }
+}
+
+fn get_tx_list(
+    chain_spec: &ChainSpec,
+    is_blob_data: bool,
+    tx_list: &[u8],
+    limits: &InputLimits,
+) -> Result<Vec<u8>, InputLimitError> {
     if chain_spec.is_taiko() {
         // taiko has some limiations to be aligned with taiko-client
         if is_blob_data {
             let compressed_tx_list = decode_blob_data(tx_list);
-            return zlib_decompress_data(&compressed_tx_list).unwrap_or_default();
+            return decompress_tx_list(&compressed_tx_list, limits);
         }
 
         if Network::TaikoA7.to_string() == chain_spec.network() {
-            let de_tx_list: Vec<u8> = zlib_decompress_data(tx_list).unwrap_or_default();
+            let de_tx_list = decompress_tx_list(tx_list, limits)?;
 
             if validate_calldata_tx_list(&de_tx_list) {
-                return de_tx_list;
+                return Ok(de_tx_list);
             }
 
             println!("validate_calldata_tx_list failed, use empty tx_list");
-            return vec![];
+            return Ok(vec![]);
         }
 
         if validate_calldata_tx_list(tx_list) {
-            zlib_decompress_data(tx_list).unwrap_or_default()
+            decompress_tx_list(tx_list, limits)
         } else {
             println!("validate_calldata_tx_list failed, use empty tx_list");
-            vec![]
+            Ok(vec![])
         }
     } else {
         // no limitation on non-taiko chains
-        zlib_decompress_data(tx_list).unwrap_or_default()
+        decompress_tx_list(tx_list, limits)
     }
 }
 
+/// Generates the transactions of a block from the proposed tx list data, failing if the
+/// data decompresses to more bytes or transactions than `limits` allow.
 pub fn generate_transactions(
     chain_spec: &ChainSpec,
     is_blob_data: bool,
     tx_list: &[u8],
-    anchor_tx: Option<AlloyTransaction>,
-) -> Vec<TxEnvelope> {
+    anchor_tx: Option<&CompactTxEip1559>,
+    limits: &InputLimits,
+) -> Result<Vec<TxEnvelope>, InputLimitError> {
     // Decode the tx list from the raw data posted onchain
-    let tx_list = get_tx_list(chain_spec, is_blob_data, tx_list);
+    let tx_list = get_tx_list(chain_spec, is_blob_data, tx_list, limits)?;
+
+    // Count the transactions before decoding them, a malformed list decodes to no
+    // transactions anyway
+    if let Ok(count) = count_list_items(&tx_list) {
+        limits.check_transactions(count + usize::from(anchor_tx.is_some()))?;
+    }
 
     // Decode the transactions from the tx list
     let mut transactions = decode_transactions(&tx_list);
     if let Some(anchor_tx) = anchor_tx {
         // Create a tx from the anchor tx that has the same type as the transactions encoded from
         // the tx list
-        let signed_eip1559_tx = Signed::<TxEip1559>::new_unchecked(
-            TxEip1559 {
-                chain_id: anchor_tx.chain_id.unwrap(),
-                nonce: anchor_tx.nonce,
-                gas_limit: anchor_tx.gas,
-                max_fee_per_gas: anchor_tx.max_fee_per_gas.unwrap(),
-                max_priority_fee_per_gas: anchor_tx.max_priority_fee_per_gas.unwrap(),
-                to: TxKind::Call(anchor_tx.to.unwrap()),
-                value: anchor_tx.value,
-                access_list: Default::default(),
-                input: anchor_tx.input,
-            },
-            Signature::from_rs_and_parity(
-                anchor_tx.signature.unwrap().r,
-                anchor_tx.signature.unwrap().s,
-                anchor_tx.signature.unwrap().y_parity.unwrap().0,
-            )
-            .unwrap(),
-            anchor_tx.hash,
-        );
+        let signed_eip1559_tx = anchor_tx.to_signed().unwrap();
         // Insert the anchor transactions generated by the node (which needs to be verified!)
         transactions.insert(0, TxEnvelope::from(signed_eip1559_tx));
     }
-    transactions
+    Ok(transactions)
 }
 
 const BLOB_FIELD_ELEMENT_NUM: usize = 4096;
 const BLOB_FIELD_ELEMENT_BYTES: usize = 32;
-const BLOB_DATA_CAPACITY: usize = BLOB_FIELD_ELEMENT_NUM * BLOB_FIELD_ELEMENT_BYTES;
+pub const BLOB_DATA_CAPACITY: usize = BLOB_FIELD_ELEMENT_NUM * BLOB_FIELD_ELEMENT_BYTES;
 // max call data bytes
 const CALL_DATA_CAPACITY: usize = BLOB_FIELD_ELEMENT_NUM * (BLOB_FIELD_ELEMENT_BYTES - 1);
 const BLOB_VERSION_OFFSET: usize = 1;
@@ -131,7 +161,12 @@ const BLOB_ENCODING_VERSION: u8 = 0;
 const MAX_BLOB_DATA_SIZE: usize = (4 * 31 + 3) * 1024 - 4;
 
 // decoding https://github.com/ethereum-optimism/optimism/blob/develop/op-service/eth/blob.go
-fn decode_blob_data(blob_buf: &[u8]) -> Vec<u8> {
+/// Decodes the data of a blob, returning an empty list for malformed blobs.
+pub fn decode_blob_data(blob_buf: &[u8]) -> Vec<u8> {
+    if blob_buf.len() < BLOB_DATA_CAPACITY {
+        return Vec::new();
+    }
+
     // check the version
     if blob_buf[BLOB_VERSION_OFFSET] != BLOB_ENCODING_VERSION {
         return Vec::new();
@@ -227,10 +262,17 @@ fn reassemble_bytes(
     opos
 }
 
-pub fn zlib_decompress_data(data: &[u8]) -> Result<Vec<u8>> {
-    let mut decoder = zlibDecoder::new(data)?;
+/// Decompresses `data`, failing with [InputLimitError::TxListTooLarge] as soon as more than
+/// `max_size` bytes are inflated.
+pub fn zlib_decompress_data(data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    let decoder = zlibDecoder::new(data)?;
     let mut decoded_buf = Vec::new();
-    decoder.read_to_end(&mut decoded_buf)?;
+    decoder
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decoded_buf)?;
+    if decoded_buf.len() > max_size {
+        return Err(InputLimitError::TxListTooLarge { max: max_size }.into());
+    }
     Ok(decoded_buf)
 }
 
@@ -375,3 +417,454 @@ pub fn to_header(header: &AlloyHeader) -> AlloyConsensusHeader {
         parent_beacon_block_root: header.parent_beacon_block_root,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_eips::{
+        eip2718::Encodable2718,
+        eip2930::{AccessList, AccessListItem},
+    };
+    use alloy_primitives::{address, b256, Bloom, Bytes, B64};
+    use alloy_rpc_types::{Parity, Signature as RpcSignature, Transaction as AlloyTransaction};
+    use hex_literal::hex;
+
+    use super::*;
+    use crate::builder::execute::recover_sender;
+
+    #[test]
+    fn test_to_header_hash() {
+        // Ethereum mainnet genesis
+        let genesis = AlloyHeader {
+            hash: Some(b256!(
+                "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+            )),
+            uncles_hash: b256!("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"),
+            state_root: b256!("d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544"),
+            transactions_root: b256!(
+                "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+            ),
+            receipts_root: b256!(
+                "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+            ),
+            difficulty: U256::from(0x400000000u64),
+            number: Some(0),
+            gas_limit: 5000,
+            extra_data: Bytes::from(hex!(
+                "11bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa"
+            )),
+            mix_hash: Some(B256::ZERO),
+            nonce: Some(B64::from(hex!("0000000000000042"))),
+            ..Default::default()
+        };
+        // A header with all fields up to Cancun
+        let cancun = AlloyHeader {
+            hash: Some(b256!(
+                "3e39096fe1b4cc9e82e1fefff01e3d3d3c21b5741e163f6c787b0cdadad2b0b2"
+            )),
+            parent_hash: B256::repeat_byte(0x11),
+            uncles_hash: b256!("1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"),
+            miner: Address::repeat_byte(0x22),
+            state_root: B256::repeat_byte(0x33),
+            transactions_root: B256::repeat_byte(0x44),
+            receipts_root: B256::repeat_byte(0x55),
+            logs_bloom: Bloom::from([0x66; 256]),
+            difficulty: U256::ZERO,
+            number: Some(123456),
+            gas_limit: 241_000_000,
+            gas_used: 1_234_567,
+            timestamp: 1_717_171_717,
+            extra_data: Bytes::from_static(b"taiko"),
+            mix_hash: Some(B256::repeat_byte(0x77)),
+            nonce: Some(B64::ZERO),
+            base_fee_per_gas: Some(10_000_000),
+            withdrawals_root: Some(B256::repeat_byte(0x88)),
+            blob_gas_used: Some(131072),
+            excess_blob_gas: Some(0),
+            parent_beacon_block_root: Some(B256::repeat_byte(0x99)),
+            ..Default::default()
+        };
+
+        for header in [genesis, cancun] {
+            assert_eq!(Some(to_header(&header).hash()), header.hash);
+        }
+    }
+
+    /// A legacy, an EIP-2930 and an EIP-1559 transaction of chain 167000, signed with the
+    /// private key 1, as their EIP-2718 encoding and hash.
+    const TRANSACTIONS: [(&[u8], B256); 3] = [
+        (
+            &hex!("f87203843b9aca0082520894aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa880de0b6b3a764000084da69d3db830518d4a0d47644539acec3da5e3ecf5fe8863c628a9c97e8b71e9ea9167a6f4f83c03c32a001b5f34680751208345ffe3c801b99cd47213207b19579b2e2bdade6dc78dbb1"),
+            b256!("bb62e0c374c288333a752701ab68bea7e0222e550533efbd608b89a8e7c5f27b"),
+        ),
+        (
+            &hex!("01f8c883028c5804843b9aca0082c35094aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa8084da69d3dbf85bf85994bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbf842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000201a0f30e4bd8094e53a679ddb8f55b5216b03c44623fc4279ef0791f9aa1f6930d49a04523a5af2545ec545714df86c481f09a8a26758f880d9db93e16f81bf35cdd86"),
+            b256!("6946001a6fa64677739d90501c8582ca9203c8aabbf810b726f76815751cacd7"),
+        ),
+        (
+            &hex!("02f8cb83028c58058203e8839896808303d09094aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa0784da69d3dbf85bf85994bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbf842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000280a012faae608bd6562562b8f85564664cd1fdcd667f6b24b2b221ef86b9231f4d74a07bc93d83519b7e36dce0f64dc67973c74c0c0dbfa78249fee6d104e83166d618"),
+            b256!("3735d96faa1d929df34c3a036ea602e3c1467a2ebf53c4ee5fb68b3c6a0490b4"),
+        ),
+    ];
+
+    /// The RLP list of the transactions, with the typed transactions as byte strings.
+    const TX_LIST: &[u8] = &hex!("f90211f87203843b9aca0082520894aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa880de0b6b3a764000084da69d3db830518d4a0d47644539acec3da5e3ecf5fe8863c628a9c97e8b71e9ea9167a6f4f83c03c32a001b5f34680751208345ffe3c801b99cd47213207b19579b2e2bdade6dc78dbb1b8cb01f8c883028c5804843b9aca0082c35094aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa8084da69d3dbf85bf85994bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbf842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000201a0f30e4bd8094e53a679ddb8f55b5216b03c44623fc4279ef0791f9aa1f6930d49a04523a5af2545ec545714df86c481f09a8a26758f880d9db93e16f81bf35cdd86b8ce02f8cb83028c58058203e8839896808303d09094aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa0784da69d3dbf85bf85994bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbf842a00000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000280a012faae608bd6562562b8f85564664cd1fdcd667f6b24b2b221ef86b9231f4d74a07bc93d83519b7e36dce0f64dc67973c74c0c0dbfa78249fee6d104e83166d618");
+
+    #[test]
+    fn test_decode_transactions() {
+        let transactions = decode_transactions(TX_LIST);
+        assert_eq!(transactions.len(), TRANSACTIONS.len());
+
+        for (tx, (encoded, hash)) in transactions.iter().zip(TRANSACTIONS) {
+            assert_eq!(tx.encoded_2718(), encoded);
+            let tx_hash = match tx {
+                TxEnvelope::Legacy(tx) => tx.hash(),
+                TxEnvelope::Eip2930(tx) => tx.hash(),
+                TxEnvelope::Eip1559(tx) => tx.hash(),
+                _ => unreachable!(),
+            };
+            assert_eq!(*tx_hash, hash);
+            assert_eq!(
+                recover_sender(tx).unwrap(),
+                address!("7e5f4552091a69125d5dfcb7b8c2659029395bdf")
+            );
+        }
+        let mut encoded = Vec::new();
+        transactions.encode(&mut encoded);
+        assert_eq!(encoded, TX_LIST);
+    }
+
+    #[test]
+    fn test_generate_transactions_limits() {
+        let chain_spec = ChainSpec::default();
+        let generate = |tx_data: &[u8], limits: &InputLimits| {
+            generate_transactions(&chain_spec, false, tx_data, None, limits)
+        };
+        let tx_data = zlib_compress_data(TX_LIST).unwrap();
+        assert_eq!(
+            generate(&tx_data, &InputLimits::default()).unwrap().len(),
+            TRANSACTIONS.len()
+        );
+        // malformed data still results in an empty block
+        assert!(generate(&[1, 2, 3], &InputLimits::default())
+            .unwrap()
+            .is_empty());
+
+        let limits = InputLimits {
+            max_transactions: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&tx_data, &limits).unwrap_err(),
+            InputLimitError::TooManyTransactions { count: 3, max: 2 }
+        );
+
+        // a list of empty entries that compresses to a fraction of its size
+        let mut oversized = Vec::new();
+        vec![Bytes::new(); 1 << 20].encode(&mut oversized);
+        let tx_data = zlib_compress_data(&oversized).unwrap();
+        assert!(tx_data.len() < BLOB_DATA_CAPACITY);
+        let limits = InputLimits {
+            max_tx_list_size: 1 << 16,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&tx_data, &limits).unwrap_err(),
+            InputLimitError::TxListTooLarge { max: 1 << 16 }
+        );
+        assert!(matches!(
+            zlib_decompress_data(&tx_data, oversized.len()),
+            Ok(tx_list) if tx_list == oversized
+        ));
+
+        // the entries are counted from the list header instead of being decoded
+        let limits = InputLimits {
+            max_tx_list_size: oversized.len(),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate(&tx_data, &limits).unwrap_err(),
+            InputLimitError::TooManyTransactions {
+                count: 1 << 20,
+                max: limits.max_transactions
+            }
+        );
+    }
+
+    #[test]
+    fn test_compact_tx_from_rpc() {
+        // the EIP-1559 transaction as returned by the node
+        let (_, hash) = TRANSACTIONS[2];
+        let rpc_tx = AlloyTransaction {
+            hash,
+            nonce: 5,
+            from: address!("7e5f4552091a69125d5dfcb7b8c2659029395bdf"),
+            to: Some(Address::repeat_byte(0xaa)),
+            value: U256::from(7),
+            gas: 250_000,
+            max_fee_per_gas: Some(10_000_000),
+            max_priority_fee_per_gas: Some(1_000),
+            input: Bytes::from(hex!("da69d3db")),
+            signature: Some(RpcSignature {
+                r: U256::from_be_bytes(hex!(
+                    "12faae608bd6562562b8f85564664cd1fdcd667f6b24b2b221ef86b9231f4d74"
+                )),
+                s: U256::from_be_bytes(hex!(
+                    "7bc93d83519b7e36dce0f64dc67973c74c0c0dbfa78249fee6d104e83166d618"
+                )),
+                v: U256::ZERO,
+                y_parity: Some(Parity(false)),
+            }),
+            chain_id: Some(167000),
+            access_list: Some(AccessList(vec![AccessListItem {
+                address: Address::repeat_byte(0xbb),
+                storage_keys: vec![B256::with_last_byte(1), B256::with_last_byte(2)],
+            }])),
+            transaction_type: Some(2),
+            ..Default::default()
+        };
+
+        let signed = CompactTxEip1559::try_from(&rpc_tx)
+            .unwrap()
+            .to_signed()
+            .unwrap();
+        assert_eq!(*signed.hash(), rpc_tx.hash);
+        assert_eq!(TxEnvelope::from(signed).encoded_2718(), TRANSACTIONS[2].0);
+    }
+
+    mod differential {
+        use alloy_consensus::{
+            SignableTransaction, TxEip2930, TxEip4844, TxEip4844Variant, TxLegacy,
+        };
+        use alloy_eips::eip2718::Decodable2718;
+        use alloy_primitives::Signature;
+        use proptest::{collection::vec, option, prelude::*};
+
+        use super::*;
+
+        prop_compose! {
+            fn arb_header()(
+                hashes in any::<[[u8; 32]; 7]>(),
+                beneficiary in any::<[u8; 20]>(),
+                logs_bloom in vec(any::<u8>(), 256),
+                (difficulty, number, gas_limit, gas_used, timestamp) in
+                    (any::<u64>(), any::<u64>(), any::<u64>(), any::<u64>(), any::<u64>()),
+                extra_data in vec(any::<u8>(), 0..=32),
+                nonce in any::<u64>(),
+                (base_fee_per_gas, blob_gas_used, excess_blob_gas) in
+                    (any::<u64>(), any::<u64>(), any::<u64>()),
+                // the optional fields are only ever added at the end by the hard forks
+                fork in 0usize..=4,
+            ) -> AlloyConsensusHeader {
+                AlloyConsensusHeader {
+                    parent_hash: hashes[0].into(),
+                    ommers_hash: hashes[1].into(),
+                    beneficiary: beneficiary.into(),
+                    state_root: hashes[2].into(),
+                    transactions_root: hashes[3].into(),
+                    receipts_root: hashes[4].into(),
+                    logs_bloom: Bloom::from_slice(&logs_bloom),
+                    difficulty: U256::from(difficulty),
+                    number,
+                    gas_limit: gas_limit.into(),
+                    gas_used: gas_used.into(),
+                    timestamp,
+                    extra_data: Bytes::from(extra_data),
+                    mix_hash: hashes[5].into(),
+                    nonce: B64::from(nonce.to_be_bytes()),
+                    base_fee_per_gas: (fork >= 1).then_some(base_fee_per_gas.into()),
+                    withdrawals_root: (fork >= 2).then_some(hashes[6].into()),
+                    blob_gas_used: (fork >= 3).then_some(blob_gas_used.into()),
+                    excess_blob_gas: (fork >= 3).then_some(excess_blob_gas.into()),
+                    parent_beacon_block_root: (fork >= 4).then_some(hashes[6].into()),
+                }
+            }
+        }
+
+        /// The header as returned by the node, with the hash computed by alloy.
+        fn to_rpc_header(header: &AlloyConsensusHeader) -> AlloyHeader {
+            AlloyHeader {
+                hash: Some(header.hash_slow()),
+                parent_hash: header.parent_hash,
+                uncles_hash: header.ommers_hash,
+                miner: header.beneficiary,
+                state_root: header.state_root,
+                transactions_root: header.transactions_root,
+                receipts_root: header.receipts_root,
+                logs_bloom: header.logs_bloom,
+                difficulty: header.difficulty,
+                number: Some(header.number),
+                gas_limit: header.gas_limit,
+                gas_used: header.gas_used,
+                timestamp: header.timestamp,
+                extra_data: header.extra_data.clone(),
+                mix_hash: Some(header.mix_hash),
+                nonce: Some(header.nonce),
+                base_fee_per_gas: header.base_fee_per_gas,
+                withdrawals_root: header.withdrawals_root,
+                blob_gas_used: header.blob_gas_used,
+                excess_blob_gas: header.excess_blob_gas,
+                parent_beacon_block_root: header.parent_beacon_block_root,
+                ..Default::default()
+            }
+        }
+
+        fn arb_access_list() -> impl Strategy<Value = AccessList> {
+            let item = (any::<[u8; 20]>(), vec(any::<[u8; 32]>(), 0..4)).prop_map(
+                |(address, storage_keys)| AccessListItem {
+                    address: address.into(),
+                    storage_keys: storage_keys.into_iter().map(B256::from).collect(),
+                },
+            );
+            vec(item, 0..3).prop_map(AccessList)
+        }
+
+        /// A signed transaction of one of the types supported by the block builder.
+        fn arb_envelope() -> impl Strategy<Value = TxEnvelope> {
+            (
+                0u8..4,
+                (
+                    // small enough for the EIP-155 `v` of legacy transactions
+                    1..u64::from(u32::MAX),
+                    any::<u64>(),
+                    any::<u64>(),
+                    any::<u128>(),
+                    any::<u128>(),
+                ),
+                (
+                    option::of(any::<[u8; 20]>()),
+                    any::<[u8; 32]>(),
+                    arb_access_list(),
+                    vec(any::<u8>(), 0..128),
+                ),
+                (vec(any::<[u8; 32]>(), 1..4), any::<u128>()),
+                (any::<[u8; 32]>(), any::<[u8; 32]>(), any::<bool>()),
+            )
+                .prop_map(
+                    |(
+                        tx_type,
+                        (chain_id, nonce, gas_limit, max_fee_per_gas, max_priority_fee_per_gas),
+                        (to, value, access_list, input),
+                        (blob_versioned_hashes, max_fee_per_blob_gas),
+                        (r, s, y_parity),
+                    )| {
+                        let signature = Signature::from_rs_and_parity(
+                            U256::from_be_bytes(r),
+                            U256::from_be_bytes(s),
+                            y_parity,
+                        )
+                        .unwrap();
+                        let kind = to.map_or(TxKind::Create, |to| TxKind::Call(to.into()));
+                        let value = U256::from_be_bytes(value);
+                        let input = Bytes::from(input);
+                        match tx_type {
+                            0 => TxLegacy {
+                                chain_id: Some(chain_id),
+                                nonce,
+                                gas_price: max_fee_per_gas,
+                                gas_limit: gas_limit.into(),
+                                to: kind,
+                                value,
+                                input,
+                            }
+                            .into_signed(signature.with_chain_id(chain_id))
+                            .into(),
+                            1 => TxEip2930 {
+                                chain_id,
+                                nonce,
+                                gas_price: max_fee_per_gas,
+                                gas_limit: gas_limit.into(),
+                                to: kind,
+                                value,
+                                access_list,
+                                input,
+                            }
+                            .into_signed(signature)
+                            .into(),
+                            2 => TxEip1559 {
+                                chain_id,
+                                nonce,
+                                gas_limit: gas_limit.into(),
+                                max_fee_per_gas,
+                                max_priority_fee_per_gas,
+                                to: kind,
+                                value,
+                                access_list,
+                                input,
+                            }
+                            .into_signed(signature)
+                            .into(),
+                            _ => TxEip4844Variant::from(TxEip4844 {
+                                chain_id,
+                                nonce,
+                                gas_limit: gas_limit.into(),
+                                max_fee_per_gas,
+                                max_priority_fee_per_gas,
+                                to: to.unwrap_or_default().into(),
+                                value,
+                                access_list,
+                                blob_versioned_hashes: blob_versioned_hashes
+                                    .into_iter()
+                                    .map(B256::from)
+                                    .collect(),
+                                max_fee_per_blob_gas,
+                                input,
+                            })
+                            .into_signed(signature)
+                            .into(),
+                        }
+                    },
+                )
+        }
+
+        /// The hash alloy computed when signing the transaction.
+        fn signed_hash(tx: &TxEnvelope) -> B256 {
+            match tx {
+                TxEnvelope::Legacy(tx) => *tx.hash(),
+                TxEnvelope::Eip2930(tx) => *tx.hash(),
+                TxEnvelope::Eip1559(tx) => *tx.hash(),
+                TxEnvelope::Eip4844(tx) => *tx.hash(),
+                _ => unreachable!(),
+            }
+        }
+
+        proptest! {
+            /// The block hash checked in the guest must not diverge from the hash computed
+            /// by alloy (and therefore reth) for any header returned by the node.
+            #[test]
+            fn rpc_header_hash_matches_alloy(header in arb_header()) {
+                let rpc_header = to_rpc_header(&header);
+                let converted = to_header(&rpc_header);
+                prop_assert_eq!(converted.hash(), header.hash_slow());
+                prop_assert_eq!(Some(converted.hash()), rpc_header.hash);
+                prop_assert_eq!(converted, header);
+            }
+
+            /// The transactions generated from a tx list must encode and hash exactly like
+            /// the transactions alloy encoded into it.
+            #[test]
+            fn tx_list_roundtrip_matches_alloy(transactions in vec(arb_envelope(), 0..8)) {
+                let mut tx_list = Vec::new();
+                transactions.encode(&mut tx_list);
+                let tx_data = zlib_compress_data(&tx_list).unwrap();
+                let generated = generate_transactions(
+                    &ChainSpec::default(),
+                    false,
+                    &tx_data,
+                    None,
+                    &InputLimits::default(),
+                )
+                .unwrap();
+                prop_assert_eq!(&generated, &transactions);
+
+                for tx in &generated {
+                    let encoded = tx.encoded_2718();
+                    prop_assert_eq!(keccak256(&encoded), signed_hash(tx));
+                    prop_assert_eq!(&TxEnvelope::decode_2718(&mut &encoded[..]).unwrap(), tx);
+                }
+                let mut encoded = Vec::new();
+                generated.encode(&mut encoded);
+                prop_assert_eq!(encoded, tx_list);
+            }
+        }
+    }
+}
@@ -2,7 +2,7 @@ use cargo_metadata::{Metadata, Target};
 
 use std::{collections::HashMap, env, path::PathBuf, process::Command};
 
-use crate::{executor::Executor, ROOT_DIR};
+use crate::{executor::Executor, manifest::BuildManifest, ROOT_DIR};
 
 pub fn parse_metadata(path: &str) -> Metadata {
     let manifest = std::path::Path::new(path).join("Cargo.toml");
@@ -120,6 +120,8 @@ pub struct CommandBuilder {
     pub cc_compiler: Option<PathBuf>,
     // gcc flag
     pub c_flags: Option<Vec<String>>,
+    // guest features, replacing the default ones
+    pub features: Option<Vec<String>>,
 
     pub custom_args: Vec<String>,
 
@@ -157,6 +159,7 @@ impl CommandBuilder {
             z_flags: None,
             cc_compiler: None,
             c_flags: None,
+            features: None,
             custom_args: Vec::new(),
             custom_env: HashMap::new(),
         }
@@ -195,6 +198,11 @@ impl CommandBuilder {
         self
     }
 
+    pub fn features(mut self, features: Option<Vec<String>>) -> Self {
+        self.features = features;
+        self
+    }
+
     pub fn custom_args(mut self, args: &[&str]) -> Self {
         self.custom_args = to_strings(args);
         self
@@ -254,6 +262,7 @@ impl CommandBuilder {
             cmd,
             artifacts,
             test: false,
+            manifest: BuildManifest::collect(self, profile),
         }
     }
 
@@ -295,6 +304,7 @@ impl CommandBuilder {
             cmd,
             artifacts,
             test: true,
+            manifest: BuildManifest::collect(self, profile),
         }
     }
 
@@ -313,6 +323,7 @@ impl CommandBuilder {
             z_flags,
             cc_compiler,
             c_flags,
+            features,
             ..
         } = self.clone();
 
@@ -341,6 +352,13 @@ impl CommandBuilder {
             args.extend(format_flags("--bin", &bins));
         }
 
+        if let Some(features) = features {
+            args.push("--no-default-features".to_owned());
+            if !features.is_empty() {
+                args.extend(["--features".to_owned(), features.join(",")]);
+            }
+        }
+
         if let Some(z_flags) = z_flags {
             args.extend(format_flags("-Z", &z_flags));
         }
@@ -1,3 +1,4 @@
+use crate::manifest::BuildManifest;
 use anyhow::bail;
 use regex::Regex;
 use std::io::BufRead;
@@ -14,6 +15,8 @@ pub struct Executor {
     pub cmd: Command,
     pub artifacts: Vec<PathBuf>,
     pub test: bool,
+    /// The manifest of the build, completed for every placed guest
+    pub manifest: BuildManifest,
 }
 
 impl Executor {
@@ -90,12 +93,29 @@ impl Executor {
                 );
             }
 
+            let name = name.replace('_', "-");
+            let elf_path = dest.join(&name);
             fs::copy(
                 root.join(src.to_str().expect("File name is not valid UTF-8")),
-                &dest.join(&name.replace('_', "-")),
+                &elf_path,
             )?;
 
             println!("Write elf from\n {src:?}\nto\n {dest:?}");
+
+            if !self.test {
+                use sp1_sdk::{HashableKey, ProverClient};
+
+                // Proofs are verified against the verification key of the ELF
+                let elf = fs::read(&elf_path)?;
+                let (_, vk) = ProverClient::new().setup(&elf);
+                BuildManifest {
+                    guest: name,
+                    backend: "sp1".to_owned(),
+                    image_id: vk.bytes32().trim_start_matches("0x").to_owned(),
+                    ..self.manifest.clone()
+                }
+                .write(&dest)?;
+            }
         }
 
         Ok(())
@@ -142,6 +162,21 @@ impl Executor {
             )?;
 
             println!("Write from\n {src:?}\nto\n {dest_file:?}");
+
+            if !self.test {
+                let image_id: Vec<u8> = guest
+                    .image_id
+                    .iter()
+                    .flat_map(|word| word.to_le_bytes())
+                    .collect();
+                BuildManifest {
+                    guest: name,
+                    backend: "risc0".to_owned(),
+                    image_id: crate::manifest::to_hex(&image_id),
+                    ..self.manifest.clone()
+                }
+                .write(&dest_dir)?;
+            }
         }
 
         Ok(())
@@ -1,11 +1,15 @@
 mod builder;
 mod executor;
+pub mod manifest;
+pub mod patches;
 #[cfg(feature = "risc0")]
 mod risc0_util;
 
 pub use builder::{parse_metadata, CommandBuilder, GuestMetadata};
 pub use cargo_metadata::Metadata;
+pub use manifest::BuildManifest;
 use once_cell::sync::OnceCell;
+pub use patches::select_patches;
 use std::path::PathBuf;
 
 pub static ROOT_DIR: OnceCell<PathBuf> = OnceCell::new();
@@ -0,0 +1,189 @@
+//! Manifests of the guest builds, recording everything needed to reproduce a guest image.
+//!
+//! A manifest is written next to every placed guest as `<guest>.manifest.json`. The host
+//! hashes the file into the metadata of the proofs, and `raiko-host verify-build` rebuilds
+//! the guest from the recorded commit, features and toolchain to check the image ID.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{CommandBuilder, ROOT_DIR};
+
+/// The directories the builders place the guests in. The guests are committed, but they
+/// are the output of a build, so changes to them don't make a build unreproducible.
+pub const GUEST_OUTPUT_DIRS: [&str; 2] =
+    ["provers/risc0/driver/src/methods", "provers/sp1/guest/elf"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildManifest {
+    /// The name of the guest binary
+    pub guest: String,
+    /// The prover backend the guest is built for, `risc0` or `sp1`
+    pub backend: String,
+    /// The version of the rustc the guest was compiled with
+    pub toolchain: String,
+    pub target: String,
+    pub profile: String,
+    /// The -C flags passed to rustc
+    pub rust_flags: Vec<String>,
+    /// The flags passed to the C compiler
+    pub c_flags: Vec<String>,
+    /// The guest features, `None` for the default features
+    pub features: Option<Vec<String>>,
+    /// The commit of the repository the guest was built from
+    pub commit: String,
+    /// Whether the repository had uncommitted changes, which makes the build unreproducible
+    pub dirty: bool,
+    /// The hex encoded image ID, the risc0 image ID or the sp1 verification key hash
+    pub image_id: String,
+}
+
+impl BuildManifest {
+    /// Records the toolchain, flags and commit of a build, without a guest and image ID.
+    pub fn collect(builder: &CommandBuilder, profile: &str) -> Self {
+        let rustc = builder
+            .rustc
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("rustc"));
+        let root = ROOT_DIR.get().expect("No reference to ROOT_DIR");
+        let commit = run(Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(root));
+        Self {
+            guest: String::new(),
+            backend: String::new(),
+            toolchain: run(Command::new(rustc).arg("--version")).unwrap_or_default(),
+            target: builder.target.clone(),
+            profile: profile.to_owned(),
+            rust_flags: builder.rust_flags.clone().unwrap_or_default(),
+            c_flags: builder.c_flags.clone().unwrap_or_default(),
+            features: builder.features.clone(),
+            commit: commit.unwrap_or_default(),
+            // Unknown if git isn't available
+            dirty: uncommitted_changes(root).map_or(true, |changes| !changes.is_empty()),
+            image_id: String::new(),
+        }
+    }
+
+    /// The path of the manifest of the guest in the destination directory.
+    pub fn path(dest: &Path, guest: &str) -> PathBuf {
+        dest.join(format!("{guest}.manifest.json"))
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self::read(path)?.0)
+    }
+
+    /// Reads the manifest along with the exact content of the file, which is what the
+    /// manifest is hashed and published as.
+    pub fn read(path: &Path) -> anyhow::Result<(Self, String)> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read build manifest {}", path.display()))?;
+        let manifest = serde_json::from_str(&content)
+            .with_context(|| format!("Couldn't parse build manifest {}", path.display()))?;
+        Ok((manifest, content))
+    }
+
+    /// Writes the manifest to `<dest>/<guest>.manifest.json`.
+    pub fn write(&self, dest: &Path) -> anyhow::Result<()> {
+        let path = Self::path(dest, &self.guest);
+        fs::write(&path, self.to_json())
+            .with_context(|| format!("Couldn't write build manifest {}", path.display()))?;
+        println!("Write build manifest to\n {path:?}");
+        Ok(())
+    }
+
+    /// The JSON encoding the manifest is written as.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("The manifest is serializable")
+    }
+}
+
+/// Returns the uncommitted changes of the repository `dir` is in, as listed by
+/// `git status --porcelain`, without the changes to the [GUEST_OUTPUT_DIRS]. `None` if git
+/// failed.
+pub fn uncommitted_changes(dir: &Path) -> Option<String> {
+    let mut cmd = Command::new("git");
+    cmd.args(["status", "--porcelain", "--", ":(top)"])
+        .current_dir(dir);
+    for output_dir in GUEST_OUTPUT_DIRS {
+        cmd.arg(format!(":(top,exclude){output_dir}"));
+    }
+    run(&mut cmd)
+}
+
+/// Returns the hex encoding of the bytes.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Returns the trimmed stdout of the command, or `None` if it failed.
+fn run(cmd: &mut Command) -> Option<String> {
+    let output = cmd.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|out| out.trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@test"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_uncommitted_changes() {
+        let repo = std::env::temp_dir().join(format!("raiko-manifest-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&repo);
+        let elf_dir = repo.join(GUEST_OUTPUT_DIRS[1]);
+        fs::create_dir_all(&elf_dir).unwrap();
+        fs::write(repo.join("lib.rs"), "").unwrap();
+        fs::write(elf_dir.join("sp1-guest"), "elf").unwrap();
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-q", "-m", "init"]);
+
+        // A new build only changes the guest outputs, also when run in the guest directory
+        fs::write(elf_dir.join("sp1-guest"), "new elf").unwrap();
+        fs::write(elf_dir.join("sp1-guest.manifest.json"), "{}").unwrap();
+        assert_eq!(uncommitted_changes(&repo).unwrap(), "");
+        assert_eq!(uncommitted_changes(&elf_dir).unwrap(), "");
+
+        fs::write(repo.join("lib.rs"), "fn main() {}").unwrap();
+        assert_eq!(uncommitted_changes(&elf_dir).unwrap(), "M lib.rs");
+    }
+
+    #[test]
+    fn test_read() {
+        let dir = std::env::temp_dir().join(format!("raiko-manifest-read-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = BuildManifest {
+            guest: "sp1-guest".to_owned(),
+            backend: "sp1".to_owned(),
+            image_id: "00ff".to_owned(),
+            ..Default::default()
+        };
+        manifest.write(&dir).unwrap();
+
+        let (read, content) = BuildManifest::read(&BuildManifest::path(&dir, "sp1-guest")).unwrap();
+        assert_eq!(read, manifest);
+        assert_eq!(content, manifest.to_json());
+    }
+}
@@ -0,0 +1,212 @@
+//! Profile-guided selection of the accelerated crypto patches compiled into a guest.
+//!
+//! Every patch (sha256, secp256k1, bn254) is a cargo feature of the guest crate. When a
+//! cycle profile of representative blocks is given, only the patches whose operation
+//! takes up a meaningful share of the profiled cycles are enabled, so the guest image
+//! doesn't carry acceleration code that the workload never benefits from. Without a
+//! profile the guest is built with its default features.
+//!
+//! To reproduce a build, the features recorded in its manifest can be set explicitly
+//! with `RAIKO_GUEST_FEATURES`, which takes precedence over the profile.
+//!
+//! The profile is a JSON file listing the cycles spent per block and per operation:
+//!
+//! ```json
+//! {
+//!   "blocks": [
+//!     { "block_number": 10, "total_cycles": 412000000, "patches": { "secp256k1": 35000000 } }
+//!   ]
+//! }
+//! ```
+
+use std::{collections::HashMap, env, fs, path::Path};
+
+use anyhow::{ensure, Context};
+use serde::{Deserialize, Serialize};
+
+/// Path of the cycle profile used to select the guest patches
+pub const CYCLE_PROFILE_ENV: &str = "RAIKO_CYCLE_PROFILE";
+/// Minimum share of the profiled cycles an operation needs for its patch to be compiled in
+pub const PATCH_MIN_SHARE_ENV: &str = "RAIKO_PATCH_MIN_SHARE";
+pub const DEFAULT_PATCH_MIN_SHARE: f64 = 0.001;
+/// Comma separated list of the guest features, overriding the selection from the profile
+pub const GUEST_FEATURES_ENV: &str = "RAIKO_GUEST_FEATURES";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BlockProfile {
+    pub block_number: u64,
+    pub total_cycles: u64,
+    /// Cycles spent in each patchable operation, keyed by the patch name
+    #[serde(default)]
+    pub patches: HashMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CycleProfile {
+    pub blocks: Vec<BlockProfile>,
+}
+
+impl CycleProfile {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read cycle profile {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Couldn't parse cycle profile {}", path.display()))
+    }
+
+    /// Share of all profiled cycles spent in the operation of the given patch
+    pub fn share(&self, patch: &str) -> f64 {
+        let total: u64 = self.blocks.iter().map(|b| b.total_cycles).sum();
+        if total == 0 {
+            return 0.0;
+        }
+        let cycles: u64 = self
+            .blocks
+            .iter()
+            .filter_map(|b| b.patches.get(patch))
+            .sum();
+        cycles as f64 / total as f64
+    }
+
+    /// Selects the patches out of `available` that reach `min_share` of the profiled cycles
+    pub fn select(&self, available: &[&str], min_share: f64) -> Vec<String> {
+        for block in &self.blocks {
+            for patch in block.patches.keys() {
+                if !available.contains(&patch.as_str()) {
+                    println!(
+                        "Ignoring unknown patch {patch} in profile of block {}",
+                        block.block_number
+                    );
+                }
+            }
+        }
+        available
+            .iter()
+            .filter(|patch| {
+                let share = self.share(patch);
+                let selected = share >= min_share;
+                println!(
+                    "Patch {patch}: {:.3}% of profiled cycles, {}",
+                    share * 100.0,
+                    if selected { "enabled" } else { "disabled" }
+                );
+                selected
+            })
+            .map(|patch| patch.to_string())
+            .collect()
+    }
+}
+
+/// Selects the guest features for the `available` patches from `RAIKO_GUEST_FEATURES` or
+/// the cycle profile in `RAIKO_CYCLE_PROFILE`, or `None` to keep the default features if
+/// neither is set.
+pub fn select_patches(available: &[&str]) -> anyhow::Result<Option<Vec<String>>> {
+    if let Ok(features) = env::var(GUEST_FEATURES_ENV) {
+        return parse_features(&features, available)
+            .with_context(|| format!("Invalid {GUEST_FEATURES_ENV}"))
+            .map(Some);
+    }
+    let Ok(path) = env::var(CYCLE_PROFILE_ENV) else {
+        return Ok(None);
+    };
+    // Only work in build.rs
+    println!("cargo::rerun-if-changed={path}");
+    let profile = CycleProfile::load(Path::new(&path))
+        .with_context(|| format!("Invalid cycle profile in {CYCLE_PROFILE_ENV}"))?;
+    let min_share = match env::var(PATCH_MIN_SHARE_ENV) {
+        Ok(share) => share.parse().with_context(|| {
+            format!("Invalid minimum patch share {share:?} in {PATCH_MIN_SHARE_ENV}")
+        })?,
+        Err(_) => DEFAULT_PATCH_MIN_SHARE,
+    };
+    Ok(Some(profile.select(available, min_share)))
+}
+
+/// Parses a comma separated list of guest features, which all have to be `available`.
+fn parse_features(features: &str, available: &[&str]) -> anyhow::Result<Vec<String>> {
+    let features: Vec<String> = features
+        .split(',')
+        .map(str::trim)
+        .filter(|feature| !feature.is_empty())
+        .map(String::from)
+        .collect();
+    for feature in &features {
+        ensure!(
+            available.contains(&feature.as_str()),
+            "Unknown guest feature {feature}, expected one of {available:?}"
+        );
+    }
+    Ok(features)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCHES: [&str; 3] = ["sha256", "secp256k1", "bn254"];
+
+    fn block(block_number: u64, total_cycles: u64, patches: &[(&str, u64)]) -> BlockProfile {
+        BlockProfile {
+            block_number,
+            total_cycles,
+            patches: patches
+                .iter()
+                .map(|(patch, cycles)| (patch.to_string(), *cycles))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_share() {
+        let profile = CycleProfile {
+            blocks: vec![
+                block(1, 1000, &[("secp256k1", 100), ("sha256", 1)]),
+                block(2, 3000, &[("secp256k1", 300)]),
+            ],
+        };
+        assert_eq!(profile.share("secp256k1"), 0.1);
+        assert_eq!(profile.share("sha256"), 1.0 / 4000.0);
+        assert_eq!(profile.share("bn254"), 0.0);
+        assert_eq!(CycleProfile::default().share("secp256k1"), 0.0);
+    }
+
+    #[test]
+    fn test_select() {
+        let profile = CycleProfile {
+            blocks: vec![block(
+                1,
+                10_000,
+                &[("secp256k1", 1000), ("sha256", 10), ("blake2", 5000)],
+            )],
+        };
+        assert_eq!(profile.select(&PATCHES, 0.001), vec!["sha256", "secp256k1"]);
+        assert_eq!(profile.select(&PATCHES, 0.01), vec!["secp256k1"]);
+        // unknown operations are never selected
+        assert!(profile.select(&PATCHES, 0.0).iter().all(|p| p != "blake2"));
+        assert!(CycleProfile::default().select(&PATCHES, 0.001).is_empty());
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        let profile: CycleProfile = serde_json::from_str(
+            r#"{"blocks": [{"block_number": 10, "total_cycles": 412000000,
+                "patches": {"secp256k1": 35000000}}, {"block_number": 11, "total_cycles": 1}]}"#,
+        )
+        .unwrap();
+        assert_eq!(profile.blocks.len(), 2);
+        assert!(profile.blocks[1].patches.is_empty());
+
+        let err = CycleProfile::load(Path::new("/nonexistent/profile.json")).unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/profile.json"));
+    }
+
+    #[test]
+    fn test_parse_features() {
+        assert_eq!(
+            parse_features(" sha256, bn254,", &PATCHES).unwrap(),
+            vec!["sha256", "bn254"]
+        );
+        assert!(parse_features("", &PATCHES).unwrap().is_empty());
+        assert!(parse_features("sha256,blake2", &PATCHES).is_err());
+    }
+}
@@ -1,20 +1,37 @@
 use raiko_pipeline::{
-    parse_metadata, rerun_if_changed, CommandBuilder, GuestMetadata, Metadata, Pipeline,
+    parse_metadata,
+    patches::{CYCLE_PROFILE_ENV, GUEST_FEATURES_ENV, PATCH_MIN_SHARE_ENV},
+    rerun_if_changed, select_patches, CommandBuilder, GuestMetadata, Metadata, Pipeline,
 };
 use std::path::PathBuf;
 
+/// Accelerated crypto patches the guest can be built with
+const PATCHES: [&str; 2] = ["sha256", "secp256k1"];
+
 fn main() {
-    let pipeline = Risc0Pipeline::new("provers/risc0/guest", "release");
+    let pipeline = Risc0Pipeline::new("provers/risc0/guest", "release")
+        .with_patches(select_patches(&PATCHES).expect("Could not select the guest patches"));
     pipeline.bins(&["risc0-guest"], "provers/risc0/driver/src/methods");
     #[cfg(feature = "test")]
     pipeline.tests(&["risc0-guest"], "provers/risc0/driver/src/methods");
+    // The benchmarks exercise every patch
     #[cfg(feature = "bench")]
-    pipeline.bins(&["ecdsa", "sha256"], "provers/risc0/driver/src/methods");
+    Risc0Pipeline::new("provers/risc0/guest", "release")
+        .with_patches(Some(PATCHES.map(String::from).to_vec()))
+        .bins(&["ecdsa", "sha256"], "provers/risc0/driver/src/methods");
 }
 
 pub struct Risc0Pipeline {
     pub meta: Metadata,
     pub profile: String,
+    pub patches: Option<Vec<String>>,
+}
+
+impl Risc0Pipeline {
+    pub fn with_patches(mut self, patches: Option<Vec<String>>) -> Self {
+        self.patches = patches;
+        self
+    }
 }
 
 impl Pipeline for Risc0Pipeline {
@@ -23,6 +40,7 @@ impl Pipeline for Risc0Pipeline {
         Risc0Pipeline {
             meta: parse_metadata(root),
             profile: profile.to_string(),
+            patches: None,
         }
     }
 
@@ -40,14 +58,15 @@ impl Pipeline for Risc0Pipeline {
                 "-mstrict-align",
                 "-falign-functions=2",
             ])
-            .custom_args(&["--ignore-rust-version"]);
+            .custom_args(&["--ignore-rust-version"])
+            .features(self.patches.clone());
         // Cannot use /.rustup/toolchains/risc0/bin/cargo, use regular cargo
         builder.unset_cargo();
         builder
     }
 
     fn bins(&self, names: &[&str], dest: &str) {
-        rerun_if_changed(&[]);
+        rerun_if_changed(&[CYCLE_PROFILE_ENV, PATCH_MIN_SHARE_ENV, GUEST_FEATURES_ENV]);
         let bins = self.meta.get_bins(names);
         let builder = self.builder();
         let executor = builder.build_command(&self.profile, &bins);
@@ -66,7 +85,7 @@ impl Pipeline for Risc0Pipeline {
     }
 
     fn tests(&self, names: &[&str], dest: &str) {
-        rerun_if_changed(&[]);
+        rerun_if_changed(&[CYCLE_PROFILE_ENV, PATCH_MIN_SHARE_ENV, GUEST_FEATURES_ENV]);
         let tests = self.meta.get_tests(names);
         let builder = self.builder();
         let executor = builder.test_command(&self.profile, &tests);
@@ -7,7 +7,7 @@ use alloy_sol_types::SolValue;
 use hex::ToHex;
 
 use raiko_lib::{
-    input::{GuestInput, GuestOutput},
+    input::{write_input, GuestInput, GuestOutput, InputWriter},
     primitives::keccak::keccak,
     protocol_instance::ProtocolInstance,
     prover::{to_proof, Proof, Prover, ProverConfig, ProverResult},
@@ -42,6 +42,16 @@ pub struct Risc0Response {
 }
 pub struct Risc0Prover;
 
+/// The input sections serialized back to back, as read by the guest with `env::read`.
+struct EncodedInput(Vec<u32>);
+
+impl InputWriter for EncodedInput {
+    fn write<T: Serialize>(&mut self, value: &T) {
+        self.0
+            .extend(to_vec(value).expect("Could not serialize proving input!"));
+    }
+}
+
 impl Prover for Risc0Prover {
     async fn run(
         input: GuestInput,
@@ -51,7 +61,9 @@ impl Prover for Risc0Prover {
         let config = Risc0Param::deserialize(config.get("risc0").unwrap()).unwrap();
 
         println!("elf code length: {}", RISC0_GUEST_ELF.len());
-        let encoded_input = to_vec(&input).expect("Could not serialize proving input!");
+        let mut encoded_input = EncodedInput(Vec::new());
+        write_input(input, &mut encoded_input);
+        let encoded_input = encoded_input.0;
 
         let result = maybe_prove::<GuestInput, GuestOutput>(
             &config,
@@ -6,9 +6,10 @@ use raiko_lib::protocol_instance::ProtocolInstance;
 use raiko_lib::{
     consts::VerifierType,
     builder::{BlockBuilderStrategy, TaikoStrategy},
-    input::{GuestInput, GuestOutput},
+    input::{read_input, GuestOutput, InputLimits, InputReader},
 };
 use revm_precompile::zk_op::ZkOperation;
+use serde::de::DeserializeOwned;
 use zk_op::Risc0Operator;
 
 pub mod mem;
@@ -17,12 +18,28 @@ pub mod mem;
 use harness::*;
 pub use mem::*;
 
+struct GuestReader;
+
+impl InputReader for GuestReader {
+    fn read<T: DeserializeOwned>(&mut self) -> T {
+        env::read()
+    }
+}
+
 fn main() {
-    let input: GuestInput = env::read();
+    let input =
+        read_input(&mut GuestReader, &InputLimits::default()).expect("Input exceeds the limits");
 
     revm_precompile::zk_op::ZKVM_OPERATOR.get_or_init(|| Box::new(Risc0Operator {}));
+    // Only the operations patched into this build are accelerated
+    #[allow(unused_mut)]
+    let mut operations: Vec<ZkOperation> = Vec::new();
+    #[cfg(feature = "sha256")]
+    operations.push(ZkOperation::Sha256);
+    #[cfg(feature = "secp256k1")]
+    operations.push(ZkOperation::Secp256k1);
     revm_precompile::zk_op::ZKVM_OPERATIONS
-        .set(Box::new(vec![ZkOperation::Sha256, ZkOperation::Secp256k1]))
+        .set(Box::new(operations))
         .expect("Failed to set ZkvmOperations");
 
     let build_result = TaikoStrategy::build_from(&input);
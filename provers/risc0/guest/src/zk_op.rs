@@ -1,5 +1,7 @@
+#[cfg(feature = "secp256k1")]
 use k256 as risc0_k256;
 use revm_precompile::{zk_op::ZkvmOperator, Error};
+#[cfg(feature = "sha256")]
 use sha2 as risc0_sha2;
 
 #[derive(Debug)]
@@ -22,11 +24,17 @@ impl ZkvmOperator for Risc0Operator {
         unreachable!()
     }
 
+    #[cfg(feature = "sha256")]
     fn sha256_run(&self, input: &[u8]) -> Result<[u8; 32], Error> {
         use risc0_sha2::Digest;
         Ok(risc0_sha2::Sha256::digest(input).into())
     }
 
+    #[cfg(not(feature = "sha256"))]
+    fn sha256_run(&self, _input: &[u8]) -> Result<[u8; 32], Error> {
+        unreachable!()
+    }
+
     fn ripemd160_run(&self, _input: &[u8]) -> Result<[u8; 32], Error> {
         unreachable!()
     }
@@ -35,13 +43,13 @@ impl ZkvmOperator for Risc0Operator {
         unreachable!()
     }
 
+    #[cfg(feature = "secp256k1")]
     fn secp256k1_ecrecover(
         &self,
         sig: &[u8; 64],
         mut recid: u8,
         msg: &[u8; 32],
     ) -> Result<[u8; 32], Error> {
-
         use risc0_k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
 
         // parse signature
@@ -68,28 +76,42 @@ impl ZkvmOperator for Risc0Operator {
         hash[..12].fill(0);
         Ok(*hash)
     }
+
+    #[cfg(not(feature = "secp256k1"))]
+    fn secp256k1_ecrecover(
+        &self,
+        _sig: &[u8; 64],
+        _recid: u8,
+        _msg: &[u8; 32],
+    ) -> Result<[u8; 32], Error> {
+        unreachable!()
+    }
 }
 
 harness::zk_suits!(
     pub mod tests {
         #[test]
         pub fn test_sha256() {
-            use harness::*;
-            use raiko_lib::primitives::hex;
-            use risc0_sha2::{Digest, Sha256};
-
-            let test_ves = [
-                ("", hex!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")),
-                (   "The quick brown fox jumps over the lazy dog",
-                    hex!("d7a8fbb307d7809469ca9abcb0082e4f8d5651e46d3cdb762d02d0bf37c9e592")
-                ),
-                ("hello", hex!("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")),
-            ];
-
-            for v in test_ves.iter() {
-                let (input, expected) = *v;
-                let result: [u8; 32] = Sha256::digest(input.as_bytes()).into();
-                harness::assert_eq!(result, expected);
+            // The patched sha2 is only a dependency of guests with the sha256 patch
+            #[cfg(feature = "sha256")]
+            {
+                use harness::*;
+                use raiko_lib::primitives::hex;
+                use sha2::{Digest, Sha256};
+
+                let test_ves = [
+                    ("", hex!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")),
+                    (   "The quick brown fox jumps over the lazy dog",
+                        hex!("d7a8fbb307d7809469ca9abcb0082e4f8d5651e46d3cdb762d02d0bf37c9e592")
+                    ),
+                    ("hello", hex!("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")),
+                ];
+
+                for v in test_ves.iter() {
+                    let (input, expected) = *v;
+                    let result: [u8; 32] = Sha256::digest(input.as_bytes()).into();
+                    harness::assert_eq!(result, expected);
+                }
             }
         }
     }
@@ -10,7 +10,7 @@ use base64_serde::base64_serde_type;
 use raiko_lib::{
     builder::{BlockBuilderStrategy, TaikoStrategy},
     consts::VerifierType,
-    input::GuestInput,
+    input::{GuestInput, InputLimits},
     primitives::Address,
     protocol_instance::ProtocolInstance,
 };
@@ -130,6 +130,9 @@ pub async fn one_shot(global_opts: GlobalOpts, args: OneShotArgs) -> Result<()>
 
     let input: GuestInput =
         bincode::deserialize_from(std::io::stdin()).expect("unable to deserialize input");
+    InputLimits::default()
+        .check(&input)
+        .expect("input exceeds the limits");
     assert!(!input.taiko.skip_verify_blob);
 
     // Process the block
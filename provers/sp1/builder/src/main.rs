@@ -1,23 +1,40 @@
 use raiko_pipeline::{
-    parse_metadata, rerun_if_changed, CommandBuilder, GuestMetadata, Metadata, Pipeline,
+    parse_metadata,
+    patches::{CYCLE_PROFILE_ENV, GUEST_FEATURES_ENV, PATCH_MIN_SHARE_ENV},
+    rerun_if_changed, select_patches, CommandBuilder, GuestMetadata, Metadata, Pipeline,
 };
 use std::path::PathBuf;
 
+/// Accelerated crypto patches the guest can be built with
+const PATCHES: [&str; 3] = ["sha256", "secp256k1", "bn254"];
+
 fn main() {
-    let pipeline = Sp1Pipeline::new("provers/sp1/guest", "release");
+    let pipeline = Sp1Pipeline::new("provers/sp1/guest", "release")
+        .with_patches(select_patches(&PATCHES).expect("Could not select the guest patches"));
     pipeline.bins(&["sp1-guest"], "provers/sp1/guest/elf");
     #[cfg(feature = "test")]
     pipeline.tests(&["sp1-guest"], "provers/sp1/guest/elf");
+    // The benchmarks exercise every patch
     #[cfg(feature = "bench")]
-    pipeline.bins(
-        &["ecdsa", "sha256", "bn254_add", "bn254_mul"],
-        "provers/sp1/guest/elf",
-    );
+    Sp1Pipeline::new("provers/sp1/guest", "release")
+        .with_patches(Some(PATCHES.map(String::from).to_vec()))
+        .bins(
+            &["ecdsa", "sha256", "bn254_add", "bn254_mul"],
+            "provers/sp1/guest/elf",
+        );
 }
 
 pub struct Sp1Pipeline {
     pub meta: Metadata,
     pub profile: String,
+    pub patches: Option<Vec<String>>,
+}
+
+impl Sp1Pipeline {
+    pub fn with_patches(mut self, patches: Option<Vec<String>>) -> Self {
+        self.patches = patches;
+        self
+    }
 }
 
 impl Pipeline for Sp1Pipeline {
@@ -26,6 +43,7 @@ impl Pipeline for Sp1Pipeline {
         Sp1Pipeline {
             meta: parse_metadata(root),
             profile: profile.to_string(),
+            patches: None,
         }
     }
 
@@ -44,10 +62,11 @@ impl Pipeline for Sp1Pipeline {
                 "-falign-functions=2",
             ])
             .custom_args(&["--ignore-rust-version"])
+            .features(self.patches.clone())
     }
 
     fn bins(&self, names: &[&str], dest: &str) {
-        rerun_if_changed(&[]);
+        rerun_if_changed(&[CYCLE_PROFILE_ENV, PATCH_MIN_SHARE_ENV, GUEST_FEATURES_ENV]);
         let bins = self.meta.get_bins(names);
         let builder = self.builder();
         let executor = builder.build_command(&self.profile, &bins);
@@ -66,7 +85,7 @@ impl Pipeline for Sp1Pipeline {
     }
 
     fn tests(&self, names: &[&str], dest: &str) {
-        rerun_if_changed(&[]);
+        rerun_if_changed(&[CYCLE_PROFILE_ENV, PATCH_MIN_SHARE_ENV, GUEST_FEATURES_ENV]);
         let tests = self.meta.get_tests(names);
         let builder = self.builder();
         let executor = builder.test_command(&self.profile, &tests);
@@ -1,19 +1,29 @@
 #![cfg(feature = "enable")]
-use std::env;
+use std::{env, sync::OnceLock};
 
 use alloy_primitives::B256;
 use alloy_sol_types::SolValue;
 use raiko_lib::{
-    input::{GuestInput, GuestOutput},
+    input::{write_input, GuestInput, GuestOutput, InputWriter},
     protocol_instance::ProtocolInstance,
     prover::{to_proof, Proof, Prover, ProverConfig, ProverResult},
 };
 use serde::{Deserialize, Serialize};
 use sha3::{self, Digest};
-use sp1_sdk::{ProverClient, SP1Stdin};
+use sp1_sdk::{HashableKey, ProverClient, SP1Stdin};
 
 const ELF: &[u8] = include_bytes!("../../guest/elf/sp1-guest");
 
+/// Returns the hash of the verification key of the guest, which the proofs are verified
+/// against.
+pub fn guest_vkey() -> &'static str {
+    static VKEY: OnceLock<String> = OnceLock::new();
+    VKEY.get_or_init(|| {
+        let (_, vk) = ProverClient::new().setup(ELF);
+        vk.bytes32()
+    })
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Sp1Response {
     pub proof: String,
@@ -22,6 +32,15 @@ pub struct Sp1Response {
 
 pub struct Sp1Prover;
 
+/// Writes each input section as a separate buffer, read by the guest with `io::read`.
+struct Sp1Input<'a>(&'a mut SP1Stdin);
+
+impl InputWriter for Sp1Input<'_> {
+    fn write<T: Serialize>(&mut self, value: &T) {
+        self.0.write(value);
+    }
+}
+
 impl Prover for Sp1Prover {
     async fn run(
         input: GuestInput,
@@ -30,7 +49,7 @@ impl Prover for Sp1Prover {
     ) -> ProverResult<Proof> {
         // Write the input.
         let mut stdin = SP1Stdin::new();
-        stdin.write(&input);
+        write_input(input, &mut Sp1Input(&mut stdin));
 
         // Generate the proof for the given program.
         let client = ProverClient::new();
@@ -5,25 +5,40 @@ harness::entrypoint!(main, tests, zk_op::tests);
 use raiko_lib::{
     consts::VerifierType,
     builder::{BlockBuilderStrategy, TaikoStrategy},
-    input::{GuestInput, GuestOutput},
+    input::{read_input, GuestOutput, InputLimits, InputReader},
     protocol_instance::ProtocolInstance,
 };
 use revm_precompile::zk_op::ZkOperation;
+use serde::de::DeserializeOwned;
 use zk_op::Sp1Operator;
 
 pub mod mem;
 pub use mem::*;
 
+struct GuestReader;
+
+impl InputReader for GuestReader {
+    fn read<T: DeserializeOwned>(&mut self) -> T {
+        sp1_zkvm::io::read()
+    }
+}
+
 pub fn main() {
-    let input = sp1_zkvm::io::read::<GuestInput>();
+    let input =
+        read_input(&mut GuestReader, &InputLimits::default()).expect("Input exceeds the limits");
 
     revm_precompile::zk_op::ZKVM_OPERATOR.get_or_init(|| Box::new(Sp1Operator {}));
+    // Only the operations patched into this build are accelerated
+    #[allow(unused_mut)]
+    let mut operations: Vec<ZkOperation> = Vec::new();
+    #[cfg(feature = "bn254")]
+    operations.extend([ZkOperation::Bn128Add, ZkOperation::Bn128Mul]);
+    #[cfg(feature = "sha256")]
+    operations.push(ZkOperation::Sha256);
+    #[cfg(feature = "secp256k1")]
+    operations.push(ZkOperation::Secp256k1);
     revm_precompile::zk_op::ZKVM_OPERATIONS
-        .set(Box::new(vec![
-            ZkOperation::Bn128Add,
-            ZkOperation::Bn128Mul,
-            ZkOperation::Secp256k1,
-        ]))
+        .set(Box::new(operations))
         .expect("Failed to set ZkvmOperations");
 
     let build_result = TaikoStrategy::build_from(&input);
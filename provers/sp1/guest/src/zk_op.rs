@@ -1,12 +1,18 @@
-use revm_precompile::{bn128::ADD_INPUT_LEN, utilities::right_pad, zk_op::ZkvmOperator, Error};
+#[cfg(feature = "bn254")]
+use revm_precompile::{bn128::ADD_INPUT_LEN, utilities::right_pad};
+use revm_precompile::{zk_op::ZkvmOperator, Error};
+#[cfg(feature = "secp256k1")]
 use revm_primitives::keccak256;
+#[cfg(feature = "sha256")]
 use sha2_v0_10_8 as sp1_sha2;
+#[cfg(feature = "bn254")]
 use sp1_zkvm::precompiles::{bn254::Bn254, utils::AffinePoint};
 
 #[derive(Debug)]
 pub struct Sp1Operator;
 
 impl ZkvmOperator for Sp1Operator {
+    #[cfg(feature = "bn254")]
     fn bn128_run_add(&self, input: &[u8]) -> Result<[u8; 64], Error> {
         let input = right_pad::<ADD_INPUT_LEN>(input);
         let mut p = be_bytes_to_point(&input[..64]);
@@ -15,6 +21,7 @@ impl ZkvmOperator for Sp1Operator {
         Ok(point_to_be_bytes(p))
     }
 
+    #[cfg(feature = "bn254")]
     fn bn128_run_mul(&self, input: &[u8]) -> Result<[u8; 64], Error> {
         let input = right_pad::<96>(input);
         let _output = [0u8; 64];
@@ -33,6 +40,16 @@ impl ZkvmOperator for Sp1Operator {
         Ok(point_to_be_bytes(p))
     }
 
+    #[cfg(not(feature = "bn254"))]
+    fn bn128_run_add(&self, _input: &[u8]) -> Result<[u8; 64], Error> {
+        unreachable!()
+    }
+
+    #[cfg(not(feature = "bn254"))]
+    fn bn128_run_mul(&self, _input: &[u8]) -> Result<[u8; 64], Error> {
+        unreachable!()
+    }
+
     fn bn128_run_pairing(&self, _input: &[u8]) -> Result<bool, Error> {
         unreachable!()
     }
@@ -41,11 +58,17 @@ impl ZkvmOperator for Sp1Operator {
         unreachable!()
     }
 
+    #[cfg(feature = "sha256")]
     fn sha256_run(&self, input: &[u8]) -> Result<[u8; 32], Error> {
         use sp1_sha2::Digest;
         Ok(sp1_sha2::Sha256::digest(input).into())
     }
 
+    #[cfg(not(feature = "sha256"))]
+    fn sha256_run(&self, _input: &[u8]) -> Result<[u8; 32], Error> {
+        unreachable!()
+    }
+
     fn ripemd160_run(&self, _input: &[u8]) -> Result<[u8; 32], Error> {
         unreachable!()
     }
@@ -54,6 +77,7 @@ impl ZkvmOperator for Sp1Operator {
         unreachable!()
     }
 
+    #[cfg(feature = "secp256k1")]
     fn secp256k1_ecrecover(
         &self,
         sig: &[u8; 64],
@@ -72,8 +96,19 @@ impl ZkvmOperator for Sp1Operator {
         hash[..12].fill(0);
         Ok(*hash)
     }
+
+    #[cfg(not(feature = "secp256k1"))]
+    fn secp256k1_ecrecover(
+        &self,
+        _sig: &[u8; 64],
+        _recid: u8,
+        _msg: &[u8; 32],
+    ) -> Result<[u8; 32], Error> {
+        unreachable!()
+    }
 }
 
+#[cfg(feature = "bn254")]
 #[inline]
 fn be_bytes_to_point(input: &[u8]) -> AffinePoint<Bn254, 16> {
     assert!(input.len() == 64, "Input length must be 64 bytes");
@@ -86,6 +121,7 @@ fn be_bytes_to_point(input: &[u8]) -> AffinePoint<Bn254, 16> {
     AffinePoint::<Bn254, 16>::from(x, y)
 }
 
+#[cfg(feature = "bn254")]
 #[inline]
 fn point_to_be_bytes(p: AffinePoint<Bn254, 16>) -> [u8; 64] {
     let p = p.to_le_bytes();
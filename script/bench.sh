@@ -0,0 +1,27 @@
+#!/usr/bin/env bash
+
+# Runs the benchmarks of raiko-bench.
+#
+#   ./script/bench.sh save    saves $BENCH_RUNS (default: 3) runs as baseline $BENCH_BASELINE
+#                             (default: main)
+#   ./script/bench.sh         compares against the baseline and fails if a benchmark is
+#                             slower than its slowest baseline run by more than
+#                             $BENCH_THRESHOLD (default: 0.1, i.e. 10%)
+
+set -e
+
+BASELINE=${BENCH_BASELINE:-main}
+THRESHOLD=${BENCH_THRESHOLD:-0.1}
+RUNS=${BENCH_RUNS:-3}
+
+if [ "$1" == "save" ]; then
+	# Runs of an older baseline would otherwise be picked up by the regression check
+	rm -rf target/criterion
+	for RUN in $(seq 1 "$RUNS"); do
+		cargo bench -p raiko-bench -- --save-baseline "$BASELINE-$RUN"
+	done
+else
+	cargo bench -p raiko-bench -- --save-baseline current
+	cargo run --release -p raiko-bench --bin check-regressions -- \
+		--baseline="$BASELINE" --current=current --threshold="$THRESHOLD"
+fi
@@ -76,8 +76,18 @@ elif [ "$proof" == "risc0-bonsai" ]; then
         "execution_po2": 20
     }
   '
+elif [ "$proof" == "sandbox" ]; then
+	proofParam='
+    "proof_type": "sandbox",
+    "sandbox": {
+        "delay_ms": 30000,
+        "jitter_ms": 10000,
+        "failure_rate": 0.05,
+        "seed": 0
+    }
+  '
 else
-	echo "Invalid proof name. Please use 'native', 'risc0[-bonsai]', 'sp1', or 'sgx'."
+	echo "Invalid proof name. Please use 'native', 'risc0[-bonsai]', 'sp1', 'sgx' or 'sandbox'."
 	exit 1
 fi
 